@@ -0,0 +1,32 @@
+#ifndef BOOK_PUZZLE_ALGORITHM_H
+#define BOOK_PUZZLE_ALGORITHM_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Mushikui Mushikui;
+typedef struct Board Board;
+
+/* 虫食算. 読めなければ NULL を返し, error が NULL でなければメッセージを書き込む */
+Mushikui *mushikui_new(const char *text, char **error);
+/* 解を空行区切りで返す. max_solutions が 0 ならすべて */
+char *mushikui_solve(const Mushikui *mushikui, size_t max_solutions);
+void mushikui_free(Mushikui *mushikui);
+
+/* 15 パズル. 空白区切りの 16 個の数 (0 は空きマス) */
+Board *fifteen_puzzle_new(const char *text, char **error);
+/* 完成までの各盤面を空行区切りで返す. 解けなければ NULL */
+char *fifteen_puzzle_solve(const Board *board);
+void fifteen_puzzle_free(Board *board);
+
+/* 上の関数が返した文字列を解放する */
+void puzzle_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,158 @@
+//! C から呼ぶための API
+//!
+//! パズルは文字列から作ったハンドル (不透明なポインタ) で扱い、解は文字列で返す。
+//! ハンドルと返された文字列は、それぞれ対応する `*_free` で解放する。
+//! 宣言は `include/book_puzzle_algorithm.h` にある。
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+#[cfg(feature = "fifteen-puzzle")]
+use book_puzzle_algorithm_rs::fifteen_puzzle::{self, Board};
+#[cfg(feature = "mushikui")]
+use book_puzzle_algorithm_rs::mushikui::{self, Mushikui};
+
+fn into_c_string(s: String) -> *mut c_char {
+    // 解の文字列に NUL は含まれない
+    CString::new(s).unwrap().into_raw()
+}
+
+unsafe fn parse<T, E>(text: *const c_char, error: *mut *mut c_char) -> *mut T
+where
+    T: std::str::FromStr<Err = E>,
+    E: std::fmt::Display,
+{
+    let result = match CStr::from_ptr(text).to_str() {
+        Ok(text) => text.parse::<T>().map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match result {
+        Ok(puzzle) => Box::into_raw(Box::new(puzzle)),
+        Err(e) => {
+            if !error.is_null() {
+                *error = into_c_string(e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// 虫食算を文字列から作る
+///
+/// 読めなければ NULL を返し、`error` が NULL でなければエラーメッセージを書き込む。
+///
+/// # Safety
+///
+/// `text` は NUL 終端の文字列を指すこと。`error` は NULL か書き込める場所を指すこと。
+#[cfg(feature = "mushikui")]
+#[no_mangle]
+pub unsafe extern "C" fn mushikui_new(
+    text: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut Mushikui {
+    parse(text, error)
+}
+
+/// 虫食算の解を空行区切りの文字列で返す
+///
+/// `max_solutions` が 0 ならすべての解を返す。
+///
+/// # Safety
+///
+/// `mushikui` は [`mushikui_new`] が返した有効なハンドルであること。
+#[cfg(feature = "mushikui")]
+#[no_mangle]
+pub unsafe extern "C" fn mushikui_solve(
+    mushikui: *const Mushikui,
+    max_solutions: usize,
+) -> *mut c_char {
+    let options = mushikui::SolveOptions {
+        max_solutions: if max_solutions == 0 {
+            None
+        } else {
+            Some(max_solutions)
+        },
+        ..Default::default()
+    };
+    let solutions = (*mushikui)
+        .solve_with_options(&options)
+        .into_solutions()
+        .iter()
+        .map(|solution| solution.to_string())
+        .collect::<Vec<_>>();
+    into_c_string(solutions.join("\n\n"))
+}
+
+/// 虫食算のハンドルを解放する
+///
+/// # Safety
+///
+/// `mushikui` は NULL か [`mushikui_new`] が返したまだ解放していないハンドルであること。
+#[cfg(feature = "mushikui")]
+#[no_mangle]
+pub unsafe extern "C" fn mushikui_free(mushikui: *mut Mushikui) {
+    if !mushikui.is_null() {
+        drop(Box::from_raw(mushikui));
+    }
+}
+
+/// 15 パズルの盤面を空白区切りの 16 個の数 (`0` は空きマス) から作る
+///
+/// 読めなければ NULL を返し、`error` が NULL でなければエラーメッセージを書き込む。
+///
+/// # Safety
+///
+/// `text` は NUL 終端の文字列を指すこと。`error` は NULL か書き込める場所を指すこと。
+#[cfg(feature = "fifteen-puzzle")]
+#[no_mangle]
+pub unsafe extern "C" fn fifteen_puzzle_new(
+    text: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut Board {
+    parse(text, error)
+}
+
+/// 15 パズルを解いて、完成までの各盤面を空行区切りの文字列で返す
+///
+/// 解けなければ NULL を返す。
+///
+/// # Safety
+///
+/// `board` は [`fifteen_puzzle_new`] が返した有効なハンドルであること。
+#[cfg(feature = "fifteen-puzzle")]
+#[no_mangle]
+pub unsafe extern "C" fn fifteen_puzzle_solve(board: *const Board) -> *mut c_char {
+    match fifteen_puzzle::solve(&mut (*board).clone()) {
+        Some(boards) => {
+            let boards = boards.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+            into_c_string(boards.join("\n\n"))
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+/// 15 パズルのハンドルを解放する
+///
+/// # Safety
+///
+/// `board` は NULL か [`fifteen_puzzle_new`] が返したまだ解放していないハンドルであること。
+#[cfg(feature = "fifteen-puzzle")]
+#[no_mangle]
+pub unsafe extern "C" fn fifteen_puzzle_free(board: *mut Board) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// この API が返した文字列を解放する
+///
+/// # Safety
+///
+/// `s` は NULL かこの API が返したまだ解放していない文字列であること。
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use book_puzzle_algorithm_fuzz::BoardRows;
+use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: BoardRows| {
+    let board = match Board::try_new(&input.0) {
+        Ok(board) => board,
+        Err(_) => return,
+    };
+    let reparsed = board
+        .to_string()
+        .parse::<Board>()
+        .expect("a displayed board must parse");
+    assert_eq!(board, reparsed);
+});
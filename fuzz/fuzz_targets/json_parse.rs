@@ -0,0 +1,27 @@
+#![no_main]
+
+use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+use book_puzzle_algorithm_rs::json::{self, Value};
+use libfuzzer_sys::fuzz_target;
+
+// `1e999` のような数は無限大として読まれ、書き出すと読み直せない
+fn is_finite(value: &Value) -> bool {
+    match value {
+        Value::Number(x) => x.is_finite(),
+        Value::Array(values) => values.iter().all(is_finite),
+        Value::Object(fields) => fields.iter().all(|(_, v)| is_finite(v)),
+        _ => true,
+    }
+}
+
+fuzz_target!(|input: &str| {
+    let _ = Board::from_json(input);
+    let value = match json::parse(input) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    if is_finite(&value) {
+        let reparsed = json::parse(&value.to_string()).expect("a displayed value must parse");
+        assert_eq!(value, reparsed);
+    }
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use book_puzzle_algorithm_fuzz::MushikuiText;
+use book_puzzle_algorithm_rs::mushikui::Mushikui;
+use libfuzzer_sys::fuzz_target;
+
+// 探索の解と、すべての組を試した解が一致する
+fuzz_target!(|input: MushikuiText| {
+    let mushikui = match input.0.parse::<Mushikui>() {
+        Ok(mushikui) => mushikui,
+        Err(_) => return,
+    };
+    let mut expected = match mushikui.solve_bruteforce() {
+        Some(solutions) => solutions
+            .iter()
+            .map(|solution| solution.to_string())
+            .collect::<Vec<_>>(),
+        None => return,
+    };
+    let mut actual = mushikui
+        .solve()
+        .iter()
+        .map(|solution| solution.to_string())
+        .collect::<Vec<_>>();
+    expected.sort();
+    actual.sort();
+    assert_eq!(actual, expected);
+});
@@ -0,0 +1,22 @@
+#![no_main]
+
+use std::time::{Duration, Instant};
+
+use book_puzzle_algorithm_fuzz::MushikuiText;
+use book_puzzle_algorithm_rs::mushikui::{Mushikui, SolveOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: MushikuiText| {
+    let mushikui = match input.0.parse::<Mushikui>() {
+        Ok(mushikui) => mushikui,
+        Err(_) => return,
+    };
+    let options = SolveOptions {
+        max_solutions: Some(16),
+        deadline: Some(Instant::now() + Duration::from_millis(100)),
+        ..Default::default()
+    };
+    for solution in mushikui.solve_with_options(&options).into_solutions() {
+        assert_eq!(mushikui.verify(&solution), Ok(()));
+    }
+});
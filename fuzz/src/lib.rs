@@ -0,0 +1,54 @@
+//! ファジング用の入力
+//!
+//! 完全にランダムなバイト列ではほとんど行の形にならないので、
+//! 虫食算に現れそうな文字を多めに使って入力を作る。
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// 虫食算のテキスト
+#[derive(Debug)]
+pub struct MushikuiText(pub String);
+
+impl<'a> Arbitrary<'a> for MushikuiText {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        const CHARS: &[char] = &[
+            '*', '*', '*', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ' ', 'x', '?',
+        ];
+        let rows = u.int_in_range(0..=8)?;
+        let mut text = String::new();
+        for _ in 0..rows {
+            if u.ratio(1, 8)? {
+                text.push_str("----\n");
+            }
+            let len = u.int_in_range(0..=6)?;
+            for _ in 0..len {
+                text.push(*u.choose(CHARS)?);
+            }
+            text.push('\n');
+        }
+        Ok(MushikuiText(text))
+    }
+}
+
+/// 15 パズルの盤面 (行数, 列数, 値がでたらめなものも含む)
+#[derive(Debug)]
+pub struct BoardRows(pub Vec<Vec<u8>>);
+
+impl<'a> Arbitrary<'a> for BoardRows {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.ratio(3, 4)? {
+            // ほとんどの場合は 0..16 の並べ替え
+            let mut values = (0..16).collect::<Vec<u8>>();
+            for i in (1..values.len()).rev() {
+                let j = u.int_in_range(0..=i)?;
+                values.swap(i, j);
+            }
+            if u.ratio(1, 4)? {
+                let i = u.int_in_range(0..=15)?;
+                values[i] = u.arbitrary()?;
+            }
+            return Ok(BoardRows(values.chunks(4).map(|row| row.to_vec()).collect()));
+        }
+        Ok(BoardRows(u.arbitrary()?))
+    }
+}
@@ -0,0 +1,232 @@
+//! 汎用の A*
+
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::ida_star::{Cost, SearchState};
+use crate::puzzle::{CancelToken, Instant, Monitor, OnProgress, SearchStats, Termination};
+
+/// 探索の設定
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// 手数の上限
+    pub max_depth: usize,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+    /// 下界にかける重み (1 以上 [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) 以下)
+    ///
+    /// 1 より大きいと評価値を `手数 + weight * 下界` にする (weighted A*)。
+    /// 見つかる手順は最短手順の `weight` 倍以内の手数になる。
+    pub weight: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            deadline: None,
+            cancel: None,
+            on_progress: None,
+            weight: 1.0,
+        }
+    }
+}
+
+// 展開を待つ状態 (評価値の小さい順, 同じなら手数の多い順に取り出す)
+struct Open<S> {
+    f: usize,
+    g: usize,
+    node: usize,
+    state: S,
+}
+
+impl<S> PartialEq for Open<S> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.f, self.g, self.node) == (other.f, other.g, other.node)
+    }
+}
+
+impl<S> Eq for Open<S> {}
+
+impl<S> PartialOrd for Open<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for Open<S> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        (Reverse(self.f), self.g, Reverse(self.node)).cmp(&(
+            Reverse(other.f),
+            other.g,
+            Reverse(other.node),
+        ))
+    }
+}
+
+/// `start` からゴールまでの最短手順を A* で探す
+///
+/// 訪れた状態を `key` で見分け、それぞれへの最短の手数を覚えておく。
+/// IDA* と違って同じ状態を何度も展開しないが、状態の数に比例したメモリを使う。
+/// 見つかった最短手順 (手の列) を返す。`state.heuristic()` は手数の下界であること。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::astar::{astar, Options};
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+///
+/// let board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// let (path, _, _) = astar(&board, |b| b.packed(), &Options::default());
+/// assert_eq!(path.unwrap().len(), 31);
+/// ```
+///
+/// # Panics
+///
+/// `options.weight` が 1 未満か [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) より大きいとき
+pub fn astar<S, K>(
+    start: &S,
+    key: impl Fn(&S) -> K,
+    options: &Options,
+) -> (Option<Vec<S::Move>>, Termination, SearchStats)
+where
+    S: SearchState + Clone,
+    K: Ord,
+{
+    let cost = Cost::new(options.weight, options.max_depth);
+    let mut monitor = Monitor::new(
+        options.deadline,
+        options.cancel.clone(),
+        options.on_progress.clone(),
+    );
+    // (親, 親からの手)
+    let mut nodes: Vec<(usize, Option<S::Move>)> = vec![(0, None)];
+    let mut best = BTreeMap::new();
+    best.insert(key(start), 0);
+    let mut open = BinaryHeap::new();
+    if let Some(f) = cost.f(0, start.heuristic()) {
+        open.push(Open {
+            f,
+            g: 0,
+            node: 0,
+            state: start.clone(),
+        });
+    }
+    let mut found = None;
+    while let Some(Open { g, node, state, .. }) = open.pop() {
+        if monitor.is_interrupted() {
+            break;
+        }
+        if best.get(&key(&state)).map_or(false, |&b| b < g) {
+            continue;
+        }
+        monitor.visit(g, Some(state.heuristic()));
+        if state.is_goal() {
+            found = Some(node);
+            break;
+        }
+        let prev = nodes[node].1;
+        for mv in state.moves() {
+            if prev.map_or(false, |prev| S::is_redundant(prev, mv)) {
+                continue;
+            }
+            let mut next = state.clone();
+            if !next.apply(mv) {
+                continue;
+            }
+            let f = match cost.f(g + 1, next.heuristic()) {
+                Some(f) => f,
+                None => {
+                    monitor.prune();
+                    continue;
+                }
+            };
+            let k = key(&next);
+            if best.get(&k).map_or(false, |&b| b <= g + 1) {
+                monitor.prune();
+                continue;
+            }
+            best.insert(k, g + 1);
+            nodes.push((node, Some(mv)));
+            open.push(Open {
+                f,
+                g: g + 1,
+                node: nodes.len() - 1,
+                state: next,
+            });
+        }
+    }
+    let path = found.map(|mut node| {
+        let mut path = Vec::new();
+        while let (parent, Some(mv)) = nodes[node] {
+            path.push(mv);
+            node = parent;
+        }
+        path.reverse();
+        path
+    });
+    let (termination, stats) = monitor.finish(false);
+    (path, termination, stats)
+}
+
+#[cfg(all(test, feature = "fifteen-puzzle"))]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::{Board, DistanceTable};
+    use crate::rng::Rng;
+
+    // 最短手数が 31 の 8 パズル
+    const HARDEST: &str = "8 6 7 2 5 4 3 0 1";
+
+    #[test]
+    fn finds_the_optimal_length_of_random_boards() {
+        let table = DistanceTable::new(&Board::solved(3, 3)).unwrap();
+        let mut rng = Rng::new(1);
+        for _ in 0..50 {
+            let board = Board::random(3, 3, &mut rng);
+            let (path, termination, _) = astar(&board, Board::packed, &Options::default());
+            assert_eq!(termination, Termination::Complete);
+            let mut solved = board.clone();
+            let path = path.unwrap();
+            for &dir in &path {
+                assert!(solved.apply(dir));
+            }
+            assert!(solved.is_solved());
+            assert_eq!(Some(path.len() as u32), table.distance(&board), "{}", board);
+        }
+    }
+
+    #[test]
+    fn gives_up_beyond_max_depth() {
+        let board: Board = HARDEST.parse().unwrap();
+        for (max_depth, len) in [(30, None), (31, Some(31))] {
+            let options = Options {
+                max_depth,
+                ..Options::default()
+            };
+            let (path, termination, _) = astar(&board, Board::packed, &options);
+            assert_eq!(termination, Termination::Complete);
+            assert_eq!(path.map(|path| path.len()), len);
+        }
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let board: Board = HARDEST.parse().unwrap();
+        let cancel = CancelToken::new();
+        let options = Options {
+            cancel: Some(cancel.clone()),
+            // 取り消しは 1024 ノードごとにしか確かめないので、その間に取り消す
+            on_progress: Some(OnProgress::new(1000, move |_| cancel.cancel())),
+            ..Options::default()
+        };
+        let (path, termination, stats) = astar(&board, Board::packed, &options);
+        assert_eq!(termination, Termination::TimedOut);
+        assert_eq!(path, None);
+        assert_eq!(stats.nodes_expanded, 1024);
+    }
+}
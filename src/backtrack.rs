@@ -0,0 +1,699 @@
+//! 汎用のバックトラック (有限領域の制約充足)
+//!
+//! 変数ごとに取りうる値の集合 ([`Domain`]) を持ち、[`Propagator`] で領域を狭めながら
+//! [`Brancher`] が選んだ変数に値を 1 つずつ割り当てていく。
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use crate::puzzle::{
+    CancelToken, Instant, Monitor, OnProgress, SearchStats, SolveOutcome, Termination,
+};
+
+/// 変数
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Var(usize);
+
+impl Var {
+    /// 何番目に作った変数か
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// 値の集合 (`0` 以上 `32` 未満)
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Domain(u32);
+
+impl Domain {
+    /// 空集合
+    pub const EMPTY: Domain = Domain(0);
+
+    /// `lo` 以上 `hi` 以下
+    pub fn range(lo: u8, hi: u8) -> Self {
+        assert!(hi < 32);
+        if lo > hi {
+            return Self::EMPTY;
+        }
+        Domain((u32::MAX >> (31 - hi)) & (u32::MAX << lo))
+    }
+
+    /// `v` だけ
+    pub fn singleton(v: u8) -> Self {
+        assert!(v < 32);
+        Domain(1 << v)
+    }
+
+    /// `v` を含むか
+    pub fn contains(self, v: u8) -> bool {
+        v < 32 && self.0 >> v & 1 == 1
+    }
+
+    /// `v` を除いた集合
+    pub fn without(self, v: u8) -> Self {
+        if v < 32 {
+            Domain(self.0 & !(1 << v))
+        } else {
+            self
+        }
+    }
+
+    /// `v` を加えた集合
+    pub fn with(self, v: u8) -> Self {
+        Domain(self.0 | Domain::singleton(v).0)
+    }
+
+    /// 共通部分
+    pub fn intersect(self, other: Self) -> Self {
+        Domain(self.0 & other.0)
+    }
+
+    /// 要素数
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// 空か
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// ただ 1 つの要素
+    pub fn value(self) -> Option<u8> {
+        if self.len() == 1 {
+            Some(self.0.trailing_zeros() as u8)
+        } else {
+            None
+        }
+    }
+
+    /// 要素を小さい順に
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        (0..32).filter(move |&v| self.contains(v))
+    }
+}
+
+impl FromIterator<u8> for Domain {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        iter.into_iter().fold(Domain::EMPTY, Domain::with)
+    }
+}
+
+/// 矛盾
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Conflict;
+
+/// 変数の値が決まった理由
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Reason {
+    /// [`Problem::add_propagator`] で加えた `index` 番目 (0 から) の制約
+    Propagator(usize),
+    /// [`Problem::add_preprocessor`] で加えた `index` 番目 (0 から) の制約
+    Preprocessor(usize),
+    /// 探索で仮定した
+    Guess,
+}
+
+/// 値が決まった 1 つの変数
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Deduction {
+    /// 変数
+    pub var: Var,
+    /// 決まった値
+    pub value: u8,
+    /// 決まる直前の領域
+    pub candidates: Domain,
+    /// 決まった理由
+    pub reason: Reason,
+}
+
+/// 各変数の領域
+#[derive(Debug, Clone)]
+pub struct Store {
+    domains: Vec<Domain>,
+    changed: Vec<Var>,
+    // 領域を狭めた変数と狭める前の領域 (探索で戻すときに使う)
+    trail: Vec<(Var, Domain)>,
+    // 値が決まった変数を記録するなら `Some`
+    log: Option<Vec<Deduction>>,
+    reason: Reason,
+}
+
+impl Store {
+    /// 変数 `var` の領域
+    pub fn domain(&self, var: Var) -> Domain {
+        self.domains[var.0]
+    }
+
+    /// 変数 `var` の値 (決まっていなければ `None`)
+    pub fn value(&self, var: Var) -> Option<u8> {
+        self.domains[var.0].value()
+    }
+
+    /// 変数 `var` の値が決まっているか
+    pub fn is_assigned(&self, var: Var) -> bool {
+        self.value(var).is_some()
+    }
+
+    /// 変数 `var` の領域を `domain` との共通部分に狭める。空になれば矛盾
+    pub fn restrict(&mut self, var: Var, domain: Domain) -> Result<(), Conflict> {
+        let old = self.domains[var.0];
+        let new = old.intersect(domain);
+        if new.is_empty() {
+            return Err(Conflict);
+        }
+        if new != old {
+            self.domains[var.0] = new;
+            self.changed.push(var);
+            self.trail.push((var, old));
+            if let (Some(log), Some(value)) = (&mut self.log, new.value()) {
+                log.push(Deduction {
+                    var,
+                    value,
+                    candidates: old,
+                    reason: self.reason,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 変数 `var` の値を `v` に決める
+    pub fn assign(&mut self, var: Var, v: u8) -> Result<(), Conflict> {
+        self.restrict(var, Domain::singleton(v))
+    }
+
+    /// 変数 `var` の領域から `v` を除く
+    pub fn remove(&mut self, var: Var, v: u8) -> Result<(), Conflict> {
+        self.restrict(var, self.domains[var.0].without(v))
+    }
+
+    // 今の状態に戻るための印
+    fn mark(&self) -> (usize, usize) {
+        (self.trail.len(), self.log.as_ref().map_or(0, Vec::len))
+    }
+
+    // `mark` の時点まで領域を戻す
+    fn undo(&mut self, (trail, log): (usize, usize)) {
+        for (var, old) in self.trail.drain(trail..).rev() {
+            self.domains[var.0] = old;
+        }
+        self.changed.clear();
+        if let Some(deductions) = &mut self.log {
+            deductions.truncate(log);
+        }
+    }
+}
+
+/// 領域を狭める制約
+pub trait Propagator {
+    /// 領域が変わったときに [`Propagator::propagate`] を呼んでほしい変数
+    fn watches(&self) -> Vec<Var>;
+
+    /// 領域を狭める。矛盾が見つかれば `Err`
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict>;
+}
+
+/// 分岐の戦略
+pub trait Brancher {
+    /// 次に値を決める変数
+    ///
+    /// `None` を返すと、値の決まっていない最初の変数で分岐する。
+    fn select(&self, store: &Store) -> Option<Var>;
+}
+
+/// 値の決まっていない最初の変数で分岐する
+#[derive(Debug, Copy, Clone, Default)]
+pub struct InOrder;
+
+impl Brancher for InOrder {
+    fn select(&self, _store: &Store) -> Option<Var> {
+        None
+    }
+}
+
+/// 並べた順に、値の決まっていない最初の変数で分岐する
+#[derive(Debug, Clone, Default)]
+pub struct Sequence(pub Vec<Var>);
+
+impl Brancher for Sequence {
+    fn select(&self, store: &Store) -> Option<Var> {
+        self.0.iter().copied().find(|&v| !store.is_assigned(v))
+    }
+}
+
+/// 探索の設定
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// 見つける解の個数の上限 (`None` なら全部)
+    pub max_solutions: Option<usize>,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+}
+
+/// 制約充足問題
+#[derive(Default)]
+pub struct Problem {
+    domains: Vec<Domain>,
+    propagators: Vec<Box<dyn Propagator>>,
+    watchers: Vec<Vec<usize>>,
+    preprocessors: Vec<Box<dyn Propagator>>,
+}
+
+struct Search<'a> {
+    max_solutions: Option<usize>,
+    monitor: Monitor,
+    found: usize,
+    stopped: bool,
+    on_solution: &'a mut dyn FnMut(&[u8]) -> ControlFlow<()>,
+}
+
+impl Search<'_> {
+    fn should_stop(&self) -> bool {
+        self.max_solutions.map_or(false, |max| self.found >= max)
+            || self.stopped
+            || self.monitor.is_interrupted()
+    }
+}
+
+impl Problem {
+    /// 変数のない問題
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 領域が `domain` の変数を作る
+    pub fn new_var(&mut self, domain: Domain) -> Var {
+        self.domains.push(domain);
+        self.watchers.push(Vec::new());
+        Var(self.domains.len() - 1)
+    }
+
+    /// 変数 `var` の領域を `domain` との共通部分に狭める
+    pub fn restrict(&mut self, var: Var, domain: Domain) {
+        self.domains[var.0] = self.domains[var.0].intersect(domain);
+    }
+
+    /// 変数の個数
+    pub fn num_vars(&self) -> usize {
+        self.domains.len()
+    }
+
+    /// 加えた制約の個数 (探索の前にだけ使う制約は数えない)
+    pub fn num_propagators(&self) -> usize {
+        self.propagators.len()
+    }
+
+    /// 制約を加える
+    pub fn add_propagator(&mut self, propagator: impl Propagator + 'static) {
+        let index = self.propagators.len();
+        for var in propagator.watches() {
+            if !self.watchers[var.0].contains(&index) {
+                self.watchers[var.0].push(index);
+            }
+        }
+        self.propagators.push(Box::new(propagator));
+    }
+
+    /// 探索を始める前にだけ使う制約を加える
+    ///
+    /// 重いが強く領域を狭める制約に使う。探索の各ノードでは呼ばない。
+    pub fn add_preprocessor(&mut self, propagator: impl Propagator + 'static) {
+        self.preprocessors.push(Box::new(propagator));
+    }
+
+    /// 探索の前に、すべての制約で領域が変わらなくなるまで狭めた各変数の領域
+    ///
+    /// 矛盾が見つかれば `Err`
+    pub fn preprocess(&self) -> Result<Vec<Domain>, Conflict> {
+        let mut store = self.store(false);
+        self.narrow(&mut store)?;
+        Ok(store.domains)
+    }
+
+    /// 狭めた領域 `domains` で変数 `var` の値を `v` に決め、領域が変わらなくなるまで狭め直す
+    ///
+    /// `domains` は [`Problem::preprocess`] やこの関数が返したもの。値の変わった変数を見ている制約だけを
+    /// 呼び直すので、初めから狭め直すより速い。矛盾が見つかれば `Err`
+    pub fn assign(&self, domains: &[Domain], var: Var, v: u8) -> Result<Vec<Domain>, Conflict> {
+        let mut store = self.store(false);
+        store.domains = domains.to_vec();
+        store.assign(var, v)?;
+        self.propagate(&mut store, false)?;
+        Ok(store.domains)
+    }
+
+    /// 最初の解にたどり着くまでに値が決まった順の変数と、その理由
+    ///
+    /// 初めから値が決まっている変数は含まない。途中で矛盾した仮定は含まない。解がなければ `None` を返す。
+    pub fn explain(&self, brancher: &dyn Brancher) -> Option<Vec<Deduction>> {
+        // 解にたどり着けば `store` をそのままにして `true`
+        fn dfs(problem: &Problem, store: &mut Store, brancher: &dyn Brancher) -> bool {
+            if problem.propagate(store, false).is_err() {
+                return false;
+            }
+            let var = match problem.select(store, brancher) {
+                Some(var) => var,
+                None => return true,
+            };
+            for v in store.domain(var).iter() {
+                let mark = store.mark();
+                store.reason = Reason::Guess;
+                store.assign(var, v).unwrap();
+                if dfs(problem, store, brancher) {
+                    return true;
+                }
+                store.undo(mark);
+            }
+            false
+        }
+        let mut store = self.store(true);
+        self.narrow(&mut store).ok()?;
+        if dfs(self, &mut store, brancher) {
+            store.log
+        } else {
+            None
+        }
+    }
+
+    fn store(&self, log: bool) -> Store {
+        Store {
+            domains: self.domains.clone(),
+            changed: Vec::new(),
+            trail: Vec::new(),
+            log: if log { Some(Vec::new()) } else { None },
+            reason: Reason::Guess,
+        }
+    }
+
+    // 次に値を決める変数 (すべて決まっていれば `None`)
+    fn select(&self, store: &Store, brancher: &dyn Brancher) -> Option<Var> {
+        brancher.select(store).or_else(|| {
+            (0..store.domains.len())
+                .map(Var)
+                .find(|&v| !store.is_assigned(v))
+        })
+    }
+
+    // 探索の前の絞り込み
+    fn narrow(&self, store: &mut Store) -> Result<(), Conflict> {
+        if store.domains.iter().any(|d| d.is_empty()) {
+            return Err(Conflict);
+        }
+        self.propagate(store, true)?;
+        loop {
+            let before = store.domains.clone();
+            for (i, p) in self.preprocessors.iter().enumerate() {
+                store.reason = Reason::Preprocessor(i);
+                p.propagate(store)?;
+            }
+            self.propagate(store, false)?;
+            if store.domains == before {
+                return Ok(());
+            }
+        }
+    }
+
+    // 領域が変わらなくなるまで制約を適用する
+    fn propagate(&self, store: &mut Store, all: bool) -> Result<(), Conflict> {
+        let mut queued = vec![all; self.propagators.len()];
+        let mut queue = if all {
+            (0..self.propagators.len()).collect()
+        } else {
+            VecDeque::new()
+        };
+        loop {
+            for var in store.changed.drain(..) {
+                for &p in &self.watchers[var.0] {
+                    if !queued[p] {
+                        queued[p] = true;
+                        queue.push_back(p);
+                    }
+                }
+            }
+            match queue.pop_front() {
+                Some(p) => {
+                    queued[p] = false;
+                    store.reason = Reason::Propagator(p);
+                    self.propagators[p].propagate(store)?;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn dfs(&self, depth: usize, store: &mut Store, brancher: &dyn Brancher, search: &mut Search) {
+        if search.should_stop() {
+            return;
+        }
+        search.monitor.visit(depth, None);
+        if self.propagate(store, false).is_err() {
+            search.monitor.prune();
+            return;
+        }
+        let var = match self.select(store, brancher) {
+            Some(var) => var,
+            None => {
+                let values = store
+                    .domains
+                    .iter()
+                    .map(|d| d.value().unwrap())
+                    .collect::<Vec<_>>();
+                search.found += 1;
+                search.stopped = (search.on_solution)(&values).is_break();
+                return;
+            }
+        };
+        search.monitor.guess();
+        for v in store.domain(var).iter() {
+            let mark = store.mark();
+            store.assign(var, v).unwrap();
+            self.dfs(depth + 1, store, brancher, search);
+            store.undo(mark);
+            if search.should_stop() {
+                return;
+            }
+        }
+    }
+
+    /// 解を探す
+    ///
+    /// 解は各変数の値を変数を作った順に並べたもの。
+    pub fn solve(
+        &self,
+        brancher: &dyn Brancher,
+        options: &Options,
+    ) -> (SolveOutcome<Vec<u8>>, SearchStats) {
+        let mut solutions = Vec::new();
+        let (termination, stats) = self.solve_with(brancher, options, |values| {
+            solutions.push(values.to_vec());
+            ControlFlow::Continue(())
+        });
+        (SolveOutcome::new(termination, solutions), stats)
+    }
+
+    /// 解を探し、見つけるたびに `on_solution` を呼ぶ
+    ///
+    /// `on_solution` が [`ControlFlow::Break`] を返すと探索をやめる。
+    pub fn solve_with(
+        &self,
+        brancher: &dyn Brancher,
+        options: &Options,
+        mut on_solution: impl FnMut(&[u8]) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        let mut store = self.store(false);
+        let store = self.narrow(&mut store).map(|()| store);
+        self.search(store, brancher, options, &mut on_solution)
+    }
+
+    /// 狭めた領域 `domains` から解を探し、見つけるたびに `on_solution` を呼ぶ
+    ///
+    /// `domains` は [`Problem::preprocess`] や [`Problem::assign`] が返したもの。探索の前の絞り込みはしない。
+    pub fn solve_from(
+        &self,
+        domains: &[Domain],
+        brancher: &dyn Brancher,
+        options: &Options,
+        mut on_solution: impl FnMut(&[u8]) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        let mut store = self.store(false);
+        store.domains = domains.to_vec();
+        let store = if store.domains.iter().any(|d| d.is_empty()) {
+            Err(Conflict)
+        } else {
+            Ok(store)
+        };
+        self.search(store, brancher, options, &mut on_solution)
+    }
+
+    // 絞り込んだ `store` から探索する (絞り込みで矛盾していれば解なし)
+    fn search(
+        &self,
+        store: Result<Store, Conflict>,
+        brancher: &dyn Brancher,
+        options: &Options,
+        on_solution: &mut dyn FnMut(&[u8]) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        let mut search = Search {
+            max_solutions: options.max_solutions,
+            monitor: Monitor::new(
+                options.deadline,
+                options.cancel.clone(),
+                options.on_progress.clone(),
+            ),
+            found: 0,
+            stopped: false,
+            on_solution,
+        };
+        if let Ok(mut store) = store {
+            self.dfs(0, &mut store, brancher, &mut search);
+        }
+        let stopped = search.stopped;
+        search.monitor.finish(stopped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `a < b`
+    struct Less(Var, Var);
+
+    impl Propagator for Less {
+        fn watches(&self) -> Vec<Var> {
+            vec![self.0, self.1]
+        }
+
+        fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+            let max = store.domain(self.1).iter().last().ok_or(Conflict)?;
+            if max == 0 {
+                return Err(Conflict);
+            }
+            store.restrict(self.0, Domain::range(0, max - 1))?;
+            let min = store.domain(self.0).iter().next().ok_or(Conflict)?;
+            store.restrict(self.1, Domain::range(min + 1, 31))
+        }
+    }
+
+    // 領域が `domain` の変数を `n` 個作り、前の変数より後の変数が大きいという制約を後ろから加える
+    fn chain(n: usize, domain: Domain) -> (Problem, Vec<Var>) {
+        let mut problem = Problem::new();
+        let vars = (0..n).map(|_| problem.new_var(domain)).collect::<Vec<_>>();
+        for w in vars.windows(2).rev() {
+            problem.add_propagator(Less(w[0], w[1]));
+        }
+        (problem, vars)
+    }
+
+    fn all_solutions(problem: &Problem) -> Vec<Vec<u8>> {
+        let options = Options {
+            max_solutions: None,
+            ..Options::default()
+        };
+        let (outcome, _) = problem.solve(&InOrder, &options);
+        assert!(!outcome.is_timed_out());
+        outcome.into_solutions()
+    }
+
+    #[test]
+    fn propagation_reaches_a_fixpoint() {
+        // 一度ずつ制約を呼ぶだけでは決まらず、変わった変数を見ている制約を呼び直して決まる
+        let (problem, _) = chain(4, Domain::range(0, 3));
+        let domains = problem.preprocess().unwrap();
+        assert_eq!(domains, (0..4).map(Domain::singleton).collect::<Vec<_>>());
+
+        let (problem, _) = chain(3, Domain::range(0, 3));
+        let domains = problem.preprocess().unwrap();
+        assert_eq!(
+            domains,
+            [
+                Domain::range(0, 1),
+                Domain::range(1, 2),
+                Domain::range(2, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_domain_is_a_conflict() {
+        let (mut problem, vars) = chain(2, Domain::range(0, 3));
+        problem.restrict(vars[0], Domain::singleton(3));
+        assert_eq!(problem.preprocess(), Err(Conflict));
+        assert!(all_solutions(&problem).is_empty());
+
+        let (mut problem, vars) = chain(2, Domain::range(0, 3));
+        problem.restrict(vars[1], Domain::EMPTY);
+        assert_eq!(problem.preprocess(), Err(Conflict));
+        assert!(all_solutions(&problem).is_empty());
+
+        let (problem, vars) = chain(2, Domain::range(0, 3));
+        let domains = problem.preprocess().unwrap();
+        assert_eq!(problem.assign(&domains, vars[1], 0), Err(Conflict));
+    }
+
+    #[test]
+    fn enumerates_every_solution_in_order() {
+        let (problem, _) = chain(3, Domain::range(0, 3));
+        assert_eq!(
+            all_solutions(&problem),
+            [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]]
+        );
+
+        let options = Options {
+            max_solutions: Some(3),
+            ..Options::default()
+        };
+        let (outcome, _) = problem.solve(&InOrder, &options);
+        assert_eq!(outcome.solutions(), [[0, 1, 2], [0, 1, 3], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn undo_restores_domains_after_a_failed_branch() {
+        let (problem, vars) = chain(3, Domain::range(0, 3));
+        let mut store = problem.store(false);
+        problem.narrow(&mut store).unwrap();
+        let root = store.domains.clone();
+        let root_mark = store.mark();
+
+        // y = 1 にすると x = 0 に決まり、z は 2 か 3
+        store.assign(vars[1], 1).unwrap();
+        problem.propagate(&mut store, false).unwrap();
+        let branch = store.domains.clone();
+        assert_eq!(
+            branch,
+            [
+                Domain::singleton(0),
+                Domain::singleton(1),
+                Domain::range(2, 3)
+            ]
+        );
+
+        // その下で z から 2 と 3 を除こうとして失敗する枝
+        let mark = store.mark();
+        store.remove(vars[2], 2).unwrap();
+        assert_eq!(store.remove(vars[2], 3), Err(Conflict));
+        store.undo(mark);
+        assert_eq!(store.domains, branch);
+        assert_eq!(store.mark(), mark);
+        assert!(store.changed.is_empty());
+
+        // x = 1, z = 2 にすると y に入る値がなくなる
+        store.undo(root_mark);
+        assert_eq!(store.domains, root);
+        store.assign(vars[0], 1).unwrap();
+        store.assign(vars[2], 2).unwrap();
+        assert_eq!(problem.propagate(&mut store, false), Err(Conflict));
+        store.undo(root_mark);
+        assert_eq!(store.domains, root);
+        assert_eq!(store.mark(), root_mark);
+        assert!(store.changed.is_empty());
+    }
+}
@@ -1,217 +1,1829 @@
-use std::fmt;
-use std::fmt::Formatter;
+use std::collections::HashMap;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::ops::ControlFlow;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, thread};
 
-const N: usize = 4;
+use book_puzzle_algorithm_rs::fifteen_puzzle::{
+    count_solutions_with, hint, moves, render_gif, render_svg, solve_partial, solve_pattern,
+    solve_with, solve_with_options, solve_with_stats, tile_moves, Algorithm, Board, BoardError,
+    Dir, DistanceTable, Heuristic, Metric, PatternDatabase, PdbBuilder, SolveOptions,
+};
+use book_puzzle_algorithm_rs::ida_star::{MoveOrder, OnIteration, SearchState, MAX_WEIGHT};
+use book_puzzle_algorithm_rs::json::Value;
+use book_puzzle_algorithm_rs::puzzle::Termination;
+use book_puzzle_algorithm_rs::rng::Rng;
 
-#[derive(Debug, Copy, Clone)]
-struct Value(u8);
+const USAGE: &str = "usage: 15-puzzle [solve] [--all | --count] [--algorithm ida-star|a-star|sma-star|bidirectional|greedy] [--metric blank|tile] [--max-states <N>] [--table-mb <M>] [--cycle-window <K>] [--move-order fixed|heuristic] [--threads <T>] [--weight <W>] [--heuristic misplaced|manhattan|linear-conflict|walking-distance|pdb] [--pdb <file>] [--goal <file>] [--partial <t1,t2,...>] [--pattern <file>] [--time-limit <ms>] [--verbose] [--animate [<ms>]] [--export svg|gif <path>] [--format boards|moves|json] [<file> | --input <file> | - | --example <name> | --batch <file> [--csv <file>]]
+       15-puzzle demo [<name>] [solve options]
+       15-puzzle demo --list
+       15-puzzle hint [solve options] [<file> | --input <file> | -]
+       15-puzzle generate [--size <H>x<W>] [--optimal-depth <K> | --moves <N>] [--seed <N>]
+       15-puzzle build-pdb [--size <H>x<W>] --output <file>
+       15-puzzle table [--size <H>x<W>] [--goal <file>] [--heuristic <name>] [<file> | -]
+       15-puzzle play [--size <H>x<W>] [--seed <N>] [<file> | --input <file> | --example <name>]
+       15-puzzle verify --moves <RULD...> [--optimal] [<file> | --input <file> | - | --example <name>]
+       15-puzzle bench [--size <H>x<W>] [--count <N>] [--optimal-depth <K>] [--seed <N>] [solve options]
+       15-puzzle dataset [--size <H>x<W>] [--count <N>] [--moves <N>] [--seed <N>] [--output <file>] [solve options]
+       15-puzzle --help
 
-impl Value {
-    // (si, sj) から正しい位置までのマンハッタン距離
-    fn move_cost(self, (si, sj): (usize, usize)) -> u32 {
-        assert!(self.0 > 0);
-        let (ti, tj) = (usize::from(self.0 - 1) / N, usize::from(self.0 - 1) % N);
-        ((si.max(ti) - si.min(ti)) + (sj.max(tj) - sj.min(tj))) as u32
+Reads a board of numbers separated by whitespace (0 for the blank) from the file, or from stdin with `-`.
+Write one row per line for any rectangular board, or k*k numbers on one line for a k x k board.
+A board can also be a JSON array of rows (or of k*k numbers), or an object with a `board` key like the output
+of `--format json`; `--batch` reads one such JSON board per line (JSON Lines, e.g. the output of `dataset`).
+Without an input, solves the example in the book.
+`demo <name>` (or `--example <name>`) solves a named example: `book`, or the 24-puzzle benchmarks `korf24-1` and `korf24-2`
+(optimal in 95 and 96 moves; try `--heuristic pdb --weight 1.5`).
+`generate` prints a board whose shortest solution has exactly K moves, a board scrambled by N random moves,
+or a uniformly random solvable board. The size defaults to 4x4. K = 0 gives the solved board, and K must not exceed
+the longest shortest solution of the size where it is known (31 for 3x3, 80 for 4x4).
+`build-pdb` builds the additive pattern database for `--heuristic pdb` and writes it to a file;
+without `--pdb` the solver builds it in memory before searching. It saves each finished group to `<file>.partial`
+and resumes from there when run again after an interruption.
+`table` finds the optimal number of moves of every board of up to 9 cells (3x3 by default) by a breadth-first search
+from the goal. It prints that number for each board in the file (like `--batch`), or without a file how many boards
+need each number of moves. `--heuristic` also prints the heuristic of each board, or its average for each number
+of moves and how many boards it overestimates.
+`play` lets you solve a board (a random one of the size, 3x3 by default, without an input) by typing moves
+of the blank (R, U, L, D; several in one line), `undo`, `hint` or `quit`.
+`verify` checks that the moves solve the board, and with `--optimal` also that no shorter solution exists.
+`bench` solves `--count` generated boards (10 by default) whose shortest solutions have K moves (40 by default,
+or half the longest shortest solution of a smaller size, e.g. 15 for 3x3)
+with the solve options, and prints the moves, the expanded nodes, the effective branching factor b* and the time of each and their totals.
+`dataset` writes `--count` random solvable boards (1000 3x3 boards by default, or boards scrambled by N random moves)
+as JSON Lines with the optimal number of moves and the misplaced, Manhattan and linear-conflict heuristics.
+Boards of up to 9 cells are looked up in the table of `table`; larger ones are solved with the solve options,
+and a board not solved within `--time-limit` gets `null`.
+`--heuristic` chooses the lower bound of IDA* and A* (manhattan by default): misplaced tiles, Manhattan distance,
+Manhattan distance plus linear conflicts, walking distance (up to 4x4) or the pattern database.
+`--algorithm a-star` remembers every board it visits: it expands fewer nodes than IDA* but needs much more memory.
+`--algorithm sma-star` searches like A* but remembers at most N boards (`--max-states`), forgetting the leaves
+with the largest estimates and regenerating them when needed; it only finds solutions of at most N - 1 moves.
+`--algorithm bidirectional` runs breadth-first searches from the board and from the goal until they meet;
+it falls back to IDA* once it remembers more than N boards (2000000 by default).
+`--algorithm greedy` places the tiles row by row and column by column like a human: fast but not optimal.
+`--metric tile` counts sliding any number of tiles in a row or column as one move and minimizes those moves
+with IDA* or A*; the solution is also printed as tile moves like `move tile 7 to (0,2)`.
+`--table-mb` gives IDA* a transposition table of about M megabytes that skips boards already reached in fewer moves.
+`--cycle-window` also prunes moves of IDA* that return to one of the last K boards of the current path
+(short cycles such as the 12 moves around a 2x2 square), without the memory of a table.
+`--move-order heuristic` makes IDA* try first the moves that lower the heuristic the most instead of R, U, L, D,
+which usually reaches a solution earlier in the last iteration.
+`--threads` splits the first moves of the IDA* tree across T threads (1 by default).
+`--weight` multiplies the heuristic by W (1 <= W <= 100): the solution is found faster but may be up to W times longer than optimal.
+`--goal` reads the goal arrangement from a file (1, 2, ... with the blank last by default);
+`--heuristic pdb` needs the blank of the goal in the bottom-right corner.
+`--partial` only places the given tiles (e.g. `1,2,3,4` for the first row of a 4x4 board) with the fewest moves,
+ignoring the other tiles and the heuristic.
+`--pattern` reads a label for each cell of the goal from a file, like colors; tiles whose goal cells have the same label
+are treated as the same, and the board is solved once every cell holds a tile of its label (the blank included).
+`--all` prints every shortest solution (one line of moves each) and `--count` only counts them; both need IDA* without `--weight`.
+`--time-limit` stops the search after ms milliseconds and prints the solution only if one was found by then.
+`hint` prints only the first move of a shortest solution (R, U, L or D for the direction the blank moves),
+so that a frontend can offer a hint; combine it with `--time-limit` to bound the search.
+`--verbose` prints the bound, the expanded nodes (and their growth from the previous iteration), the cutoffs and the time
+of each IDA* iteration to stderr, and then the effective branching factor b* of the whole search.
+`--animate` redraws the board in place instead of printing every board, waiting ms milliseconds per move (200 by default).
+`--format moves` prints only the line of moves instead of every board (the counts go to stderr), and `--format json` prints one JSON object
+with the first board, the moves, their number and the expanded nodes for web frontends.
+`--export` also writes the solution as an animated SVG (tiles slide between the boards) or GIF (one frame per move).
+`--batch` solves every board in the file (separated by blank lines, or one per line on single lines) and writes
+the size, the board, the number of moves and tile moves, the expanded nodes and the time of each as CSV to the `--csv` file or stdout.";
+
+// `--animate` で値を省いたときの 1 手ごとの待ち時間 (ミリ秒)
+const ANIMATE_DELAY_MS: u64 = 200;
+// `--export` で書くアニメーションの 1 手の時間 (ミリ秒)
+const EXPORT_DELAY_MS: u32 = 300;
+// `play` でヒントを探す時間の上限 (ミリ秒)
+const HINT_TIME_LIMIT_MS: u64 = 3000;
+
+// 名前の付いた盤面 (図3-4 と、Korf の 24 パズルの問題)
+//
+// Korf の問題は空きマスを左上に置いた完成形のものなので、盤面を 180 度回して数 `v` を `25 - v` に付け替え、
+// 空きマスが右下の完成形にそろえた。最短手数は変わらない (1 番は 95 手, 2 番は 96 手)。
+const EXAMPLES: [(&str, &str); 3] = [
+    (
+        "book",
+        "
+ 5  4  7  6
+15  0 13 10
+ 2  1  8  3
+12 14 11  9
+",
+    ),
+    (
+        "korf24-1",
+        "
+12  3  9 22  1
+18 24  4 14 19
+21  5 15  0 10
+ 8 13  6  2 17
+ 7 23 16 20 11
+",
+    ),
+    (
+        "korf24-2",
+        "
+11 22 17 14 10
+ 4 18  2  0  5
+ 6  7 12 15 21
+ 3 23 16  8  1
+19 13 24 20  9
+",
+    ),
+];
+
+enum Command {
+    Solve(Box<SolveArgs>),
+    Generate(GenerateArgs),
+    BuildPdb(BuildPdbArgs),
+    Table(TableArgs),
+    Play(PlayArgs),
+    Verify(VerifyArgs),
+    Bench(Box<BenchArgs>),
+    Dataset(Box<DatasetArgs>),
+    List,
+    // 使い方を表示する (`-h`, `--help`)
+    Help,
+}
+
+struct BuildPdbArgs {
+    height: usize,
+    width: usize,
+    output: String,
+}
+
+struct TableArgs {
+    size: Option<(usize, usize)>,
+    goal: Option<String>,
+    heuristic: Option<HeuristicName>,
+    source: Option<Source>,
+}
+
+struct GenerateArgs {
+    height: usize,
+    width: usize,
+    depth: Option<usize>,
+    moves: Option<usize>,
+    seed: Option<u64>,
+}
+
+struct PlayArgs {
+    height: usize,
+    width: usize,
+    seed: Option<u64>,
+    // 遊ぶ盤面 (`None` ならランダムな盤面)
+    source: Option<Source>,
+}
+
+struct VerifyArgs {
+    moves: Vec<Dir>,
+    // 最短手順と手数を比べるか
+    optimal: bool,
+    source: Option<Source>,
+}
+
+struct BenchArgs {
+    height: usize,
+    width: usize,
+    count: usize,
+    depth: usize,
+    seed: u64,
+    solve: SolveArgs,
+}
+
+struct DatasetArgs {
+    height: usize,
+    width: usize,
+    count: usize,
+    // 完成形から空きマスを動かす回数 (`None` なら一様にランダムな盤面)
+    moves: Option<usize>,
+    seed: Option<u64>,
+    output: Option<String>,
+    solve: SolveArgs,
+}
+
+struct SolveArgs {
+    // 入力 (`None` なら本の問題)
+    source: Option<Source>,
+    mode: Mode,
+    algorithm: Algorithm,
+    metric: Metric,
+    max_states: Option<usize>,
+    table_mb: usize,
+    cycle_window: usize,
+    move_order: MoveOrder,
+    threads: usize,
+    weight: f64,
+    heuristic: HeuristicName,
+    pdb: Option<String>,
+    goal: Option<String>,
+    // 盤面を並べたファイル (`--batch`) と、結果を書く CSV ファイル
+    batch: Option<Source>,
+    csv: Option<String>,
+    // アニメーションで 1 手ごとに待つミリ秒 (`None` なら各盤面を並べて表示する)
+    animate: Option<u64>,
+    export: Option<(ExportFormat, String)>,
+    format: Format,
+    // 探索を打ち切るまでのミリ秒
+    time_limit: Option<u64>,
+    // そろえるタイル (`--partial`。`None` なら完成させる)
+    partial: Option<Vec<u8>>,
+    // 完成形の各マスの組を書いたファイル (`--pattern`)
+    pattern: Option<String>,
+    verbose: bool,
+}
+
+// 解いた結果の書き方
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    // 完成までの各盤面と手順
+    Boards,
+    // 手順だけ
+    Moves,
+    // 最初の盤面, 手順, 手数, 展開したノード数を並べた JSON のオブジェクト
+    Json,
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Svg,
+    Gif,
+}
+
+// 最短手順をいくつ探すか
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    First,
+    All,
+    Count,
+    // 最短手順の 1 手目だけ (`hint`)
+    Hint,
+}
+
+// パターンデータベースは盤面を読んでから作るか読むので、名前だけ覚えておく
+#[derive(Clone, Copy, PartialEq)]
+enum HeuristicName {
+    Misplaced,
+    Manhattan,
+    LinearConflict,
+    WalkingDistance,
+    Pdb,
+}
+
+enum Source {
+    Example(String),
+    Stdin,
+    File(String),
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+    match args.first().map(String::as_str) {
+        Some("generate") => parse_generate_args(&args[1..]).map(Command::Generate),
+        Some("build-pdb") => parse_build_pdb_args(&args[1..]).map(Command::BuildPdb),
+        Some("table") => parse_table_args(&args[1..]).map(Command::Table),
+        Some("play") => parse_play_args(&args[1..]).map(Command::Play),
+        Some("verify") => parse_verify_args(&args[1..]).map(Command::Verify),
+        Some("bench") => parse_bench_args(&args[1..]).map(|args| Command::Bench(Box::new(args))),
+        Some("dataset") => {
+            parse_dataset_args(&args[1..]).map(|args| Command::Dataset(Box::new(args)))
+        }
+        Some("solve") => parse_args(&args[1..]).map(|args| Command::Solve(Box::new(args))),
+        Some("demo") => parse_demo_args(&args[1..]),
+        Some("hint") => parse_args(&args[1..]).and_then(|args| {
+            if args.mode != Mode::First
+                || args.batch.is_some()
+                || args.partial.is_some()
+                || args.pattern.is_some()
+                || args.animate.is_some()
+                || args.export.is_some()
+            {
+                return Err("hint cannot be used with --all, --count, --batch, --partial, --pattern, --animate or --export".to_string());
+            }
+            Ok(Command::Solve(Box::new(SolveArgs {
+                mode: Mode::Hint,
+                ..args
+            })))
+        }),
+        _ => parse_args(args).map(|args| Command::Solve(Box::new(args))),
     }
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{:2}", self.0)
+// `demo <name> ...` は `solve --example <name> ...`、名前がなければ本の問題を解く
+fn parse_demo_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("--list") if args.len() == 1 => Ok(Command::List),
+        Some(name) if !name.starts_with('-') => {
+            let mut rest = vec!["--example".to_string(), name.to_string()];
+            rest.extend_from_slice(&args[1..]);
+            parse_args(&rest).map(|args| Command::Solve(Box::new(args)))
+        }
+        _ => parse_args(args).map(|args| Command::Solve(Box::new(args))),
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-enum Dir {
-    R,
-    U,
-    L,
-    D,
+fn parse_generate_args(args: &[String]) -> Result<GenerateArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut parsed = GenerateArgs {
+        height: 4,
+        width: 4,
+        depth: None,
+        moves: None,
+        seed: None,
+    };
+    while let Some(arg) = args.next() {
+        match arg {
+            "--size" => (parsed.height, parsed.width) = parse_size(args.next())?,
+            "--optimal-depth" => parsed.depth = Some(parse_value(arg, args.next())?),
+            "--moves" => parsed.moves = Some(parse_value(arg, args.next())?),
+            "--seed" => parsed.seed = Some(parse_value(arg, args.next())?),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    if parsed.depth.is_some() && parsed.moves.is_some() {
+        return Err("--optimal-depth and --moves cannot be used together".to_string());
+    }
+    if let Some(depth) = parsed.depth {
+        check_depth(depth, parsed.height, parsed.width)?;
+    }
+    Ok(parsed)
 }
 
-impl Dir {
-    fn reverse(self) -> Self {
-        match self {
-            Dir::R => Dir::L,
-            Dir::U => Dir::D,
-            Dir::L => Dir::R,
-            Dir::D => Dir::U,
+// 最短手数が `depth` の `height` x `width` の盤面があるかを調べる
+fn check_depth(depth: usize, height: usize, width: usize) -> Result<(), String> {
+    match Board::max_optimal_depth(height, width) {
+        Some(max) if depth > max => Err(format!(
+            "--optimal-depth {} is too large: every {}x{} board is solved in at most {} moves",
+            depth, height, width, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn parse_build_pdb_args(args: &[String]) -> Result<BuildPdbArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut size, mut output) = ((4, 4), None);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--size" => size = parse_size(args.next())?,
+            "--output" => output = Some(parse_value(arg, args.next())?),
+            _ => return Err(format!("unknown option: {}", arg)),
         }
     }
+    Ok(BuildPdbArgs {
+        height: size.0,
+        width: size.1,
+        output: output.ok_or("--output is required")?,
+    })
 }
 
-type B = [[Value; N]; N];
+fn parse_table_args(args: &[String]) -> Result<TableArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut parsed = TableArgs {
+        size: None,
+        goal: None,
+        heuristic: None,
+        source: None,
+    };
+    while let Some(arg) = args.next() {
+        let new = match arg {
+            "--size" => {
+                parsed.size = Some(parse_size(args.next())?);
+                continue;
+            }
+            "--goal" => {
+                parsed.goal = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--heuristic" => {
+                parsed.heuristic = Some(parse_heuristic(args.next())?);
+                continue;
+            }
+            "-" => Source::Stdin,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => Source::File(path.to_string()),
+        };
+        if parsed.source.replace(new).is_some() {
+            return Err("only one input can be given".to_string());
+        }
+    }
+    if parsed.size.is_some() && (parsed.source.is_some() || parsed.goal.is_some()) {
+        return Err("--size cannot be used with an input or --goal".to_string());
+    }
+    Ok(parsed)
+}
 
-#[derive(Debug)]
-struct Board {
-    board: B,
-    empty: (usize, usize),
-    estimate: u32,
+fn parse_play_args(args: &[String]) -> Result<PlayArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut parsed = PlayArgs {
+        height: 3,
+        width: 3,
+        seed: None,
+        source: None,
+    };
+    while let Some(arg) = args.next() {
+        let new = match arg {
+            "--size" => {
+                (parsed.height, parsed.width) = parse_size(args.next())?;
+                continue;
+            }
+            "--seed" => {
+                parsed.seed = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--input" => Source::File(parse_value(arg, args.next())?),
+            "--example" => Source::Example(parse_value(arg, args.next())?),
+            // 手は標準入力から読むので、盤面は標準入力から読めない
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => Source::File(path.to_string()),
+        };
+        if parsed.source.replace(new).is_some() {
+            return Err("only one input can be given".to_string());
+        }
+    }
+    Ok(parsed)
 }
 
-impl Board {
-    fn new(board: [[u8; N]; N]) -> Self {
-        let mut seen = vec![false; N * N];
-        for i in 0..N {
-            for j in 0..N {
-                assert!(board[i][j] <= 15);
-                seen[usize::from(board[i][j])] = true;
+fn parse_verify_args(args: &[String]) -> Result<VerifyArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut moves, mut optimal, mut source) = (None, false, None);
+    while let Some(arg) = args.next() {
+        let new = match arg {
+            "--moves" => {
+                let value = args.next().ok_or("--moves requires a value")?;
+                moves = Some(parse_moves(value)?);
+                continue;
             }
+            "--optimal" => {
+                optimal = true;
+                continue;
+            }
+            "--input" => Source::File(parse_value(arg, args.next())?),
+            "--example" => Source::Example(parse_value(arg, args.next())?),
+            "-" => Source::Stdin,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => Source::File(path.to_string()),
+        };
+        if source.replace(new).is_some() {
+            return Err("only one input can be given".to_string());
+        }
+    }
+    Ok(VerifyArgs {
+        moves: moves.ok_or("--moves is required")?,
+        optimal,
+        source,
+    })
+}
+
+// `bench` の盤面の数と大きさなどを読み、残りは解くときの設定として読む
+fn parse_bench_args(args: &[String]) -> Result<BenchArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut size, mut count, mut depth, mut seed) = ((4, 4), 10, None, 1);
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg {
+            "--size" => size = parse_size(args.next())?,
+            "--count" => count = parse_value(arg, args.next())?,
+            "--optimal-depth" => depth = Some(parse_value(arg, args.next())?),
+            "--seed" => seed = parse_value(arg, args.next())?,
+            _ => rest.push(arg.to_string()),
+        }
+    }
+    let solve = parse_args(&rest)?;
+    if solve.source.is_some()
+        || solve.batch.is_some()
+        || solve.mode != Mode::First
+        || solve.partial.is_some()
+        || solve.pattern.is_some()
+        || solve.goal.is_some()
+        || solve.animate.is_some()
+        || solve.export.is_some()
+        || solve.format != Format::Boards
+    {
+        return Err("bench solves generated boards and cannot be used with an input, --batch, --all, --count, --partial, --pattern, --goal, --animate, --export or --format".to_string());
+    }
+    // 既定は 40 手で、小さな盤面ではすぐ作れるように最長手数の半分までにする
+    let depth = match depth {
+        Some(depth) => {
+            check_depth(depth, size.0, size.1)?;
+            depth
         }
-        for v in 0..(N * N) {
-            assert!(seen[v]);
+        None => Board::max_optimal_depth(size.0, size.1).map_or(40, |max| (max / 2).min(40)),
+    };
+    Ok(BenchArgs {
+        height: size.0,
+        width: size.1,
+        count,
+        depth,
+        seed,
+        solve,
+    })
+}
+
+// `dataset` の盤面の数と大きさなどを読み、残りは解くときの設定として読む
+fn parse_dataset_args(args: &[String]) -> Result<DatasetArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut size, mut count, mut moves, mut seed, mut output) = ((3, 3), 1000, None, None, None);
+    let mut rest = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg {
+            "--size" => size = parse_size(args.next())?,
+            "--count" => count = parse_value(arg, args.next())?,
+            "--moves" => moves = Some(parse_value(arg, args.next())?),
+            "--seed" => seed = Some(parse_value(arg, args.next())?),
+            "--output" => output = Some(parse_value(arg, args.next())?),
+            _ => rest.push(arg.to_string()),
         }
+    }
+    let solve = parse_args(&rest)?;
+    if solve.source.is_some()
+        || solve.batch.is_some()
+        || solve.mode != Mode::First
+        || solve.partial.is_some()
+        || solve.pattern.is_some()
+        || solve.goal.is_some()
+        || solve.animate.is_some()
+        || solve.export.is_some()
+        || solve.format != Format::Boards
+    {
+        return Err("dataset generates its boards and cannot be used with an input, --batch, --all, --count, --partial, --pattern, --goal, --animate, --export or --format".to_string());
+    }
+    if solve.algorithm == Algorithm::Greedy || solve.weight > 1.0 || solve.metric == Metric::Tile {
+        return Err(
+            "dataset needs optimal solutions: do not use --algorithm greedy, --weight or --metric tile"
+                .to_string(),
+        );
+    }
+    Ok(DatasetArgs {
+        height: size.0,
+        width: size.1,
+        count,
+        moves,
+        seed,
+        output,
+        solve,
+    })
+}
 
-        let mut board_v = [[Value(0); N]; N];
-        let mut empty = (0, 0);
-        for i in 0..N {
-            for j in 0..N {
-                board_v[i][j] = Value(board[i][j]);
-                if board[i][j] == 0 {
-                    empty = (i, j);
+// `RULD` のような、空きマスを動かす向きの列 (空白は読み飛ばす)
+fn parse_moves(text: &str) -> Result<Vec<Dir>, String> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_ascii_uppercase() {
+            'R' => Ok(Dir::R),
+            'U' => Ok(Dir::U),
+            'L' => Ok(Dir::L),
+            'D' => Ok(Dir::D),
+            _ => Err(format!("invalid move: {} (use R, U, L or D)", c)),
+        })
+        .collect()
+}
+
+// `4x4` のような盤面の大きさ
+fn parse_size(value: Option<&str>) -> Result<(usize, usize), String> {
+    let value = value.ok_or("--size requires a value")?;
+    value
+        .split_once('x')
+        .and_then(|(h, w)| Some((h.parse().ok()?, w.parse().ok()?)))
+        .filter(|&(h, w): &(usize, usize)| h >= 2 && w >= 2 && h * w <= 256)
+        .ok_or_else(|| format!("invalid value for --size: {}", value))
+}
+
+fn parse_heuristic(value: Option<&str>) -> Result<HeuristicName, String> {
+    match value {
+        Some("misplaced") => Ok(HeuristicName::Misplaced),
+        Some("manhattan") => Ok(HeuristicName::Manhattan),
+        Some("linear-conflict") => Ok(HeuristicName::LinearConflict),
+        Some("walking-distance") => Ok(HeuristicName::WalkingDistance),
+        Some("pdb") => Ok(HeuristicName::Pdb),
+        Some(value) => Err(format!("unknown heuristic: {}", value)),
+        None => Err("--heuristic requires a value".to_string()),
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<&str>) -> Result<T, String> {
+    match value {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("invalid value for {}: {}", name, value)),
+        None => Err(format!("{} requires a value", name)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<SolveArgs, String> {
+    let mut args = args.iter().map(String::as_str).peekable();
+    let (mut source, mut heuristic, mut pdb, mut goal) =
+        (None, HeuristicName::Manhattan, None, None);
+    let (mut algorithm, mut max_states, mut table_mb) = (Algorithm::IdaStar, None, 0);
+    let (mut cycle_window, mut move_order) = (0, MoveOrder::Fixed);
+    let mut metric = Metric::Blank;
+    let (mut threads, mut weight, mut animate) = (1, 1.0, None);
+    let (mut batch, mut csv, mut export) = (None, None, None);
+    let (mut partial, mut pattern, mut verbose) = (None, None, false);
+    let (mut mode, mut time_limit, mut format) = (Mode::First, None, Format::Boards);
+    while let Some(arg) = args.next() {
+        let new = match arg {
+            "--algorithm" => {
+                algorithm = match args.next() {
+                    Some("ida-star") => Algorithm::IdaStar,
+                    Some("a-star") => Algorithm::AStar,
+                    Some("sma-star") => Algorithm::SmaStar,
+                    Some("bidirectional") => Algorithm::Bidirectional,
+                    Some("greedy") => Algorithm::Greedy,
+                    Some(value) => return Err(format!("unknown algorithm: {}", value)),
+                    None => return Err("--algorithm requires a value".to_string()),
+                };
+                continue;
+            }
+            "--metric" => {
+                metric = match args.next() {
+                    Some("blank") => Metric::Blank,
+                    Some("tile") => Metric::Tile,
+                    Some(value) => return Err(format!("unknown metric: {}", value)),
+                    None => return Err("--metric requires a value".to_string()),
+                };
+                continue;
+            }
+            "--heuristic" => {
+                heuristic = parse_heuristic(args.next())?;
+                continue;
+            }
+            "--max-states" => {
+                max_states = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--table-mb" => {
+                table_mb = parse_value(arg, args.next())?;
+                continue;
+            }
+            "--cycle-window" => {
+                cycle_window = parse_value(arg, args.next())?;
+                continue;
+            }
+            "--move-order" => {
+                move_order = match args.next() {
+                    Some("fixed") => MoveOrder::Fixed,
+                    Some("heuristic") => MoveOrder::Heuristic,
+                    Some(value) => return Err(format!("unknown move order: {}", value)),
+                    None => return Err("--move-order requires a value".to_string()),
+                };
+                continue;
+            }
+            "--weight" => {
+                weight = parse_value::<f64>(arg, args.next())?;
+                if !(1.0..=MAX_WEIGHT).contains(&weight) {
+                    return Err(format!("--weight must be between 1 and {}", MAX_WEIGHT));
                 }
+                continue;
             }
-        }
-        let mut board = Self {
-            board: board_v,
-            empty,
-            estimate: 0,
+            "--threads" => {
+                threads = parse_value(arg, args.next())?;
+                continue;
+            }
+            "--pdb" => {
+                pdb = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--batch" => {
+                batch = match args.next() {
+                    Some("-") => Some(Source::Stdin),
+                    Some(path) => Some(Source::File(path.to_string())),
+                    None => return Err("--batch requires a file".to_string()),
+                };
+                continue;
+            }
+            "--csv" => {
+                csv = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--export" => {
+                let format = match args.next() {
+                    Some("svg") => ExportFormat::Svg,
+                    Some("gif") => ExportFormat::Gif,
+                    Some(value) => return Err(format!("unknown export format: {}", value)),
+                    None => return Err("--export requires a format and a path".to_string()),
+                };
+                let path = args.next().ok_or("--export requires a format and a path")?;
+                export = Some((format, path.to_string()));
+                continue;
+            }
+            "--format" => {
+                format = match args.next() {
+                    Some("boards") => Format::Boards,
+                    Some("moves") => Format::Moves,
+                    Some("json") => Format::Json,
+                    Some(value) => return Err(format!("unknown format: {}", value)),
+                    None => return Err("--format requires a value".to_string()),
+                };
+                continue;
+            }
+            "--all" => {
+                mode = Mode::All;
+                continue;
+            }
+            "--count" => {
+                mode = Mode::Count;
+                continue;
+            }
+            "--partial" => {
+                let value = args.next().ok_or("--partial requires tiles")?;
+                let tiles = value
+                    .split(',')
+                    .map(|t| t.trim().parse().ok().filter(|&t| t > 0))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| format!("invalid value for --partial: {}", value))?;
+                partial = Some(tiles);
+                continue;
+            }
+            "--pattern" => {
+                pattern = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--time-limit" => {
+                time_limit = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--verbose" => {
+                verbose = true;
+                continue;
+            }
+            "--goal" => {
+                goal = Some(parse_value(arg, args.next())?);
+                continue;
+            }
+            "--animate" => {
+                // 待ち時間は省ける (数でなければ入力のファイル名として読む)
+                let delay = args.peek().and_then(|value| value.parse().ok());
+                if delay.is_some() {
+                    args.next();
+                }
+                animate = Some(delay.unwrap_or(ANIMATE_DELAY_MS));
+                continue;
+            }
+            "--input" => match args.next() {
+                Some(path) => Source::File(path.to_string()),
+                None => return Err("--input requires a file".to_string()),
+            },
+            "--example" => match args.next() {
+                Some(name) => Source::Example(name.to_string()),
+                None => return Err("--example requires a name".to_string()),
+            },
+            "-" => Source::Stdin,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => Source::File(path.to_string()),
         };
-        board.estimate = board.estimate_all();
-        board
+        if source.replace(new).is_some() {
+            return Err("only one input can be given".to_string());
+        }
+    }
+    if pdb.is_some() && heuristic != HeuristicName::Pdb {
+        return Err("--pdb requires --heuristic pdb".to_string());
+    }
+    if batch.is_some() && (source.is_some() || animate.is_some() || export.is_some()) {
+        return Err("--batch cannot be used with another input, --animate or --export".to_string());
+    }
+    if partial.is_some() && (batch.is_some() || metric == Metric::Tile) {
+        return Err("--partial cannot be used with --batch or --metric tile".to_string());
+    }
+    if pattern.is_some() && (batch.is_some() || metric == Metric::Tile || partial.is_some()) {
+        return Err(
+            "--pattern cannot be used with --batch, --metric tile or --partial".to_string(),
+        );
     }
+    if mode != Mode::First {
+        if algorithm != Algorithm::IdaStar || weight > 1.0 {
+            return Err("--all and --count need --algorithm ida-star without --weight".to_string());
+        }
+        if batch.is_some()
+            || partial.is_some()
+            || pattern.is_some()
+            || animate.is_some()
+            || export.is_some()
+        {
+            return Err("--all and --count cannot be used with --batch, --partial, --pattern, --animate or --export".to_string());
+        }
+    }
+    if format != Format::Boards && animate.is_some() {
+        return Err("--format cannot be used with --animate".to_string());
+    }
+    if csv.is_some() && batch.is_none() {
+        return Err("--csv requires --batch".to_string());
+    }
+    Ok(SolveArgs {
+        source,
+        mode,
+        algorithm,
+        metric,
+        max_states,
+        table_mb,
+        cycle_window,
+        move_order,
+        threads,
+        weight,
+        heuristic,
+        pdb,
+        goal,
+        batch,
+        csv,
+        animate,
+        export,
+        format,
+        time_limit,
+        partial,
+        pattern,
+        verbose,
+    })
+}
 
-    fn move_cost(&self, (i, j): (usize, usize)) -> u32 {
-        self.board[i][j].move_cost((i, j))
+fn read_text(source: &Source) -> Result<String, String> {
+    match source {
+        Source::Example(name) => EXAMPLES
+            .iter()
+            .find(|(example, _)| example.eq_ignore_ascii_case(name))
+            .map(|(_, board)| board.to_string())
+            .ok_or_else(|| format!("unknown example: {} (see `15-puzzle demo --list`)", name)),
+        Source::Stdin => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("failed to read stdin: {}", e))?;
+            Ok(text)
+        }
+        Source::File(path) => {
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))
+        }
     }
+}
+
+// `goal` のファイルの並び (なければ `None`)
+fn read_goal(goal: Option<&str>) -> Result<Option<Board>, String> {
+    goal.map(|path| {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        text.parse().map_err(|e| format!("invalid goal: {}", e))
+    })
+    .transpose()
+}
+
+// `goal` があればその並びを完成形にする
+fn with_goal(board: Board, goal: Option<&Board>) -> Result<Board, String> {
+    match goal {
+        Some(goal) => board
+            .with_goal(goal)
+            .map_err(|e| format!("invalid goal: {}", e)),
+        None => Ok(board),
+    }
+}
+
+// `source` の盤面を読み、`goal` のファイルがあればその並びを完成形にする
+//
+// 一部のタイルだけをそろえるときや、同じ組のタイルを区別しないとき (`partial`) は、完成させられない盤面も読む。
+fn read_board(source: &Source, goal: Option<&str>, partial: bool) -> Result<Board, String> {
+    let text = read_text(source)?;
+    let board = parse_board(&text).map_err(|e| format!("invalid board: {}", e))?;
+    let board = with_goal(board, read_goal(goal)?.as_ref())?;
+    if !partial && !board.is_solvable() {
+        return Err("this board cannot be solved".to_string());
+    }
+    Ok(board)
+}
+
+// `path` に書いた完成形の各マスの組から、`board` の数ごとの組を求める
+fn read_pattern(path: &str, board: &Board) -> Result<Vec<u8>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let labels = text
+        .split_whitespace()
+        .map(|label| {
+            label
+                .parse::<u8>()
+                .map_err(|_| format!("invalid label in the pattern: {}", label))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let goal = board.goal();
+    let cells = goal.board().concat();
+    if labels.len() != cells.len() {
+        return Err(format!(
+            "the pattern has {} labels but the board has {} cells",
+            labels.len(),
+            cells.len()
+        ));
+    }
+    let mut classes = vec![0; cells.len()];
+    for (v, label) in cells.iter().zip(labels) {
+        classes[usize::from(v.get())] = label;
+    }
+    Ok(classes)
+}
+
+// `[` か `{` で始まれば JSON の、そうでなければ空白区切りの数の盤面
+fn parse_board(text: &str) -> Result<Board, BoardError> {
+    if text.trim_start().starts_with(['[', '{']) {
+        Board::from_json(text)
+    } else {
+        text.parse()
+    }
+}
 
-    fn estimate_all(&self) -> u32 {
-        let mut cost = 0;
-        for i in 0..N {
-            for j in 0..N {
-                if (i, j) == self.empty {
-                    continue;
+// 空行で区切った盤面を読む
+//
+// 区切った 1 かたまりが盤面として読めなければ、各行を 1 行に書いた盤面として読む。
+// JSON の盤面は 1 行に 1 つずつ書く。
+fn read_boards(source: &Source, goal: Option<&str>) -> Result<Vec<Board>, String> {
+    let text = read_text(source)?;
+    let goal = read_goal(goal)?;
+    let mut blocks = vec![Vec::new()];
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            blocks.push(Vec::new());
+        } else {
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+    let mut boards = Vec::new();
+    for block in blocks.iter().filter(|block| !block.is_empty()) {
+        if block[0].trim_start().starts_with(['[', '{']) {
+            for line in block {
+                let board = Board::from_json(line)
+                    .map_err(|e| format!("invalid board: {}: {}", line.trim(), e))?;
+                boards.push(board);
+            }
+            continue;
+        }
+        match block.join("\n").parse::<Board>() {
+            Ok(board) => boards.push(board),
+            Err(e) if block.len() == 1 => return Err(format!("invalid board: {}", e)),
+            Err(_) => {
+                for line in block {
+                    let board = line
+                        .parse()
+                        .map_err(|e| format!("invalid board: {}: {}", line.trim(), e))?;
+                    boards.push(board);
                 }
-                cost += self.move_cost((i, j));
             }
         }
-        cost as u32
     }
+    boards
+        .into_iter()
+        .map(|board| with_goal(board, goal.as_ref()))
+        .collect()
+}
 
-    // 空きマスを dir の方向にずらす
-    fn slide(&mut self, dir: Dir) -> Result<(), ()> {
-        let (i, j) = self.empty;
-        match dir {
-            Dir::R => {
-                if j + 1 >= N {
-                    return Err(());
-                }
-                self.estimate -= self.move_cost((i, j + 1));
-                self.board[i].swap(j, j + 1);
-                self.empty = (i, j + 1);
-                self.estimate += self.move_cost((i, j));
-            }
-            Dir::U => {
-                if i == 0 {
-                    return Err(());
-                }
-                self.estimate -= self.move_cost((i - 1, j));
-                let val = self.board[i - 1][j];
-                self.board[i - 1][j] = self.board[i][j];
-                self.board[i][j] = val;
-                self.empty = (i - 1, j);
-                self.estimate += self.move_cost((i, j));
-            }
-            Dir::L => {
-                if j == 0 {
-                    return Err(());
+// `board` と同じ大きさのパターンデータベースを `path` から読むか、なければ作る
+fn pattern_database(board: &Board, path: Option<&str>) -> Result<PatternDatabase, String> {
+    let (height, width) = (board.height(), board.width());
+    let pdb = match path {
+        Some(path) => {
+            let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+            PatternDatabase::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => {
+            eprintln!("building the pattern database for {}x{}...", height, width);
+            PatternDatabase::new(height, width).map_err(|e| e.to_string())?
+        }
+    };
+    let (i, j) = board.goal().empty();
+    if (i, j) != (height - 1, width - 1) {
+        return Err(
+            "--heuristic pdb needs the blank of the goal in the bottom-right corner".to_string(),
+        );
+    }
+    if (pdb.height(), pdb.width()) != (height, width) {
+        return Err(format!(
+            "the pattern database is for {}x{} boards, but the board is {}x{}",
+            pdb.height(),
+            pdb.width(),
+            height,
+            width
+        ));
+    }
+    Ok(pdb)
+}
+
+// 1 グループずつ表を作り、作り終えるたびに `<output>.partial` に保存する
+//
+// 同じ大きさの作りかけがあればその続きから作る。
+fn build_pdb(args: &BuildPdbArgs) {
+    let start = Instant::now();
+    let partial = format!("{}.partial", args.output);
+    let groups = PatternDatabase::default_groups(args.height, args.width);
+    let resumed = fs::read(&partial)
+        .ok()
+        .and_then(|bytes| PdbBuilder::from_bytes(&bytes).ok())
+        .filter(|builder| {
+            (builder.height(), builder.width()) == (args.height, args.width)
+                && builder.groups() == &groups[..]
+        });
+    let mut builder = match resumed {
+        Some(builder) => {
+            eprintln!(
+                "resuming from {} ({} of {} groups built)",
+                partial,
+                builder.built(),
+                groups.len()
+            );
+            builder
+        }
+        None => match PdbBuilder::new(args.height, args.width, &groups) {
+            Ok(builder) => builder,
+            Err(e) => exit_with(&e.to_string()),
+        },
+    };
+    loop {
+        let group_start = Instant::now();
+        let g = match builder.build_next() {
+            Some(g) => g,
+            None => break,
+        };
+        if let Err(e) = fs::write(&partial, builder.to_bytes()) {
+            exit_with(&format!("failed to write {}: {}", partial, e));
+        }
+        let tiles = groups[g].iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        eprintln!(
+            "group {}/{} ({}) built in {:.1}s",
+            g + 1,
+            groups.len(),
+            tiles.join(" "),
+            group_start.elapsed().as_secs_f64()
+        );
+    }
+    let pdb = match builder.finish() {
+        Ok(pdb) => pdb,
+        Err(e) => exit_with(&e.to_string()),
+    };
+    if let Err(e) = fs::write(&args.output, pdb.to_bytes()) {
+        exit_with(&format!("failed to write {}: {}", args.output, e));
+    }
+    let _ = fs::remove_file(&partial);
+    eprintln!(
+        "wrote {} ({} groups) in {:.1}s",
+        args.output,
+        pdb.groups().len(),
+        start.elapsed().as_secs_f64()
+    );
+}
+
+// 完成形から幅優先探索ですべての盤面の最短手数を求め、入力の各盤面の手数か、手数ごとの盤面の数を書く
+fn table(args: &TableArgs) -> Result<(), String> {
+    let boards = match &args.source {
+        Some(source) => read_boards(source, args.goal.as_deref())?,
+        None => Vec::new(),
+    };
+    let goal = match (read_goal(args.goal.as_deref())?, boards.first()) {
+        (Some(goal), _) => goal,
+        (None, Some(board)) => board.goal(),
+        (None, None) => {
+            let (height, width) = args.size.unwrap_or((3, 3));
+            Board::solved(height, width)
+        }
+    };
+    let start = Instant::now();
+    let table = DistanceTable::new(&goal).ok_or_else(|| {
+        format!(
+            "table supports boards of up to {} cells, but the board is {}x{}",
+            DistanceTable::MAX_CELLS,
+            goal.height(),
+            goal.width()
+        )
+    })?;
+    eprintln!(
+        "built the table for {}x{} in {:.1}s",
+        goal.height(),
+        goal.width(),
+        start.elapsed().as_secs_f64()
+    );
+    let heuristic = match args.heuristic {
+        Some(name) => Some((
+            name,
+            heuristic(name, None, table.goal(), &mut HashMap::new())?,
+        )),
+        None => None,
+    };
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let write_error = |e: io::Error| format!("failed to write: {}", e);
+    if !boards.is_empty() {
+        for board in &boards {
+            if (board.height(), board.width()) != (goal.height(), goal.width()) {
+                return Err("all boards must have the same size".to_string());
+            }
+            match table.distance(board) {
+                Some(d) => write!(stdout, "{} moves", d),
+                None => write!(stdout, "unsolvable"),
+            }
+            .map_err(write_error)?;
+            if let Some((_, heuristic)) = &heuristic {
+                write!(stdout, " (heuristic {})", board.heuristic(heuristic))
+                    .map_err(write_error)?;
+            }
+            writeln!(stdout).map_err(write_error)?;
+        }
+        return Ok(());
+    }
+    let histogram = table.histogram();
+    // 手数ごとの下界の和と、下界が手数を超えた盤面の数
+    let mut sums = vec![0u64; histogram.len()];
+    let mut overestimated = 0;
+    if let Some((_, heuristic)) = &heuristic {
+        for (board, d) in table.boards() {
+            let h = board.heuristic(heuristic);
+            sums[d as usize] += u64::from(h);
+            if h > d {
+                overestimated += 1;
+            }
+        }
+    }
+    for (d, &count) in histogram.iter().enumerate() {
+        write!(stdout, "{} moves: {} boards", d, count).map_err(write_error)?;
+        if heuristic.is_some() {
+            write!(
+                stdout,
+                ", heuristic {:.2} on average",
+                sums[d] as f64 / count as f64
+            )
+            .map_err(write_error)?;
+        }
+        writeln!(stdout).map_err(write_error)?;
+    }
+    writeln!(
+        stdout,
+        "{} boards, at most {} moves",
+        histogram.iter().sum::<usize>(),
+        histogram.len() - 1
+    )
+    .map_err(write_error)?;
+    if let Some((name, _)) = heuristic {
+        writeln!(
+            stdout,
+            "{} overestimates {} boards",
+            heuristic_name(name),
+            overestimated
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn heuristic_name(name: HeuristicName) -> &'static str {
+    match name {
+        HeuristicName::Misplaced => "misplaced",
+        HeuristicName::Manhattan => "manhattan",
+        HeuristicName::LinearConflict => "linear-conflict",
+        HeuristicName::WalkingDistance => "walking-distance",
+        HeuristicName::Pdb => "pdb",
+    }
+}
+
+// 種が指定されなければ時刻から決めた擬似乱数
+fn rng(seed: Option<u64>) -> Rng {
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    Rng::new(seed)
+}
+
+fn generate(args: &GenerateArgs) {
+    let mut rng = rng(args.seed);
+    let board = match (args.depth, args.moves) {
+        (Some(depth), _) => Board::generate(args.height, args.width, depth, &mut rng)
+            .expect("the depth is checked by parse_generate_args"),
+        (None, Some(moves)) => {
+            let mut board = Board::solved(args.height, args.width);
+            board.scramble(&mut rng, moves);
+            board
+        }
+        (None, None) => Board::random(args.height, args.width, &mut rng),
+    };
+    println!("{}", board);
+}
+
+// 盤面を表示しては標準入力から 1 行読み、空きマスを動かす。完成するか `quit` で終わる
+fn play(args: &PlayArgs) -> Result<(), String> {
+    let mut board = match &args.source {
+        Some(source) => read_board(source, None, false)?,
+        None => Board::random(args.height, args.width, &mut rng(args.seed)),
+    };
+    let mut history = Vec::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        println!("{}\n", board);
+        if board.is_solved() {
+            println!("solved in {} moves", history.len());
+            return Ok(());
+        }
+        print!("> ");
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| format!("failed to read stdin: {}", e))?,
+            None => return Ok(()),
+        };
+        match line.trim() {
+            "quit" | "q" => return Ok(()),
+            "undo" | "u" => match history.pop() {
+                Some(dir) => {
+                    board.undo(dir);
                 }
-                self.estimate -= self.move_cost((i, j - 1));
-                self.board[i].swap(j - 1, j);
-                self.empty = (i, j - 1);
-                self.estimate += self.move_cost((i, j));
-            }
-            Dir::D => {
-                if i + 1 >= N {
-                    return Err(());
+                None => println!("nothing to undo"),
+            },
+            "hint" | "h" => {
+                let options = SolveOptions {
+                    deadline: Some(Instant::now() + Duration::from_millis(HINT_TIME_LIMIT_MS)),
+                    heuristic: Heuristic::WalkingDistance,
+                    ..SolveOptions::default()
+                };
+                match hint(&board, &options) {
+                    Some(dir) => println!("hint: {}", dir),
+                    None => println!("no hint found in time"),
                 }
-                self.estimate -= self.move_cost((i + 1, j));
-                let val = self.board[i + 1][j];
-                self.board[i + 1][j] = self.board[i][j];
-                self.board[i][j] = val;
-                self.empty = (i + 1, j);
-                self.estimate += self.move_cost((i, j));
             }
+            text => match parse_moves(text) {
+                Ok(dirs) => {
+                    for dir in dirs {
+                        if !board.apply(dir) {
+                            println!("cannot move {}", dir);
+                            break;
+                        }
+                        history.push(dir);
+                    }
+                }
+                Err(e) => println!("{}", e),
+            },
         }
-        Ok(())
     }
+}
 
-    fn board(&self) -> B {
-        self.board
+// 手順で盤面が完成するかと、`args.optimal` なら最短かを調べる
+fn verify(args: &VerifyArgs) -> Result<(), String> {
+    let example = Source::Example(EXAMPLES[0].0.to_string());
+    let mut start = read_board(args.source.as_ref().unwrap_or(&example), None, false)?;
+    let mut board = start.clone();
+    for (i, &dir) in args.moves.iter().enumerate() {
+        if !board.apply(dir) {
+            return Err(format!("move {} ({}) leaves the board", i + 1, dir));
+        }
+    }
+    let len = args.moves.len();
+    if !board.is_solved() {
+        return Err(format!("the {} moves do not solve the board", len));
     }
+    if args.optimal {
+        let options = SolveOptions {
+            heuristic: Heuristic::WalkingDistance,
+            ..SolveOptions::default()
+        };
+        let shortest = solve_with_options(&mut start, &options)
+            .into_solutions()
+            .pop()
+            .map_or(0, |boards| boards.len() - 1);
+        if shortest < len {
+            return Err(format!(
+                "solved in {} moves, but the shortest solution has {} moves",
+                len, shortest
+            ));
+        }
+        println!("solved in {} moves (optimal)", len);
+    } else {
+        println!("solved in {} moves", len);
+    }
+    Ok(())
 }
 
-fn dfs(max_depth: usize, depth: usize, board: &mut Board, pre_dir: Dir, result: &mut Vec<B>) {
-    if !result.is_empty() {
-        return;
+// 最短手数が `args.depth` の盤面を作って解き、手数, 展開したノード数, 時間とその合計を書く
+fn bench(args: &BenchArgs) -> Result<(), String> {
+    let mut rng = Rng::new(args.seed);
+    let solve = &args.solve;
+    let mut pdbs = HashMap::new();
+    println!(
+        "{:<6} {:>6} {:>12} {:>8} {:>10}",
+        "board", "moves", "nodes", "b*", "time (ms)"
+    );
+    let (mut total_nodes, mut total_time) = (0, Duration::ZERO);
+    // 解けた盤面の有効分岐因子
+    let mut factors = Vec::new();
+    for i in 0..args.count {
+        let board = Board::generate(args.height, args.width, args.depth, &mut rng)
+            .expect("the depth is checked by parse_bench_args");
+        let heuristic = heuristic(solve.heuristic, solve.pdb.as_deref(), &board, &mut pdbs)?;
+        let (outcome, stats) =
+            solve_with_stats(&mut board.clone(), &solve_options(solve, heuristic));
+        let depth = outcome
+            .solutions()
+            .first()
+            .map(|boards| match solve.metric {
+                Metric::Blank => boards.len() - 1,
+                Metric::Tile => tile_moves(boards).len(),
+            });
+        let factor = depth.and_then(|depth| stats.effective_branching_factor(depth));
+        factors.extend(factor);
+        let or_dash = |x: Option<String>| x.unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<6} {:>6} {:>12} {:>8} {:>10.3}",
+            i + 1,
+            or_dash(depth.map(|depth| depth.to_string())),
+            stats.nodes_expanded,
+            or_dash(factor.map(|b| format!("{:.4}", b))),
+            stats.elapsed.as_secs_f64() * 1000.0
+        );
+        total_nodes += stats.nodes_expanded;
+        total_time += stats.elapsed;
     }
+    // 有効分岐因子は盤面ごとの平均を書く
+    let mean = (!factors.is_empty())
+        .then(|| format!("{:.4}", factors.iter().sum::<f64>() / factors.len() as f64));
+    println!(
+        "{:<6} {:>6} {:>12} {:>8} {:>10.3}",
+        "total",
+        "",
+        total_nodes,
+        mean.unwrap_or_default(),
+        total_time.as_secs_f64() * 1000.0
+    );
+    Ok(())
+}
 
-    if board.estimate == 0 {
-        result.push(board.board());
-        return;
+// ランダムな盤面と最短手数, 下界の値を JSON Lines で 1 行ずつ書く
+//
+// 9 マス以下なら最短手数の表を引き、それより大きい盤面は解いて求める。
+// `--time-limit` までに解けなかった盤面の最短手数は `null` にする。
+fn dataset(args: &DatasetArgs) -> Result<(), String> {
+    let mut rng = rng(args.seed);
+    let out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(
+            fs::File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?,
+        ),
+        None => Box::new(io::stdout()),
+    };
+    let mut out = BufWriter::new(out);
+    let write_error = |e: io::Error| format!("failed to write the dataset: {}", e);
+    let start = Instant::now();
+    let table = DistanceTable::new(&Board::solved(args.height, args.width));
+    let solve = &args.solve;
+    let mut pdbs = HashMap::new();
+    for i in 0..args.count {
+        let board = match args.moves {
+            Some(moves) => {
+                let mut board = Board::solved(args.height, args.width);
+                board.scramble(&mut rng, moves);
+                board
+            }
+            None => Board::random(args.height, args.width, &mut rng),
+        };
+        let optimal = match &table {
+            Some(table) => table.distance(&board),
+            None => {
+                let heuristic =
+                    heuristic(solve.heuristic, solve.pdb.as_deref(), &board, &mut pdbs)?;
+                let outcome =
+                    solve_with_options(&mut board.clone(), &solve_options(solve, heuristic));
+                outcome
+                    .into_solutions()
+                    .pop()
+                    .map(|boards| boards.len() as u32 - 1)
+            }
+        };
+        let number = |x: u32| Value::Number(f64::from(x));
+        let fields = [
+            ("board", board.to_json()),
+            ("optimal", optimal.map_or(Value::Null, number)),
+            ("misplaced", number(board.heuristic(&Heuristic::Misplaced))),
+            ("manhattan", number(board.heuristic(&Heuristic::Manhattan))),
+            (
+                "linear_conflict",
+                number(board.heuristic(&Heuristic::LinearConflict)),
+            ),
+        ];
+        let line = Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        );
+        writeln!(out, "{}", line).map_err(write_error)?;
+        if (i + 1) % 100 == 0 {
+            out.flush().map_err(write_error)?;
+            eprintln!("{} / {} boards", i + 1, args.count);
+        }
     }
+    out.flush().map_err(write_error)?;
+    eprintln!(
+        "wrote {} boards in {:.1}s",
+        args.count,
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
 
-    if depth >= max_depth {
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = match parse_command(&args) {
+        Ok(Command::Solve(args)) => *args,
+        Ok(Command::Generate(args)) => return generate(&args),
+        Ok(Command::BuildPdb(args)) => return build_pdb(&args),
+        Ok(Command::Table(args)) => {
+            if let Err(e) = table(&args) {
+                exit_with(&e);
+            }
+            return;
+        }
+        Ok(Command::Play(args)) => {
+            if let Err(e) = play(&args) {
+                exit_with(&e);
+            }
+            return;
+        }
+        Ok(Command::Verify(args)) => {
+            if let Err(e) = verify(&args) {
+                exit_with(&e);
+            }
+            return;
+        }
+        Ok(Command::Bench(args)) => {
+            if let Err(e) = bench(&args) {
+                exit_with(&e);
+            }
+            return;
+        }
+        Ok(Command::Dataset(args)) => {
+            if let Err(e) = dataset(&args) {
+                exit_with(&e);
+            }
+            return;
+        }
+        Ok(Command::List) => {
+            for (name, _) in EXAMPLES {
+                println!("{}", name);
+            }
+            return;
+        }
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => exit_with(&format!("{}\n{}", e, USAGE)),
+    };
+    if let Some(source) = &args.batch {
+        if let Err(e) = batch(&args, source) {
+            exit_with(&e);
+        }
         return;
     }
+    let example = Source::Example(EXAMPLES[0].0.to_string());
+    let source = args.source.as_ref().unwrap_or(&example);
+    let partial = args.partial.is_some() || args.pattern.is_some();
+    // 入力の誤りは使い方を添えずに知らせる
+    let mut board = match read_board(source, args.goal.as_deref(), partial) {
+        Ok(board) => board,
+        Err(e) => exit_with(&e),
+    };
 
-    for dir in [Dir::R, Dir::U, Dir::L, Dir::D] {
-        if depth >= 1 && dir.reverse() == pre_dir {
-            continue;
+    let heuristic = match heuristic(
+        args.heuristic,
+        args.pdb.as_deref(),
+        &board,
+        &mut HashMap::new(),
+    ) {
+        Ok(heuristic) => heuristic,
+        Err(e) => exit_with(&e),
+    };
+    let options = solve_options(&args, heuristic);
+    match args.mode {
+        Mode::First => {}
+        Mode::Hint => {
+            match hint(&board, &options) {
+                Some(dir) => println!("{}", dir),
+                None if board.is_solved() => println!("already solved"),
+                None => exit_with("no move found within the time limit"),
+            }
+            return;
         }
-        if let Ok(()) = board.slide(dir) {
-            if depth + board.estimate as usize <= max_depth {
-                dfs(max_depth, depth + 1, board, dir, result);
+        Mode::All => return print_all(&mut board, &options),
+        Mode::Count => {
+            let (count, termination, _) = count_solutions_with(&board, &options);
+            let suffix = if termination == Termination::TimedOut {
+                " (interrupted)"
+            } else {
+                ""
+            };
+            println!("{}{}", shortest_solutions(count), suffix);
+            return;
+        }
+    }
+    let (outcome, stats) = match &args.partial {
+        Some(tiles) => {
+            let cells = board.height() * board.width();
+            if let Some(t) = tiles.iter().find(|&&t| usize::from(t) >= cells) {
+                exit_with(&format!("tile {} is not on the board", t));
             }
-            assert!(board.slide(dir.reverse()).is_ok());
-            if !result.is_empty() {
-                result.push(board.board());
-                return;
+            let (outcome, stats) = solve_partial(&board, tiles, &options);
+            if outcome.solutions().is_empty() && !outcome.is_timed_out() {
+                exit_with("the tiles cannot be placed on this board");
             }
+            (outcome, stats)
+        }
+        None => match &args.pattern {
+            Some(path) => {
+                let classes = match read_pattern(path, &board) {
+                    Ok(classes) => classes,
+                    Err(e) => exit_with(&e),
+                };
+                let (outcome, stats) = solve_pattern(&board, &classes, &options);
+                if outcome.solutions().is_empty() && !outcome.is_timed_out() {
+                    exit_with("the pattern cannot be made on this board");
+                }
+                (outcome, stats)
+            }
+            None => solve_with_stats(&mut board, &options),
+        },
+    };
+    let timed_out = outcome.is_timed_out();
+    let result = match outcome.into_solutions().pop() {
+        Some(result) => result,
+        None if timed_out => exit_with("no solution found within the time limit"),
+        // SMA* は覚えられる盤面の数より長い手順を見つけられない
+        None if args.algorithm == Algorithm::SmaStar => exit_with(&format!(
+            "no solution of at most {} moves within --max-states {}",
+            options.max_states.saturating_sub(1),
+            options.max_states
+        )),
+        None => exit_with("no solution found"),
+    };
+    if let Some((format, path)) = &args.export {
+        let bytes = match format {
+            ExportFormat::Svg => render_svg(&result, EXPORT_DELAY_MS).into_bytes(),
+            ExportFormat::Gif => render_gif(&result, EXPORT_DELAY_MS),
+        };
+        if let Err(e) = fs::write(path, bytes) {
+            exit_with(&format!("failed to write {}: {}", path, e));
         }
     }
-}
-
-fn main() {
-    // 図3-4
-    #[rustfmt::skip]
-    let mut board = Board::new([
-        [ 5,  4,  7,  6],
-        [15,  0, 13, 10],
-        [ 2,  1,  8,  3],
-        [12, 14, 11,  9],
-    ]);
-
-    for max_depth in 0..80 {
-        let mut result = Vec::new();
-        dfs(
-            max_depth,
-            0,
-            &mut board,
-            Dir::R, // dummy
-            &mut result,
-        );
-        if !result.is_empty() {
-            result.reverse();
+    let moves = moves(&result);
+    let moves = moves.iter().map(|dir| dir.to_string()).collect::<String>();
+    if args.verbose {
+        // 探索の深さは最小にした手数の数え方で数える
+        let depth = match args.metric {
+            Metric::Blank => moves.len(),
+            Metric::Tile => tile_moves(&result).len(),
+        };
+        if let Some(b) = stats.effective_branching_factor(depth) {
+            eprintln!(
+                "effective branching factor: {:.4} ({} nodes, depth {})",
+                b, stats.nodes_expanded, depth
+            );
+        }
+    }
+    if args.format == Format::Json {
+        let fields = [
+            ("board", result[0].to_json()),
+            ("moves", Value::String(moves.clone())),
+            ("length", Value::Number(moves.len() as f64)),
+            ("nodes", Value::Number(stats.nodes_expanded as f64)),
+        ];
+        let object = fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        println!("{}", Value::Object(object));
+        return;
+    }
+    match args.animate {
+        Some(delay) => animate(&result, Duration::from_millis(delay)),
+        None if args.format == Format::Moves => {}
+        None => {
             for (i, board) in result.iter().enumerate() {
                 println!("{} th move:", i);
-                for row in board {
-                    let row: Vec<String> = row.iter().map(|val| format!("{}", val)).collect();
-                    println!("{}", row.join(" "));
-                }
+                println!("{}", board);
                 println!();
             }
-            break;
         }
     }
+    let tile_moves = tile_moves(&result);
+    let mut summary = format!("{} moves ({} tile moves)", moves.len(), tile_moves.len());
+    if args.weight > 1.0 {
+        // 最短手数は見つけた手数の 1 / weight 倍以上
+        let (found, unit) = match args.metric {
+            Metric::Blank => (moves.len(), "moves"),
+            Metric::Tile => (tile_moves.len(), "tile moves"),
+        };
+        let optimal = (found as f64 / args.weight).ceil();
+        summary = format!(
+            "{} (at most {} times the optimal, which is at least {} {})",
+            summary, args.weight, optimal, unit
+        );
+    }
+    if args.format == Format::Moves {
+        // 標準出力には手順だけを書き、`verify --moves` などにそのまま渡せるようにする
+        if args.metric == Metric::Tile {
+            for (i, tile_move) in tile_moves.iter().enumerate() {
+                eprintln!("{}: {}", i + 1, tile_move);
+            }
+        }
+        eprintln!("{}", summary);
+        println!("{}", moves);
+        return;
+    }
+    if args.metric == Metric::Tile {
+        for (i, tile_move) in tile_moves.iter().enumerate() {
+            println!("{}: {}", i + 1, tile_move);
+        }
+    }
+    println!("{}: {}", summary, moves);
+}
+
+// 最短手順をすべて、空きマスを動かす向きの列で 1 行ずつ書く
+fn print_all(board: &mut Board, options: &SolveOptions) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut count = 0;
+    solve_with(board, options, |boards| {
+        count += 1;
+        let moves = moves(&boards)
+            .iter()
+            .map(|dir| dir.to_string())
+            .collect::<String>();
+        let _ = writeln!(stdout, "{}", moves);
+        ControlFlow::Continue(())
+    });
+    let _ = writeln!(stdout, "{}", shortest_solutions(count));
+}
+
+// 最短手順の数 (1 つなら単数形)
+fn shortest_solutions(count: usize) -> String {
+    match count {
+        1 => "1 shortest solution".to_string(),
+        n => format!("{} shortest solutions", n),
+    }
+}
+
+// `name` の下界 (パターンデータベースは `path` から読むか作り、盤面の大きさごとに `pdbs` に覚えて使い回す)
+fn heuristic(
+    name: HeuristicName,
+    path: Option<&str>,
+    board: &Board,
+    pdbs: &mut HashMap<(usize, usize), Arc<PatternDatabase>>,
+) -> Result<Heuristic, String> {
+    Ok(match name {
+        HeuristicName::Misplaced => Heuristic::Misplaced,
+        HeuristicName::Manhattan => Heuristic::Manhattan,
+        HeuristicName::LinearConflict => Heuristic::LinearConflict,
+        HeuristicName::WalkingDistance if board.height().max(board.width()) > 4 => {
+            return Err(
+                "--heuristic walking-distance supports boards up to 4x4; use --heuristic pdb"
+                    .to_string(),
+            )
+        }
+        HeuristicName::WalkingDistance => Heuristic::WalkingDistance,
+        HeuristicName::Pdb => {
+            let size = (board.height(), board.width());
+            let pdb = match pdbs.get(&size) {
+                Some(pdb) => Arc::clone(pdb),
+                None => {
+                    let pdb = Arc::new(pattern_database(board, path)?);
+                    pdbs.insert(size, Arc::clone(&pdb));
+                    pdb
+                }
+            };
+            Heuristic::PatternDatabase(pdb)
+        }
+    })
+}
+
+fn solve_options(args: &SolveArgs, heuristic: Heuristic) -> SolveOptions {
+    let mut options = SolveOptions {
+        max_solutions: match args.mode {
+            Mode::First | Mode::Hint => Some(1),
+            Mode::All | Mode::Count => None,
+        },
+        deadline: args
+            .time_limit
+            .map(|ms| Instant::now() + Duration::from_millis(ms)),
+        heuristic,
+        algorithm: args.algorithm,
+        metric: args.metric,
+        table_memory: args.table_mb << 20,
+        cycle_window: args.cycle_window,
+        move_order: args.move_order,
+        threads: args.threads,
+        weight: args.weight,
+        ..SolveOptions::default()
+    };
+    if let Some(max_states) = args.max_states {
+        options.max_states = max_states;
+    }
+    if args.verbose {
+        // 前の反復のノード数 (反復ごとに何倍に増えたかを書く)
+        let previous = AtomicU64::new(0);
+        options.on_iteration = Some(OnIteration::new(move |iteration| {
+            let nodes = iteration.nodes_expanded;
+            let growth = match previous.swap(nodes, Ordering::Relaxed) {
+                0 => String::new(),
+                before => format!(" (x{:.2})", nodes as f64 / before as f64),
+            };
+            eprintln!(
+                "bound {}: {} nodes{}, {} cutoffs, {:.3} ms",
+                iteration.bound,
+                nodes,
+                growth,
+                iteration.pruned,
+                iteration.elapsed.as_secs_f64() * 1000.0
+            );
+        }));
+    }
+    options
+}
+
+// `source` の各盤面を解き、大きさ, 盤面, 手数, タイルを動かす手の数, 展開したノード数, 時間を CSV で書く
+//
+// 完成させられない盤面の手数は空にする。
+fn batch(args: &SolveArgs, source: &Source) -> Result<(), String> {
+    let boards = read_boards(source, args.goal.as_deref())?;
+    let out: Box<dyn Write> = match &args.csv {
+        Some(path) => Box::new(
+            fs::File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?,
+        ),
+        None => Box::new(io::stdout()),
+    };
+    let mut out = BufWriter::new(out);
+    let write_error = |e: io::Error| format!("failed to write the CSV: {}", e);
+    writeln!(out, "index,size,board,moves,tile_moves,nodes,time_ms").map_err(write_error)?;
+    let mut pdbs = HashMap::new();
+    let (start, mut solved) = (Instant::now(), 0);
+    for (i, board) in boards.iter().enumerate() {
+        let options = solve_options(
+            args,
+            heuristic(args.heuristic, args.pdb.as_deref(), board, &mut pdbs)?,
+        );
+        let (outcome, stats) = solve_with_stats(&mut board.clone(), &options);
+        let (moves, tile_moves) =
+            outcome
+                .solutions()
+                .first()
+                .map_or((String::new(), String::new()), |boards| {
+                    let tile_moves = tile_moves(boards).len();
+                    ((boards.len() - 1).to_string(), tile_moves.to_string())
+                });
+        if !moves.is_empty() {
+            solved += 1;
+        }
+        let cells = board
+            .board()
+            .iter()
+            .flatten()
+            .map(|v| v.get().to_string())
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "{},{}x{},{},{},{},{},{:.3}",
+            i + 1,
+            board.height(),
+            board.width(),
+            cells.join(" "),
+            moves,
+            tile_moves,
+            stats.nodes_expanded,
+            stats.elapsed.as_secs_f64() * 1000.0
+        )
+        .map_err(write_error)?;
+        out.flush().map_err(write_error)?;
+    }
+    eprintln!(
+        "solved {} of {} boards in {:.1}s",
+        solved,
+        boards.len(),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+// 画面を消して、各盤面を `delay` ごとに同じ位置に描き直す
+fn animate(boards: &[Board], delay: Duration) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for (i, board) in boards.iter().enumerate() {
+        if i > 0 {
+            thread::sleep(delay);
+        }
+        // カーソルを左上に戻して、そこから下を消す
+        let _ = write!(
+            stdout,
+            "\x1b[H\x1b[J{}\n\n{} / {}\n",
+            board,
+            i,
+            boards.len() - 1
+        );
+        let _ = stdout.flush();
+    }
+    let _ = writeln!(stdout);
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
@@ -1,347 +1,32 @@
-// ref: https://github.com/drken1215/mushikui_solver
+use std::io::Read;
+use std::ops::ControlFlow;
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
 
-use std::fmt::{Display, Formatter};
+use book_puzzle_algorithm_rs::backtrack::Domain;
+use book_puzzle_algorithm_rs::json::{self, Value};
+use book_puzzle_algorithm_rs::mushikui::{
+    AnyMushikui, DigitFilter, DigitLimit, Mushikui, SolveOptions,
+};
+use book_puzzle_algorithm_rs::puzzle::{Puzzle, SearchStats};
+use book_puzzle_algorithm_rs::rng::Rng;
 
-#[derive(Debug, Copy, Clone)]
-enum Digit {
-    Fix(u8),
-    Any,
-}
-
-impl From<char> for Digit {
-    fn from(ch: char) -> Self {
-        if ch.is_digit(10) {
-            Digit::Fix(ch as u8 - '0' as u8)
-        } else if ch == '*' {
-            Digit::Any
-        } else {
-            unreachable!()
-        }
-    }
-}
-
-impl Display for Digit {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Digit::Fix(d) => {
-                write!(f, "{}", d)
-            }
-            Digit::Any => {
-                write!(f, "*")
-            }
-        }
-    }
-}
-
-impl Digit {
-    fn digit(self) -> Option<u8> {
-        match self {
-            Digit::Fix(d) => Some(d),
-            Digit::Any => None,
-        }
-    }
-
-    fn accept(self, digit: u8) -> bool {
-        match self {
-            Digit::Fix(d) => d == digit,
-            Digit::Any => true,
-        }
-    }
-
-    fn is_any(self) -> bool {
-        match self {
-            Digit::Any => true,
-            _ => false,
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-struct Mushikui {
-    multiplicand: Vec<Digit>,
-    multiplier: Vec<Digit>,
-    partial_product: Vec<Vec<Digit>>,
-    product: Vec<Digit>,
-}
-
-impl Mushikui {
-    fn new(
-        multiplicand: &[char],
-        multiplier: &[char],
-        partial_product: &[Vec<char>],
-        product: &[char],
-    ) -> Self {
-        assert!(!multiplicand.is_empty());
-        assert!(!multiplier.is_empty());
-        assert!(multiplicand.len() >= multiplier.len());
-        assert_eq!(partial_product.len(), multiplier.len());
-        assert!(
-            multiplicand.len() + multiplier.len() - 1 <= product.len()
-                && product.len() <= multiplicand.len() + multiplier.len()
-        );
-        assert_ne!(multiplicand[0], '0');
-        assert_ne!(multiplier[0], '0');
-        for part in partial_product {
-            assert_ne!(part[0], '0');
-        }
-        assert_ne!(product[0], '0');
-        let multiplicand = multiplicand
-            .iter()
-            .copied()
-            .map(Digit::from)
-            .collect::<Vec<_>>();
-        let multiplier = multiplier
-            .into_iter()
-            .copied()
-            .map(Digit::from)
-            .collect::<Vec<_>>();
-        let partial_product = partial_product
-            .into_iter()
-            .map(|part| {
-                part.into_iter()
-                    .copied()
-                    .map(Digit::from)
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        let product = product
-            .into_iter()
-            .copied()
-            .map(Digit::from)
-            .collect::<Vec<_>>();
-        Self {
-            multiplicand,
-            multiplier,
-            partial_product,
-            product,
-        }
-    }
-
-    fn calculate_partial_product(&self, d: u8) -> Vec<u8> {
-        let multiplicand = self
-            .multiplicand
-            .iter()
-            .rev()
-            .map_while(|digit| digit.digit())
-            .collect::<Vec<_>>();
-        let mut prod = Vec::new();
-        let mut carry = 0;
-        for m in &multiplicand {
-            let e = m * d + carry;
-            assert!(e <= 90);
-            prod.push(e % 10);
-            carry = e / 10;
-        }
-        if carry > 0 {
-            assert!(carry <= 9);
-            if multiplicand.len() == self.multiplicand.len() {
-                prod.push(carry);
-            }
-        }
-        prod.reverse();
-        prod
-    }
-
-    fn calculate_product(&self) -> Vec<u8> {
-        let mut prod = Vec::new();
-        let mut carry = 0;
-        let partial_product = &self.partial_product;
-        for k in 0..(partial_product[partial_product.len() - 1].len() + self.multiplier.len() - 1) {
-            let mut s = 0;
-            for j in 0..partial_product.len() {
-                if k >= j && k - j < partial_product[j].len() {
-                    let d = partial_product[j][partial_product[j].len() - (k - j) - 1]
-                        .digit()
-                        .unwrap_or(0);
-                    s += u32::from(d);
-                }
-            }
-            prod.push(((s + carry) % 10) as u8);
-            carry = (s + carry) / 10;
-        }
-        while carry > 0 {
-            prod.push((carry % 10) as u8);
-            carry /= 10;
-        }
-        prod.reverse();
-        prod
-    }
-
-    fn rec_multiplicand(&mut self, i: usize, result: &mut Vec<Self>) {
-        let len = self.multiplicand.len();
-        if i == len {
-            self.rec_multiplier(i, 0, true, result);
-            return;
-        }
-
-        for d in 0..=9 {
-            if d == 0 && i == len - 1 {
-                continue;
-            }
-            let old = self.multiplicand[len - i - 1];
-            if old.accept(d) {
-                self.multiplicand[len - i - 1] = Digit::Fix(d);
-                self.rec_multiplier(i, 0, false, result);
-                self.multiplicand[len - i - 1] = old;
-            }
-        }
-    }
-
-    fn rec_multiplier(&mut self, i: usize, j: usize, last: bool, result: &mut Vec<Self>) {
-        let len = self.multiplier.len();
-        if j == len {
-            if last {
-                assert_eq!(i, self.multiplicand.len());
-                for d in &self.multiplicand {
-                    assert!(d.digit().is_some());
-                }
-                for d in &self.multiplier {
-                    assert!(d.digit().is_some());
-                }
-                let old_partial_product = self.partial_product.clone();
-                let mut ok = true;
-                for j in 0..self.multiplier.len() {
-                    let d = self.multiplier[self.multiplier.len() - j - 1]
-                        .digit()
-                        .unwrap();
-                    let partial_product = self
-                        .calculate_partial_product(d)
-                        .into_iter()
-                        .collect::<Vec<_>>();
-                    let accept = partial_product
-                        .iter()
-                        .zip(self.partial_product[j].iter())
-                        .all(|(&d, e)| e.accept(d));
-                    if self.partial_product[j].len() == partial_product.len() && accept {
-                        self.partial_product[j] =
-                            partial_product.into_iter().map(Digit::Fix).collect();
-                    } else {
-                        ok = false;
-                    }
-                }
-                if ok {
-                    let product = self.calculate_product();
-                    let accept = product
-                        .iter()
-                        .zip(self.product.iter())
-                        .all(|(&d, e)| e.accept(d));
-                    if product.len() == self.product.len() && accept {
-                        let old_product = self.product.clone();
-                        self.product = product.into_iter().map(Digit::Fix).collect();
-                        result.push(Clone::clone(self));
-                        self.product = old_product;
-                    }
-                }
-                self.partial_product = old_partial_product;
-            } else {
-                assert!(i < self.multiplicand.len());
-                self.rec_multiplicand(i + 1, result);
-            }
-            return;
-        }
-
-        let part = &self.partial_product[j];
-        if !last && part[part.len() - i - 1].is_any() {
-            self.rec_multiplier(i, j + 1, last, result);
-            return;
-        }
-
-        for d in 1..=9 {
-            let old_digit = self.multiplier[len - j - 1];
-            if old_digit.accept(d) {
-                let old_part = self.partial_product[j].clone();
-                let part = self.calculate_partial_product(d);
-                let accept = part
-                    .iter()
-                    .rev()
-                    .zip(old_part.iter().rev())
-                    .all(|(&d, e)| e.accept(d));
-                if part.len() <= old_part.len() && accept {
-                    self.multiplier[len - j - 1] = Digit::Fix(d);
-                    for k in 0..(part.len().min(old_part.len())) {
-                        self.partial_product[j][old_part.len() - k - 1] =
-                            Digit::Fix(part[part.len() - k - 1]);
-                    }
-                    self.rec_multiplier(i, j + 1, last, result);
-                    self.multiplier[len - j - 1] = old_digit;
-                    self.partial_product[j] = old_part;
-                }
-            }
-        }
-    }
+const USAGE: &str = "usage: mushikui [solve] [--all | --first | --count | --batch] [--format text|json|latex|unicode|code] [--prime | --prime-hidden] [--allow-leading-zero] [--digit-counts <c0,c1,...,c9>] [--max-uses <N>] [--uses <d>=<N>]... [--operands-equal] [--dedupe-commutative] [--explain] [--quiet] [--time] [<file> | --input <file> | --examples | --example <name>]
+       mushikui demo [<name>] [solve options]
+       mushikui demo --list
+       mushikui generate --multiplicand-len <A> --multiplier-len <B> [--number <N>] [--seed <N>]
+       mushikui minimize [--seed <N>] [<file> | --input <file>]
+       mushikui cnf [<file> | --input <file>]
+       mushikui hint [<file> | --input <file>]
+       mushikui candidates [<file> | --input <file>]
+       mushikui bench [--seed <N>] [--repeat <N>]
+       mushikui --help";
 
-    fn solve(&mut self) -> Vec<Self> {
-        let mut result = Vec::new();
-        self.rec_multiplicand(0, &mut result);
-        result
-    }
-}
-
-impl Display for Mushikui {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let width = self.product.len();
-        let multiplicand = self
-            .multiplicand
-            .iter()
-            .map(|d| d.to_string())
-            .collect::<Vec<_>>();
-        writeln!(
-            f,
-            "{digits:>width$}",
-            digits = multiplicand.join(""),
-            width = width
-        )?;
-        let multiplier = self
-            .multiplier
-            .iter()
-            .map(|d| d.to_string())
-            .collect::<Vec<_>>();
-        writeln!(
-            f,
-            "{digits:>width$}",
-            digits = multiplier.join(""),
-            width = width
-        )?;
-        writeln!(f, "{}", "-".repeat(width))?;
-        for (i, part) in self.partial_product.iter().enumerate() {
-            let part = part.iter().map(|d| d.to_string()).collect::<Vec<_>>();
-            writeln!(
-                f,
-                "{digits:>width$}",
-                digits = part.join(""),
-                width = width - i
-            )?;
-        }
-        writeln!(f, "{}", "-".repeat(width))?;
-        let product = self
-            .product
-            .iter()
-            .map(|d| d.to_string())
-            .collect::<Vec<_>>();
-        write!(
-            f,
-            "{digits:>width$}",
-            digits = product.join(""),
-            width = width
-        )
-    }
-}
-
-fn mushikui_from(s: &str) -> Mushikui {
-    let lines = s
-        .trim()
-        .lines()
-        .filter(|s| !s.contains("---"))
-        .map(|s| s.trim().chars().collect())
-        .collect::<Vec<Vec<char>>>();
-    let n = lines.len();
-    assert!(n >= 4);
-    Mushikui::new(&lines[0], &lines[1], &lines[2..(n - 1)], &lines[n - 1])
-}
-
-fn main() {
-    let problems = [
-        // Q.1
+// 本の問題と名前 (番号の書かれていない問題は直前の問題の名前に `-2` を付ける)
+const EXAMPLES: [(&str, &str); 8] = [
+    (
+        "Q1",
         r#"
           9
           *
@@ -350,7 +35,9 @@ fn main() {
         ---
          27
         "#,
-        // Q.2
+    ),
+    (
+        "Q2",
         r#"
          27
           *
@@ -359,7 +46,9 @@ fn main() {
         ---
         **9
         "#,
-        // Q.6
+    ),
+    (
+        "Q6",
         r#"
           *1
           2*
@@ -369,7 +58,9 @@ fn main() {
         ----
         ****
         "#,
-        // Q.7
+    ),
+    (
+        "Q7",
         r#"
          2*
          4*
@@ -379,6 +70,9 @@ fn main() {
         ---
         ***
         "#,
+    ),
+    (
+        "Q7-2",
         r#"
          7*
          **
@@ -388,7 +82,9 @@ fn main() {
         ---
         *3*
         "#,
-        // Q.15
+    ),
+    (
+        "Q15",
         r#"
             *1**
             2***
@@ -400,7 +96,9 @@ fn main() {
         -------
         ****7**
         "#,
-        // Q.17
+    ),
+    (
+        "Q17",
         r#"
               *1*****
                ******
@@ -414,7 +112,9 @@ fn main() {
         -------------
         *******9*****
         "#,
-        // Q.22
+    ),
+    (
+        "Q22",
         r#"
                             ************************
                                 ********************
@@ -441,15 +141,784 @@ fn main() {
         **0*9********************
         --------------------------------------------
         ********************************************
-    "#,
-    ];
+        "#,
+    ),
+];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Mode {
+    All,
+    First,
+    Count,
+    // 各問題の解がただ 1 つかを調べる
+    Batch,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    Latex,
+    Unicode,
+    Code,
+}
+
+#[derive(Debug)]
+enum Source {
+    Stdin,
+    File(String),
+    Examples,
+    Example(String),
+}
+
+#[derive(Debug)]
+struct Args {
+    mode: Mode,
+    format: Format,
+    quiet: bool,
+    time: bool,
+    explain: bool,
+    filter: Option<DigitFilter>,
+    allow_leading_zero: bool,
+    operands_equal: bool,
+    dedupe_commutative: bool,
+    digit_counts: Option<[usize; 10]>,
+    digit_limits: Vec<DigitLimit>,
+    source: Source,
+}
+
+#[derive(Debug)]
+struct GenerateArgs {
+    multiplicand_len: usize,
+    multiplier_len: usize,
+    number: usize,
+    seed: Option<u64>,
+}
+
+#[derive(Debug)]
+struct MinimizeArgs {
+    seed: Option<u64>,
+    source: Source,
+}
+
+#[derive(Debug)]
+struct BenchArgs {
+    seed: u64,
+    repeat: usize,
+}
+
+#[derive(Debug)]
+enum Command {
+    Solve(Args),
+    Generate(GenerateArgs),
+    Minimize(MinimizeArgs),
+    Cnf(Source),
+    Hint(Source),
+    Candidates(Source),
+    Bench(BenchArgs),
+    List,
+    // 使い方を表示する (`-h`, `--help`)
+    Help,
+}
+
+fn parse_command(args: &[String]) -> Result<Command, String> {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(Command::Help);
+    }
+    // サブコマンドを省略したら solve
+    match args.first().map(String::as_str) {
+        Some("generate") => parse_generate_args(&args[1..]).map(Command::Generate),
+        Some("minimize") => parse_minimize_args(&args[1..]).map(Command::Minimize),
+        Some("cnf") => parse_source_args(&args[1..]).map(Command::Cnf),
+        Some("hint") => parse_source_args(&args[1..]).map(Command::Hint),
+        Some("candidates") => parse_source_args(&args[1..]).map(Command::Candidates),
+        Some("bench") => parse_bench_args(&args[1..]).map(Command::Bench),
+        Some("solve") => parse_args(&args[1..]).map(Command::Solve),
+        Some("demo") => parse_demo_args(&args[1..]),
+        _ => parse_args(args).map(Command::Solve),
+    }
+}
+
+// `demo <name> ...` は `solve --example <name> ...`、名前がなければ `solve --examples ...`
+fn parse_demo_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("--list") if args.len() == 1 => Ok(Command::List),
+        Some(name) if !name.starts_with('-') => {
+            let mut rest = vec!["--example".to_string(), name.to_string()];
+            rest.extend_from_slice(&args[1..]);
+            parse_args(&rest).map(Command::Solve)
+        }
+        _ => {
+            let mut rest = vec!["--examples".to_string()];
+            rest.extend_from_slice(args);
+            parse_args(&rest).map(Command::Solve)
+        }
+    }
+}
 
-    for problem in problems {
-        let mut mushikui = mushikui_from(problem);
+fn parse_generate_args(args: &[String]) -> Result<GenerateArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut multiplicand_len, mut multiplier_len, mut number, mut seed) = (None, None, None, None);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--multiplicand-len" => multiplicand_len = Some(parse_value(arg, args.next())?),
+            "--multiplier-len" => multiplier_len = Some(parse_value(arg, args.next())?),
+            "--number" => number = Some(parse_value(arg, args.next())?),
+            "--seed" => seed = Some(parse_value(arg, args.next())?),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    let positive = |name: &str, len: Option<usize>| match len {
+        Some(0) => Err(format!("{} must be positive", name)),
+        Some(len) => Ok(len),
+        None => Err(format!("{} is required", name)),
+    };
+    Ok(GenerateArgs {
+        multiplicand_len: positive("--multiplicand-len", multiplicand_len)?,
+        multiplier_len: positive("--multiplier-len", multiplier_len)?,
+        number: positive("--number", number.or(Some(1)))?,
+        seed,
+    })
+}
+
+fn parse_bench_args(args: &[String]) -> Result<BenchArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut seed, mut repeat) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--seed" => seed = Some(parse_value(arg, args.next())?),
+            "--repeat" => repeat = Some(parse_value(arg, args.next())?),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    if repeat == Some(0) {
+        return Err("--repeat must be positive".to_string());
+    }
+    Ok(BenchArgs {
+        seed: seed.unwrap_or(1),
+        repeat: repeat.unwrap_or(5),
+    })
+}
+
+fn parse_minimize_args(args: &[String]) -> Result<MinimizeArgs, String> {
+    let mut args = args.iter().map(String::as_str);
+    let (mut seed, mut source) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--seed" => seed = Some(parse_value(arg, args.next())?),
+            "--input" => match args.next() {
+                Some(path) => source = replace_source(source, Source::File(path.to_string()))?,
+                None => return Err("--input requires a file".to_string()),
+            },
+            "-" => source = replace_source(source, Source::Stdin)?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => source = replace_source(source, Source::File(path.to_string()))?,
+        }
+    }
+    Ok(MinimizeArgs {
+        seed,
+        source: source.unwrap_or(Source::Stdin),
+    })
+}
+
+fn parse_source_args(args: &[String]) -> Result<Source, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut source = None;
+    while let Some(arg) = args.next() {
+        match arg {
+            "--input" => match args.next() {
+                Some(path) => source = replace_source(source, Source::File(path.to_string()))?,
+                None => return Err("--input requires a file".to_string()),
+            },
+            "-" => source = replace_source(source, Source::Stdin)?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => source = replace_source(source, Source::File(path.to_string()))?,
+        }
+    }
+    Ok(source.unwrap_or(Source::Stdin))
+}
+
+// `0` から `9` の各数字を使う回数をカンマで区切ったもの
+fn parse_digit_counts(value: Option<&str>) -> Result<[usize; 10], String> {
+    let value = value.ok_or_else(|| "--digit-counts requires a value".to_string())?;
+    let invalid = || format!("invalid value for --digit-counts: {}", value);
+    let counts = value
+        .split(',')
+        .map(|count| count.trim().parse().map_err(|_| invalid()))
+        .collect::<Result<Vec<usize>, _>>()?;
+    counts.try_into().map_err(|_| invalid())
+}
+
+// `<数字>=<回数>`
+fn parse_uses(value: Option<&str>) -> Result<DigitLimit, String> {
+    let value = value.ok_or_else(|| "--uses requires a value".to_string())?;
+    let invalid = || format!("invalid value for --uses: {}", value);
+    let (digit, count) = value.split_once('=').ok_or_else(invalid)?;
+    let digit = digit.trim().parse().map_err(|_| invalid())?;
+    let count = count.trim().parse().map_err(|_| invalid())?;
+    if digit > 9 {
+        return Err(invalid());
+    }
+    Ok(DigitLimit::exactly(digit, count))
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<&str>) -> Result<T, String> {
+    match value {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("invalid value for {}: {}", name, value)),
+        None => Err(format!("{} requires a value", name)),
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut args = args.iter().map(String::as_str);
+    let mut parsed = Args {
+        mode: Mode::All,
+        format: Format::Text,
+        quiet: false,
+        time: false,
+        explain: false,
+        filter: None,
+        allow_leading_zero: false,
+        operands_equal: false,
+        dedupe_commutative: false,
+        digit_counts: None,
+        digit_limits: Vec::new(),
+        source: Source::Stdin,
+    };
+    let (mut mode, mut source) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg {
+            "--all" => mode = replace_mode(mode, arg, Mode::All)?,
+            "--first" => mode = replace_mode(mode, arg, Mode::First)?,
+            "--count" => mode = replace_mode(mode, arg, Mode::Count)?,
+            "--batch" => mode = replace_mode(mode, arg, Mode::Batch)?,
+            "--format" => {
+                parsed.format = match args.next() {
+                    Some("text") => Format::Text,
+                    Some("json") => Format::Json,
+                    Some("latex") => Format::Latex,
+                    Some("unicode") => Format::Unicode,
+                    Some("code") => Format::Code,
+                    Some(other) => return Err(format!("unknown format: {}", other)),
+                    None => return Err("--format requires a value".to_string()),
+                }
+            }
+            "--quiet" | "-q" => parsed.quiet = true,
+            "--time" => parsed.time = true,
+            "--explain" => parsed.explain = true,
+            "--prime" => parsed.filter = Some(DigitFilter::prime()),
+            "--prime-hidden" => parsed.filter = Some(DigitFilter::hidden_prime()),
+            "--allow-leading-zero" => parsed.allow_leading_zero = true,
+            "--operands-equal" => parsed.operands_equal = true,
+            "--dedupe-commutative" => parsed.dedupe_commutative = true,
+            "--digit-counts" => parsed.digit_counts = Some(parse_digit_counts(args.next())?),
+            "--max-uses" => parsed.digit_limits.push(DigitLimit::at_most(
+                Domain::range(0, 9),
+                parse_value(arg, args.next())?,
+            )),
+            "--uses" => parsed.digit_limits.push(parse_uses(args.next())?),
+            "--examples" => source = replace_source(source, Source::Examples)?,
+            "--example" => match args.next() {
+                Some(name) => source = replace_source(source, Source::Example(name.to_string()))?,
+                None => return Err("--example requires a name".to_string()),
+            },
+            "--input" => match args.next() {
+                Some(path) => source = replace_source(source, Source::File(path.to_string()))?,
+                None => return Err("--input requires a file".to_string()),
+            },
+            "-" => source = replace_source(source, Source::Stdin)?,
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            path => source = replace_source(source, Source::File(path.to_string()))?,
+        }
+    }
+    if let Some((_, mode)) = mode {
+        parsed.mode = mode;
+    }
+    if let Some(source) = source {
+        parsed.source = source;
+    }
+    Ok(parsed)
+}
+
+// `--all`, `--first`, `--count`, `--batch` はどれか 1 つだけ
+fn replace_mode<'a>(
+    old: Option<(&'a str, Mode)>,
+    flag: &'a str,
+    new: Mode,
+) -> Result<Option<(&'a str, Mode)>, String> {
+    match old {
+        Some((old, _)) if old != flag => {
+            Err(format!("{} and {} cannot be used together", old, flag))
+        }
+        _ => Ok(Some((flag, new))),
+    }
+}
+
+fn replace_source(old: Option<Source>, new: Source) -> Result<Option<Source>, String> {
+    match old {
+        Some(_) => Err("only one input can be given".to_string()),
+        None => Ok(Some(new)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = match parse_command(&args) {
+        Ok(Command::Solve(args)) => args,
+        Ok(Command::Generate(args)) => return generate(&args),
+        Ok(Command::Minimize(args)) => return minimize(&args),
+        Ok(Command::Cnf(source)) => return cnf(&source),
+        Ok(Command::Hint(source)) => return hint(&source),
+        Ok(Command::Candidates(source)) => return candidates(&source),
+        Ok(Command::Bench(args)) => return bench(&args),
+        Ok(Command::List) => {
+            for (name, _) in EXAMPLES {
+                println!("{}", name);
+            }
+            return;
+        }
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(e) => exit_with(&format!("{}\n{}", e, USAGE)),
+    };
+    if args.mode == Mode::Batch {
+        return batch(&args);
+    }
+
+    let mut reports = Vec::new();
+    for mushikui in read_problems(&args.source) {
+        let options = solve_options(&args);
+        let (solutions, count, stats) = if args.mode == Mode::Count {
+            let (count, stats) = count_solutions(&mushikui, &options);
+            (Vec::new(), count, stats)
+        } else {
+            let (outcome, stats) = mushikui.solve_with_stats(&options);
+            let solutions = outcome.into_solutions();
+            let count = solutions.len();
+            (solutions, count, stats)
+        };
+        let explanation = if args.explain {
+            multiplication(&mushikui, "--explain").explain(&options)
+        } else {
+            None
+        };
+        let report = Report {
+            mushikui,
+            explanation,
+            solutions,
+            count,
+            elapsed: stats.elapsed,
+        };
+        match args.format {
+            Format::Json => reports.push(report),
+            _ => print_text(&report, &args),
+        }
+    }
+    if args.format == Format::Json {
+        print_json(&reports, &args);
+    }
+}
+
+fn solve_options(args: &Args) -> SolveOptions {
+    SolveOptions {
+        max_solutions: match args.mode {
+            Mode::First => Some(1),
+            Mode::Batch => Some(2),
+            Mode::All | Mode::Count => None,
+        },
+        filter: args.filter.clone(),
+        allow_leading_zero: args.allow_leading_zero,
+        operands_equal: args.operands_equal,
+        dedupe_commutative: args.dedupe_commutative,
+        digit_counts: args.digit_counts,
+        digit_limits: args.digit_limits.clone(),
+        ..SolveOptions::default()
+    }
+}
+
+// 各問題の解がただ 1 つかを 1 行ずつ表示し、そうでない問題があれば失敗で終える
+fn batch(args: &Args) {
+    let options = solve_options(args);
+    let problems = read_problems(&args.source);
+    let mut failed = 0;
+    for (i, mushikui) in problems.iter().enumerate() {
+        let (count, stats) = count_solutions(mushikui, &options);
+        let status = match count {
+            0 => "no solution",
+            1 => "unique",
+            _ => "multiple solutions",
+        };
+        if count != 1 {
+            failed += 1;
+        }
+        let ms = stats.elapsed.as_secs_f64() * 1000.0;
+        println!("#{}: {} ({:.3}ms)", i + 1, status, ms);
+    }
+    println!(
+        "{} of {} puzzles have a unique solution",
+        problems.len() - failed,
+        problems.len()
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
+}
+
+fn count_solutions(mushikui: &AnyMushikui, options: &SolveOptions) -> (usize, SearchStats) {
+    match mushikui {
+        AnyMushikui::Multiplication(mushikui) => {
+            let (count, _, stats) = mushikui.count_solutions_with(options);
+            (count, stats)
+        }
+        mushikui => {
+            let mut count = 0;
+            let (_, stats) = mushikui.solve_with(options, |_| {
+                count += 1;
+                ControlFlow::Continue(())
+            });
+            (count, stats)
+        }
+    }
+}
+
+// 掛け算の虫食算にしか使えない機能を、ほかの演算の問題で使おうとしたら失敗で終える
+fn multiplication<'a>(mushikui: &'a AnyMushikui, feature: &str) -> &'a Mushikui {
+    match mushikui {
+        AnyMushikui::Multiplication(mushikui) => mushikui,
+        _ => exit_with(&format!(
+            "{} supports only multiplication puzzles\n{}",
+            feature, mushikui
+        )),
+    }
+}
+
+// 名前の本の問題 (大文字と小文字, `.` の有無は問わない)
+fn find_example(name: &str) -> Option<&'static str> {
+    let normalize = |name: &str| name.replace('.', "").to_ascii_uppercase();
+    EXAMPLES
+        .iter()
+        .find(|(example, _)| normalize(example) == normalize(name))
+        .map(|&(_, problem)| problem)
+}
+
+// 掛け算の虫食算だけを読む
+fn read_multiplications(source: &Source, command: &str) -> Vec<Mushikui> {
+    read_problems(source)
+        .iter()
+        .map(|mushikui| multiplication(mushikui, command).clone())
+        .collect()
+}
+
+// `[` か `{` で始まる入力は JSON (問題 1 つか、その配列) として読む。
+// JSON と符号は掛け算、それ以外は書かれた演算子で種類を決める
+fn read_problems(source: &Source) -> Vec<AnyMushikui> {
+    let text = match source {
+        Source::Stdin => {
+            let mut text = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut text) {
+                exit_with(&format!("failed to read stdin: {}", e));
+            }
+            text
+        }
+        Source::File(path) => match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => exit_with(&format!("failed to read {}: {}", path, e)),
+        },
+        Source::Examples => return EXAMPLES.iter().map(|(_, p)| parse_problem(p)).collect(),
+        Source::Example(name) => match find_example(name) {
+            Some(problem) => return vec![parse_problem(problem)],
+            None => exit_with(&format!(
+                "unknown example: {} (see `mushikui demo --list`)",
+                name
+            )),
+        },
+    };
+    if !text.trim_start().starts_with(&['[', '{'][..]) {
+        return split_problems(&text)
+            .iter()
+            .flat_map(|p| {
+                // `m.` で始まる符号は 1 行に 1 問
+                if p.trim_start().starts_with("m.") {
+                    p.lines().map(parse_code).collect()
+                } else {
+                    vec![parse_problem(p)]
+                }
+            })
+            .collect();
+    }
+    let value = match json::parse(&text) {
+        Ok(value) => value,
+        Err(e) => exit_with(&format!("invalid JSON: {}", e)),
+    };
+    let values = match &value {
+        Value::Array(values) => &values[..],
+        value => std::slice::from_ref(value),
+    };
+    values
+        .iter()
+        .map(|value| match Mushikui::from_json_value(value) {
+            Ok(mushikui) => AnyMushikui::Multiplication(mushikui),
+            Err(e) => exit_with(&format!("invalid puzzle: {}\n{}", e, value)),
+        })
+        .collect()
+}
+
+fn parse_problem(problem: &str) -> AnyMushikui {
+    match problem.parse() {
+        Ok(mushikui) => mushikui,
+        Err(e) => exit_with(&format!("invalid puzzle: {}\n{}", e, problem)),
+    }
+}
+
+fn parse_code(code: &str) -> AnyMushikui {
+    match Mushikui::from_code(code) {
+        Ok(mushikui) => AnyMushikui::Multiplication(mushikui),
+        Err(e) => exit_with(&format!("invalid puzzle: {}\n{}", e, code)),
+    }
+}
+
+// 種が指定されなければ時刻から決める
+fn rng(seed: Option<u64>) -> Rng {
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+    Rng::new(seed)
+}
+
+fn minimize(args: &MinimizeArgs) {
+    let mut rng = rng(args.seed);
+    for (i, mushikui) in read_multiplications(&args.source, "minimize")
+        .iter()
+        .enumerate()
+    {
+        match mushikui.minimize(&mut rng) {
+            Some(minimized) => {
+                if i > 0 {
+                    println!();
+                }
+                println!("{}", minimized);
+            }
+            None => exit_with(&format!(
+                "puzzle does not have a unique solution\n{}",
+                mushikui
+            )),
+        }
+    }
+}
+
+fn cnf(source: &Source) {
+    match &read_multiplications(source, "cnf")[..] {
+        [mushikui] => print!("{}", mushikui.to_cnf()),
+        problems => exit_with(&format!(
+            "cnf expects exactly one puzzle, found {}",
+            problems.len()
+        )),
+    }
+}
+
+// 各問題で推論だけで決まるマスを 1 つ示す
+fn hint(source: &Source) {
+    for mushikui in read_multiplications(source, "hint") {
+        match mushikui.hint(&SolveOptions::default()) {
+            Some(hint) => println!("{}", hint),
+            None => println!("no cell can be determined without guessing"),
+        }
+    }
+}
+
+// 各問題の推論で絞った候補を示す
+fn candidates(source: &Source) {
+    for (i, mushikui) in read_multiplications(source, "candidates")
+        .iter()
+        .enumerate()
+    {
+        if i > 0 {
+            println!();
+        }
+        match mushikui.render_candidates(&SolveOptions::default()) {
+            Some(candidates) => println!("{}", candidates),
+            None => println!("no solution"),
+        }
+    }
+}
+
+// 本の問題と、種から作った問題や解の多い問題をすべての解まで解き、ノード数と時間を表示する
+fn bench(args: &BenchArgs) {
+    let mut problems = EXAMPLES
+        .iter()
+        .map(|&(name, problem)| (name.to_string(), parse_problem(problem)))
+        .collect::<Vec<_>>();
+    let mut rng = Rng::new(args.seed);
+    for (a, b) in [(4, 3), (5, 4), (6, 4)] {
+        let mushikui = Mushikui::generate(a, b, &mut rng);
+        problems.push((
+            format!("gen-{}x{}", a, b),
+            AnyMushikui::Multiplication(mushikui),
+        ));
+    }
+    for (name, problem) in [
+        ("all-3x2", "  ***\n   **\n ****\n***\n*****"),
+        ("all-4x2", "  ****\n    **\n *****\n****\n******"),
+    ] {
+        problems.push((name.to_string(), parse_problem(problem)));
+    }
+    println!(
+        "{:<10} {:>9} {:>9} {:>10}",
+        "name", "solutions", "nodes", "time (ms)"
+    );
+    let (mut total_nodes, mut total_time) = (0, Duration::ZERO);
+    for (name, mushikui) in &problems {
+        let (mut count, mut nodes, mut best) = (0, 0, Duration::MAX);
+        // 最も速かった回の時間をとる
+        for _ in 0..args.repeat {
+            count = 0;
+            let (_, stats) = mushikui.solve_with(&SolveOptions::default(), |_| {
+                count += 1;
+                ControlFlow::Continue(())
+            });
+            nodes = stats.nodes_expanded;
+            best = best.min(stats.elapsed);
+        }
+        let ms = best.as_secs_f64() * 1000.0;
+        println!("{:<10} {:>9} {:>9} {:>10.3}", name, count, nodes, ms);
+        total_nodes += nodes;
+        total_time += best;
+    }
+    println!(
+        "{:<10} {:>9} {:>9} {:>10.3}",
+        "total",
+        "",
+        total_nodes,
+        total_time.as_secs_f64() * 1000.0
+    );
+}
+
+fn generate(args: &GenerateArgs) {
+    let mut rng = rng(args.seed);
+    let mut puzzles = (0..args.number)
+        .map(|_| {
+            let mushikui = Mushikui::generate(args.multiplicand_len, args.multiplier_len, &mut rng);
+            (mushikui.rate(), mushikui)
+        })
+        .collect::<Vec<_>>();
+    // 易しい順に並べる。難しさは問題として読めるように標準エラーに出す
+    puzzles.sort_by_key(|&(difficulty, _)| difficulty);
+    for (i, (difficulty, mushikui)) in puzzles.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
         println!("{}", mushikui);
+        eprintln!("#{}: {}", i + 1, difficulty);
+    }
+}
+
+struct Report {
+    mushikui: AnyMushikui,
+    // 最初の解までの推論 (`--explain`)
+    explanation: Option<Vec<String>>,
+    solutions: Vec<AnyMushikui>,
+    count: usize,
+    elapsed: Duration,
+}
+
+fn print_text(report: &Report, args: &Args) {
+    let render = |mushikui: &AnyMushikui| match args.format {
+        Format::Latex => multiplication(mushikui, "--format latex").render_latex(),
+        Format::Unicode => multiplication(mushikui, "--format unicode").render_unicode(),
+        Format::Code => multiplication(mushikui, "--format code").to_code(),
+        _ => mushikui.to_string(),
+    };
+    if !args.quiet {
+        println!("{}", render(&report.mushikui));
         println!();
-        let result = mushikui.solve();
-        assert_eq!(result.len(), 1);
-        println!("{}", result[0]);
     }
+    if args.mode == Mode::Count {
+        println!("{}", report.count);
+    } else {
+        match report.count {
+            0 => println!("no solution"),
+            1 => println!("1 solution"),
+            n => println!("{} solutions", n),
+        }
+        for (i, solution) in report.solutions.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{}", render(solution));
+        }
+    }
+    if let Some(explanation) = &report.explanation {
+        println!();
+        for (i, line) in explanation.iter().enumerate() {
+            println!("{}. {}", i + 1, line);
+        }
+    }
+    if args.time {
+        eprintln!("solved in {:.3}ms", report.elapsed.as_secs_f64() * 1000.0);
+    }
+}
+
+fn print_json(reports: &[Report], args: &Args) {
+    let reports: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            let mut fields = Vec::new();
+            if !args.quiet {
+                fields.push(format!("\"puzzle\":{}", to_json(&report.mushikui)));
+            }
+            fields.push(format!("\"count\":{}", report.count));
+            if args.mode != Mode::Count {
+                let solutions: Vec<String> = report.solutions.iter().map(to_json).collect();
+                fields.push(format!("\"solutions\":[{}]", solutions.join(",")));
+            }
+            if let Some(explanation) = &report.explanation {
+                let lines: Vec<String> = explanation.iter().map(|line| json::quote(line)).collect();
+                fields.push(format!("\"explanation\":[{}]", lines.join(",")));
+            }
+            if args.time {
+                fields.push(format!(
+                    "\"elapsed_ms\":{:.3}",
+                    report.elapsed.as_secs_f64() * 1000.0
+                ));
+            }
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect();
+    println!("[{}]", reports.join(","));
+}
+
+// 掛け算の問題はオブジェクト、ほかの演算の問題は問題の文字列にする
+fn to_json(mushikui: &AnyMushikui) -> String {
+    match mushikui {
+        AnyMushikui::Multiplication(mushikui) => mushikui.to_json().to_string(),
+        mushikui => json::quote(&mushikui.to_string()),
+    }
+}
+
+// 空行で区切られた問題に分ける
+fn split_problems(text: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut lines = Vec::new();
+    for line in text.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !lines.is_empty() {
+                problems.push(lines.join("\n"));
+                lines.clear();
+            }
+        } else {
+            lines.push(line);
+        }
+    }
+    problems
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
 }
@@ -0,0 +1,1200 @@
+//! 15 パズルのソルバ (IDA*, A*, 両方向幅優先探索, 人の解き方をまねた貪欲法)
+//!
+//! 盤面の大きさは実行時に決まり、8 パズル (3x3) や 24 パズル (5x5)、長方形の盤面も解ける。
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Formatter;
+use core::mem;
+use core::num::ParseIntError;
+use core::ops::ControlFlow;
+use core::ptr;
+use core::str::FromStr;
+
+use crate::astar::{self, astar};
+use crate::ida_star::{self, ida_star_with_table, MoveOrder, OnIteration, SearchState};
+use crate::json;
+use crate::puzzle::{
+    CancelToken, Instant, Monitor, OnProgress, Puzzle, SearchStats, SolveOutcome, Termination,
+};
+use crate::sma_star::{self, sma_star};
+use cells::Cells;
+use heuristic::Estimated;
+use pdb::PatternSearch;
+use walking_distance::{Table, WalkingDistance};
+
+mod bidirectional;
+mod cells;
+mod distance_table;
+mod generate;
+mod greedy;
+mod heuristic;
+mod partial;
+mod pattern;
+mod pdb;
+mod render;
+mod tile_moves;
+mod walking_distance;
+
+pub use distance_table::DistanceTable;
+pub use heuristic::{BoardHeuristic, LinearConflict, Misplaced};
+pub use partial::solve_partial;
+pub use pattern::solve_pattern;
+pub use pdb::{PatternDatabase, PdbBuilder, PdbError};
+pub use render::{render_gif, render_svg};
+pub use tile_moves::{tile_moves, TileMove};
+
+/// 15 パズルの盤面の一辺の長さ
+pub const N: usize = 4;
+
+/// 盤面のマスに置かれた数 (`0` は空きマス)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Value(u8);
+
+impl Value {
+    /// 数
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:2}", self.0)
+    }
+}
+
+/// 空きマスを動かす向き
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Dir {
+    R,
+    U,
+    L,
+    D,
+}
+
+impl Dir {
+    /// 逆向き
+    pub fn reverse(self) -> Self {
+        match self {
+            Dir::R => Dir::L,
+            Dir::U => Dir::D,
+            Dir::L => Dir::R,
+            Dir::D => Dir::U,
+        }
+    }
+}
+
+/// `R`, `U`, `L`, `D` の 1 文字で表す
+impl fmt::Display for Dir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Dir::R => 'R',
+            Dir::U => 'U',
+            Dir::L => 'L',
+            Dir::D => 'D',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+/// 15 パズルの盤面 (大きさは任意)
+///
+/// 完成形は 1, 2, ... の順に並んで空きマスが右下にある並びだが、[`Board::with_goal`] でほかの並びにできる。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Board {
+    // 各マスの数
+    cells: Cells,
+    height: usize,
+    width: usize,
+    empty: (usize, usize),
+    estimate: u32,
+    // 各マスの数から作る Zobrist ハッシュ (空きマスの位置はほかのタイルで決まるので含めない)
+    hash: u64,
+    // 各数が完成形で置かれるマス (`None` なら 1, 2, ... の順で空きマスが最後)
+    goal: Option<Arc<Vec<u8>>>,
+}
+
+// `k` 番目のマスに `v` があることに対応する Zobrist ハッシュの乱数
+//
+// 表を持たずに、(マス, 数) を splitmix64 で混ぜて作る。
+fn zobrist(k: usize, v: Value) -> u64 {
+    let mut z = ((k as u64) << 8 | u64::from(v.0)).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 盤面として読めなかった理由
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BoardError {
+    /// 2 回以上現れた数
+    DuplicateTile(u8),
+    /// 現れなかった数
+    MissingTile(u8),
+    /// `0` からマスの数 - 1 の範囲外の数
+    OutOfRange(u8),
+    /// 盤面が 2x2 以上の長方形でない (1 行に書くときは数の個数が平方数でない)
+    WrongDimensions,
+    /// 完成形の盤面の大きさが盤面と違う
+    GoalDimensions,
+    /// 数として読めなかった
+    Parse(ParseIntError),
+    /// JSON として読めないか、盤面の形になっていない (理由)
+    InvalidJson(String),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::DuplicateTile(v) => write!(f, "tile {} appears more than once", v),
+            BoardError::MissingTile(v) => write!(f, "tile {} is missing", v),
+            BoardError::OutOfRange(v) => write!(f, "tile {} is out of range", v),
+            BoardError::WrongDimensions => write!(
+                f,
+                "board must be a rectangle of at least 2x2 (or a square number of tiles on one line)"
+            ),
+            BoardError::GoalDimensions => write!(f, "goal must be the same size as the board"),
+            BoardError::Parse(e) => write!(f, "{}", e),
+            BoardError::InvalidJson(reason) => write!(f, "invalid JSON: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BoardError {}
+
+impl From<ParseIntError> for BoardError {
+    fn from(e: ParseIntError) -> Self {
+        BoardError::Parse(e)
+    }
+}
+
+impl Board {
+    /// `0` を空きマスとして 4x4 の盤面を作る
+    ///
+    /// # Panics
+    ///
+    /// [`Board::try_new`] が失敗するとき
+    pub fn new(board: [[u8; N]; N]) -> Self {
+        match Self::try_new(&board) {
+            Ok(board) => board,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// `0` を空きマスとして、行の列から盤面を作る
+    ///
+    /// 盤面は 2x2 以上の長方形で、`0` からマスの数 - 1 がちょうど 1 回ずつ現れないときはエラーを返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// // 8 パズル
+    /// let board = Board::try_new(&[[1, 2, 3], [4, 5, 6], [7, 0, 8]]).unwrap();
+    /// assert_eq!((board.height(), board.width()), (3, 3));
+    /// ```
+    pub fn try_new<R: AsRef<[u8]>>(rows: &[R]) -> Result<Self, BoardError> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.as_ref().len());
+        if height < 2
+            || width < 2
+            || height * width > 256
+            || rows.iter().any(|row| row.as_ref().len() != width)
+        {
+            return Err(BoardError::WrongDimensions);
+        }
+        let mut cells = Vec::with_capacity(height * width);
+        let mut seen = vec![false; height * width];
+        let mut empty = (0, 0);
+        for (i, row) in rows.iter().enumerate() {
+            for (j, &v) in row.as_ref().iter().enumerate() {
+                if usize::from(v) >= height * width {
+                    return Err(BoardError::OutOfRange(v));
+                }
+                if seen[usize::from(v)] {
+                    return Err(BoardError::DuplicateTile(v));
+                }
+                seen[usize::from(v)] = true;
+                if v == 0 {
+                    empty = (i, j);
+                }
+                cells.push(Value(v));
+            }
+        }
+        if let Some(v) = seen.iter().position(|&s| !s) {
+            return Err(BoardError::MissingTile(v as u8));
+        }
+
+        let hash = cells
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 > 0)
+            .fold(0, |hash, (k, &v)| hash ^ zobrist(k, v));
+        let mut board = Self {
+            cells: Cells::new(cells),
+            height,
+            width,
+            empty,
+            estimate: 0,
+            hash,
+            goal: None,
+        };
+        board.estimate = board.estimate_all();
+        Ok(board)
+    }
+
+    /// 完成形を `goal` の並びにした盤面
+    ///
+    /// 空きマスを左上に置いた並びや渦巻きの並びなど、どの並びでも完成形にできる。
+    /// `goal` の完成形は見ない。`goal` が盤面と違う大きさのときはエラーを返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve, Board};
+    ///
+    /// let goal: Board = "0 1 2 3 4 5 6 7 8".parse().unwrap();
+    /// let board: Board = "1 0 2 3 4 5 6 7 8".parse().unwrap();
+    /// let mut board = board.with_goal(&goal).unwrap();
+    /// assert_eq!(solve(&mut board).unwrap().len(), 1 + 1);
+    /// assert_eq!(board.goal().get((0, 0)).get(), 0);
+    /// ```
+    pub fn with_goal(mut self, goal: &Board) -> Result<Board, BoardError> {
+        if (goal.height, goal.width) != (self.height, self.width) {
+            return Err(BoardError::GoalDimensions);
+        }
+        let mut targets = vec![0; goal.cells.len()];
+        for (k, v) in goal.cells.iter().enumerate() {
+            targets[usize::from(v.0)] = k as u8;
+        }
+        self.goal = if goal.cells == Board::solved(self.height, self.width).cells {
+            None
+        } else {
+            Some(Arc::new(targets))
+        };
+        self.estimate = self.estimate_all();
+        Ok(self)
+    }
+
+    /// 完成形の盤面
+    pub fn goal(&self) -> Board {
+        let mut cells = vec![0; self.cells.len()];
+        for v in 0..cells.len() {
+            cells[self.target(Value(v as u8))] = v as u8;
+        }
+        let mut goal = Board::try_new(&cells.chunks(self.width).collect::<Vec<_>>()).unwrap();
+        goal.goal = self.goal.clone();
+        goal.estimate = 0;
+        goal
+    }
+
+    // 完成形で `v` が置かれるマス
+    fn target(&self, v: Value) -> usize {
+        match &self.goal {
+            Some(targets) => usize::from(targets[usize::from(v.0)]),
+            None if v.0 == 0 => self.cells.len() - 1,
+            None => usize::from(v.0 - 1),
+        }
+    }
+
+    // (i, j) のタイルの正しい位置までのマンハッタン距離
+    fn move_cost(&self, (i, j): (usize, usize)) -> u32 {
+        let target = self.target(self.cells.get(i * self.width + j));
+        let (ti, tj) = (target / self.width, target % self.width);
+        ((i.max(ti) - i.min(ti)) + (j.max(tj) - j.min(tj))) as u32
+    }
+
+    fn estimate_all(&self) -> u32 {
+        let mut cost = 0;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                if (i, j) == self.empty {
+                    continue;
+                }
+                cost += self.move_cost((i, j));
+            }
+        }
+        cost
+    }
+
+    // 空きマスを dir の方向にずらした先 (盤面の外なら `None`)
+    fn neighbor(&self, dir: Dir) -> Option<(usize, usize)> {
+        let (i, j) = self.empty;
+        match dir {
+            Dir::R if j + 1 < self.width => Some((i, j + 1)),
+            Dir::U if i > 0 => Some((i - 1, j)),
+            Dir::L if j > 0 => Some((i, j - 1)),
+            Dir::D if i + 1 < self.height => Some((i + 1, j)),
+            _ => None,
+        }
+    }
+
+    // 空きマスを dir の方向にずらす
+    fn slide(&mut self, dir: Dir) -> Result<(), ()> {
+        let (i, j) = self.empty;
+        let next = self.neighbor(dir).ok_or(())?;
+        self.estimate -= self.move_cost(next);
+        let (from, to) = (next.0 * self.width + next.1, i * self.width + j);
+        let v = self.cells.get(from);
+        self.hash ^= zobrist(from, v) ^ zobrist(to, v);
+        self.cells.swap(from, to);
+        self.empty = next;
+        self.estimate += self.move_cost((i, j));
+        Ok(())
+    }
+
+    /// 各マスの数 (行ごと)
+    pub fn board(&self) -> Vec<Vec<Value>> {
+        self.cells
+            .to_vec()
+            .chunks(self.width)
+            .map(<[Value]>::to_vec)
+            .collect()
+    }
+
+    /// `(i, j)` のマスの数
+    ///
+    /// # Panics
+    ///
+    /// `(i, j)` が盤面の外のとき
+    pub fn get(&self, (i, j): (usize, usize)) -> Value {
+        assert!(i < self.height && j < self.width);
+        self.cells.get(i * self.width + j)
+    }
+
+    /// 16 マス以下の盤面を 1 マス 4 ビットで `u64` に詰めた値 (それより大きい盤面では `None`)
+    ///
+    /// 左上のマスから順に下位ビットに詰める。盤面の大きさが同じなら、値が等しいことと盤面が等しいことは同じ。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// let board: Board = "1 2 3 4 5 6 7 8 0".parse().unwrap();
+    /// assert_eq!(board.packed(), Some(0x8765_4321));
+    /// assert_eq!(board.get((2, 1)).get(), 8);
+    /// ```
+    pub fn packed(&self) -> Option<u64> {
+        self.cells.packed()
+    }
+
+    /// 盤面の Zobrist ハッシュ
+    ///
+    /// (マス, 数) ごとに決めた乱数の排他的論理和で、空きマスを動かすたびに差分で更新する。
+    /// 置換表や訪れた盤面の表を引くのに使う。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// let a: Board = "1 2 3 4 5 6 7 8 0".parse().unwrap();
+    /// let b: Board = "1 2 3 4 5 6 7 0 8".parse().unwrap();
+    /// assert_ne!(a.zobrist(), b.zobrist());
+    /// assert_eq!(a.zobrist(), Board::solved(3, 3).zobrist());
+    /// ```
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    // 探索の表で盤面を見分けるキー (ハッシュ値で先に比べる)
+    fn key(&self) -> (u64, Cells) {
+        (self.hash, self.cells.clone())
+    }
+
+    /// 行の数
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 列の数
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// 空きマスの位置
+    pub fn empty(&self) -> (usize, usize) {
+        self.empty
+    }
+
+    /// 各マスの正しい位置までのマンハッタン距離の和
+    pub fn estimate(&self) -> u32 {
+        self.estimate
+    }
+
+    /// `kind` の下界で見た、完成までの手数の下界
+    ///
+    /// 探索で使うのと同じ値 (walking distance とパターンデータベースはマンハッタン距離より小さければマンハッタン距離)。
+    /// 盤面の難しさで並べたり、下界ごとの分布を調べたりするのに使う。
+    /// [`Heuristic::WalkingDistance`] は呼ぶたびに表を作るので、たくさんの盤面を調べるときは遅い。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::{Board, Heuristic};
+    ///
+    /// let board: Board = "5 4 7 6 15 0 13 10 2 1 8 3 12 14 11 9".parse().unwrap();
+    /// assert_eq!(board.heuristic(&Heuristic::Manhattan), board.estimate());
+    /// assert!(board.heuristic(&Heuristic::Misplaced) <= board.heuristic(&Heuristic::Manhattan));
+    /// assert!(board.heuristic(&Heuristic::LinearConflict) >= board.heuristic(&Heuristic::Manhattan));
+    /// assert!(board.heuristic(&Heuristic::WalkingDistance) <= 52);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// [`Heuristic::PatternDatabase`] のパターンデータベースが違う大きさの盤面のものか、盤面の完成形の空きマスが右下にないとき
+    pub fn heuristic(&self, kind: &Heuristic) -> u32 {
+        match kind {
+            Heuristic::Misplaced => Misplaced.estimate(self),
+            Heuristic::Manhattan => self.estimate,
+            Heuristic::LinearConflict => LinearConflict.estimate(self),
+            Heuristic::WalkingDistance
+                if walking_distance::is_feasible(self.height, self.width) =>
+            {
+                SearchState::heuristic(&walking_distance_state(self))
+            }
+            Heuristic::WalkingDistance => self.estimate,
+            Heuristic::PatternDatabase(pdb) => pdb.estimate(self).max(self.estimate),
+            Heuristic::Custom(heuristic) => heuristic.estimate(self),
+        }
+    }
+
+    /// 完成しているか
+    pub fn is_solved(&self) -> bool {
+        self.estimate == 0
+    }
+
+    /// 完成させられるか
+    ///
+    /// 数の並びの置換 (空きマスを含む) の偶奇と、空きマスの完成位置までのマンハッタン距離の偶奇が一致すればよい。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// let board: Board = "1 2 3 4 5 6 7 8 9 10 11 12 13 15 14 0".parse().unwrap();
+    /// assert!(!board.is_solvable());
+    /// ```
+    pub fn is_solvable(&self) -> bool {
+        let size = self.cells.len();
+        // 各マスの数が完成形で置かれる位置
+        let cells = self
+            .cells
+            .iter()
+            .map(|v| self.target(v))
+            .collect::<Vec<_>>();
+        let mut seen = vec![false; size];
+        let mut swaps = 0;
+        for i in 0..size {
+            if seen[i] {
+                continue;
+            }
+            // 長さ `len` の巡回置換は `len - 1` 回の互換
+            let mut j = i;
+            while !seen[j] {
+                seen[j] = true;
+                j = cells[j];
+                swaps += 1;
+            }
+            swaps -= 1;
+        }
+        let (i, j) = self.empty;
+        let blank = self.target(Value(0));
+        let (ti, tj) = (blank / self.width, blank % self.width);
+        swaps % 2 == ((i.max(ti) - i.min(ti)) + (j.max(tj) - j.min(tj))) % 2
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // 数の桁数 (15 パズルまでは 2 桁にそろえる)
+        let width = (self.cells.len() - 1).to_string().len().max(2);
+        for (i, row) in self.cells.to_vec().chunks(self.width).enumerate() {
+            let row: Vec<String> = row
+                .iter()
+                .map(|val| format!("{:width$}", val.get(), width = width))
+                .collect();
+            if i + 1 < self.height {
+                writeln!(f, "{}", row.join(" "))?;
+            } else {
+                write!(f, "{}", row.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 空白区切りの数 (`0` は空きマス) から盤面を作る
+///
+/// 複数行に書けば各行を盤面の行とする。1 行に書いたときは、数の個数が平方数なら正方形の盤面とする (16 個なら 4x4)。
+impl FromStr for Board {
+    type Err = BoardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|v| v.parse::<u8>())
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let [values] = &rows[..] {
+            return Board::square(values);
+        }
+        Board::try_new(&rows)
+    }
+}
+
+impl Board {
+    /// 行ごとの数の配列を並べた JSON の配列にする
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// let board: Board = "1 2 3 4 5 6 7 0 8".parse().unwrap();
+    /// let json = board.to_json().to_string();
+    /// assert_eq!(json, "[[1,2,3],[4,5,6],[7,0,8]]");
+    /// assert_eq!(Board::from_json(&json).unwrap(), board);
+    /// assert_eq!(Board::from_json("[1,2,3,4,5,6,7,0,8]").unwrap(), board);
+    /// assert_eq!(Board::from_json(r#"{"board":[[1,2,3],[4,5,6],[7,0,8]],"moves":"R"}"#).unwrap(), board);
+    /// ```
+    pub fn to_json(&self) -> json::Value {
+        let rows = self
+            .board()
+            .iter()
+            .map(|row| {
+                json::Value::Array(
+                    row.iter()
+                        .map(|v| json::Value::Number(f64::from(v.0)))
+                        .collect(),
+                )
+            })
+            .collect();
+        json::Value::Array(rows)
+    }
+
+    /// JSON の配列から盤面を作る
+    ///
+    /// 行ごとの数の配列を並べた配列か、数の個数が平方数の 1 つの配列 (正方形の盤面) を読む。
+    /// `"board"` を持つオブジェクトなら、その値を読む (ほかのキーは読み飛ばす)。
+    pub fn from_json(s: &str) -> Result<Self, BoardError> {
+        let value = json::parse(s).map_err(|e| BoardError::InvalidJson(e.to_string()))?;
+        Self::from_json_value(&value)
+    }
+
+    /// 読んだ JSON の値から盤面を作る
+    pub fn from_json_value(value: &json::Value) -> Result<Self, BoardError> {
+        let value = value.get("board").unwrap_or(value);
+        let invalid =
+            || BoardError::InvalidJson("expected an array of numbers or rows".to_string());
+        let numbers = |values: &[json::Value]| {
+            values
+                .iter()
+                .map(|v| v.as_u64().and_then(|v| u8::try_from(v).ok()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid)
+        };
+        let values = value.as_array().ok_or_else(invalid)?;
+        if values.iter().all(|v| v.as_array().is_some()) && !values.is_empty() {
+            let rows = values
+                .iter()
+                .map(|row| numbers(row.as_array().unwrap()))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Board::try_new(&rows);
+        }
+        Board::square(&numbers(values)?)
+    }
+
+    // 1 列に並べた数から正方形の盤面を作る (数の個数が平方数でなければエラー)
+    fn square(values: &[u8]) -> Result<Self, BoardError> {
+        let side = (1..=values.len())
+            .find(|&k| k * k >= values.len())
+            .filter(|&k| k * k == values.len())
+            .ok_or(BoardError::WrongDimensions)?;
+        Board::try_new(&values.chunks(side).collect::<Vec<_>>())
+    }
+}
+
+impl Puzzle for Board {
+    /// 初期盤面から完成までの各盤面
+    type Solution = Vec<Board>;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(&self, options: &Self::Options, on_solution: F) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        solve_with(&mut self.clone(), options, on_solution)
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        match (solution.first(), solution.last()) {
+            (Some(first), Some(last)) if first == self && last.is_solved() => {}
+            _ => return false,
+        }
+        solution.windows(2).all(|w| {
+            [Dir::R, Dir::U, Dir::L, Dir::D].iter().any(|&dir| {
+                let mut board = w[0].clone();
+                board.slide(dir).is_ok() && board == w[1]
+            })
+        })
+    }
+}
+
+/// 探索で使う、完成までの手数の下界
+#[derive(Debug, Clone)]
+pub enum Heuristic {
+    /// 正しい位置にないタイルの枚数 ([`Misplaced`])
+    Misplaced,
+    /// 各タイルの正しい位置までのマンハッタン距離の和
+    Manhattan,
+    /// マンハッタン距離に、同じ行 (列) で逆の順に並んだタイルの分を足したもの ([`LinearConflict`])
+    LinearConflict,
+    /// walking distance
+    ///
+    /// 空きマスを縦に動かす手だけを見て、各行にゴールが何行目のタイルが何枚あるかだけで数えた最短手数と、
+    /// 横について同じように数えた最短手数の和 (マンハッタン距離より小さければマンハッタン距離)。
+    /// 同じ列のタイルどうしの入れ替えも数えるので、難しい 4x4 の盤面で探索するノードがずっと少ない。
+    /// 探索の前に盤面の大きさごとの表を作る。
+    ///
+    /// 5 行 (列) 以上ある盤面では表が大きすぎる (5x5 で 6500 万状態を超える) ので、マンハッタン距離を使う。
+    /// 24 パズルには [`Heuristic::PatternDatabase`] を使う。
+    WalkingDistance,
+    /// 加法的パターンデータベース ([`PatternDatabase`]。マンハッタン距離より小さければマンハッタン距離)
+    ///
+    /// 盤面と同じ大きさのもので、盤面の完成形の空きマスが右下にあること (タイルの並びはほかの並びでもよい)。
+    PatternDatabase(Arc<PatternDatabase>),
+    /// 自分で書いた下界 ([`BoardHeuristic`])
+    Custom(Arc<dyn BoardHeuristic>),
+}
+
+/// [`Heuristic::Custom`] は同じ値を指しているときだけ等しい
+impl PartialEq for Heuristic {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Heuristic::PatternDatabase(a), Heuristic::PatternDatabase(b)) => a == b,
+            (Heuristic::Custom(a), Heuristic::Custom(b)) => {
+                ptr::eq(Arc::as_ptr(a) as *const u8, Arc::as_ptr(b) as *const u8)
+            }
+            _ => mem::discriminant(self) == mem::discriminant(other),
+        }
+    }
+}
+
+impl Eq for Heuristic {}
+
+impl Default for Heuristic {
+    fn default() -> Self {
+        Heuristic::Manhattan
+    }
+}
+
+/// 探索のアルゴリズム
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_with_options, Algorithm, Board, SolveOptions};
+///
+/// let mut board: Board = "1 2 3 4 5 6 7 8 9 10 0 12 13 14 11 15".parse().unwrap();
+/// let options = SolveOptions {
+///     algorithm: Algorithm::AStar,
+///     ..SolveOptions::default()
+/// };
+/// let boards = solve_with_options(&mut board, &options).into_solutions().pop().unwrap();
+/// assert_eq!(boards.len(), 3);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Algorithm {
+    /// IDA* (メモリをほとんど使わない)
+    IdaStar,
+    /// A*
+    ///
+    /// 訪れた盤面をすべて覚えるので同じ盤面を何度も展開しないが、盤面の数に比例したメモリを使う。
+    /// 最短手順を 1 つだけ見つける (`max_solutions` は見ない)。
+    AStar,
+    /// SMA* ([`sma_star`](crate::sma_star::sma_star))
+    ///
+    /// A* のように評価値の小さい盤面から広げるが、覚える盤面が `max_states` を超えたら評価値の最も大きい葉を捨てる。
+    /// A* ではメモリが足りず、IDA* では同じ盤面を展開し直しすぎるときに使う。見つかるのは `max_states - 1` 手以下の手順だけ。
+    /// 最短手順を 1 つだけ見つける (`max_solutions` は見ない)。
+    SmaStar,
+    /// 両方向幅優先探索
+    ///
+    /// 盤面と完成形の両方から幅優先探索して、出会ったところで最短手順を作る。下界は使わない。
+    /// 最適な手数がほどほどの盤面では速いが、覚える盤面が `max_states` を超えたら IDA* に切り替える。
+    /// 最短手順を 1 つだけ見つける (`max_solutions` は見ない)。
+    Bidirectional,
+    /// 人の解き方をまねた貪欲法 ([`solve_greedy`])
+    ///
+    /// 最短とは限らない手順を 1 つだけすぐに見つける。下界と手数の上限は使わない。
+    Greedy,
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Algorithm::IdaStar
+    }
+}
+
+/// 手数の数え方
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_with_options, tile_moves, Board, Metric, SolveOptions};
+///
+/// let mut board: Board = "1 2 3 4 5 6 0 7 8".parse().unwrap();
+/// let options = SolveOptions {
+///     metric: Metric::Tile,
+///     ..SolveOptions::default()
+/// };
+/// let boards = solve_with_options(&mut board, &options).into_solutions().pop().unwrap();
+/// assert_eq!(boards.len(), 2 + 1);
+/// assert_eq!(tile_moves(&boards).len(), 1);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Metric {
+    /// 空きマスを 1 マス動かすごとに 1 手
+    Blank,
+    /// 同じ行か列に並んだタイルをまとめてずらすごとに 1 手 ([`TileMove`])
+    ///
+    /// [`Algorithm::IdaStar`] と [`Algorithm::AStar`] で、タイルを動かす手の数が最小の手順を探す。
+    /// 下界はタイルの横と縦の距離の和から作り、[`SolveOptions::heuristic`] は使わない。
+    /// `max_depth` もタイルを動かす手の数で数える。ほかのアルゴリズムでは使わない。
+    Tile,
+}
+
+impl Default for Metric {
+    fn default() -> Self {
+        Metric::Blank
+    }
+}
+
+/// 探索の設定
+#[derive(Debug, Clone)]
+pub struct SolveOptions {
+    /// 見つける最短手順の個数の上限 (`None` なら全部)
+    pub max_solutions: Option<usize>,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+    /// IDA* の反復を終えるたびにその統計を受け取るコールバック (ほかのアルゴリズムでは使わない)
+    pub on_iteration: Option<OnIteration>,
+    /// 手数の上限 (`None` なら上限なし)
+    pub max_depth: Option<usize>,
+    /// 手数の下界
+    pub heuristic: Heuristic,
+    /// 探索のアルゴリズム
+    pub algorithm: Algorithm,
+    /// 最小にする手数の数え方
+    pub metric: Metric,
+    /// [`Algorithm::Bidirectional`] と [`Algorithm::SmaStar`] で覚える盤面の数の上限
+    pub max_states: usize,
+    /// IDA* の置換表に使うメモリのおおよそのバイト数 (`0` なら置換表を使わない)
+    ///
+    /// 置換表は盤面ごとに着いた最小の手数を反復をまたいで覚え、同じ盤面をより深いところで探索しない。
+    /// 難しい盤面で展開するノードが減る。
+    pub table_memory: usize,
+    /// IDA* で、今の手順の何手前までの盤面に戻る手を枝刈りするか (`0` なら直前の手を戻す手だけを除く)
+    ///
+    /// 置換表と違ってメモリを使わずに、2x2 のマスを空きマスが 3 周する 12 手のような短い閉路を除ける。
+    pub cycle_window: usize,
+    /// IDA* で子を試す順
+    ///
+    /// [`MoveOrder::Heuristic`] なら下界を減らす手から試し、最後の反復で早く解に着く。
+    pub move_order: MoveOrder,
+    /// IDA* で使うスレッドの数
+    ///
+    /// 2 以上なら根に近い数手で探索木を分けて並列に探す ([`parallel_ida_star_with`](ida_star::parallel_ida_star_with))。
+    /// このとき置換表は使わない。`std` なしでは常に 1 つのスレッドで探す。
+    pub threads: usize,
+    /// 下界にかける重み (1 以上 [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) 以下)
+    ///
+    /// 1 より大きいと IDA* と A* は評価値を `手数 + weight * 下界` にして、最短手順の `weight` 倍以内の手順を速く探す。
+    /// [`Algorithm::Bidirectional`] では使わない (IDA* に切り替えたときは使う)。
+    pub weight: f64,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            max_solutions: Some(1),
+            deadline: None,
+            cancel: None,
+            on_progress: None,
+            on_iteration: None,
+            max_depth: None,
+            heuristic: Heuristic::default(),
+            algorithm: Algorithm::default(),
+            metric: Metric::default(),
+            max_states: 2_000_000,
+            table_memory: 0,
+            cycle_window: 0,
+            move_order: MoveOrder::Fixed,
+            threads: 1,
+            weight: 1.0,
+        }
+    }
+}
+
+impl SearchState for Board {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.slide(dir).is_ok()
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.slide(dir.reverse()).is_ok());
+    }
+
+    fn heuristic(&self) -> u32 {
+        self.estimate
+    }
+
+    fn is_goal(&self) -> bool {
+        self.estimate == 0
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+/// 続く盤面の間で空きマスを動かした向きの列
+///
+/// [`solve`] などが返した盤面の列から、`ULDDR` のような手順の文字列を作るのに使う。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{moves, solve, Board};
+///
+/// let mut board: Board = "1 2 3 4 5 6 7 8 9 10 11 12 13 14 0 15".parse().unwrap();
+/// let boards = solve(&mut board).unwrap();
+/// let moves = moves(&boards).iter().map(|dir| dir.to_string()).collect::<String>();
+/// assert_eq!(moves, "R");
+/// ```
+///
+/// # Panics
+///
+/// 続く盤面が空きマスを 1 マス動かしたものでないとき
+pub fn moves(boards: &[Board]) -> Vec<Dir> {
+    boards
+        .windows(2)
+        .map(|w| {
+            let ((i, j), (ni, nj)) = (w[0].empty, w[1].empty);
+            match (ni as isize - i as isize, nj as isize - j as isize) {
+                (0, 1) => Dir::R,
+                (-1, 0) => Dir::U,
+                (0, -1) => Dir::L,
+                (1, 0) => Dir::D,
+                _ => panic!("boards are not one move apart"),
+            }
+        })
+        .collect()
+}
+
+/// IDA* で最短手順を探す
+///
+/// 見つかれば `board` から完成までの各盤面を順に返す。
+pub fn solve(board: &mut Board) -> Option<Vec<Board>> {
+    solve_with_options(board, &SolveOptions::default())
+        .into_solutions()
+        .pop()
+}
+
+/// 人が解くように、上の行と左の列から 1 本ずつタイルをそろえて完成させる
+///
+/// 各タイルはそろえたタイルを崩さずに運び、行 (列) の最後の 2 枚は、最後のタイルを 1 つ手前のマスに、
+/// 1 つ手前のタイルをその隣に並べてから 2 手で入れる。残った 2x2 はまとめて回す。
+/// 最短ではないが、どの盤面でもすぐに解ける。完成させられない盤面では `None` を返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_greedy, Board};
+///
+/// let board: Board = "5 4 7 6 15 0 13 10 2 1 8 3 12 14 11 9".parse().unwrap();
+/// let boards = solve_greedy(&board).unwrap();
+/// assert!(boards.last().unwrap().is_solved());
+/// // 最短は 52 手
+/// assert!(boards.len() - 1 >= 52);
+/// ```
+pub fn solve_greedy(board: &Board) -> Option<Vec<Board>> {
+    let options = SolveOptions {
+        algorithm: Algorithm::Greedy,
+        ..SolveOptions::default()
+    };
+    solve_with_options(&mut board.clone(), &options)
+        .into_solutions()
+        .pop()
+}
+
+/// `options` にしたがって最短手順を探す
+///
+/// 見つかった最短手順ごとに `board` から完成までの各盤面を返す。
+pub fn solve_with_options(board: &mut Board, options: &SolveOptions) -> SolveOutcome<Vec<Board>> {
+    solve_with_stats(board, options).0
+}
+
+/// `options` にしたがって最短手順を探し、探索の統計も返す
+pub fn solve_with_stats(
+    board: &mut Board,
+    options: &SolveOptions,
+) -> (SolveOutcome<Vec<Board>>, SearchStats) {
+    let mut solutions = Vec::new();
+    let (termination, stats) = solve_with(board, options, |boards| {
+        solutions.push(boards);
+        ControlFlow::Continue(())
+    });
+    (SolveOutcome::new(termination, solutions), stats)
+}
+
+/// 最短手順の最初の 1 手 (空きマスを動かす向き)
+///
+/// `options` で探索し (`max_solutions` は見ない)、最初に見つかった手順の 1 手目を返す。
+/// 手順全体を返さないので、GUI でヒントとして次の 1 手だけを示すのに使う。`options.deadline` で時間を区切れる。
+/// 完成している盤面, 完成させられない盤面, 時間切れか中断で手順が見つからなかったときは `None` を返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{hint, Board, Dir, SolveOptions};
+///
+/// let board: Board = "1 2 3 4 5 6 0 7 8".parse().unwrap();
+/// assert_eq!(hint(&board, &SolveOptions::default()), Some(Dir::R));
+/// let solved: Board = "1 2 3 4 5 6 7 8 0".parse().unwrap();
+/// assert_eq!(hint(&solved, &SolveOptions::default()), None);
+/// ```
+pub fn hint(board: &Board, options: &SolveOptions) -> Option<Dir> {
+    let options = SolveOptions {
+        max_solutions: Some(1),
+        ..options.clone()
+    };
+    let mut first = None;
+    solve_with(&mut board.clone(), &options, |boards| {
+        first = moves(&boards[..boards.len().min(2)]).first().copied();
+        ControlFlow::Break(())
+    });
+    first
+}
+
+/// `options` にしたがって最短手順を数え、探索の終わり方と統計も返す
+///
+/// `options.max_solutions` を `None` にすると、最短手順 (空きマスを動かす向きの列) をすべて数える。
+/// 最短手順を全部見つけるのは IDA* だけで、ほかのアルゴリズムでは高々 1 つ数える。
+/// 時間切れや中断で打ち切ったときは、それまでに見つかった最短手順の個数を返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{count_solutions_with, Board, SolveOptions};
+///
+/// // 最短の 31 手の手順は 40 通り
+/// let board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// let options = SolveOptions {
+///     max_solutions: None,
+///     ..SolveOptions::default()
+/// };
+/// let (count, _, _) = count_solutions_with(&board, &options);
+/// assert_eq!(count, 40);
+/// ```
+pub fn count_solutions_with(
+    board: &Board,
+    options: &SolveOptions,
+) -> (usize, Termination, SearchStats) {
+    let mut count = 0;
+    let (termination, stats) = solve_with(&mut board.clone(), options, |_| {
+        count += 1;
+        ControlFlow::Continue(())
+    });
+    (count, termination, stats)
+}
+
+/// `options` にしたがって最短手順を探し、見つけるたびに `on_solution` を呼ぶ
+///
+/// `on_solution` が [`ControlFlow::Break`] を返すと探索をやめる。
+/// 完成させられない盤面 ([`Board::is_solvable`]) では解なしで終わる。
+///
+/// # Panics
+///
+/// - [`Heuristic::PatternDatabase`] のパターンデータベースが `board` と違う大きさの盤面のものであるとき
+/// - [`Heuristic::PatternDatabase`] で、`board` の完成形の空きマスが右下にないとき
+/// - `options.weight` が 1 未満か [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) より大きいとき
+pub fn solve_with(
+    board: &mut Board,
+    options: &SolveOptions,
+    mut on_solution: impl FnMut(Vec<Board>) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    // 完成させられない盤面は探索しない (手数の上限なしに探し続けてしまう)
+    if !board.is_solvable() {
+        return (Termination::Complete, SearchStats::default());
+    }
+    let start = board.clone();
+    let mut on_path = |path: &[Dir]| {
+        let mut board = start.clone();
+        let mut boards = vec![board.clone()];
+        for &dir in path {
+            assert!(board.slide(dir).is_ok());
+            boards.push(board.clone());
+        }
+        on_solution(boards)
+    };
+    if options.metric == Metric::Tile
+        && matches!(options.algorithm, Algorithm::IdaStar | Algorithm::AStar)
+    {
+        return tile_moves::search(board, options, &mut on_path);
+    }
+    match &options.heuristic {
+        Heuristic::Manhattan => search(board, options, &mut on_path),
+        Heuristic::Misplaced => {
+            let mut state = Estimated::new(board.clone(), Arc::new(Misplaced));
+            search(&mut state, options, &mut on_path)
+        }
+        Heuristic::LinearConflict => {
+            let mut state = Estimated::new(board.clone(), Arc::new(LinearConflict));
+            search(&mut state, options, &mut on_path)
+        }
+        Heuristic::Custom(heuristic) => {
+            let mut state = Estimated::new(board.clone(), Arc::clone(heuristic));
+            search(&mut state, options, &mut on_path)
+        }
+        Heuristic::WalkingDistance if !walking_distance::is_feasible(board.height, board.width) => {
+            search(board, options, &mut on_path)
+        }
+        Heuristic::WalkingDistance => {
+            let mut state = walking_distance_state(board);
+            search(&mut state, options, &mut on_path)
+        }
+        Heuristic::PatternDatabase(pdb) => {
+            let mut state = PatternSearch::new(board.clone(), Arc::clone(pdb));
+            search(&mut state, options, &mut on_path)
+        }
+    }
+}
+
+// `board` の完成形に合わせた walking distance の表を作り、それを使う探索の状態にする
+fn walking_distance_state(board: &Board) -> WalkingDistance {
+    let blank = board.target(Value(0));
+    let rows = Arc::new(Table::new(board.height, board.width, blank / board.width));
+    let columns = Arc::new(Table::new(board.width, board.height, blank % board.width));
+    WalkingDistance::new(board.clone(), rows, columns)
+}
+
+// 下界の計算を持った盤面
+trait BoardState: SearchState<Move = Dir> + Clone + Send + 'static {
+    fn board(&self) -> &Board;
+
+    // 探索の表で状態を見分けるキー
+    fn key(&self) -> (u64, Cells) {
+        self.board().key()
+    }
+}
+
+impl BoardState for Board {
+    fn board(&self) -> &Board {
+        self
+    }
+}
+
+// `options.algorithm` で `state` から完成までの最短手順を探す
+fn search<S: BoardState>(
+    state: &mut S,
+    options: &SolveOptions,
+    on_path: &mut dyn FnMut(&[Dir]) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    let max_depth = options.max_depth.unwrap_or(usize::MAX);
+    let ida_star_options = ida_star::Options {
+        max_solutions: options.max_solutions,
+        max_depth,
+        deadline: options.deadline,
+        cancel: options.cancel.clone(),
+        on_progress: options.on_progress.clone(),
+        on_iteration: options.on_iteration.clone(),
+        weight: options.weight,
+        // 大きい盤面では枠のほかに各マスの数を持つ
+        table_size: options.table_memory
+            / (mem::size_of::<Option<(Cells, usize)>>() + state.board().cells.len()),
+        cycle_window: options.cycle_window,
+        move_order: options.move_order,
+    };
+    let key = |s: &S| s.key();
+    match options.algorithm {
+        #[cfg(feature = "std")]
+        Algorithm::IdaStar if options.threads > 1 => {
+            ida_star::parallel_ida_star_with(state, &ida_star_options, options.threads, on_path)
+        }
+        Algorithm::IdaStar => ida_star_with_table(state, key, &ida_star_options, on_path),
+        Algorithm::AStar => {
+            let (path, termination, stats) = astar(
+                state,
+                key,
+                &astar::Options {
+                    max_depth,
+                    deadline: options.deadline,
+                    cancel: options.cancel.clone(),
+                    on_progress: options.on_progress.clone(),
+                    weight: options.weight,
+                },
+            );
+            let stopped = path.map_or(false, |path| on_path(&path).is_break());
+            match termination {
+                Termination::Complete if stopped => (Termination::Stopped, stats),
+                termination => (termination, stats),
+            }
+        }
+        Algorithm::SmaStar => {
+            let (path, termination, stats) = sma_star(
+                state,
+                &sma_star::Options {
+                    max_nodes: options.max_states,
+                    max_depth,
+                    deadline: options.deadline,
+                    cancel: options.cancel.clone(),
+                    on_progress: options.on_progress.clone(),
+                    weight: options.weight,
+                },
+            );
+            let stopped = path.map_or(false, |path| on_path(&path).is_break());
+            match termination {
+                Termination::Complete if stopped => (Termination::Stopped, stats),
+                termination => (termination, stats),
+            }
+        }
+        Algorithm::Greedy => {
+            let mut monitor = Monitor::new(
+                options.deadline,
+                options.cancel.clone(),
+                options.on_progress.clone(),
+            );
+            let path = greedy::solve(state.board(), &mut monitor);
+            let stopped = path.map_or(false, |path| on_path(&path).is_break());
+            monitor.finish(stopped)
+        }
+        Algorithm::Bidirectional => {
+            let mut monitor = Monitor::new(
+                options.deadline,
+                options.cancel.clone(),
+                options.on_progress.clone(),
+            );
+            match bidirectional::search(state.board(), max_depth, options.max_states, &mut monitor)
+            {
+                bidirectional::Outcome::Found(path) => {
+                    let stopped = on_path(&path).is_break();
+                    monitor.finish(stopped)
+                }
+                bidirectional::Outcome::NotFound => monitor.finish(false),
+                bidirectional::Outcome::TooManyStates => {
+                    let (_, before) = monitor.finish(false);
+                    let (termination, after) =
+                        ida_star_with_table(state, key, &ida_star_options, on_path);
+                    let stats = SearchStats {
+                        nodes_expanded: before.nodes_expanded + after.nodes_expanded,
+                        max_depth: before.max_depth.max(after.max_depth),
+                        pruned: before.pruned + after.pruned,
+                        guesses: before.guesses + after.guesses,
+                        elapsed: before.elapsed + after.elapsed,
+                    };
+                    (termination, stats)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use super::{Board, Cells, Dir};
+use crate::puzzle::Monitor;
+
+// 両方向幅優先探索の結果
+pub(super) enum Outcome {
+    // 見つけた最短手順
+    Found(Vec<Dir>),
+    // 手数の上限までに解がない (か、探索を打ち切った)
+    NotFound,
+    // 覚える盤面の数が上限を超えた
+    TooManyStates,
+}
+
+// 片側の探索
+struct Side {
+    // 訪れた盤面と、その盤面に来たときの手 (根では `None`)
+    seen: BTreeMap<(u64, Cells), Option<Dir>>,
+    // 根からちょうど `depth` 手の盤面
+    frontier: Vec<Board>,
+    depth: usize,
+}
+
+impl Side {
+    fn new(root: Board) -> Self {
+        let mut seen = BTreeMap::new();
+        seen.insert(root.key(), None);
+        Side {
+            seen,
+            frontier: vec![root],
+            depth: 0,
+        }
+    }
+
+    // 根から `board` までの手順
+    fn path(&self, board: &Board) -> Vec<Dir> {
+        let mut board = board.clone();
+        let mut path = Vec::new();
+        while let Some(&Some(dir)) = self.seen.get(&board.key()) {
+            path.push(dir);
+            assert!(board.slide(dir.reverse()).is_ok());
+        }
+        path.reverse();
+        path
+    }
+}
+
+// `board` と完成形の両方から 1 層ずつ幅優先探索し、出会ったところで最短手順を作る
+//
+// 盤面の少ない側の層を広げる。両側で覚えた盤面の数が `max_states` を超えたらやめる。
+pub(super) fn search(
+    board: &Board,
+    max_depth: usize,
+    max_states: usize,
+    monitor: &mut Monitor,
+) -> Outcome {
+    if board.is_solved() {
+        return Outcome::Found(Vec::new());
+    }
+    let goal = board.goal();
+    let mut sides = [Side::new(board.clone()), Side::new(goal)];
+    while sides[0].depth + sides[1].depth < max_depth {
+        let (i, j) = if sides[0].frontier.len() <= sides[1].frontier.len() {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let frontier = mem::take(&mut sides[i].frontier);
+        if frontier.is_empty() {
+            break;
+        }
+        let mut next = Vec::new();
+        for board in frontier {
+            monitor.visit(sides[i].depth, None);
+            if monitor.is_interrupted() {
+                return Outcome::NotFound;
+            }
+            let prev = sides[i].seen[&board.key()];
+            for dir in [Dir::R, Dir::U, Dir::L, Dir::D] {
+                if prev == Some(dir.reverse()) {
+                    continue;
+                }
+                let mut board = board.clone();
+                if board.slide(dir).is_err() {
+                    continue;
+                }
+                let key = board.key();
+                if sides[i].seen.contains_key(&key) {
+                    monitor.prune();
+                    continue;
+                }
+                let met = sides[j].seen.contains_key(&key);
+                sides[i].seen.insert(key, Some(dir));
+                if met {
+                    let forward = sides[0].path(&board);
+                    let backward = sides[1].path(&board);
+                    let path = forward
+                        .into_iter()
+                        .chain(backward.into_iter().rev().map(Dir::reverse))
+                        .collect();
+                    return Outcome::Found(path);
+                }
+                if sides[0].seen.len() + sides[1].seen.len() > max_states {
+                    return Outcome::TooManyStates;
+                }
+                next.push(board);
+            }
+        }
+        sides[i].frontier = next;
+        sides[i].depth += 1;
+    }
+    Outcome::NotFound
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::{
+        moves, solve_with_options, Algorithm, DistanceTable, SolveOptions,
+    };
+    use crate::rng::Rng;
+
+    // 3x3 のランダムな盤面と最短手数
+    fn boards() -> Vec<(Board, usize)> {
+        let table = DistanceTable::new(&Board::solved(3, 3)).unwrap();
+        let mut rng = Rng::new(1);
+        (0..30)
+            .map(|_| {
+                let board = Board::random(3, 3, &mut rng);
+                let distance = table.distance(&board).unwrap() as usize;
+                (board, distance)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_optimal_paths() {
+        for (board, distance) in boards() {
+            let mut monitor = Monitor::new(None, None, None);
+            let path = match search(&board, usize::MAX, usize::MAX, &mut monitor) {
+                Outcome::Found(path) => path,
+                _ => panic!("no path for {}", board),
+            };
+            assert_eq!(path.len(), distance, "{}", board);
+            let mut solved = board.clone();
+            assert!(path.iter().all(|&dir| solved.slide(dir).is_ok()));
+            assert!(solved.is_solved());
+        }
+    }
+
+    #[test]
+    fn falls_back_to_ida_star_with_few_states() {
+        for (board, distance) in boards() {
+            let mut monitor = Monitor::new(None, None, None);
+            if distance > 2 {
+                assert!(matches!(
+                    search(&board, usize::MAX, 10, &mut monitor),
+                    Outcome::TooManyStates
+                ));
+            }
+            let options = SolveOptions {
+                algorithm: Algorithm::Bidirectional,
+                max_states: 10,
+                ..SolveOptions::default()
+            };
+            let solutions = solve_with_options(&mut board.clone(), &options).into_solutions();
+            assert_eq!(solutions.len(), 1);
+            assert_eq!(moves(&solutions[0]).len(), distance, "{}", board);
+            assert_eq!(solutions[0][0], board);
+            assert!(solutions[0].last().unwrap().is_solved());
+        }
+    }
+}
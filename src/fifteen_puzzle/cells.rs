@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+
+use super::Value;
+
+// 詰めて持てるマスの数の上限
+const MAX_PACKED: usize = 16;
+
+// 盤面の各マスの数 (行ごとに左から)
+//
+// 16 マス以下の盤面は 1 マス 4 ビットで `u64` に詰め (左上のマスが下位ビット)、コピーや比較を安くする。
+// それより大きい盤面は 1 マス 1 バイトで持つ。
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub(super) enum Cells {
+    Packed { bits: u64, len: u8 },
+    Bytes(Vec<Value>),
+}
+
+impl Cells {
+    pub(super) fn new(values: Vec<Value>) -> Self {
+        if values.len() <= MAX_PACKED {
+            let bits = values
+                .iter()
+                .rev()
+                .fold(0, |bits, v| bits << 4 | u64::from(v.0));
+            Cells::Packed {
+                bits,
+                len: values.len() as u8,
+            }
+        } else {
+            Cells::Bytes(values)
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        match self {
+            Cells::Packed { len, .. } => usize::from(*len),
+            Cells::Bytes(values) => values.len(),
+        }
+    }
+
+    pub(super) fn get(&self, k: usize) -> Value {
+        match self {
+            Cells::Packed { bits, .. } => Value((bits >> (k * 4) & 0xf) as u8),
+            Cells::Bytes(values) => values[k],
+        }
+    }
+
+    pub(super) fn swap(&mut self, a: usize, b: usize) {
+        match self {
+            Cells::Packed { bits, .. } => {
+                let (x, y) = (*bits >> (a * 4) & 0xf, *bits >> (b * 4) & 0xf);
+                *bits ^= (x ^ y) << (a * 4) | (x ^ y) << (b * 4);
+            }
+            Cells::Bytes(values) => values.swap(a, b),
+        }
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = Value> + '_ {
+        (0..self.len()).map(move |k| self.get(k))
+    }
+
+    pub(super) fn to_vec(&self) -> Vec<Value> {
+        self.iter().collect()
+    }
+
+    // 詰めた値 (16 マスより大きい盤面では `None`)
+    pub(super) fn packed(&self) -> Option<u64> {
+        match self {
+            Cells::Packed { bits, .. } => Some(*bits),
+            Cells::Bytes(_) => None,
+        }
+    }
+}
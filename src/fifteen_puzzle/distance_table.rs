@@ -0,0 +1,179 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Formatter;
+
+use super::{Board, BoardHeuristic, Dir};
+
+// 表を作れるマスの数の上限 (3x3 で 9! = 362880 通りの並び)
+const MAX_CELLS: usize = 9;
+// まだ届いていない並び
+const UNSEEN: u8 = u8::MAX;
+
+/// 小さな盤面のすべての並びの最短手数の表
+///
+/// 完成形から幅優先探索で、届くすべての並び (8 パズルなら 181440 通り) の最短手数を求めておく。
+/// 表を作ったあとは、どの盤面の最短手数も探索せずに引ける。下界の正しさや強さを調べる正解としても使う。
+/// マスが [`DistanceTable::MAX_CELLS`] 以下の盤面だけ作れる。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{Board, DistanceTable};
+///
+/// let table = DistanceTable::new(&Board::solved(3, 3)).unwrap();
+/// let board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// assert_eq!(table.distance(&board), Some(31));
+/// assert_eq!(table.histogram().iter().sum::<usize>(), 181440);
+/// assert_eq!(table.histogram()[31], 2);
+/// ```
+#[derive(Clone, Eq, PartialEq)]
+pub struct DistanceTable {
+    goal: Board,
+    // 各マスの数の並びの番号 (辞書順) ごとの最短手数
+    distances: Vec<u8>,
+}
+
+impl fmt::Debug for DistanceTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DistanceTable")
+            .field("height", &self.goal.height)
+            .field("width", &self.goal.width)
+            .finish()
+    }
+}
+
+impl DistanceTable {
+    /// 表を作れるマスの数の上限
+    pub const MAX_CELLS: usize = MAX_CELLS;
+
+    /// `goal` の並びを完成形とする表を作る
+    ///
+    /// `goal` の完成形は見ない。マスが [`DistanceTable::MAX_CELLS`] を超えるときは `None` を返す。
+    pub fn new(goal: &Board) -> Option<Self> {
+        let cells = goal.cells.len();
+        if cells > MAX_CELLS {
+            return None;
+        }
+        let goal = goal.clone().with_goal(goal).unwrap();
+        let mut distances = vec![UNSEEN; factorial(cells)];
+        distances[rank(&goal)] = 0;
+        let mut queue = VecDeque::from([goal.clone()]);
+        while let Some(board) = queue.pop_front() {
+            let d = distances[rank(&board)];
+            for dir in [Dir::R, Dir::U, Dir::L, Dir::D] {
+                let mut next = board.clone();
+                if next.slide(dir).is_err() {
+                    continue;
+                }
+                let r = rank(&next);
+                if distances[r] == UNSEEN {
+                    distances[r] = d + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        Some(DistanceTable { goal, distances })
+    }
+
+    /// 完成形の盤面
+    pub fn goal(&self) -> &Board {
+        &self.goal
+    }
+
+    /// `board` の最短手数
+    ///
+    /// 完成させられない盤面と、大きさか完成形が表と違う盤面では `None` を返す。
+    pub fn distance(&self, board: &Board) -> Option<u32> {
+        if !self.matches(board) {
+            return None;
+        }
+        let d = self.distances[rank(board)];
+        (d != UNSEEN).then(|| u32::from(d))
+    }
+
+    /// 最短手数ごとの並びの数 (`d` 番目が `d` 手で完成する並びの数)
+    pub fn histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
+        for &d in self.distances.iter().filter(|&&d| d != UNSEEN) {
+            let d = usize::from(d);
+            if histogram.len() <= d {
+                histogram.resize(d + 1, 0);
+            }
+            histogram[d] += 1;
+        }
+        histogram
+    }
+
+    /// 完成させられるすべての盤面と、その最短手数
+    ///
+    /// 盤面の完成形は表の完成形にしてある。
+    pub fn boards(&self) -> impl Iterator<Item = (Board, u32)> + '_ {
+        let (cells, width) = (self.goal.cells.len(), self.goal.width);
+        self.distances
+            .iter()
+            .enumerate()
+            .filter(|&(_, &d)| d != UNSEEN)
+            .map(move |(r, &d)| {
+                let values = unrank(r, cells);
+                let board = Board::try_new(&values.chunks(width).collect::<Vec<_>>()).unwrap();
+                (board.with_goal(&self.goal).unwrap(), u32::from(d))
+            })
+    }
+
+    // `board` が表と同じ大きさで、同じ完成形を持つか
+    fn matches(&self, board: &Board) -> bool {
+        (board.height, board.width) == (self.goal.height, self.goal.width)
+            && self
+                .goal
+                .cells
+                .iter()
+                .enumerate()
+                .all(|(k, v)| board.target(v) == k)
+    }
+}
+
+/// 表の最短手数をそのまま下界にする (探索は寄り道せずに完成形へ向かう)
+///
+/// # Panics
+///
+/// 盤面の大きさか完成形が表と違うとき
+impl BoardHeuristic for DistanceTable {
+    fn estimate(&self, board: &Board) -> u32 {
+        assert!(
+            self.matches(board),
+            "the board does not match the distance table"
+        );
+        // 完成させられない盤面はどこまで探しても完成しないので、下界は何でもよい
+        self.distance(board).unwrap_or(0)
+    }
+}
+
+fn factorial(n: usize) -> usize {
+    (1..=n).product()
+}
+
+// 各マスの数の並びの辞書順の番号
+fn rank(board: &Board) -> usize {
+    let cells = board.cells.len();
+    let mut used = 0u32;
+    let mut r = 0;
+    for (k, v) in board.cells.iter().enumerate() {
+        // すでに使った数のうち `v` より小さいもの
+        let smaller = (used & ((1 << v.0) - 1)).count_ones() as usize;
+        r = r * (cells - k) + usize::from(v.0) - smaller;
+        used |= 1 << v.0;
+    }
+    r
+}
+
+// 辞書順で `r` 番目の並び
+fn unrank(mut r: usize, cells: usize) -> Vec<u8> {
+    let mut rest = (0..cells as u8).collect::<Vec<_>>();
+    let mut values = Vec::with_capacity(cells);
+    for k in 0..cells {
+        let f = factorial(cells - 1 - k);
+        values.push(rest.remove(r / f));
+        r %= f;
+    }
+    values
+}
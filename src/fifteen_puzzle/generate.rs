@@ -0,0 +1,180 @@
+use alloc::vec::Vec;
+
+use super::{solve_with_options, Board, Dir, SolveOptions};
+use crate::rng::Rng;
+
+impl Board {
+    /// 完成した `height` x `width` の盤面
+    ///
+    /// # Panics
+    ///
+    /// 盤面が 2x2 以上の長方形でないか、マスが 256 を超えるとき
+    pub fn solved(height: usize, width: usize) -> Board {
+        let size = height * width;
+        let cells = (1..size).chain([0]).map(|v| v as u8).collect::<Vec<_>>();
+        match Board::try_new(&cells.chunks(width.max(1)).collect::<Vec<_>>()) {
+            Ok(board) => board,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// 空きマスをランダムに `moves` 回動かす
+    ///
+    /// 直前の手を戻す手は選ばないが、`moves` 手より短い手順で完成することはある。
+    pub fn scramble(&mut self, rng: &mut Rng, moves: usize) {
+        let mut prev = None;
+        for _ in 0..moves {
+            prev = Some(self.random_move(rng, prev));
+        }
+    }
+
+    // 直前の手 `prev` を戻す手以外からランダムに選んで空きマスを動かす
+    fn random_move(&mut self, rng: &mut Rng, prev: Option<Dir>) -> Dir {
+        let dirs = [Dir::R, Dir::U, Dir::L, Dir::D]
+            .into_iter()
+            .filter(|&dir| prev != Some(dir.reverse()) && self.neighbor(dir).is_some())
+            .collect::<Vec<_>>();
+        let dir = dirs[rng.below(dirs.len())];
+        assert!(self.slide(dir).is_ok());
+        dir
+    }
+
+    /// 完成させられる `height` x `width` の盤面から一様に 1 つ選ぶ
+    ///
+    /// # Panics
+    ///
+    /// [`Board::solved`] と同じ
+    pub fn random(height: usize, width: usize, rng: &mut Rng) -> Board {
+        let mut cells = Board::solved(height, width)
+            .cells
+            .iter()
+            .map(|v| v.get())
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut cells);
+        let board = Board::try_new(&cells.chunks(width).collect::<Vec<_>>()).unwrap();
+        if board.is_solvable() {
+            return board;
+        }
+        // 空きマスでない 2 枚を入れ替えると偶奇が変わる
+        let tiles = cells
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != 0)
+            .map(|(i, _)| i)
+            .take(2)
+            .collect::<Vec<_>>();
+        cells.swap(tiles[0], tiles[1]);
+        Board::try_new(&cells.chunks(width).collect::<Vec<_>>()).unwrap()
+    }
+
+    /// 完成させられる `height` x `width` の盤面の最短手数の最大値
+    ///
+    /// 全探索などで知られている大きさだけ返し、ほかの大きさ (5x5 など) は `None` を返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+    ///
+    /// assert_eq!(Board::max_optimal_depth(3, 3), Some(31));
+    /// assert_eq!(Board::max_optimal_depth(4, 4), Some(80));
+    /// assert_eq!(Board::max_optimal_depth(5, 5), None);
+    /// ```
+    pub fn max_optimal_depth(height: usize, width: usize) -> Option<usize> {
+        match (height.min(width), height.max(width)) {
+            (2, 2) => Some(6),
+            (2, 3) => Some(21),
+            (2, 4) => Some(36),
+            (2, 5) => Some(55),
+            (2, 6) => Some(80),
+            (3, 3) => Some(31),
+            (3, 4) => Some(53),
+            (3, 5) => Some(84),
+            (4, 4) => Some(80),
+            _ => None,
+        }
+    }
+
+    /// 最短手数がちょうど `depth` の `height` x `width` の盤面を作る
+    ///
+    /// 完成形から空きマスをランダムに動かし、手数が `depth` 以上で偶奇が合うたびに IDA* で最短手数を確かめる。
+    /// `depth` が [`Board::max_optimal_depth`] を超えるときは `None` を返す。
+    /// 最長手数が知られていない大きさで、`depth` がそれを超えていると終わらない。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve, Board};
+    /// use book_puzzle_algorithm_rs::rng::Rng;
+    ///
+    /// let mut board = Board::generate(4, 4, 12, &mut Rng::new(1)).unwrap();
+    /// assert_eq!(solve(&mut board).unwrap().len(), 12 + 1);
+    /// assert_eq!(Board::generate(3, 3, 32, &mut Rng::new(1)), None);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// [`Board::solved`] と同じ
+    pub fn generate(height: usize, width: usize, depth: usize, rng: &mut Rng) -> Option<Board> {
+        if Board::max_optimal_depth(height, width).map_or(false, |max| depth > max) {
+            return None;
+        }
+        if depth == 0 {
+            return Some(Board::solved(height, width));
+        }
+        let options = SolveOptions {
+            max_depth: Some(depth),
+            ..SolveOptions::default()
+        };
+        loop {
+            let mut board = Board::solved(height, width);
+            let mut prev = None;
+            // 下界が `depth` を超えるか、動かし過ぎたらやり直す
+            for moves in 1..=depth * 4 {
+                prev = Some(board.random_move(rng, prev));
+                if board.estimate() as usize > depth {
+                    break;
+                }
+                if moves < depth || (moves - depth) % 2 != 0 {
+                    continue;
+                }
+                let solutions = solve_with_options(&mut board, &options).into_solutions();
+                if solutions
+                    .first()
+                    .map_or(false, |boards| boards.len() == depth + 1)
+                {
+                    return Some(board);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::DistanceTable;
+
+    #[test]
+    fn max_optimal_depth_matches_the_distance_table() {
+        for (height, width) in [(2, 2), (2, 3), (3, 2), (2, 4), (4, 2), (3, 3)] {
+            let table = DistanceTable::new(&Board::solved(height, width)).unwrap();
+            let max = table.histogram().len() - 1;
+            assert_eq!(
+                Board::max_optimal_depth(height, width),
+                Some(max),
+                "{}x{}",
+                height,
+                width
+            );
+        }
+    }
+
+    #[test]
+    fn generates_boards_of_every_reachable_depth() {
+        let mut rng = Rng::new(1);
+        let table = DistanceTable::new(&Board::solved(2, 3)).unwrap();
+        for depth in 0..=21 {
+            let board = Board::generate(2, 3, depth, &mut rng).unwrap();
+            assert_eq!(table.distance(&board), Some(depth as u32));
+        }
+        assert_eq!(Board::generate(2, 3, 22, &mut rng), None);
+        assert_eq!(Board::generate(4, 4, 81, &mut rng), None);
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use core::iter;
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{Board, Dir};
+use crate::puzzle::Monitor;
+
+// 人が解くように 1 行 (1 列) ずつタイルをそろえる
+struct Greedy<'a> {
+    board: Board,
+    // 各マスにそろえるタイル (空きマスは右下)
+    targets: Vec<u8>,
+    // そろえ終えて動かさないマス
+    locked: Vec<bool>,
+    moves: Vec<Dir>,
+    monitor: &'a mut Monitor,
+}
+
+impl Greedy<'_> {
+    fn position(&self, value: u8) -> usize {
+        self.board.cells.iter().position(|v| v.0 == value).unwrap()
+    }
+
+    fn blank(&self) -> usize {
+        self.board.empty.0 * self.board.width + self.board.empty.1
+    }
+
+    // 位置 `k` のマスから `dir` に動いた先
+    fn neighbor(&self, k: usize, dir: Dir) -> Option<usize> {
+        let (height, width) = (self.board.height, self.board.width);
+        let (i, j) = (k / width, k % width);
+        match dir {
+            Dir::R if j + 1 < width => Some(k + 1),
+            Dir::U if i > 0 => Some(k - width),
+            Dir::L if j > 0 => Some(k - 1),
+            Dir::D if i + 1 < height => Some(k + width),
+            _ => None,
+        }
+    }
+
+    // 動かさないマスを避けて空きマスを動かし、`tiles` の各タイル (数, 行き先) と空きマス (`blank` が `Some` なら) を
+    // 行き先に運ぶ最短の手順を幅優先探索で探して適用する
+    fn bring(&mut self, tiles: &[(u8, usize)], blank: Option<usize>) -> bool {
+        // 状態は追っているタイルの位置と空きマスの位置を `cells` 進法で並べた数
+        let cells = self.board.cells.len() as u64;
+        let encode = |positions: &mut dyn Iterator<Item = usize>| {
+            positions.fold(0, |state, k| state * cells + k as u64)
+        };
+        let start = encode(
+            &mut tiles
+                .iter()
+                .map(|&(v, _)| self.position(v))
+                .chain([self.blank()]),
+        );
+        let mut visited = Visited::new(cells.pow(tiles.len() as u32 + 1));
+        visited.insert(start, START);
+        let mut queue = VecDeque::from(vec![start]);
+        let mut goal = None;
+        while let Some(state) = queue.pop_front() {
+            self.monitor.visit(0, None);
+            let mut positions = decode(state, cells, tiles.len() + 1);
+            let b = positions.pop().unwrap();
+            if tiles.iter().zip(&positions).all(|(&(_, to), &at)| at == to)
+                && blank.map_or(true, |to| b == to)
+            {
+                goal = Some(state);
+                break;
+            }
+            for (d, &dir) in DIRS.iter().enumerate() {
+                let next = match self.neighbor(b, dir) {
+                    Some(next) if !self.locked[next] => next,
+                    _ => continue,
+                };
+                let state = encode(
+                    &mut positions
+                        .iter()
+                        .map(|&at| if at == next { b } else { at })
+                        .chain([next]),
+                );
+                if visited.get(state).is_none() {
+                    visited.insert(state, d as u8);
+                    queue.push_back(state);
+                }
+            }
+        }
+        let mut state = match goal {
+            Some(goal) => goal,
+            None => return false,
+        };
+        // 手順を逆にたどる
+        let mut path = Vec::new();
+        while let Some(d) = visited.get(state).filter(|&d| d != START) {
+            let dir = DIRS[usize::from(d)];
+            path.push(dir);
+            let mut positions = decode(state, cells, tiles.len() + 1);
+            let b = positions.pop().unwrap();
+            let back = self.neighbor(b, dir.reverse()).unwrap();
+            state = encode(
+                &mut positions
+                    .iter()
+                    .map(|&at| if at == back { b } else { at })
+                    .chain([back]),
+            );
+        }
+        for &dir in path.iter().rev() {
+            self.slide(dir);
+        }
+        true
+    }
+
+    fn slide(&mut self, dir: Dir) {
+        assert!(self.board.slide(dir).is_ok());
+        self.moves.push(dir);
+    }
+
+    // `cells` の各マスに完成形のタイルを順にそろえる
+    //
+    // 最後の 2 枚は、最後のタイルを 1 つ手前のマスに、1 つ手前のタイルをその `inward` 側の隣に置いてから、
+    // 空きマスを最後のマスに運んで 2 手で入れる。
+    fn line(&mut self, cells: &[usize], inward: Dir) -> bool {
+        let (last, rest) = cells.split_last().unwrap();
+        let (second, rest) = rest.split_last().unwrap();
+        for &k in rest {
+            if !self.bring(&[(self.targets[k], k)], None) {
+                return false;
+            }
+            self.locked[k] = true;
+        }
+        let (a, b) = (self.targets[*second], self.targets[*last]);
+        if self.position(a) != *second || self.position(b) != *last {
+            let below = self.neighbor(*second, inward).unwrap();
+            let staged = self.bring(&[(b, *second)], None) && {
+                self.locked[*second] = true;
+                let placed = self.bring(&[(a, below)], None) && {
+                    self.locked[below] = true;
+                    let ready = self.bring(&[], Some(*last));
+                    self.locked[below] = false;
+                    ready
+                };
+                self.locked[*second] = false;
+                placed
+            };
+            if staged {
+                // 最後のマスから手前のマスへ、そこから `inward` へ
+                let back = match inward {
+                    Dir::D => Dir::L,
+                    _ => Dir::U,
+                };
+                self.slide(back);
+                self.slide(inward);
+            } else if !self.bring(&[(a, *second), (b, *last)], None) {
+                // うまく並べられなければ 2 枚をまとめて探す
+                return false;
+            }
+        }
+        self.locked[*second] = true;
+        self.locked[*last] = true;
+        true
+    }
+
+    fn solve(&mut self) -> bool {
+        let (height, width) = (self.board.height, self.board.width);
+        let (mut top, mut left) = (0, 0);
+        while height - top > 2 || width - left > 2 {
+            // 残りの長い方の辺をそろえる
+            if height - top > 2 && (height - top >= width - left || width - left == 2) {
+                let cells = (left..width).map(|j| top * width + j).collect::<Vec<_>>();
+                if !self.line(&cells, Dir::D) {
+                    return false;
+                }
+                top += 1;
+            } else {
+                let cells = (top..height).map(|i| i * width + left).collect::<Vec<_>>();
+                if !self.line(&cells, Dir::R) {
+                    return false;
+                }
+                left += 1;
+            }
+        }
+        // 残った 2x2 は 3 枚と空きマスをまとめて運ぶ
+        let corner = [
+            top * width + left,
+            top * width + left + 1,
+            (top + 1) * width + left,
+        ];
+        let tiles = corner
+            .iter()
+            .map(|&k| (self.targets[k], k))
+            .collect::<Vec<_>>();
+        self.bring(&tiles, Some(height * width - 1))
+    }
+}
+
+const DIRS: [Dir; 4] = [Dir::R, Dir::U, Dir::L, Dir::D];
+// 探索を始めた状態の印 (ほかは来たときの手の `DIRS` での番号)
+const START: u8 = 4;
+// 訪れた状態を配列で持つ状態の数の上限
+const MAX_DENSE: u64 = 1 << 24;
+
+// 訪れた状態と、そこに来たときの手
+enum Visited {
+    Dense(Vec<u8>),
+    Sparse(BTreeMap<u64, u8>),
+}
+
+impl Visited {
+    // 状態の数 (の上限) が `states` のとき
+    fn new(states: u64) -> Self {
+        if states <= MAX_DENSE {
+            Visited::Dense(vec![u8::MAX; states as usize])
+        } else {
+            Visited::Sparse(BTreeMap::new())
+        }
+    }
+
+    fn get(&self, state: u64) -> Option<u8> {
+        match self {
+            Visited::Dense(v) => Some(v[state as usize]).filter(|&d| d != u8::MAX),
+            Visited::Sparse(m) => m.get(&state).copied(),
+        }
+    }
+
+    fn insert(&mut self, state: u64, d: u8) {
+        match self {
+            Visited::Dense(v) => v[state as usize] = d,
+            Visited::Sparse(m) => {
+                m.insert(state, d);
+            }
+        }
+    }
+}
+
+// `cells` 進法で並べた `len` 個の位置
+fn decode(mut state: u64, cells: u64, len: usize) -> Vec<usize> {
+    let mut positions = vec![0; len];
+    for p in positions.iter_mut().rev() {
+        *p = (state % cells) as usize;
+        state /= cells;
+    }
+    positions
+}
+
+// `board` を人が解くような手順で完成させる (完成させられなければ `None`)
+//
+// 最後に右下の 2x2 をそろえるので、完成形の空きマスを右と下に動かして右下に運んだ並びにそろえてから、
+// 空きマスを上と左に動かして完成形に戻す。
+pub(super) fn solve(board: &Board, monitor: &mut Monitor) -> Option<Vec<Dir>> {
+    if !board.is_solvable() {
+        return None;
+    }
+    let mut goal = board.goal();
+    let (i, j) = goal.empty;
+    let back = iter::repeat(Dir::U)
+        .take(board.height - 1 - i)
+        .chain(iter::repeat(Dir::L).take(board.width - 1 - j))
+        .collect::<Vec<_>>();
+    for &dir in back.iter().rev() {
+        assert!(goal.slide(dir.reverse()).is_ok());
+    }
+    let mut greedy = Greedy {
+        board: board.clone(),
+        targets: goal.cells.iter().map(|v| v.0).collect(),
+        locked: vec![false; board.cells.len()],
+        moves: Vec::new(),
+        monitor,
+    };
+    if !greedy.solve() {
+        return None;
+    }
+    for dir in back {
+        greedy.slide(dir);
+    }
+    if greedy.board.is_solved() {
+        Some(greedy.moves)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fifteen_puzzle::{solve_greedy, Board};
+    use crate::puzzle::Puzzle;
+    use crate::rng::Rng;
+
+    #[test]
+    fn solves_random_boards_of_every_shape() {
+        let mut rng = Rng::new(1);
+        for height in 2..=6 {
+            for width in 2..=6 {
+                for _ in 0..5 {
+                    let board = Board::random(height, width, &mut rng);
+                    let boards = solve_greedy(&board).unwrap();
+                    assert!(board.verify(&boards), "{}x{}: {}", height, width, board);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unsolvable_boards() {
+        let board: Board = "2 1 3 4 5 6 7 8 0".parse().unwrap();
+        assert_eq!(solve_greedy(&board), None);
+    }
+}
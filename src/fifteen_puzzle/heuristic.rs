@@ -0,0 +1,158 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{Board, BoardState, Dir};
+use crate::ida_star::SearchState;
+
+/// 盤面から完成までの手数の下界
+///
+/// [`Heuristic::Custom`](super::Heuristic::Custom) に渡すと、自分で書いた下界で探索できる。
+/// 完成までの最短手数を超える値を返すと、最短でない手順が見つかることがある。
+/// ノードごとに盤面全体から計算し直すので、盤面を動かしながら値を更新する組み込みの下界より遅い。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_with_options, Board, BoardHeuristic, Heuristic, SolveOptions};
+/// use std::sync::Arc;
+///
+/// // マンハッタン距離の半分 (弱い下界)
+/// struct HalfManhattan;
+///
+/// impl BoardHeuristic for HalfManhattan {
+///     fn estimate(&self, board: &Board) -> u32 {
+///         board.estimate() / 2
+///     }
+/// }
+///
+/// let mut board: Board = "1 2 3 4 5 6 0 7 8".parse().unwrap();
+/// let options = SolveOptions {
+///     heuristic: Heuristic::Custom(Arc::new(HalfManhattan)),
+///     ..SolveOptions::default()
+/// };
+/// let boards = solve_with_options(&mut board, &options).into_solutions().pop().unwrap();
+/// assert_eq!(boards.len(), 2 + 1);
+/// ```
+pub trait BoardHeuristic: Send + Sync {
+    /// `board` から完成までの手数の下界
+    fn estimate(&self, board: &Board) -> u32;
+}
+
+impl fmt::Debug for dyn BoardHeuristic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoardHeuristic")
+    }
+}
+
+/// 正しい位置にないタイルの枚数
+///
+/// 1 手で動くタイルは 1 枚なので下界になる。マンハッタン距離よりずっと弱い。
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Misplaced;
+
+impl BoardHeuristic for Misplaced {
+    fn estimate(&self, board: &Board) -> u32 {
+        board
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|&(k, v)| v.0 > 0 && board.target(v) != k)
+            .count() as u32
+    }
+}
+
+/// マンハッタン距離に linear conflict の分を足したもの
+///
+/// 完成形でも同じ行に置かれる 2 枚のタイルが行の中で逆の順に並んでいると、どちらかが一度行を出て戻るので 2 手余計にかかる。
+/// 行ごとに、そのまま残せるタイルの最大枚数 (完成形の列の番号の最長増加部分列) を除いた枚数の 2 倍を足す。列も同じように数える。
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct LinearConflict;
+
+impl BoardHeuristic for LinearConflict {
+    fn estimate(&self, board: &Board) -> u32 {
+        let (height, width) = (board.height, board.width);
+        let mut conflicts = 0;
+        for i in 0..height {
+            // 完成形でもこの行にあるタイルの、完成形での列
+            let columns = (0..width).filter_map(|j| {
+                let v = board.cells.get(i * width + j);
+                let target = board.target(v);
+                (v.0 > 0 && target / width == i).then(|| target % width)
+            });
+            conflicts += removed(columns);
+        }
+        for j in 0..width {
+            let rows = (0..height).filter_map(|i| {
+                let v = board.cells.get(i * width + j);
+                let target = board.target(v);
+                (v.0 > 0 && target % width == j).then(|| target / width)
+            });
+            conflicts += removed(rows);
+        }
+        board.estimate + 2 * conflicts
+    }
+}
+
+// 列から取り除いて残りを増加列にするのに必要な最小の個数 (長さから最長増加部分列の長さを引いたもの)
+fn removed(values: impl Iterator<Item = usize>) -> u32 {
+    // `tails[l]`: 長さ `l + 1` の増加部分列の末尾の最小値
+    let mut tails = Vec::new();
+    let mut len = 0;
+    for x in values {
+        len += 1;
+        let l = tails.partition_point(|&t| t < x);
+        if l == tails.len() {
+            tails.push(x);
+        } else {
+            tails[l] = x;
+        }
+    }
+    (len - tails.len()) as u32
+}
+
+// 下界を盤面全体から計算し直す探索の状態
+#[derive(Clone)]
+pub(super) struct Estimated {
+    board: Board,
+    heuristic: Arc<dyn BoardHeuristic>,
+}
+
+impl Estimated {
+    pub(super) fn new(board: Board, heuristic: Arc<dyn BoardHeuristic>) -> Self {
+        Estimated { board, heuristic }
+    }
+}
+
+impl SearchState for Estimated {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.board.slide(dir).is_ok()
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.board.slide(dir.reverse()).is_ok());
+    }
+
+    fn heuristic(&self) -> u32 {
+        self.heuristic.estimate(&self.board)
+    }
+
+    fn is_goal(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+impl BoardState for Estimated {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+}
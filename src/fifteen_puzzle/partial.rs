@@ -0,0 +1,153 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use super::{search, Algorithm, Board, BoardState, Dir, SolveOptions};
+use crate::ida_star::SearchState;
+use crate::puzzle::{SearchStats, SolveOutcome, Termination};
+
+/// `tiles` のタイルだけを完成形の位置に置く最短手順を探す
+///
+/// ほかのタイルと空きマスはどこにあってもよい。上の行から順にそろえるような、段階ごとの最短手順を見るのに使う。
+/// 下界は `tiles` のタイルの正しい位置までのマンハッタン距離の和で、`options.heuristic` と `options.metric` は使わない。
+/// `options.algorithm` が [`Algorithm::Bidirectional`] か [`Algorithm::Greedy`] なら IDA* で探す。
+/// 見つかった最短手順ごとに `board` から `tiles` をそろえるまでの各盤面を返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_partial, Board, SolveOptions};
+///
+/// let board: Board = "5 4 7 6 15 0 13 10 2 1 8 3 12 14 11 9".parse().unwrap();
+/// let (outcome, _) = solve_partial(&board, &[1, 2, 3, 4], &SolveOptions::default());
+/// let boards = outcome.into_solutions().pop().unwrap();
+/// let first_row = boards.last().unwrap().board()[0].iter().map(|v| v.get()).collect::<Vec<_>>();
+/// assert_eq!(first_row, [1, 2, 3, 4]);
+/// ```
+///
+/// # Panics
+///
+/// - `tiles` に空きマス (`0`) か盤面にない数があるとき
+/// - `options.weight` が 1 未満のとき
+pub fn solve_partial(
+    board: &Board,
+    tiles: &[u8],
+    options: &SolveOptions,
+) -> (SolveOutcome<Vec<Board>>, SearchStats) {
+    let cells = board.cells.len();
+    let mut mask = vec![false; cells];
+    for &t in tiles {
+        assert!(
+            t > 0 && usize::from(t) < cells,
+            "tile {} is not on the board",
+            t
+        );
+        mask[usize::from(t)] = true;
+    }
+    // ほかのタイルが 2 枚以上あれば入れ替えて偶奇を合わせられるので、完成させられない盤面でもそろえられる
+    let free = cells - 1 - mask.iter().filter(|&&m| m).count();
+    if free < 2 && !board.is_solvable() {
+        return (
+            SolveOutcome::new(Termination::Complete, Vec::new()),
+            SearchStats::default(),
+        );
+    }
+    let mut options = options.clone();
+    if matches!(
+        options.algorithm,
+        Algorithm::Bidirectional | Algorithm::Greedy
+    ) {
+        options.algorithm = Algorithm::IdaStar;
+    }
+    let mut solutions = Vec::new();
+    let mut on_path = |path: &[Dir]| {
+        let mut board = board.clone();
+        let mut boards = vec![board.clone()];
+        for &dir in path {
+            assert!(board.slide(dir).is_ok());
+            boards.push(board.clone());
+        }
+        solutions.push(boards);
+        ControlFlow::Continue(())
+    };
+    let mut state = Partial::new(board.clone(), Arc::new(mask));
+    let (termination, stats) = search(&mut state, &options, &mut on_path);
+    (SolveOutcome::new(termination, solutions), stats)
+}
+
+// 一部のタイルだけをそろえる探索の状態
+#[derive(Clone)]
+struct Partial {
+    board: Board,
+    // 数ごとに、そろえるタイルか
+    tiles: Arc<Vec<bool>>,
+    // そろえるタイルの正しい位置までのマンハッタン距離の和
+    estimate: u32,
+}
+
+impl Partial {
+    fn new(board: Board, tiles: Arc<Vec<bool>>) -> Self {
+        let width = board.width;
+        let estimate = (0..board.cells.len())
+            .filter(|&k| tiles[usize::from(board.cells.get(k).0)])
+            .map(|k| board.move_cost((k / width, k % width)))
+            .sum();
+        Partial {
+            board,
+            tiles,
+            estimate,
+        }
+    }
+
+    // 空きマスを `dir` に動かし、距離の和も更新する
+    fn step(&mut self, dir: Dir) -> bool {
+        let (i, j) = match self.board.neighbor(dir) {
+            Some(next) => next,
+            None => return false,
+        };
+        let empty = self.board.empty;
+        let counted = self.tiles[usize::from(self.board.cells.get(i * self.board.width + j).0)];
+        if counted {
+            self.estimate -= self.board.move_cost((i, j));
+        }
+        assert!(self.board.slide(dir).is_ok());
+        if counted {
+            self.estimate += self.board.move_cost(empty);
+        }
+        true
+    }
+}
+
+impl SearchState for Partial {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.step(dir)
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.step(dir.reverse()));
+    }
+
+    fn heuristic(&self) -> u32 {
+        self.estimate
+    }
+
+    fn is_goal(&self) -> bool {
+        self.estimate == 0
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+impl BoardState for Partial {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+}
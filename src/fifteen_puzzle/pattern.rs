@@ -0,0 +1,224 @@
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+use super::{search, zobrist, Algorithm, Board, BoardState, Cells, Dir, SolveOptions, Value};
+use crate::ida_star::SearchState;
+use crate::puzzle::{SearchStats, SolveOutcome, Termination};
+
+/// 同じ組のタイルを区別しない完成形へ向かう最短手順を探す
+///
+/// `classes[v]` が数 `v` のタイルの組 (色) で、`classes[0]` は空きマスの組。
+/// 同じ組のタイルは入れ替わってもよく、各マスに完成形のそのマスのタイルと同じ組のタイルがあれば完成とみなす。
+/// 空きマスと同じ組のタイルがあれば、空きマスもそのタイルの正しい位置のどこにあってもよい。
+/// 下界は各タイルから同じ組の正しい位置のうち最も近いものまでのマンハッタン距離の和で、
+/// 探索の表も組の並びで盤面を見分ける。`options.heuristic` と `options.metric` は使わない。
+/// `options.algorithm` が [`Algorithm::Bidirectional`] か [`Algorithm::Greedy`] なら IDA* で探す。
+/// 見つかった最短手順ごとに `board` から完成までの各盤面を返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_pattern, Board, SolveOptions};
+///
+/// // 1 行目の 1, 2, 3 と 2 行目の 4, 5, 6 をそれぞれ同じ色にする
+/// let classes = [0, 1, 1, 1, 2, 2, 2, 3, 4];
+/// let board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// let (outcome, _) = solve_pattern(&board, &classes, &SolveOptions::default());
+/// let boards = outcome.into_solutions().pop().unwrap();
+/// assert!(boards.len() - 1 < 31);
+/// let last = boards.last().unwrap().board();
+/// assert!(last[0].iter().all(|v| classes[usize::from(v.get())] == 1));
+/// assert!(last[1].iter().all(|v| classes[usize::from(v.get())] == 2));
+/// ```
+///
+/// # Panics
+///
+/// - `classes` の長さがマスの数と違うとき
+/// - `options.weight` が 1 未満のとき
+pub fn solve_pattern(
+    board: &Board,
+    classes: &[u8],
+    options: &SolveOptions,
+) -> (SolveOutcome<Vec<Board>>, SearchStats) {
+    assert_eq!(
+        classes.len(),
+        board.cells.len(),
+        "the number of classes differs from the number of cells"
+    );
+    if !is_reachable(board, classes) {
+        return (
+            SolveOutcome::new(Termination::Complete, Vec::new()),
+            SearchStats::default(),
+        );
+    }
+    let mut options = options.clone();
+    if matches!(
+        options.algorithm,
+        Algorithm::Bidirectional | Algorithm::Greedy
+    ) {
+        options.algorithm = Algorithm::IdaStar;
+    }
+    let mut solutions = Vec::new();
+    let mut on_path = |path: &[Dir]| {
+        let mut board = board.clone();
+        let mut boards = vec![board.clone()];
+        for &dir in path {
+            assert!(board.slide(dir).is_ok());
+            boards.push(board.clone());
+        }
+        solutions.push(boards);
+        ControlFlow::Continue(())
+    };
+    let mut state = Pattern::new(board.clone(), classes);
+    let (termination, stats) = search(&mut state, &options, &mut on_path);
+    (SolveOutcome::new(termination, solutions), stats)
+}
+
+// 組の完成形にできる並びか
+fn is_reachable(board: &Board, classes: &[u8]) -> bool {
+    if board.is_solvable() {
+        return true;
+    }
+    // 同じ組のタイルが 2 枚あれば入れ替えて偶奇を合わせられる
+    let mut seen = [false; 256];
+    for &c in &classes[1..] {
+        if seen[usize::from(c)] {
+            return true;
+        }
+        seen[usize::from(c)] = true;
+    }
+    // 空きマスと同じ組のタイルがあれば、完成形で空きマスと入れ替えた並びも試す
+    let goal = board.goal();
+    (1..classes.len())
+        .filter(|&v| classes[v] == classes[0])
+        .any(|v| {
+            let mut cells = goal.cells.iter().map(|t| t.0).collect::<Vec<_>>();
+            let (a, b) = (board.target(Value(0)), board.target(Value(v as u8)));
+            cells.swap(a, b);
+            let swapped = Board::try_new(&cells.chunks(board.width).collect::<Vec<_>>()).unwrap();
+            board.clone().with_goal(&swapped).unwrap().is_solvable()
+        })
+}
+
+// 同じ組のタイルを区別しない探索の状態
+#[derive(Clone)]
+struct Pattern {
+    board: Board,
+    // 数ごとの組の番号 (空きマスは 0 で、タイルの組は 1 から詰める)
+    labels: Arc<Vec<u8>>,
+    // 数 `v` のタイルがマス `k` にあるときの、同じ組の正しい位置までの最短距離 (`v * cells + k` 番目)
+    nearest: Arc<Vec<u32>>,
+    // 各タイルの `nearest` の和
+    estimate: u32,
+}
+
+impl Pattern {
+    fn new(board: Board, classes: &[u8]) -> Self {
+        let (cells, width) = (board.cells.len(), board.width);
+        let mut labels = vec![0; cells];
+        let mut next = 1;
+        for v in 1..cells {
+            labels[v] = match (1..v).find(|&u| classes[u] == classes[v]) {
+                Some(u) => labels[u],
+                None => {
+                    next += 1;
+                    next - 1
+                }
+            };
+        }
+        let mut nearest = vec![u32::MAX; cells * cells];
+        for v in 1..cells {
+            for t in (0..cells).filter(|&t| classes[t] == classes[v]) {
+                let target = board.target(Value(t as u8));
+                let (ti, tj) = (target / width, target % width);
+                for k in 0..cells {
+                    let (i, j) = (k / width, k % width);
+                    let d = (i.max(ti) - i.min(ti) + j.max(tj) - j.min(tj)) as u32;
+                    nearest[v * cells + k] = nearest[v * cells + k].min(d);
+                }
+            }
+        }
+        let estimate = board
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 > 0)
+            .map(|(k, v)| nearest[usize::from(v.0) * cells + k])
+            .sum();
+        Pattern {
+            board,
+            labels: Arc::new(labels),
+            nearest: Arc::new(nearest),
+            estimate,
+        }
+    }
+
+    // 空きマスを `dir` に動かし、距離の和も更新する
+    fn step(&mut self, dir: Dir) -> bool {
+        let (i, j) = match self.board.neighbor(dir) {
+            Some(next) => next,
+            None => return false,
+        };
+        let (cells, width) = (self.board.cells.len(), self.board.width);
+        let (from, to) = (
+            i * width + j,
+            self.board.empty.0 * width + self.board.empty.1,
+        );
+        let v = usize::from(self.board.cells.get(from).0);
+        self.estimate =
+            self.estimate + self.nearest[v * cells + to] - self.nearest[v * cells + from];
+        assert!(self.board.slide(dir).is_ok());
+        true
+    }
+}
+
+impl SearchState for Pattern {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.step(dir)
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.step(dir.reverse()));
+    }
+
+    fn heuristic(&self) -> u32 {
+        self.estimate
+    }
+
+    // どのタイルも同じ組の正しい位置にあれば、残った空きマスも空きマスの組の位置にある
+    fn is_goal(&self) -> bool {
+        self.estimate == 0
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+impl BoardState for Pattern {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+
+    // 各マスの組の番号で見分ける
+    fn key(&self) -> (u64, Cells) {
+        let values = self
+            .board
+            .cells
+            .iter()
+            .map(|v| Value(self.labels[usize::from(v.0)]))
+            .collect::<Vec<_>>();
+        let hash = values
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (k, &v)| hash ^ zobrist(k, v));
+        (hash, Cells::new(values))
+    }
+}
@@ -0,0 +1,703 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Formatter;
+
+use super::{Board, BoardState, Dir, Value};
+use crate::ida_star::SearchState;
+
+// ファイルの先頭に書く印 (このあとに形式の版を 1 文字で書く)
+const MAGIC: &[u8; 3] = b"PDB";
+// 今の形式の版 (`1` はグループを作り終えた数と検査用のハッシュ値がない)
+const VERSION: u8 = b'2';
+// 表の大きさ (マスの数の `k + 1` 乗, `k` はグループのタイルの数) の上限
+const MAX_STATES: usize = 1 << 25;
+// まだ届いていない状態
+const UNSEEN: u8 = u8::MAX;
+
+/// 加法的パターンデータベース
+///
+/// タイルをいくつかのグループに分け、グループごとに「そのグループのタイルの位置だけを見たときの、
+/// グループのタイルを動かす手数の最小値」の表を持つ。ほかのタイルを動かす手は数えないので、
+/// グループごとの値の和も完成までの手数の下界になる。
+///
+/// 表はグループのタイルと空きマスの位置を状態とした 0-1 幅優先探索で完成形から作る。
+/// 4x4 の既定の分け方 (5-5-5) では数秒かかるので、[`PatternDatabase::to_bytes`] で保存して
+/// [`PatternDatabase::from_bytes`] で読み込める。1 グループずつ作って途中から再開するには [`PdbBuilder`] を使う。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{solve_with_options, Board, Heuristic, PatternDatabase, SolveOptions};
+/// use std::sync::Arc;
+///
+/// let pdb = PatternDatabase::build(3, 3, &[vec![1, 2, 3, 4], vec![5, 6, 7, 8]]).unwrap();
+/// let bytes = pdb.to_bytes();
+/// let pdb = PatternDatabase::from_bytes(&bytes).unwrap();
+///
+/// let mut board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// let options = SolveOptions {
+///     heuristic: Heuristic::PatternDatabase(Arc::new(pdb)),
+///     ..SolveOptions::default()
+/// };
+/// let boards = solve_with_options(&mut board, &options).into_solutions().pop().unwrap();
+/// assert_eq!(boards.len(), 31 + 1);
+/// ```
+#[derive(Clone, Eq, PartialEq)]
+pub struct PatternDatabase {
+    height: usize,
+    width: usize,
+    groups: Vec<Vec<u8>>,
+    // グループごとの表。グループの `s` 番目のタイルの位置を `p_s` として `Σ p_s * cells^s` 番目
+    tables: Vec<Vec<u8>>,
+}
+
+/// パターンデータベースを作れなかったか、読めなかった理由
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PdbError {
+    /// グループの分け方がおかしい (空きマスや盤面にない数, 2 つのグループに入った数, 表が大きすぎるグループ)
+    InvalidGroups,
+    /// 盤面が 2x2 以上の長方形でない
+    WrongDimensions,
+    /// 保存したデータとして読めない (検査用のハッシュ値が合わないときも)
+    Corrupt,
+    /// 読めない版の形式で保存されている
+    UnsupportedVersion(u8),
+    /// 作りかけのデータで、まだ表を作っていないグループがある
+    Incomplete,
+}
+
+impl fmt::Display for PdbError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PdbError::InvalidGroups => write!(f, "invalid tile groups for a pattern database"),
+            PdbError::WrongDimensions => write!(f, "board must be a rectangle of at least 2x2"),
+            PdbError::Corrupt => write!(f, "not a pattern database file, or the file is damaged"),
+            PdbError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported pattern database version {}; build it again",
+                char::from(*v)
+            ),
+            PdbError::Incomplete => write!(
+                f,
+                "the pattern database is only partially built; resume the build first"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PdbError {}
+
+impl fmt::Debug for PatternDatabase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternDatabase")
+            .field("height", &self.height)
+            .field("width", &self.width)
+            .field("groups", &self.groups)
+            .finish()
+    }
+}
+
+impl PatternDatabase {
+    /// `height` x `width` の盤面の既定の分け方
+    ///
+    /// 4x4 なら {1, 2, 3, 4, 7}, {5, 6, 9, 10, 13}, {8, 11, 12, 14, 15} の 5-5-5。
+    /// 5x5 なら近くのタイルを 4 枚ずつまとめた 4-4-4-4-4-4 ({1, 2, 6, 7}, {3, 4, 8, 9}, {5, 10, 15, 20},
+    /// {11, 12, 16, 21}, {13, 17, 18, 22}, {14, 19, 23, 24})。
+    /// ほかの大きさでは、表が大きくなりすぎない枚数ずつ番号順に分ける。
+    pub fn default_groups(height: usize, width: usize) -> Vec<Vec<u8>> {
+        match (height, width) {
+            (4, 4) => {
+                return vec![
+                    vec![1, 2, 3, 4, 7],
+                    vec![5, 6, 9, 10, 13],
+                    vec![8, 11, 12, 14, 15],
+                ]
+            }
+            (5, 5) => {
+                return vec![
+                    vec![1, 2, 6, 7],
+                    vec![3, 4, 8, 9],
+                    vec![5, 10, 15, 20],
+                    vec![11, 12, 16, 21],
+                    vec![13, 17, 18, 22],
+                    vec![14, 19, 23, 24],
+                ]
+            }
+            _ => {}
+        }
+        let cells = height * width;
+        let mut k = 1;
+        while k + 1 < cells && states(cells, k + 2).map_or(false, |n| n <= MAX_STATES) {
+            k += 1;
+        }
+        let tiles = (1..cells).map(|v| v as u8).collect::<Vec<_>>();
+        tiles.chunks(k).map(<[u8]>::to_vec).collect()
+    }
+
+    /// 既定の分け方 ([`PatternDatabase::default_groups`]) で作る
+    pub fn new(height: usize, width: usize) -> Result<Self, PdbError> {
+        Self::build(height, width, &Self::default_groups(height, width))
+    }
+
+    /// タイルを `groups` に分けて作る
+    ///
+    /// どのグループにも入らないタイルは下界に数えない。
+    pub fn build(height: usize, width: usize, groups: &[Vec<u8>]) -> Result<Self, PdbError> {
+        let mut builder = PdbBuilder::new(height, width, groups)?;
+        while builder.build_next().is_some() {}
+        builder.finish()
+    }
+
+    /// 盤面の行の数
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 盤面の列の数
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// タイルのグループ
+    pub fn groups(&self) -> &[Vec<u8>] {
+        &self.groups
+    }
+
+    /// `board` から完成までの手数の下界
+    ///
+    /// 完成形がほかの並びの盤面では、各タイルを完成形で置かれるマスの番号 (1 から) のタイルとみて引く。
+    ///
+    /// # Panics
+    ///
+    /// 盤面の大きさが違うか、盤面の完成形の空きマスが右下にないとき
+    pub fn estimate(&self, board: &Board) -> u32 {
+        assert_eq!((board.height, board.width), (self.height, self.width));
+        assert_eq!(board.target(Value(0)), self.height * self.width - 1);
+        self.indices(board)
+            .iter()
+            .zip(&self.tables)
+            .map(|(&index, table)| u32::from(table[index]))
+            .sum()
+    }
+
+    // グループごとの表の添字
+    fn indices(&self, board: &Board) -> Vec<usize> {
+        let cells = self.height * self.width;
+        let mut position = vec![0; cells];
+        for (k, v) in board.cells.iter().enumerate().filter(|(_, v)| v.0 > 0) {
+            position[label(board, v)] = k;
+        }
+        self.groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .rev()
+                    .fold(0, |index, &t| index * cells + position[usize::from(t)])
+            })
+            .collect()
+    }
+
+    /// 保存用のバイト列
+    ///
+    /// 形式は [`PdbBuilder::to_bytes`] と同じで、すべてのグループの表を作り終えたもの。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(self)
+    }
+
+    /// [`PatternDatabase::to_bytes`] で保存したバイト列から読む
+    ///
+    /// 印と版, 検査用のハッシュ値を確かめる。作りかけのデータなら [`PdbError::Incomplete`] を返す。
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PdbError> {
+        PdbBuilder::from_bytes(bytes)?.finish()
+    }
+}
+
+/// 1 グループずつ表を作るパターンデータベースの作りかけ
+///
+/// グループの表を作り終えるたびに [`PdbBuilder::to_bytes`] で保存しておけば、作るのを中断しても
+/// [`PdbBuilder::from_bytes`] で読み直して続きから作れる。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{PatternDatabase, PdbBuilder, PdbError};
+///
+/// let groups = [vec![1, 2, 3, 4], vec![5, 6, 7, 8]];
+/// let mut builder = PdbBuilder::new(3, 3, &groups).unwrap();
+/// assert_eq!(builder.build_next(), Some(0));
+/// let saved = builder.to_bytes();
+/// assert_eq!(PatternDatabase::from_bytes(&saved), Err(PdbError::Incomplete));
+///
+/// let mut builder = PdbBuilder::from_bytes(&saved).unwrap();
+/// assert_eq!(builder.built(), 1);
+/// assert_eq!(builder.build_next(), Some(1));
+/// assert_eq!(builder.build_next(), None);
+/// let pdb = builder.finish().unwrap();
+/// assert_eq!(pdb, PatternDatabase::build(3, 3, &groups).unwrap());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PdbBuilder {
+    // 表は作り終えたグループの分だけ持つ
+    pdb: PatternDatabase,
+}
+
+impl PdbBuilder {
+    /// タイルを `groups` に分けて作り始める ([`PatternDatabase::build`] と同じ分け方)
+    pub fn new(height: usize, width: usize, groups: &[Vec<u8>]) -> Result<Self, PdbError> {
+        check(height, width, groups)?;
+        Ok(PdbBuilder {
+            pdb: PatternDatabase {
+                height,
+                width,
+                groups: groups.to_vec(),
+                tables: Vec::new(),
+            },
+        })
+    }
+
+    /// 盤面の行の数
+    pub fn height(&self) -> usize {
+        self.pdb.height
+    }
+
+    /// 盤面の列の数
+    pub fn width(&self) -> usize {
+        self.pdb.width
+    }
+
+    /// タイルのグループ
+    pub fn groups(&self) -> &[Vec<u8>] {
+        &self.pdb.groups
+    }
+
+    /// 表を作り終えたグループの数 (先頭から順に作る)
+    pub fn built(&self) -> usize {
+        self.pdb.tables.len()
+    }
+
+    /// 次のグループの表を作り、そのグループの番号を返す (すべて作り終えていたら `None`)
+    pub fn build_next(&mut self) -> Option<usize> {
+        let g = self.built();
+        let group = self.pdb.groups.get(g)?;
+        let table = build_table(self.pdb.height, self.pdb.width, group);
+        self.pdb.tables.push(table);
+        Some(g)
+    }
+
+    /// 作り終えたパターンデータベース (まだ作っていないグループがあれば [`PdbError::Incomplete`])
+    pub fn finish(self) -> Result<PatternDatabase, PdbError> {
+        if self.built() < self.pdb.groups.len() {
+            return Err(PdbError::Incomplete);
+        }
+        Ok(self.pdb)
+    }
+
+    /// 保存用のバイト列
+    ///
+    /// 印 `PDB` と版 `2`, 行と列の数, グループの数, 各グループの枚数とタイル, 表を作り終えたグループの数,
+    /// 作り終えた各グループの表, それまでのバイト列の FNV-1a ハッシュ値 (8 バイト, リトルエンディアン) の順に並べる。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode(&self.pdb)
+    }
+
+    /// [`PdbBuilder::to_bytes`] か [`PatternDatabase::to_bytes`] で保存したバイト列から読む
+    ///
+    /// 版 `1` の形式 (作り終えたものだけを保存し、ハッシュ値を持たない) も読める。
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PdbError> {
+        let rest = bytes.strip_prefix(&MAGIC[..]).ok_or(PdbError::Corrupt)?;
+        let (&version, rest) = rest.split_first().ok_or(PdbError::Corrupt)?;
+        let rest = match version {
+            b'1' => rest,
+            VERSION => {
+                if rest.len() < 8 {
+                    return Err(PdbError::Corrupt);
+                }
+                let (body, hash) = bytes.split_at(bytes.len() - 8);
+                if hash != fnv1a(body).to_le_bytes() {
+                    return Err(PdbError::Corrupt);
+                }
+                &rest[..rest.len() - 8]
+            }
+            v => return Err(PdbError::UnsupportedVersion(v)),
+        };
+        let (height, width, count) = match rest {
+            [height, width, count, ..] => (usize::from(*height), usize::from(*width), *count),
+            _ => return Err(PdbError::Corrupt),
+        };
+        let mut rest = &rest[3..];
+        let mut groups = Vec::new();
+        for _ in 0..count {
+            let (&len, tail) = rest.split_first().ok_or(PdbError::Corrupt)?;
+            if tail.len() < usize::from(len) {
+                return Err(PdbError::Corrupt);
+            }
+            let (group, tail) = tail.split_at(usize::from(len));
+            groups.push(group.to_vec());
+            rest = tail;
+        }
+        check(height, width, &groups).map_err(|_| PdbError::Corrupt)?;
+        let built = if version == VERSION {
+            let (&built, tail) = rest.split_first().ok_or(PdbError::Corrupt)?;
+            rest = tail;
+            usize::from(built)
+        } else {
+            groups.len()
+        };
+        if built > groups.len() {
+            return Err(PdbError::Corrupt);
+        }
+        let mut tables = Vec::new();
+        for group in &groups[..built] {
+            let size = states(height * width, group.len()).ok_or(PdbError::Corrupt)?;
+            if rest.len() < size {
+                return Err(PdbError::Corrupt);
+            }
+            let (table, tail) = rest.split_at(size);
+            tables.push(table.to_vec());
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(PdbError::Corrupt);
+        }
+        Ok(PdbBuilder {
+            pdb: PatternDatabase {
+                height,
+                width,
+                groups,
+                tables,
+            },
+        })
+    }
+}
+
+// 表を作り終えたグループの分だけ保存するバイト列 ([`PdbBuilder::to_bytes`])
+fn encode(pdb: &PatternDatabase) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.extend([pdb.height as u8, pdb.width as u8, pdb.groups.len() as u8]);
+    for group in &pdb.groups {
+        bytes.push(group.len() as u8);
+        bytes.extend(group);
+    }
+    bytes.push(pdb.tables.len() as u8);
+    for table in &pdb.tables {
+        bytes.extend(table);
+    }
+    let hash = fnv1a(&bytes);
+    bytes.extend(hash.to_le_bytes());
+    bytes
+}
+
+// 64 ビットの FNV-1a ハッシュ
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// 完成形で `v` が置かれるマスの番号 (1 から)
+//
+// 完成形の空きマスが右下なら、表を作ったときの完成形でそのマスに置かれるタイルの数になる。
+fn label(board: &Board, v: Value) -> usize {
+    board.target(v) + 1
+}
+
+// `cells` マスの盤面で `k` 個の位置を並べた状態の数 (`cells^k`)
+fn states(cells: usize, k: usize) -> Option<usize> {
+    (0..k).try_fold(1usize, |n, _| n.checked_mul(cells))
+}
+
+fn check(height: usize, width: usize, groups: &[Vec<u8>]) -> Result<(), PdbError> {
+    if height < 2 || width < 2 || height * width > 256 {
+        return Err(PdbError::WrongDimensions);
+    }
+    let cells = height * width;
+    let mut seen = vec![false; cells];
+    for group in groups {
+        if group.is_empty() || states(cells, group.len() + 1).map_or(true, |n| n > MAX_STATES) {
+            return Err(PdbError::InvalidGroups);
+        }
+        for &t in group {
+            if t == 0 || usize::from(t) >= cells || seen[usize::from(t)] {
+                return Err(PdbError::InvalidGroups);
+            }
+            seen[usize::from(t)] = true;
+        }
+    }
+    Ok(())
+}
+
+// `group` のタイルの表を、グループのタイルと空きマスの位置を状態とした 0-1 幅優先探索で作る
+fn build_table(height: usize, width: usize, group: &[u8]) -> Vec<u8> {
+    let cells = height * width;
+    let k = group.len();
+    let size = states(cells, k).unwrap();
+    // 状態の添字は `Σ p_s * cells^s` (空きマスは `s = k`)
+    let encode = |positions: &[usize]| positions.iter().rev().fold(0, |n, &p| n * cells + p);
+    let goal = group
+        .iter()
+        .map(|&t| usize::from(t) - 1)
+        .chain([cells - 1])
+        .collect::<Vec<_>>();
+    let mut dist = vec![UNSEEN; size * cells];
+    let start = encode(&goal);
+    dist[start] = 0;
+    let mut queue = VecDeque::from([start]);
+    let mut positions = vec![0; k + 1];
+    while let Some(state) = queue.pop_front() {
+        let mut n = state;
+        for p in positions.iter_mut() {
+            *p = n % cells;
+            n /= cells;
+        }
+        let d = dist[state];
+        let blank = positions[k];
+        let (i, j) = (blank / width, blank % width);
+        for dir in [Dir::R, Dir::U, Dir::L, Dir::D] {
+            let next = match dir {
+                Dir::R if j + 1 < width => blank + 1,
+                Dir::U if i > 0 => blank - width,
+                Dir::L if j > 0 => blank - 1,
+                Dir::D if i + 1 < height => blank + width,
+                _ => continue,
+            };
+            // 空きマスと入れ替わるのがグループのタイルなら 1 手, ほかのタイルなら 0 手
+            let tile = positions[..k].iter().position(|&p| p == next);
+            let mut moved = positions.clone();
+            moved[k] = next;
+            if let Some(s) = tile {
+                moved[s] = blank;
+            }
+            let cost = u8::from(tile.is_some());
+            let neighbor = encode(&moved);
+            if d + cost < dist[neighbor] {
+                dist[neighbor] = d + cost;
+                if cost == 0 {
+                    queue.push_front(neighbor);
+                } else {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+    // 空きマスの位置について最小をとる
+    let mut table = vec![UNSEEN; size];
+    for (state, &d) in dist.iter().enumerate() {
+        let index = state % size;
+        table[index] = table[index].min(d);
+    }
+    table
+}
+
+// パターンデータベースを下界に使う探索の状態
+#[derive(Clone)]
+pub(super) struct PatternSearch {
+    board: Board,
+    pdb: Arc<PatternDatabase>,
+    // 盤面のタイルごとの (グループ, グループの中の番号)
+    slots: Vec<Option<(usize, usize)>>,
+    // `cells^s`
+    powers: Vec<usize>,
+    indices: Vec<usize>,
+}
+
+impl PatternSearch {
+    // `pdb` は `board` と同じ大きさの盤面のもので、`board` の完成形の空きマスは右下
+    pub(super) fn new(board: Board, pdb: Arc<PatternDatabase>) -> Self {
+        assert_eq!((board.height, board.width), (pdb.height, pdb.width));
+        let cells = pdb.height * pdb.width;
+        assert_eq!(board.target(Value(0)), cells - 1);
+        let mut groups = vec![None; cells];
+        for (g, group) in pdb.groups.iter().enumerate() {
+            for (s, &t) in group.iter().enumerate() {
+                groups[usize::from(t)] = Some((g, s));
+            }
+        }
+        let mut slots = vec![None; cells];
+        for v in 1..cells {
+            slots[v] = groups[label(&board, Value(v as u8))];
+        }
+        let max = pdb.groups.iter().map(Vec::len).max().unwrap_or(0);
+        let powers = (0..max)
+            .scan(1, |power, _| {
+                let p = *power;
+                *power *= cells;
+                Some(p)
+            })
+            .collect();
+        let indices = pdb.indices(&board);
+        PatternSearch {
+            board,
+            pdb,
+            slots,
+            powers,
+            indices,
+        }
+    }
+
+    // 空きマスを `dir` に動かし、表の添字も更新する
+    fn step(&mut self, dir: Dir) -> bool {
+        let (i, j) = match self.board.neighbor(dir) {
+            Some(next) => next,
+            None => return false,
+        };
+        let width = self.board.width;
+        let (from, to) = (
+            i * width + j,
+            self.board.empty.0 * width + self.board.empty.1,
+        );
+        if let Some((g, s)) = self.slots[usize::from(self.board.cells.get(from).0)] {
+            self.indices[g] = self.indices[g] - from * self.powers[s] + to * self.powers[s];
+        }
+        assert!(self.board.slide(dir).is_ok());
+        true
+    }
+}
+
+impl SearchState for PatternSearch {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.step(dir)
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.step(dir.reverse()));
+    }
+
+    fn heuristic(&self) -> u32 {
+        let pattern = self
+            .indices
+            .iter()
+            .zip(&self.pdb.tables)
+            .map(|(&index, table)| u32::from(table[index]))
+            .sum::<u32>();
+        pattern.max(self.board.estimate)
+    }
+
+    fn is_goal(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+impl BoardState for PatternSearch {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::DistanceTable;
+    use crate::rng::Rng;
+
+    fn builder() -> PdbBuilder {
+        let mut builder = PdbBuilder::new(2, 3, &[vec![1, 2], vec![3, 4, 5]]).unwrap();
+        builder.build_next();
+        builder
+    }
+
+    // `body` のあとにハッシュ値を付ける
+    fn with_hash(body: &[u8]) -> Vec<u8> {
+        let mut bytes = body.to_vec();
+        bytes.extend(fnv1a(body).to_le_bytes());
+        bytes
+    }
+
+    // ハッシュ値を除いたバイト列
+    fn body(builder: &PdbBuilder) -> Vec<u8> {
+        let bytes = builder.to_bytes();
+        bytes[..bytes.len() - 8].to_vec()
+    }
+
+    #[test]
+    fn reads_what_it_wrote() {
+        let builder = builder();
+        assert_eq!(PdbBuilder::from_bytes(&builder.to_bytes()), Ok(builder));
+    }
+
+    #[test]
+    fn rejects_a_wrong_hash() {
+        let mut bytes = builder().to_bytes();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 1;
+        assert_eq!(PdbBuilder::from_bytes(&bytes), Err(PdbError::Corrupt));
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_bytes() {
+        let bytes = builder().to_bytes();
+        for len in [0, 3, 4, 10, bytes.len() - 1] {
+            assert_eq!(
+                PdbBuilder::from_bytes(&bytes[..len]),
+                Err(PdbError::Corrupt),
+                "{}",
+                len
+            );
+        }
+        // ハッシュ値が合っていても、表が足りなかったり余ったりすれば読まない
+        let body = body(&builder());
+        assert_eq!(
+            PdbBuilder::from_bytes(&with_hash(&body[..body.len() - 1])),
+            Err(PdbError::Corrupt)
+        );
+        assert_eq!(
+            PdbBuilder::from_bytes(&with_hash(&[&body[..], &[0]].concat())),
+            Err(PdbError::Corrupt)
+        );
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let mut builder = builder();
+        builder.build_next();
+        // 版 `1` は作り終えたグループの数とハッシュ値を持たない
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(b'1');
+        bytes.extend([2, 3, 2, 2, 1, 2, 3, 3, 4, 5]);
+        for table in &builder.pdb.tables {
+            bytes.extend(table);
+        }
+        assert_eq!(
+            PatternDatabase::from_bytes(&bytes),
+            builder.clone().finish()
+        );
+        bytes.push(0);
+        assert_eq!(PatternDatabase::from_bytes(&bytes), Err(PdbError::Corrupt));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let mut bytes = builder().to_bytes();
+        bytes[3] = b'3';
+        assert_eq!(
+            PdbBuilder::from_bytes(&bytes),
+            Err(PdbError::UnsupportedVersion(b'3'))
+        );
+    }
+
+    #[test]
+    fn is_admissible() {
+        let mut rng = Rng::new(1);
+        for (height, width) in [(2, 3), (3, 3)] {
+            let pdb = PatternDatabase::new(height, width).unwrap();
+            let table = DistanceTable::new(&Board::solved(height, width)).unwrap();
+            for _ in 0..200 {
+                let board = Board::random(height, width, &mut rng);
+                let distance = table.distance(&board).unwrap();
+                assert!(pdb.estimate(&board) <= distance, "{}", board);
+            }
+        }
+    }
+}
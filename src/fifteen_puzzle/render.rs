@@ -0,0 +1,297 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::Board;
+
+// SVG の 1 マスの大きさ, マスの間と盤面の縁の幅 (ピクセル)
+const SVG_TILE: usize = 60;
+const SVG_GAP: usize = 4;
+
+/// 盤面の列を、タイルが 1 手ごとに滑って動くアニメーションの SVG で書く
+///
+/// 各タイルを角の丸い四角と数で描き、`delay_ms` ミリ秒ごとに次の盤面へ動かす。最後の盤面で 1 手分止まってから繰り返す。
+/// アニメーションを再生しないビューアでは最初の盤面を表示する。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{render_svg, solve, Board};
+///
+/// let mut board: Board = "1 2 3 4 5 6 7 0 8".parse().unwrap();
+/// let svg = render_svg(&solve(&mut board).unwrap(), 300);
+/// assert!(svg.starts_with("<svg"));
+/// assert!(svg.contains("dur=\"600ms\""));
+/// ```
+///
+/// # Panics
+///
+/// `boards` が空か、大きさの違う盤面を含むとき
+pub fn render_svg(boards: &[Board], delay_ms: u32) -> String {
+    let first = &boards[0];
+    let (height, width) = (first.height, first.width);
+    assert!(boards
+        .iter()
+        .all(|b| (b.height, b.width) == (height, width)));
+    let step = SVG_TILE + SVG_GAP;
+    let (w, h) = (width * step + SVG_GAP, height * step + SVG_GAP);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" rx=\"8\" fill=\"#3d405b\"/>\n",
+        w = w,
+        h = h
+    );
+    // 最後の盤面をもう 1 度並べて、1 手分止める
+    let frames = boards.iter().chain(boards.last()).collect::<Vec<_>>();
+    let dur = delay_ms as usize * (frames.len() - 1);
+    let cells = height * width;
+    for v in 1..cells {
+        let positions = frames
+            .iter()
+            .map(|board| {
+                let k = board
+                    .cells
+                    .iter()
+                    .position(|t| usize::from(t.0) == v)
+                    .unwrap();
+                format!(
+                    "{} {}",
+                    k % width * step + SVG_GAP,
+                    k / width * step + SVG_GAP
+                )
+            })
+            .collect::<Vec<_>>();
+        svg.push_str(&format!("<g transform=\"translate({})\">\n", positions[0]));
+        if boards.len() > 1 {
+            svg.push_str(&format!(
+                "<animateTransform attributeName=\"transform\" type=\"translate\" values=\"{}\" dur=\"{}ms\" repeatCount=\"indefinite\"/>\n",
+                positions.join(";"),
+                dur
+            ));
+        }
+        svg.push_str(&format!(
+            "<rect width=\"{t}\" height=\"{t}\" rx=\"8\" fill=\"#f2cc8f\" stroke=\"#e07a5f\" stroke-width=\"3\"/>\n\
+             <text x=\"{c}\" y=\"{c}\" font-family=\"sans-serif\" font-size=\"26\" font-weight=\"bold\" fill=\"#3d405b\" text-anchor=\"middle\" dominant-baseline=\"central\">{v}</text>\n\
+             </g>\n",
+            t = SVG_TILE,
+            c = SVG_TILE / 2,
+            v = v
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// GIF の 1 マスの大きさ, マスの間と盤面の縁の幅 (ピクセル)
+const GIF_TILE: usize = 48;
+const GIF_GAP: usize = 4;
+// 色の番号 (背景, タイル, タイルの縁, 数)
+const BACKGROUND: u8 = 0;
+const FACE: u8 = 1;
+const EDGE: u8 = 2;
+const INK: u8 = 3;
+const PALETTE: [[u8; 3]; 4] = [
+    [0x3d, 0x40, 0x5b],
+    [0xf2, 0xcc, 0x8f],
+    [0xe0, 0x7a, 0x5f],
+    [0x3d, 0x40, 0x5b],
+];
+
+// 5x7 ドットの数字 (各行の下位 5 ビットを左から)
+const DIGITS: [[u8; 7]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+
+/// 盤面の列を、1 手を 1 コマにしたアニメーション GIF にする
+///
+/// 各コマを `delay_ms` ミリ秒 (10 ミリ秒単位) ずつ表示し、最後のコマは 1 秒長く表示してから繰り返す。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{render_gif, solve, Board};
+///
+/// let mut board: Board = "1 2 3 4 5 6 7 0 8".parse().unwrap();
+/// let gif = render_gif(&solve(&mut board).unwrap(), 300);
+/// assert!(gif.starts_with(b"GIF89a"));
+/// assert_eq!(gif.last(), Some(&0x3b));
+/// ```
+///
+/// # Panics
+///
+/// `boards` が空か、大きさの違う盤面を含むとき
+pub fn render_gif(boards: &[Board], delay_ms: u32) -> Vec<u8> {
+    let first = &boards[0];
+    let (height, width) = (first.height, first.width);
+    assert!(boards
+        .iter()
+        .all(|b| (b.height, b.width) == (height, width)));
+    let step = GIF_TILE + GIF_GAP;
+    let (w, h) = (width * step + GIF_GAP, height * step + GIF_GAP);
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend((w as u16).to_le_bytes());
+    gif.extend((h as u16).to_le_bytes());
+    // 4 色の色表を持ち、背景は 0 番
+    gif.extend([0x91, BACKGROUND, 0]);
+    for color in PALETTE {
+        gif.extend(color);
+    }
+    // 繰り返し再生する
+    gif.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    let delay = (delay_ms / 10).min(u32::from(u16::MAX)) as u16;
+    for (i, board) in boards.iter().enumerate() {
+        let delay = if i + 1 == boards.len() {
+            delay.saturating_add(100)
+        } else {
+            delay
+        };
+        gif.extend([0x21, 0xf9, 0x04, 0x00]);
+        gif.extend(delay.to_le_bytes());
+        gif.extend([0x00, 0x00]);
+        gif.push(0x2c);
+        gif.extend([0, 0, 0, 0]);
+        gif.extend((w as u16).to_le_bytes());
+        gif.extend((h as u16).to_le_bytes());
+        gif.push(0);
+        gif.push(2);
+        let data = lzw(&draw(board, w, h), 2);
+        for block in data.chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend(block);
+        }
+        gif.push(0);
+    }
+    gif.push(0x3b);
+    gif
+}
+
+// 盤面を `w` x `h` の画素 (色の番号) に描く
+fn draw(board: &Board, w: usize, h: usize) -> Vec<u8> {
+    let mut pixels = vec![BACKGROUND; w * h];
+    let step = GIF_TILE + GIF_GAP;
+    for (k, v) in board.cells.iter().enumerate().filter(|(_, v)| v.0 > 0) {
+        let (top, left) = (
+            k / board.width * step + GIF_GAP,
+            k % board.width * step + GIF_GAP,
+        );
+        for y in 0..GIF_TILE {
+            for x in 0..GIF_TILE {
+                // 角を落として丸く見せ、右と下の縁に影を付ける
+                let (dx, dy) = (x.min(GIF_TILE - 1 - x), y.min(GIF_TILE - 1 - y));
+                if dx + dy < 3 {
+                    continue;
+                }
+                let edge = x + 3 >= GIF_TILE || y + 3 >= GIF_TILE;
+                pixels[(top + y) * w + left + x] = if edge { EDGE } else { FACE };
+            }
+        }
+        let text = format!("{}", v.0);
+        // 3 桁の数は小さく書く
+        let scale = if text.len() <= 2 { 3 } else { 2 };
+        let text_width = text.len() * 6 * scale - scale;
+        let (x0, y0) = (
+            left + (GIF_TILE - text_width) / 2,
+            top + (GIF_TILE - 7 * scale) / 2,
+        );
+        for (n, c) in text.bytes().enumerate() {
+            let glyph = DIGITS[usize::from(c - b'0')];
+            for (row, bits) in glyph.iter().enumerate() {
+                for col in 0..5 {
+                    if bits >> (4 - col) & 1 == 0 {
+                        continue;
+                    }
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            let x = x0 + (n * 6 + col) * scale + sx;
+                            let y = y0 + row * scale + sy;
+                            pixels[y * w + x] = INK;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    pixels
+}
+
+// GIF の LZW で圧縮する (符号は下位ビットから詰める)
+fn lzw(pixels: &[u8], min_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_size;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut dict = BTreeMap::new();
+    let mut next = end + 1;
+    let mut size = min_size + 1;
+    // 符号を書き、表が今の幅に収まらなくなったら幅を広げる
+    let emit = |writer: &mut BitWriter, code: u16, size: &mut u32, next: u16| {
+        writer.write(code, *size);
+        if u32::from(next) >= 1 << *size && *size < 12 {
+            *size += 1;
+        }
+    };
+    emit(&mut writer, clear, &mut size, next);
+    let mut prefix: Option<u16> = None;
+    for &p in pixels {
+        let current = match prefix {
+            None => {
+                prefix = Some(u16::from(p));
+                continue;
+            }
+            Some(current) => current,
+        };
+        if let Some(&code) = dict.get(&(current, p)) {
+            prefix = Some(code);
+            continue;
+        }
+        emit(&mut writer, current, &mut size, next);
+        if next < 4096 {
+            dict.insert((current, p), next);
+            next += 1;
+        } else {
+            // 表がいっぱいになったら作り直す
+            emit(&mut writer, clear, &mut size, next);
+            dict.clear();
+            next = end + 1;
+            size = min_size + 1;
+        }
+        prefix = Some(u16::from(p));
+    }
+    if let Some(current) = prefix {
+        emit(&mut writer, current, &mut size, next);
+    }
+    emit(&mut writer, end, &mut size, next);
+    writer.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= u32::from(code) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
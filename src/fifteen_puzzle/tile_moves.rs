@@ -0,0 +1,244 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Formatter;
+use core::iter;
+use core::mem;
+use core::ops::ControlFlow;
+
+use super::{moves, Algorithm, Board, Cells, Dir, SolveOptions, Value};
+use crate::astar::{self, astar};
+use crate::ida_star::{self, ida_star_with_table, SearchState};
+use crate::puzzle::{SearchStats, Termination};
+
+/// タイルを動かす 1 手
+///
+/// 空きマスと同じ行か列に並んだタイルを、空きマスの側へまとめて 1 マスずつずらす。
+/// 空きマスを同じ向きに続けて動かす手をまとめたもの。
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TileMove {
+    /// 押したタイル (動かしたタイルのうち空きマスから最も遠いもの)
+    pub tile: Value,
+    /// 押したタイルを動かした先
+    pub to: (usize, usize),
+    /// 動かしたタイルの枚数
+    pub count: usize,
+    /// 空きマスを動かした向き (タイルはその逆向きに動く)
+    pub dir: Dir,
+}
+
+/// `move tile 7 to (0,2)` のように書く
+impl fmt::Display for TileMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move tile {} to ({},{})",
+            self.tile.get(),
+            self.to.0,
+            self.to.1
+        )
+    }
+}
+
+/// 続く盤面の間の手を、タイルを動かす手にまとめた列
+///
+/// 空きマスを同じ向きに続けて動かす手を 1 手と数える (multi-tile metric)。
+/// 列の長さがタイルを動かす手の数になる。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::{tile_moves, Board};
+///
+/// let board: Board = "1 2 3 4 5 6 0 7 8".parse().unwrap();
+/// let mut boards = vec![board.clone()];
+/// for next in ["1 2 3 4 5 6 7 0 8", "1 2 3 4 5 6 7 8 0"] {
+///     boards.push(next.parse().unwrap());
+/// }
+/// let tile_moves = tile_moves(&boards);
+/// assert_eq!(tile_moves.len(), 1);
+/// assert_eq!(tile_moves[0].to_string(), "move tile 8 to (2,1)");
+/// ```
+///
+/// # Panics
+///
+/// 続く盤面が空きマスを 1 マス動かしたものでないとき
+pub fn tile_moves(boards: &[Board]) -> Vec<TileMove> {
+    let dirs = moves(boards);
+    let mut tile_moves = Vec::new();
+    let mut start = 0;
+    while start < dirs.len() {
+        let dir = dirs[start];
+        let count = dirs[start..].iter().take_while(|&&d| d == dir).count();
+        let (before, after) = (&boards[start], &boards[start + count]);
+        tile_moves.push(TileMove {
+            tile: before.get(after.empty),
+            to: boards[start + count - 1].empty,
+            count,
+            dir,
+        });
+        start += count;
+    }
+    tile_moves
+}
+
+// タイルを動かす手を 1 手と数えて探索する状態
+//
+// 手は空きマスを動かす向きと、動かすマスの数。
+// 横の手は 1 つの行のタイルを最大 `width - 1` 枚、それぞれ 1 マス動かすので、横のマンハッタン距離の和を
+// `width - 1` で割って切り上げた値は横の手の数の下界になる。縦も同じように数え、その和を下界にする。
+#[derive(Clone)]
+pub(super) struct TileMetric {
+    board: Board,
+    // 各タイルの正しい位置までの横の距離と縦の距離の和
+    horizontal: u32,
+    vertical: u32,
+}
+
+impl TileMetric {
+    pub(super) fn new(board: Board) -> Self {
+        let (mut horizontal, mut vertical) = (0, 0);
+        for (k, v) in board.cells.iter().enumerate().filter(|(_, v)| v.0 > 0) {
+            let (dx, dy) = distance(&board, v, k);
+            horizontal += dx;
+            vertical += dy;
+        }
+        TileMetric {
+            board,
+            horizontal,
+            vertical,
+        }
+    }
+
+    // 空きマスを `dir` に 1 マス動かし、距離の和も更新する
+    fn step(&mut self, dir: Dir) -> bool {
+        let (i, j) = match self.board.neighbor(dir) {
+            Some(next) => next,
+            None => return false,
+        };
+        let width = self.board.width;
+        let (from, to) = (
+            i * width + j,
+            self.board.empty.0 * width + self.board.empty.1,
+        );
+        let v = self.board.cells.get(from);
+        let (before, after) = (distance(&self.board, v, from), distance(&self.board, v, to));
+        self.horizontal = self.horizontal + after.0 - before.0;
+        self.vertical = self.vertical + after.1 - before.1;
+        assert!(self.board.slide(dir).is_ok());
+        true
+    }
+}
+
+// マス `k` にある `v` の正しい位置までの (横の距離, 縦の距離)
+fn distance(board: &Board, v: Value, k: usize) -> (u32, u32) {
+    let target = board.target(v);
+    let (i, j) = (k / board.width, k % board.width);
+    let (ti, tj) = (target / board.width, target % board.width);
+    (
+        (j.max(tj) - j.min(tj)) as u32,
+        (i.max(ti) - i.min(ti)) as u32,
+    )
+}
+
+impl SearchState for TileMetric {
+    type Move = (Dir, usize);
+    type Moves = Vec<(Dir, usize)>;
+
+    fn moves(&self) -> Self::Moves {
+        let (i, j) = self.board.empty;
+        let (height, width) = (self.board.height, self.board.width);
+        let mut moves = Vec::new();
+        for (dir, max) in [
+            (Dir::R, width - 1 - j),
+            (Dir::U, i),
+            (Dir::L, j),
+            (Dir::D, height - 1 - i),
+        ] {
+            moves.extend((1..=max).map(|count| (dir, count)));
+        }
+        moves
+    }
+
+    fn apply(&mut self, (dir, count): (Dir, usize)) -> bool {
+        for done in 0..count {
+            if !self.step(dir) {
+                for _ in 0..done {
+                    assert!(self.step(dir.reverse()));
+                }
+                return false;
+            }
+        }
+        true
+    }
+
+    fn undo(&mut self, (dir, count): (Dir, usize)) {
+        for _ in 0..count {
+            assert!(self.step(dir.reverse()));
+        }
+    }
+
+    fn heuristic(&self) -> u32 {
+        let (height, width) = (self.board.height as u32, self.board.width as u32);
+        (self.horizontal + width - 2) / (width - 1) + (self.vertical + height - 2) / (height - 1)
+    }
+
+    fn is_goal(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    // 同じ行 (列) で続けて動かす手は 1 手にまとめられる
+    fn is_redundant((prev, _): (Dir, usize), (dir, _): (Dir, usize)) -> bool {
+        let horizontal = |dir| matches!(dir, Dir::R | Dir::L);
+        horizontal(prev) == horizontal(dir)
+    }
+}
+
+// タイルを動かす手の数が最小の手順を IDA* (`options.algorithm` が A* なら A*) で探す
+//
+// 見つけた手順は空きマスを動かす向きの列にして `on_path` に渡す。
+pub(super) fn search(
+    board: &Board,
+    options: &SolveOptions,
+    on_path: &mut dyn FnMut(&[Dir]) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    let expand = |path: &[(Dir, usize)]| {
+        path.iter()
+            .flat_map(|&(dir, count)| iter::repeat(dir).take(count))
+            .collect::<Vec<_>>()
+    };
+    let mut state = TileMetric::new(board.clone());
+    let max_depth = options.max_depth.unwrap_or(usize::MAX);
+    let key = |s: &TileMetric| s.board.key();
+    if options.algorithm == Algorithm::AStar {
+        let (path, termination, stats) = astar(
+            &state,
+            key,
+            &astar::Options {
+                max_depth,
+                deadline: options.deadline,
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone(),
+                weight: options.weight,
+            },
+        );
+        let stopped = path.map_or(false, |path| on_path(&expand(&path)).is_break());
+        return match termination {
+            Termination::Complete if stopped => (Termination::Stopped, stats),
+            termination => (termination, stats),
+        };
+    }
+    let ida_star_options = ida_star::Options {
+        max_solutions: options.max_solutions,
+        max_depth,
+        deadline: options.deadline,
+        cancel: options.cancel.clone(),
+        on_progress: options.on_progress.clone(),
+        on_iteration: options.on_iteration.clone(),
+        weight: options.weight,
+        table_size: options.table_memory
+            / (mem::size_of::<Option<(Cells, usize)>>() + board.cells.len()),
+        cycle_window: options.cycle_window,
+        move_order: options.move_order,
+    };
+    ida_star_with_table(&mut state, key, &ida_star_options, |path| {
+        on_path(&expand(path))
+    })
+}
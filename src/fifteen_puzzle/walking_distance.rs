@@ -0,0 +1,245 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{Board, BoardState, Dir};
+use crate::ida_star::SearchState;
+
+// どの状態からも動かせないことを表す
+const NONE: u32 = u32::MAX;
+
+// walking distance の表
+//
+// 空きマスを縦に動かす手だけを見ると、タイルはどの列にあるかに関係なく行の間を動く。
+// 各行にゴールが何行目のタイルが何枚あるかの表と空きマスの行を状態とし、完成形から幅優先探索して手数を求める。
+// 横の手は行と列を入れ替えた盤面の表で同じように数える。
+// 行 (列) の数え方はタイルが完成形でどこに置かれるかだけで決まるので、完成形がほかの並びでも同じ表を使える。
+pub(super) struct Table {
+    lines: usize,
+    // 盤面から状態を引くための番号
+    ids: BTreeMap<Vec<u8>, u32>,
+    // 状態ごとの完成までの手数
+    dist: Vec<u32>,
+    // `next[(id * 2 + d) * lines + g]`: 空きマスを前 (`d = 0`) か後ろ (`d = 1`) の行に動かし、
+    // ゴールが `g` 行目のタイルと入れ替えた状態 (入れ替えられなければ `NONE`)
+    next: Vec<u32>,
+}
+
+impl Table {
+    // 1 行に `capacity` マスある `lines` 行の盤面で、完成形の空きマスが `goal_blank` 行目にあるときの表
+    pub(super) fn new(lines: usize, capacity: usize, goal_blank: usize) -> Self {
+        let blank = lines * lines;
+        let mut goal = vec![0; blank + 1];
+        for i in 0..lines {
+            goal[i * lines + i] = capacity as u8;
+        }
+        goal[goal_blank * lines + goal_blank] -= 1;
+        goal[blank] = goal_blank as u8;
+
+        let mut ids = BTreeMap::new();
+        ids.insert(goal.clone(), 0);
+        let mut states = vec![goal];
+        let mut dist = vec![0];
+        let mut next = Vec::new();
+        let mut head = 0;
+        while head < states.len() {
+            let state = states[head].clone();
+            let b = usize::from(state[blank]);
+            let d = dist[head] + 1;
+            for from in [b.checked_sub(1), Some(b + 1).filter(|&r| r < lines)] {
+                for g in 0..lines {
+                    let id = match from {
+                        Some(r) if state[r * lines + g] > 0 => {
+                            let mut s = state.clone();
+                            s[r * lines + g] -= 1;
+                            s[b * lines + g] += 1;
+                            s[blank] = r as u8;
+                            let len = states.len() as u32;
+                            *ids.entry(s.clone()).or_insert_with(|| {
+                                states.push(s);
+                                dist.push(d);
+                                len
+                            })
+                        }
+                        _ => NONE,
+                    };
+                    next.push(id);
+                }
+            }
+            head += 1;
+        }
+        Table {
+            lines,
+            ids,
+            dist,
+            next,
+        }
+    }
+
+    // 各マスのタイルの (今の行, ゴールの行) と空きマスの行から状態を引く
+    fn id(&self, tiles: impl Iterator<Item = (usize, usize)>, blank: usize) -> u32 {
+        let mut state = vec![0; self.lines * self.lines + 1];
+        for (line, goal) in tiles {
+            state[line * self.lines + goal] += 1;
+        }
+        state[self.lines * self.lines] = blank as u8;
+        self.ids[&state]
+    }
+
+    fn next(&self, id: u32, forward: bool, goal: usize) -> u32 {
+        self.next[(id as usize * 2 + usize::from(forward)) * self.lines + goal]
+    }
+}
+
+// `height` x `width` の盤面の表を作れるか
+//
+// 表の状態の数は行 (列) の数とともに急に増え、4x4 では 24964 だが 5x5 では 6500 万を超える。
+pub(super) fn is_feasible(height: usize, width: usize) -> bool {
+    height.max(width) <= 4
+}
+
+// walking distance を下界に使う探索の状態
+#[derive(Clone)]
+pub(super) struct WalkingDistance {
+    board: Board,
+    rows: Arc<Table>,
+    columns: Arc<Table>,
+    row: u32,
+    column: u32,
+}
+
+impl WalkingDistance {
+    // `rows` は `Table::new(height, width, i)`, `columns` は `Table::new(width, height, j)`
+    // ((i, j) は完成形の空きマスの位置)
+    pub(super) fn new(board: Board, rows: Arc<Table>, columns: Arc<Table>) -> Self {
+        let width = board.width;
+        let tiles = board
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 > 0)
+            .map(|(k, v)| ((k / width, k % width), board.target(v)))
+            .collect::<Vec<_>>();
+        let (i, j) = board.empty;
+        let row = rows.id(tiles.iter().map(|&((r, _), t)| (r, t / width)), i);
+        let column = columns.id(tiles.iter().map(|&((_, c), t)| (c, t % width)), j);
+        WalkingDistance {
+            board,
+            rows,
+            columns,
+            row,
+            column,
+        }
+    }
+
+    // 空きマスを `dir` に動かし、表の状態も進める
+    fn step(&mut self, dir: Dir) -> bool {
+        let (i, j) = match self.board.neighbor(dir) {
+            Some(next) => next,
+            None => return false,
+        };
+        let target = self
+            .board
+            .target(self.board.cells.get(i * self.board.width + j));
+        let (goal_row, goal_column) = (target / self.board.width, target % self.board.width);
+        match dir {
+            Dir::U => self.row = self.rows.next(self.row, false, goal_row),
+            Dir::D => self.row = self.rows.next(self.row, true, goal_row),
+            Dir::L => self.column = self.columns.next(self.column, false, goal_column),
+            Dir::R => self.column = self.columns.next(self.column, true, goal_column),
+        }
+        assert!(self.board.slide(dir).is_ok());
+        true
+    }
+}
+
+impl SearchState for WalkingDistance {
+    type Move = Dir;
+    type Moves = [Dir; 4];
+
+    fn moves(&self) -> Self::Moves {
+        [Dir::R, Dir::U, Dir::L, Dir::D]
+    }
+
+    fn apply(&mut self, dir: Dir) -> bool {
+        self.step(dir)
+    }
+
+    fn undo(&mut self, dir: Dir) {
+        assert!(self.step(dir.reverse()));
+    }
+
+    fn heuristic(&self) -> u32 {
+        let walking = self.rows.dist[self.row as usize] + self.columns.dist[self.column as usize];
+        walking.max(self.board.estimate)
+    }
+
+    fn is_goal(&self) -> bool {
+        self.board.is_solved()
+    }
+
+    fn is_redundant(prev: Dir, dir: Dir) -> bool {
+        dir.reverse() == prev
+    }
+}
+
+impl BoardState for WalkingDistance {
+    fn board(&self) -> &Board {
+        &self.board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::DistanceTable;
+    use crate::rng::Rng;
+
+    // 空きマスを除くタイルの縦と横のマンハッタン距離の和
+    fn manhattan(board: &Board) -> (u32, u32) {
+        let width = board.width;
+        board
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| v.0 > 0)
+            .fold((0, 0), |(vertical, horizontal), (k, v)| {
+                let target = board.target(v);
+                (
+                    vertical + (k / width).abs_diff(target / width) as u32,
+                    horizontal + (k % width).abs_diff(target % width) as u32,
+                )
+            })
+    }
+
+    #[test]
+    fn lies_between_manhattan_and_the_exact_distance() {
+        let table = DistanceTable::new(&Board::solved(3, 3)).unwrap();
+        let rows = Arc::new(Table::new(3, 3, 2));
+        let columns = Arc::new(Table::new(3, 3, 2));
+        let mut rng = Rng::new(1);
+        for _ in 0..500 {
+            let board = Board::random(3, 3, &mut rng);
+            let mut state = WalkingDistance::new(board.clone(), rows.clone(), columns.clone());
+            // 空きマスを動かしながら進めた状態は、その盤面から作り直した状態と同じ
+            for _ in 0..10 {
+                let dir = [Dir::R, Dir::U, Dir::L, Dir::D][rng.below(4)];
+                if state.apply(dir) {
+                    let fresh =
+                        WalkingDistance::new(state.board.clone(), rows.clone(), columns.clone());
+                    assert_eq!((state.row, state.column), (fresh.row, fresh.column));
+                }
+            }
+            let board = &state.board;
+            let (vertical, horizontal) = manhattan(board);
+            let (row, column) = (
+                rows.dist[state.row as usize],
+                columns.dist[state.column as usize],
+            );
+            assert!(row >= vertical, "{}", board);
+            assert!(column >= horizontal, "{}", board);
+            assert!(row + column <= table.distance(board).unwrap(), "{}", board);
+        }
+    }
+}
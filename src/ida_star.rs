@@ -0,0 +1,870 @@
+//! 汎用の IDA*
+
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::ControlFlow;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+mod parallel;
+
+#[cfg(feature = "std")]
+pub use parallel::parallel_ida_star_with;
+
+use crate::puzzle::{
+    CancelToken, Instant, Monitor, OnProgress, SearchStats, SolveOutcome, Termination,
+};
+
+/// IDA* で探索できる状態
+///
+/// 手はすべてコスト 1 とする。
+pub trait SearchState {
+    /// 手
+    type Move: Copy;
+    /// 試す手の列
+    type Moves: IntoIterator<Item = Self::Move>;
+
+    /// 試す手 (適用できない手を含んでもよい)
+    fn moves(&self) -> Self::Moves;
+
+    /// 手を適用する。適用できないときは状態を変えずに `false` を返す
+    fn apply(&mut self, mv: Self::Move) -> bool;
+
+    /// [`SearchState::apply`] した手を戻す
+    fn undo(&mut self, mv: Self::Move);
+
+    /// ゴールまでの手数の下界
+    fn heuristic(&self) -> u32;
+
+    /// ゴールか
+    fn is_goal(&self) -> bool;
+
+    /// 直前の手 `prev` のあとに `mv` を試す必要がないか (すぐに戻る手など)
+    fn is_redundant(_prev: Self::Move, _mv: Self::Move) -> bool {
+        false
+    }
+}
+
+/// 探索の設定
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// 見つける最短手順の個数の上限 (`None` なら全部)
+    pub max_solutions: Option<usize>,
+    /// 手数の上限
+    pub max_depth: usize,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+    /// 反復を終えるたびにその統計を受け取るコールバック
+    pub on_iteration: Option<OnIteration>,
+    /// 置換表に覚える状態の数の上限 (`0` なら使わない。[`ida_star_with_table`] だけが使う)
+    pub table_size: usize,
+    /// 今の手順の何手前までの状態に戻る手を枝刈りするか (`0` なら使わない。[`ida_star_with_table`] だけが使う)
+    ///
+    /// [`SearchState::is_redundant`] ですぐに戻る手を除いても、数手で元の状態に戻る閉路
+    /// (15 パズルなら 2x2 のマスを空きマスが 3 周する 12 手など) は残る。閉路を含む手順は最短にならないので、
+    /// 手順の途中の状態に戻ったところで探索をやめても最短手順を取りこぼさない。
+    pub cycle_window: usize,
+    /// 下界にかける重み (1 以上 [`MAX_WEIGHT`] 以下)
+    ///
+    /// 1 より大きいと評価値を `手数 + weight * 下界` にする (weighted IDA*)。
+    /// 見つかる手順は最短とは限らないが、最短手順の `weight` 倍以内の手数になり、ずっと速く見つかることが多い。
+    pub weight: f64,
+    /// 展開したノードで子を試す順
+    pub move_order: MoveOrder,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_solutions: Some(1),
+            max_depth: usize::MAX,
+            deadline: None,
+            cancel: None,
+            on_progress: None,
+            on_iteration: None,
+            table_size: 0,
+            cycle_window: 0,
+            weight: 1.0,
+            move_order: MoveOrder::Fixed,
+        }
+    }
+}
+
+/// 展開したノードで子を試す順
+///
+/// 上限を超える枝はどの順でもすべて試すので、展開するノード数が変わるのはゴールのある最後の反復だけ。
+/// 最後の反復では見込みのある子から試すと、早くゴールに着いて残りの枝を探さずに済むことが多い。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+/// use book_puzzle_algorithm_rs::ida_star::{ida_star, MoveOrder, Options};
+///
+/// let mut board: Board = "0 1 2 3 4 5 6 7 8".parse().unwrap();
+/// let options = Options {
+///     move_order: MoveOrder::Heuristic,
+///     ..Options::default()
+/// };
+/// let (outcome, _) = ida_star(&mut board, &options);
+/// assert_eq!(outcome.into_solutions()[0].len(), 22);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MoveOrder {
+    /// [`SearchState::moves`] が返す順 (状態の側で順を決められる)
+    Fixed,
+    /// 手を適用したあとの下界が小さい順 (同じなら [`SearchState::moves`] の順)
+    ///
+    /// 順を決めるのに子ごとに手を 1 回余計に適用して戻すので、1 ノードあたりの時間は増える。
+    Heuristic,
+}
+
+impl Default for MoveOrder {
+    fn default() -> Self {
+        MoveOrder::Fixed
+    }
+}
+
+// 展開したノードでまだ試していない手
+enum Children<I: Iterator> {
+    Fixed(I),
+    // 手を適用したあとの下界と手
+    Sorted(vec::IntoIter<(u32, I::Item)>),
+}
+
+impl<I: Iterator> Iterator for Children<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        match self {
+            Children::Fixed(moves) => moves.next(),
+            Children::Sorted(moves) => moves.next().map(|(_, mv)| mv),
+        }
+    }
+}
+
+/// IDA* の 1 回の反復 (評価値の上限を決めた深さ優先探索) の統計
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Iteration {
+    /// 評価値の上限 (手数と、重みをかけた下界の和)
+    pub bound: f64,
+    /// この反復で展開したノード数
+    pub nodes_expanded: u64,
+    /// この反復で枝刈りした枝の数
+    pub pruned: u64,
+    /// この反復にかかった時間
+    pub elapsed: Duration,
+}
+
+/// 反復を終えるたびに [`Iteration`] を受け取るコールバック
+///
+/// 上限を上げるごとにノード数がどれだけ増えるかを見ると、下界の良し悪しがわかる。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+/// use book_puzzle_algorithm_rs::ida_star::{ida_star, OnIteration, Options};
+/// use std::sync::{Arc, Mutex};
+///
+/// let bounds = Arc::new(Mutex::new(Vec::new()));
+/// let options = Options {
+///     on_iteration: Some(OnIteration::new({
+///         let bounds = Arc::clone(&bounds);
+///         move |iteration| bounds.lock().unwrap().push(iteration.bound)
+///     })),
+///     ..Options::default()
+/// };
+/// let mut board: Board = "1 2 3 4 0 6 7 5 8".parse().unwrap();
+/// ida_star(&mut board, &options);
+/// assert_eq!(*bounds.lock().unwrap(), vec![2.0]);
+/// ```
+#[derive(Clone)]
+pub struct OnIteration(Arc<dyn Fn(&Iteration) + Send + Sync>);
+
+impl OnIteration {
+    /// 反復ごとに `callback` を呼ぶ
+    pub fn new(callback: impl Fn(&Iteration) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    // 評価値の上限を `bound` とした反復を、統計が `before` のときに始めて終えた
+    fn report(&self, bound: usize, before: &SearchStats, monitor: &Monitor) {
+        let after = monitor.stats();
+        (self.0)(&Iteration {
+            bound: bound as f64 / Cost::SCALE as f64,
+            nodes_expanded: after.nodes_expanded - before.nodes_expanded,
+            pruned: after.pruned - before.pruned,
+            elapsed: after.elapsed.saturating_sub(before.elapsed),
+        });
+    }
+}
+
+impl fmt::Debug for OnIteration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnIteration").finish_non_exhaustive()
+    }
+}
+
+/// 下界にかける重みの上限
+///
+/// 見つかる手順の長さは重みにほぼ比例して伸びるので、これより大きい重みは役に立たない。
+pub const MAX_WEIGHT: f64 = 100.0;
+
+// 評価値の計算
+//
+// 評価値は `SCALE * 手数 + weight * 下界` で、重みは `SCALE` 倍して整数で持つ。
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Cost {
+    weight: usize,
+    max_depth: usize,
+}
+
+impl Cost {
+    const SCALE: usize = 1000;
+
+    // `weight` が 1 以上 `MAX_WEIGHT` 以下でなければ panic する
+    pub(crate) fn new(weight: f64, max_depth: usize) -> Self {
+        assert!(
+            (1.0..=MAX_WEIGHT).contains(&weight),
+            "weight must be between 1 and {}",
+            MAX_WEIGHT
+        );
+        Cost {
+            weight: (weight * Self::SCALE as f64 + 0.5) as usize,
+            max_depth,
+        }
+    }
+
+    // `depth` 手で下界が `h` の状態の評価値 (手数の上限までにゴールに着けないなら `None`)
+    pub(crate) fn f(self, depth: usize, h: u32) -> Option<usize> {
+        if depth.saturating_add(h as usize) > self.max_depth {
+            return None;
+        }
+        Some(
+            depth
+                .saturating_mul(Self::SCALE)
+                .saturating_add(self.weight.saturating_mul(h as usize)),
+        )
+    }
+}
+
+// 置換表
+//
+// 状態ごとにこれまで (前の反復も含めて) 着いた最小の深さを覚え、それより深く着いたら探索しない。
+// 浅い方の経路は今の反復でも探索されるので、最短手順を取りこぼさない。
+// 状態のハッシュ値で決まる枠に 1 つずつ覚え、ほかの状態と枠がぶつかったら上書きする。
+struct Table<K> {
+    slots: Vec<Option<(K, usize)>>,
+}
+
+impl<K: Eq + Clone> Table<K> {
+    // ハッシュ値が `hash` でキーが `key` の状態に `depth` で着いたことを記録する。もっと浅く着いたことがあれば `true` を返す
+    fn visit(&mut self, hash: u64, key: &K, depth: usize) -> bool {
+        let index = (hash % self.slots.len() as u64) as usize;
+        let slot = &mut self.slots[index];
+        match slot {
+            Some((k, best)) if k == key => {
+                if *best < depth {
+                    return true;
+                }
+                *best = depth;
+            }
+            _ => *slot = Some((key.clone(), depth)),
+        }
+        false
+    }
+}
+
+struct Search<'a, S: SearchState, K> {
+    max_solutions: Option<usize>,
+    cost: Cost,
+    monitor: Monitor,
+    path: Vec<S::Move>,
+    found: usize,
+    stopped: bool,
+    on_solution: &'a mut dyn FnMut(&[S::Move]) -> ControlFlow<()>,
+    // 状態のハッシュ値とキー (置換表と閉路の枝刈りに使う)
+    key: Option<&'a dyn Fn(&S) -> (u64, K)>,
+    table: Option<Table<K>>,
+    // 閉路を調べる祖先の数と、展開中の各ノードの状態のハッシュ値とキー
+    cycle_window: usize,
+    ancestors: Vec<(u64, K)>,
+    move_order: MoveOrder,
+    // ほかのスレッドと共有する、探索をやめる合図
+    stop: Option<CancelToken>,
+    // この反復で上限を超えた評価値の最小値 (次の反復の上限)
+    next_bound: Option<usize>,
+}
+
+impl<S: SearchState, K> Search<'_, S, K> {
+    fn should_stop(&self) -> bool {
+        self.max_solutions.map_or(false, |max| self.found >= max)
+            || self.stopped
+            || self.monitor.is_interrupted()
+            || self.stop.as_ref().map_or(false, CancelToken::is_cancelled)
+    }
+
+    // 評価値が `f` で上限を超えたので枝刈りする
+    fn exceed(&mut self, f: usize) {
+        self.next_bound = Some(self.next_bound.map_or(f, |bound| bound.min(f)));
+        self.monitor.prune();
+    }
+}
+
+// 深さ `depth` の `state` から、評価値が `bound` 以下の枝を深さ優先で探す
+//
+// 再帰せずに、展開中のノードごとにまだ試していない手を積んだスタックで探す。
+// 深いノードでもスタックがあふれず、探索をやめるときは積んだ手をすべて戻して `state` を元に戻す。
+fn dfs<S: SearchState, K: Eq + Clone>(
+    bound: usize,
+    depth: usize,
+    state: &mut S,
+    search: &mut Search<S, K>,
+) {
+    let (start, base) = (depth, search.ancestors.len());
+    // `stack[d]`: 深さ `start + d` のノードでまだ試していない手 (`d > 0` のノードへの手は `search.path` の末尾にある)
+    let mut stack = Vec::new();
+    stack.extend(expand(start, state, search));
+    while let Some(d) = stack.len().checked_sub(1) {
+        let depth = start + d;
+        let mut child = None;
+        for mv in &mut stack[d] {
+            if let Some(&prev) = search.path.last() {
+                if S::is_redundant(prev, mv) {
+                    continue;
+                }
+            }
+            if !state.apply(mv) {
+                continue;
+            }
+            match search.cost.f(depth + 1, state.heuristic()) {
+                Some(f) if f <= bound => {
+                    child = Some(mv);
+                    break;
+                }
+                Some(f) => search.exceed(f),
+                None => search.monitor.prune(),
+            }
+            state.undo(mv);
+            if search.should_stop() {
+                break;
+            }
+        }
+        match child {
+            Some(mv) => {
+                search.path.push(mv);
+                if let Some(moves) = expand(depth + 1, state, search) {
+                    stack.push(moves);
+                    continue;
+                }
+                search.path.pop();
+                state.undo(mv);
+            }
+            // 手を試し終えた (か、探索をやめる) ので親に戻る
+            None => {
+                stack.pop();
+                search.ancestors.truncate(base + stack.len());
+                if stack.is_empty() {
+                    return;
+                }
+                let mv = search.path.pop().unwrap();
+                state.undo(mv);
+            }
+        }
+        if search.should_stop() {
+            for _ in 1..stack.len() {
+                let mv = search.path.pop().unwrap();
+                state.undo(mv);
+            }
+            search.ancestors.truncate(base);
+            return;
+        }
+    }
+}
+
+// 深さ `depth` の `state` に着いた。子を試すなら試す手を `search.move_order` の順で返す
+fn expand<S: SearchState, K: Eq + Clone>(
+    depth: usize,
+    state: &mut S,
+    search: &mut Search<S, K>,
+) -> Option<Children<<S::Moves as IntoIterator>::IntoIter>> {
+    if search.should_stop() {
+        return None;
+    }
+    let tracked = search.table.is_some() || search.cycle_window > 0;
+    let key = search.key.filter(|_| tracked).map(|key| key(state));
+    if let Some((hash, key)) = &key {
+        let cycle = search
+            .ancestors
+            .iter()
+            .rev()
+            .take(search.cycle_window)
+            .any(|(h, k)| h == hash && k == key);
+        if cycle
+            || search
+                .table
+                .as_mut()
+                .map_or(false, |table| table.visit(*hash, key, depth))
+        {
+            search.monitor.prune();
+            return None;
+        }
+    }
+    let estimate = state.heuristic();
+    search.monitor.visit(depth, Some(estimate));
+
+    if state.is_goal() {
+        search.found += 1;
+        search.stopped = (search.on_solution)(&search.path).is_break();
+        return None;
+    }
+    if let Some(key) = key.filter(|_| search.cycle_window > 0) {
+        search.ancestors.push(key);
+    }
+    let moves = state.moves().into_iter();
+    match search.move_order {
+        MoveOrder::Fixed => Some(Children::Fixed(moves)),
+        MoveOrder::Heuristic => {
+            let prev = search.path.last().copied();
+            let mut scored = Vec::new();
+            for mv in moves {
+                if prev.map_or(false, |prev| S::is_redundant(prev, mv)) || !state.apply(mv) {
+                    continue;
+                }
+                scored.push((state.heuristic(), mv));
+                state.undo(mv);
+            }
+            scored.sort_by_key(|&(h, _)| h);
+            Some(Children::Sorted(scored.into_iter()))
+        }
+    }
+}
+
+/// `state` からゴールまでの最短手順を IDA* で探す
+///
+/// 評価値 (手数と下界の和) の上限を決めて深さ優先探索し、見つからなければ上限を超えた評価値の最小値を次の上限にする。
+/// 最初の上限は `state` の評価値。
+/// 見つかった最短手順 (手の列) を返す。探索後の `state` は元に戻っている。
+///
+/// # Panics
+///
+/// `options.weight` が 1 未満か [`MAX_WEIGHT`] より大きいとき
+pub fn ida_star<S: SearchState>(
+    state: &mut S,
+    options: &Options,
+) -> (SolveOutcome<Vec<S::Move>>, SearchStats) {
+    let mut solutions = Vec::new();
+    let (termination, stats) = ida_star_with(state, options, |path| {
+        solutions.push(path.to_vec());
+        ControlFlow::Continue(())
+    });
+    (SolveOutcome::new(termination, solutions), stats)
+}
+
+/// [`ida_star`] と同じように探し、最短手順を見つけるたびに `on_solution` を呼ぶ
+///
+/// `on_solution` が [`ControlFlow::Break`] を返すと探索をやめる。
+pub fn ida_star_with<S: SearchState>(
+    state: &mut S,
+    options: &Options,
+    mut on_solution: impl FnMut(&[S::Move]) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    run::<S, ()>(state, options, None, None, &mut on_solution)
+}
+
+/// [`ida_star_with`] と同じように探すが、`key` で見分けた状態を置換表に覚えて同じ状態の探索をはぶく
+///
+/// `key` は状態のハッシュ値 (Zobrist ハッシュなど) と、状態を見分けるキーの組を返す。
+/// 置換表には `options.table_size` 個の枠を用意し、ハッシュ値で決まる枠に状態を 1 つずつ覚える。
+/// `options.cycle_window` が 1 以上なら、今の手順のその手数前までの状態に戻る手も枝刈りする。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+/// use book_puzzle_algorithm_rs::ida_star::{ida_star_with_table, Options};
+/// use std::ops::ControlFlow;
+///
+/// let mut board: Board = "0 1 2 3 4 5 6 7 8".parse().unwrap();
+/// let options = Options {
+///     table_size: 1 << 16,
+///     ..Options::default()
+/// };
+/// let mut length = None;
+/// ida_star_with_table(&mut board, |b: &Board| (b.zobrist(), b.packed()), &options, |path| {
+///     length = Some(path.len());
+///     ControlFlow::Break(())
+/// });
+/// assert_eq!(length, Some(22));
+/// ```
+pub fn ida_star_with_table<S: SearchState, K: Eq + Clone>(
+    state: &mut S,
+    key: impl Fn(&S) -> (u64, K),
+    options: &Options,
+    mut on_solution: impl FnMut(&[S::Move]) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    let table = Some(options.table_size)
+        .filter(|&size| size > 0)
+        .map(|size| Table {
+            slots: vec![None; size],
+        });
+    run(state, options, Some(&key), table, &mut on_solution)
+}
+
+fn run<S: SearchState, K: Eq + Clone>(
+    state: &mut S,
+    options: &Options,
+    key: Option<&dyn Fn(&S) -> (u64, K)>,
+    table: Option<Table<K>>,
+    on_solution: &mut dyn FnMut(&[S::Move]) -> ControlFlow<()>,
+) -> (Termination, SearchStats) {
+    let cost = Cost::new(options.weight, options.max_depth);
+    let mut search = Search {
+        max_solutions: options.max_solutions,
+        cost,
+        monitor: Monitor::new(
+            options.deadline,
+            options.cancel.clone(),
+            options.on_progress.clone(),
+        ),
+        path: Vec::new(),
+        found: 0,
+        stopped: false,
+        on_solution,
+        key,
+        table,
+        cycle_window: options.cycle_window,
+        ancestors: Vec::new(),
+        move_order: options.move_order,
+        stop: None,
+        next_bound: None,
+    };
+    let mut bound = match cost.f(0, state.heuristic()) {
+        Some(f) => f,
+        None => return search.monitor.finish(false),
+    };
+    loop {
+        search.next_bound = None;
+        let before = search.monitor.stats();
+        dfs(bound, 0, state, &mut search);
+        if let Some(on_iteration) = &options.on_iteration {
+            on_iteration.report(bound, &before, &search.monitor);
+        }
+        if search.found > 0 || search.stopped || search.monitor.is_interrupted() {
+            break;
+        }
+        match search.next_bound {
+            Some(next) => bound = next,
+            // 上限を超えた枝がなければ、もう探す状態がない
+            None => break,
+        }
+    }
+    let stopped = search.stopped;
+    search.monitor.finish(stopped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 上下左右の向き (`(mv + 2) % 4` が逆向き)
+    const DELTA: [(isize, isize); 4] = [(0, 1), (-1, 0), (0, -1), (1, 0)];
+
+    // `#` を壁とした迷路を、左上から右下まで歩く
+    #[derive(Clone)]
+    struct Maze {
+        rows: &'static [&'static str],
+        pos: (usize, usize),
+        // 下界にマンハッタン距離を使うか (`false` なら常に 0)
+        informed: bool,
+        // 戻していない手の数
+        applied: usize,
+    }
+
+    impl Maze {
+        fn new(rows: &'static [&'static str], informed: bool) -> Self {
+            Maze {
+                rows,
+                pos: (0, 0),
+                informed,
+                applied: 0,
+            }
+        }
+
+        fn goal(&self) -> (usize, usize) {
+            (self.rows.len() - 1, self.rows[0].len() - 1)
+        }
+
+        fn step(&mut self, mv: u8) -> bool {
+            let (di, dj) = DELTA[usize::from(mv)];
+            let i = self.pos.0 as isize + di;
+            let j = self.pos.1 as isize + dj;
+            let open = self
+                .rows
+                .get(i as usize)
+                .and_then(|row| row.as_bytes().get(j as usize))
+                .map_or(false, |&c| c != b'#');
+            if i < 0 || j < 0 || !open {
+                return false;
+            }
+            self.pos = (i as usize, j as usize);
+            true
+        }
+    }
+
+    impl SearchState for Maze {
+        type Move = u8;
+        type Moves = [u8; 4];
+
+        fn moves(&self) -> [u8; 4] {
+            [0, 1, 2, 3]
+        }
+
+        fn apply(&mut self, mv: u8) -> bool {
+            let applied = self.step(mv);
+            self.applied += usize::from(applied);
+            applied
+        }
+
+        fn undo(&mut self, mv: u8) {
+            assert!(self.step((mv + 2) % 4));
+            self.applied -= 1;
+        }
+
+        fn heuristic(&self) -> u32 {
+            let (gi, gj) = self.goal();
+            let (i, j) = self.pos;
+            if self.informed {
+                (gi - i + gj - j) as u32
+            } else {
+                0
+            }
+        }
+
+        fn is_goal(&self) -> bool {
+            self.pos == self.goal()
+        }
+
+        fn is_redundant(prev: u8, mv: u8) -> bool {
+            mv == (prev + 2) % 4
+        }
+    }
+
+    // 壁で遠回りさせられ、反復が何度も要る迷路
+    #[rustfmt::skip]
+    const MAZE: &[&str] = &[
+        "..#...",
+        "..#.#.",
+        "..#.#.",
+        "....#.",
+        "###.#.",
+    ];
+
+    // 書き換える前の再帰で書いた IDA* で、最短手順をすべて見つかった順に返す
+    fn recursive_ida_star<S: SearchState>(state: &mut S) -> Vec<Vec<S::Move>> {
+        fn dfs<S: SearchState>(
+            bound: usize,
+            state: &mut S,
+            path: &mut Vec<S::Move>,
+            solutions: &mut Vec<Vec<S::Move>>,
+            next_bound: &mut Option<usize>,
+        ) {
+            if state.is_goal() {
+                solutions.push(path.clone());
+                return;
+            }
+            for mv in state.moves() {
+                if path.last().map_or(false, |&prev| S::is_redundant(prev, mv)) {
+                    continue;
+                }
+                if !state.apply(mv) {
+                    continue;
+                }
+                let f = path.len() + 1 + state.heuristic() as usize;
+                if f <= bound {
+                    path.push(mv);
+                    dfs(bound, state, path, solutions, next_bound);
+                    path.pop();
+                } else {
+                    *next_bound = Some(next_bound.map_or(f, |b| b.min(f)));
+                }
+                state.undo(mv);
+            }
+        }
+
+        let mut bound = state.heuristic() as usize;
+        loop {
+            let (mut solutions, mut next_bound) = (Vec::new(), None);
+            dfs(
+                bound,
+                state,
+                &mut Vec::new(),
+                &mut solutions,
+                &mut next_bound,
+            );
+            match next_bound {
+                Some(next) if solutions.is_empty() => bound = next,
+                _ => return solutions,
+            }
+        }
+    }
+
+    // 最短手順をすべて見つかった順に集め、探索の終わり方と合わせて返す
+    fn collect<S: SearchState>(
+        state: &mut S,
+        options: &Options,
+    ) -> (Vec<Vec<S::Move>>, Termination, SearchStats) {
+        let mut solutions = Vec::new();
+        let (termination, stats) = ida_star_with(state, options, |path| {
+            solutions.push(path.to_vec());
+            ControlFlow::Continue(())
+        });
+        (solutions, termination, stats)
+    }
+
+    fn all_solutions<S: SearchState>(state: &mut S, options: &Options) -> Vec<Vec<S::Move>> {
+        let options = Options {
+            max_solutions: None,
+            ..options.clone()
+        };
+        let (solutions, termination, _) = collect(state, &options);
+        assert_eq!(termination, Termination::Complete);
+        solutions
+    }
+
+    #[test]
+    fn cancel_in_the_middle_of_an_iteration_restores_the_state() {
+        let cancel = CancelToken::new();
+        let options = Options {
+            max_solutions: None,
+            cancel: Some(cancel.clone()),
+            // 取り消しは 1024 ノードごとにしか確かめないので、その間に取り消す
+            on_progress: Some(OnProgress::new(1000, move |_| cancel.cancel())),
+            ..Options::default()
+        };
+        let mut maze = Maze::new(&["........", "........", "........", "........"], false);
+        let (solutions, termination, stats) = collect(&mut maze, &options);
+        assert_eq!(termination, Termination::TimedOut);
+        assert!(solutions.is_empty());
+        assert_eq!(stats.nodes_expanded, 1024);
+        assert_eq!(maze.pos, (0, 0));
+        assert_eq!(maze.applied, 0);
+    }
+
+    #[test]
+    fn stops_at_exactly_max_solutions() {
+        // 3x3 の空の迷路には最短手順が 6 通りある
+        let rows = &["...", "...", "..."];
+        let all = all_solutions(&mut Maze::new(rows, true), &Options::default());
+        assert_eq!(all.len(), 6);
+        for max in 1..=6 {
+            let options = Options {
+                max_solutions: Some(max),
+                ..Options::default()
+            };
+            let mut maze = Maze::new(rows, true);
+            let (solutions, termination, _) = collect(&mut maze, &options);
+            assert_eq!(termination, Termination::Complete);
+            assert_eq!(solutions, &all[..max]);
+            assert_eq!(maze.applied, 0);
+        }
+    }
+
+    #[test]
+    fn matches_the_recursive_version() {
+        for informed in [true, false] {
+            let expected = recursive_ida_star(&mut Maze::new(MAZE, informed));
+            assert!(expected.len() > 1);
+            assert!(expected.iter().all(|path| path.len() == 15));
+            let mut maze = Maze::new(MAZE, informed);
+            assert_eq!(all_solutions(&mut maze, &Options::default()), expected);
+            assert_eq!(maze.applied, 0);
+        }
+    }
+
+    #[test]
+    fn huge_weights_do_not_overflow() {
+        let cost = Cost::new(MAX_WEIGHT, usize::MAX);
+        assert_eq!(cost.f(usize::MAX / 2, u32::MAX), Some(usize::MAX));
+        let options = Options {
+            weight: MAX_WEIGHT,
+            ..Options::default()
+        };
+        let mut maze = Maze::new(MAZE, true);
+        let (solutions, termination, _) = collect(&mut maze, &options);
+        assert_eq!(termination, Termination::Complete);
+        assert_eq!(solutions.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "weight must be between 1 and")]
+    fn rejects_weights_above_the_limit() {
+        Cost::new(1e30, usize::MAX);
+    }
+
+    // 8 パズルの盤面と最短手数
+    #[cfg(feature = "fifteen-puzzle")]
+    const BOARDS: [(&str, usize); 6] = [
+        ("1 2 3 4 0 6 7 5 8", 2),
+        ("4 1 3 7 2 6 0 5 8", 6),
+        ("8 1 3 4 0 2 7 6 5", 14),
+        ("7 2 4 5 0 6 8 3 1", 20),
+        ("0 1 2 3 4 5 6 7 8", 22),
+        ("8 6 7 2 5 4 3 0 1", 31),
+    ];
+
+    // 最短手数と最短手順の個数
+    #[cfg(feature = "fifteen-puzzle")]
+    fn optimal(board: &str, options: &crate::fifteen_puzzle::SolveOptions) -> (usize, usize) {
+        use crate::fifteen_puzzle::{count_solutions_with, solve_with_options, SolveOptions};
+
+        let mut board = board.parse().unwrap();
+        let first = solve_with_options(&mut board, options).into_solutions();
+        let all = SolveOptions {
+            max_solutions: None,
+            ..options.clone()
+        };
+        let (count, termination, _) = count_solutions_with(&board, &all);
+        assert_eq!(termination, Termination::Complete);
+        (first[0].len() - 1, count)
+    }
+
+    #[test]
+    #[cfg(feature = "fifteen-puzzle")]
+    fn table_keeps_optimal_lengths_and_counts() {
+        use crate::fifteen_puzzle::SolveOptions;
+
+        for (board, length) in BOARDS {
+            let expected = optimal(board, &SolveOptions::default());
+            assert_eq!(expected.0, length, "{}", board);
+            // 枠がぶつかって上書きされる小さな表も試す
+            for table_memory in [64, 1 << 10, 1 << 20] {
+                let options = SolveOptions {
+                    table_memory,
+                    ..SolveOptions::default()
+                };
+                assert_eq!(optimal(board, &options), expected, "{}", board);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "fifteen-puzzle")]
+    fn cycle_window_keeps_optimal_lengths_and_counts() {
+        use crate::fifteen_puzzle::SolveOptions;
+
+        for (board, length) in BOARDS {
+            let expected = optimal(board, &SolveOptions::default());
+            assert_eq!(expected.0, length, "{}", board);
+            // 12 手の閉路を除ける窓と、それより広い窓
+            for cycle_window in [2, 12, 32] {
+                let options = SolveOptions {
+                    cycle_window,
+                    ..SolveOptions::default()
+                };
+                assert_eq!(optimal(board, &options), expected, "{}", board);
+            }
+        }
+    }
+}
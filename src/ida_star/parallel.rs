@@ -0,0 +1,221 @@
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::{dfs, Cost, Options, Search, SearchState};
+use crate::puzzle::{CancelToken, Monitor, SearchStats, Termination};
+
+// 根から進めて木を分ける手数の上限
+const MAX_SPLIT: usize = 4;
+
+// 根から数手進めた状態 (か、それより浅いゴール) と、そこまでの手順と、途中の評価値の最大
+type Subtree<S> = (S, Vec<<S as SearchState>::Move>, usize);
+
+/// [`ida_star_with`](super::ida_star_with) と同じように探すが、根に近い数手で木を分けて `threads` 個のスレッドで探す
+///
+/// 反復ごとに、分けた部分木を各スレッドが順に取って探索する。解が見つかったら残りのスレッドも止める。
+/// `on_solution` は呼び出したスレッドで呼ぶ。最短手順が複数あるとき、見つかる順番は決まらない。
+/// 置換表 (`options.table_size`) と閉路の枝刈り (`options.cycle_window`) は使わない。
+///
+/// # Panics
+///
+/// `options.weight` が 1 未満か [`MAX_WEIGHT`](super::MAX_WEIGHT) より大きいとき
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+/// use book_puzzle_algorithm_rs::ida_star::{parallel_ida_star_with, Options};
+/// use std::ops::ControlFlow;
+///
+/// let board: Board = "0 1 2 3 4 5 6 7 8".parse().unwrap();
+/// let mut length = None;
+/// parallel_ida_star_with(&board, &Options::default(), 4, |path| {
+///     length = Some(path.len());
+///     ControlFlow::Break(())
+/// });
+/// assert_eq!(length, Some(22));
+/// ```
+pub fn parallel_ida_star_with<S>(
+    state: &S,
+    options: &Options,
+    threads: usize,
+    mut on_solution: impl FnMut(&[S::Move]) -> ControlFlow<()>,
+) -> (Termination, SearchStats)
+where
+    S: SearchState + Clone + Send + 'static,
+    S::Move: Send + 'static,
+{
+    let threads = threads.max(1);
+    let cost = Cost::new(options.weight, options.max_depth);
+    let subtrees = split(state, cost, threads * 4);
+    let mut search = Search::<S, ()> {
+        max_solutions: options.max_solutions,
+        cost,
+        monitor: Monitor::new(
+            options.deadline,
+            options.cancel.clone(),
+            options.on_progress.clone(),
+        ),
+        path: Vec::new(),
+        found: 0,
+        stopped: false,
+        on_solution: &mut on_solution,
+        key: None,
+        table: None,
+        cycle_window: 0,
+        ancestors: Vec::new(),
+        move_order: options.move_order,
+        stop: None,
+        next_bound: None,
+    };
+    let mut bound = match subtrees.iter().map(|(_, _, f)| *f).min() {
+        Some(f) => f,
+        None => return search.monitor.finish(false),
+    };
+    loop {
+        search.next_bound = None;
+        let before = search.monitor.stats();
+        search_subtrees(bound, cost, &subtrees, threads, options, &mut search);
+        if let Some(on_iteration) = &options.on_iteration {
+            on_iteration.report(bound, &before, &search.monitor);
+        }
+        if search.found > 0 || search.stopped || search.monitor.is_interrupted() {
+            break;
+        }
+        match search.next_bound {
+            Some(next) => bound = next,
+            None => break,
+        }
+    }
+    let stopped = search.stopped;
+    search.monitor.finish(stopped)
+}
+
+// 部分木が `min_subtrees` 個以上になるまで根から 1 手ずつ (`MAX_SPLIT` 手まで) 進める
+//
+// 途中で見つけたゴールはそのまま残し、手数の上限までにゴールに着けない状態は捨てる。
+fn split<S: SearchState + Clone>(state: &S, cost: Cost, min_subtrees: usize) -> Vec<Subtree<S>> {
+    let mut subtrees = Vec::new();
+    if let Some(f) = cost.f(0, state.heuristic()) {
+        subtrees.push((state.clone(), Vec::new(), f));
+    }
+    for depth in 0..MAX_SPLIT {
+        if subtrees.len() >= min_subtrees {
+            break;
+        }
+        let mut next = Vec::new();
+        for (state, path, f) in subtrees {
+            if state.is_goal() {
+                next.push((state, path, f));
+                continue;
+            }
+            for mv in state.moves() {
+                if path.last().map_or(false, |&prev| S::is_redundant(prev, mv)) {
+                    continue;
+                }
+                let mut state = state.clone();
+                if !state.apply(mv) {
+                    continue;
+                }
+                if let Some(g) = cost.f(depth + 1, state.heuristic()) {
+                    let mut path = path.clone();
+                    path.push(mv);
+                    next.push((state, path, f.max(g)));
+                }
+            }
+        }
+        subtrees = next;
+    }
+    subtrees
+}
+
+// 評価値の上限を `bound` として、部分木をスレッドで分けて探す
+fn search_subtrees<S>(
+    bound: usize,
+    cost: Cost,
+    subtrees: &[Subtree<S>],
+    threads: usize,
+    options: &Options,
+    search: &mut Search<S, ()>,
+) where
+    S: SearchState + Clone + Send + 'static,
+    S::Move: Send + 'static,
+{
+    let mut queue = Vec::new();
+    for (state, path, f) in subtrees.iter().rev() {
+        if *f <= bound {
+            queue.push((state.clone(), path.clone(), *f));
+        } else {
+            search.exceed(*f);
+        }
+    }
+    let queue = Arc::new(Mutex::new(queue));
+    let stop = CancelToken::new();
+    let (sender, receiver) = mpsc::channel();
+    let workers = (0..threads)
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            let stop = stop.clone();
+            let sender = sender.clone();
+            let options = options.clone();
+            thread::spawn(move || {
+                let mut send = |path: &[S::Move]| {
+                    // 受け取る側がもういなければ何もしない
+                    let _ = sender.send(path.to_vec());
+                    ControlFlow::Continue(())
+                };
+                let mut search = Search::<S, ()> {
+                    max_solutions: options.max_solutions,
+                    cost,
+                    monitor: Monitor::new(options.deadline, options.cancel, options.on_progress),
+                    path: Vec::new(),
+                    found: 0,
+                    stopped: false,
+                    on_solution: &mut send,
+                    key: None,
+                    table: None,
+                    cycle_window: 0,
+                    ancestors: Vec::new(),
+                    move_order: options.move_order,
+                    stop: Some(stop),
+                    next_bound: None,
+                };
+                loop {
+                    let subtree = queue.lock().unwrap().pop();
+                    let (mut state, path, _) = match subtree {
+                        Some(subtree) => subtree,
+                        None => break,
+                    };
+                    let depth = path.len();
+                    search.path = path;
+                    dfs(bound, depth, &mut state, &mut search);
+                    if search.should_stop() {
+                        break;
+                    }
+                }
+                let next_bound = search.next_bound;
+                let (termination, stats) = search.monitor.finish(false);
+                (termination, stats, next_bound)
+            })
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+    for path in receiver {
+        if search.should_stop() {
+            continue;
+        }
+        search.found += 1;
+        search.stopped = (search.on_solution)(&path).is_break();
+        if search.should_stop() {
+            stop.cancel();
+        }
+    }
+    for worker in workers {
+        let (termination, stats, next_bound) = worker.join().unwrap();
+        search.monitor.merge(termination, &stats);
+        if let Some(f) = next_bound {
+            search.next_bound = Some(search.next_bound.map_or(f, |bound| bound.min(f)));
+        }
+    }
+}
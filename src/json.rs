@@ -0,0 +1,377 @@
+//! 小さな JSON の読み書き
+//!
+//! 問題と解をやりとりするためのもので、外部のクレートは使わない。
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+/// JSON の値
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `null`
+    Null,
+    /// `true` か `false`
+    Bool(bool),
+    /// 数
+    Number(f64),
+    /// 文字列
+    String(String),
+    /// 配列
+    Array(Vec<Value>),
+    /// オブジェクト。キーと値の組 (書かれた順)
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// オブジェクトのキー `key` の値
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// 文字列の中身
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// 配列の要素
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// 0 以上の整数
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Number(x) if x >= 0.0 && x < u64::MAX as f64 && x as u64 as f64 == x => {
+                Some(x as u64)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(x) => write!(f, "{}", x),
+            Value::String(s) => write!(f, "{}", quote(s)),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", quote(k), v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// 文字列を JSON の文字列リテラルにする
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                quoted.push_str(&alloc::format!("\\u{:04x}", c as u32));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// JSON として読めなかった位置 (バイト単位)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct JsonError {
+    /// 先頭からのバイト数
+    pub offset: usize,
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "syntax error at byte {}", self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for JsonError {}
+
+/// 配列とオブジェクトを入れ子にできる深さ
+///
+/// 再帰で読むので、これより深いものはスタックを使い切る前にエラーにする。
+pub const MAX_DEPTH: usize = 64;
+
+/// 文字列全体を 1 つの JSON の値として読む
+pub fn parse(s: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser {
+        input: s,
+        bytes: s.as_bytes(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos == s.len() {
+        Ok(value)
+    } else {
+        Err(parser.error())
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    // いま開いている配列とオブジェクトの数
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> JsonError {
+        JsonError { offset: self.pos }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), JsonError> {
+        if self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+        core::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Value::Number)
+            .ok_or(JsonError { offset: start })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect("\"")?;
+        let mut s = String::new();
+        loop {
+            let c = self.input[self.pos..]
+                .chars()
+                .next()
+                .ok_or_else(|| self.error())?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.bytes.get(self.pos) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let hex = self
+                                .bytes
+                                .get(self.pos + 1..self.pos + 5)
+                                .and_then(|hex| core::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error())?;
+                            self.pos += 4;
+                            hex
+                        }
+                        _ => return Err(self.error()),
+                    };
+                    self.pos += 1;
+                    s.push(escaped);
+                }
+                c if (c as u32) < 0x20 => return Err(self.error()),
+                c => s.push(c),
+            }
+        }
+    }
+
+    // `open` と `close` で囲まれ、`,` で区切られた要素を読む
+    fn list(
+        &mut self,
+        open: &str,
+        close: u8,
+        mut item: impl FnMut(&mut Self) -> Result<(), JsonError>,
+    ) -> Result<(), JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.expect(open)?;
+        self.depth += 1;
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&close) {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(&b) if b == close => {
+                    self.pos += 1;
+                    self.depth -= 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        let mut values = Vec::new();
+        self.list("[", b']', |p| {
+            values.push(p.value()?);
+            Ok(())
+        })?;
+        Ok(Value::Array(values))
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        let mut fields = Vec::new();
+        self.list("{", b'}', |p| {
+            p.skip_whitespace();
+            let key = p.string()?;
+            p.skip_whitespace();
+            p.expect(":")?;
+            fields.push((key, p.value()?));
+            Ok(())
+        })?;
+        Ok(Value::Object(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn reads_escapes() {
+        let value = parse(r#""a\"b\\c\/d\b\f\n\r\téああ""#).unwrap();
+        assert_eq!(
+            value,
+            Value::String("a\"b\\c/d\u{8}\u{c}\n\r\té\u{3042}あ".to_string())
+        );
+        // 書き出したものを読むと元に戻る
+        let s = "\"\\\n\u{1}x";
+        assert_eq!(parse(&quote(s)).unwrap(), Value::String(s.to_string()));
+    }
+
+    #[test]
+    fn reads_numbers() {
+        assert_eq!(parse("0").unwrap(), Value::Number(0.0));
+        assert_eq!(parse("-12").unwrap(), Value::Number(-12.0));
+        assert_eq!(parse("2.5e3").unwrap(), Value::Number(2500.0));
+        assert_eq!(parse("15").unwrap().as_u64(), Some(15));
+        assert_eq!(parse("1.5").unwrap().as_u64(), None);
+        assert_eq!(parse("-1").unwrap().as_u64(), None);
+        assert_eq!(parse("1-2"), Err(JsonError { offset: 0 }));
+    }
+
+    #[test]
+    fn reads_nested_values() {
+        let value = parse(r#" { "a" : [1, [true, null], {}], "b": [] } "#).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                (
+                    "a".to_string(),
+                    Value::Array(vec![
+                        Value::Number(1.0),
+                        Value::Array(vec![Value::Bool(true), Value::Null]),
+                        Value::Object(vec![]),
+                    ])
+                ),
+                ("b".to_string(), Value::Array(vec![])),
+            ])
+        );
+        assert_eq!(parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_too_deep_nesting() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)),
+            Err(JsonError { offset: MAX_DEPTH })
+        );
+        // 閉じていない深い入れ子もスタックを使い切らない
+        assert_eq!(
+            parse(&"[".repeat(200_000)),
+            Err(JsonError { offset: MAX_DEPTH })
+        );
+    }
+
+    #[test]
+    fn reports_the_offset_of_errors() {
+        assert_eq!(parse("[1, 2] x"), Err(JsonError { offset: 7 }));
+        assert_eq!(parse("[1 2]"), Err(JsonError { offset: 3 }));
+        assert_eq!(parse("{\"a\" 1}"), Err(JsonError { offset: 5 }));
+        assert_eq!(parse("\"abc"), Err(JsonError { offset: 4 }));
+        assert_eq!(parse("\"a\\x\""), Err(JsonError { offset: 3 }));
+        assert_eq!(parse("\"\n\""), Err(JsonError { offset: 2 }));
+        assert_eq!(parse("nul"), Err(JsonError { offset: 0 }));
+        assert_eq!(parse(""), Err(JsonError { offset: 0 }));
+    }
+}
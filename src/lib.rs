@@ -0,0 +1,33 @@
+//! 『パズルで鍛えるアルゴリズム力』のパズルのソルバ
+//!
+//! - [`mushikui`]: 虫食算
+//! - [`fifteen_puzzle`]: 15 パズル
+//! - [`ida_star`]: 汎用の IDA*
+//! - [`astar`]: 汎用の A*
+//! - [`sma_star`]: 汎用の SMA* (メモリに上限のある A*)
+//! - [`backtrack`]: 汎用のバックトラック
+//! - [`rng`]: 問題の生成に使う擬似乱数
+//! - [`json`]: 問題と解をやりとりするための JSON
+//!
+//! どちらも [`puzzle::Puzzle`] を実装している。C からは `capi/` のライブラリを通して呼べる。
+//!
+//! 各ソルバは同名の feature (`mushikui`, `fifteen-puzzle`) で有効になる。既定ではすべて有効。
+//!
+//! 探索の本体は `core` と `alloc` だけで書いてあり、`std` feature を外すと `#![no_std]` でビルドできる。
+//! このとき締め切り ([`puzzle::Instant`]) は使えない。
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod astar;
+pub mod backtrack;
+#[cfg(feature = "fifteen-puzzle")]
+pub mod fifteen_puzzle;
+pub mod ida_star;
+pub mod json;
+#[cfg(feature = "mushikui")]
+pub mod mushikui;
+pub mod puzzle;
+pub mod rng;
+pub mod sma_star;
@@ -0,0 +1,2161 @@
+//! 虫食算 (四則演算) のソルバ
+//!
+//! ref: https://github.com/drken1215/mushikui_solver
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use crate::backtrack::{self, Brancher, Conflict, Domain, Problem, Propagator, Reason, Store, Var};
+use crate::json::{self, Value};
+use crate::puzzle::{
+    CancelToken, Instant, OnProgress, Puzzle, SearchStats, SolveOutcome, Termination,
+};
+
+mod addition;
+mod any;
+mod builder;
+mod cnf;
+mod columns;
+mod cryptarithm;
+mod division;
+mod generate;
+mod render;
+mod subtraction;
+
+pub use addition::AdditionMushikui;
+pub use any::AnyMushikui;
+pub use builder::{IntoRow, MushikuiBuilder};
+use columns::{PartialProductColumns, ProductColumns};
+pub use cryptarithm::Cryptarithm;
+pub use division::Division;
+pub use subtraction::SubtractionMushikui;
+
+/// 虫食算の 1 マス
+#[derive(Debug, Copy, Clone)]
+pub enum Digit {
+    /// 数字が決まっているマス
+    Fix(u8),
+    /// 虫食いのマス (`*`。読むときは `?`, `_`, `.` も同じ)
+    Any,
+    /// 覆面算の文字のマス。同じ文字には同じ数字、違う文字には違う数字が入る
+    Letter(char),
+}
+
+impl TryFrom<char> for Digit {
+    type Error = MushikuiError;
+
+    fn try_from(ch: char) -> Result<Self, Self::Error> {
+        if ch.is_ascii_digit() {
+            Ok(Digit::Fix(ch as u8 - b'0'))
+        } else if matches!(ch, '*' | '?' | '_' | '.') {
+            Ok(Digit::Any)
+        } else if ch.is_ascii_alphabetic() {
+            Ok(Digit::Letter(ch))
+        } else {
+            Err(MushikuiError::BadCharacter(ch))
+        }
+    }
+}
+
+impl Display for Digit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Digit::Fix(d) => {
+                write!(f, "{}", d)
+            }
+            Digit::Any => {
+                write!(f, "*")
+            }
+            Digit::Letter(ch) => {
+                write!(f, "{}", ch)
+            }
+        }
+    }
+}
+
+impl Digit {
+    /// 決まっている数字
+    pub fn digit(self) -> Option<u8> {
+        match self {
+            Digit::Fix(d) => Some(d),
+            Digit::Any | Digit::Letter(_) => None,
+        }
+    }
+
+    /// `digit` を置けるか (文字のマスどうしの関係は見ない)
+    pub fn accept(self, digit: u8) -> bool {
+        match self {
+            Digit::Fix(d) => d == digit,
+            Digit::Any | Digit::Letter(_) => true,
+        }
+    }
+
+    /// 虫食いのマスか
+    pub fn is_any(self) -> bool {
+        matches!(self, Digit::Any)
+    }
+
+    // `lo` 以上の数字 (決まっていればその数字だけ)
+    fn domain(self, lo: u8) -> Domain {
+        match self {
+            Digit::Fix(d) => Domain::range(lo, 9).intersect(Domain::singleton(d)),
+            Digit::Any | Digit::Letter(_) => Domain::range(lo, 9),
+        }
+    }
+}
+
+// 同じ文字のマスには同じ数字、違う文字のマスには違う数字
+struct Letters {
+    groups: Vec<Vec<Var>>,
+}
+
+impl Letters {
+    // マスと変数の組から文字ごとに変数をまとめる。文字のマスがなければ `None`
+    fn new(cells: impl IntoIterator<Item = (Digit, Var)>) -> Option<Self> {
+        let mut groups = BTreeMap::new();
+        for (digit, var) in cells {
+            if let Digit::Letter(ch) = digit {
+                groups.entry(ch).or_insert_with(Vec::new).push(var);
+            }
+        }
+        if groups.is_empty() {
+            None
+        } else {
+            Some(Self {
+                groups: groups.into_values().collect(),
+            })
+        }
+    }
+}
+
+impl Propagator for Letters {
+    fn watches(&self) -> Vec<Var> {
+        self.groups.iter().flatten().copied().collect()
+    }
+
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        for (g, group) in self.groups.iter().enumerate() {
+            let value = match group.iter().find_map(|&v| store.value(v)) {
+                Some(value) => value,
+                None => continue,
+            };
+            for &v in group {
+                store.assign(v, value)?;
+            }
+            for (_, other) in self.groups.iter().enumerate().filter(|&(h, _)| h != g) {
+                for &v in other {
+                    store.remove(v, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 手掛かりの同じ文字に同じ数字、違う文字に違う数字が入っているか
+fn letters_consistent(cells: impl IntoIterator<Item = (Digit, Digit)>) -> bool {
+    let mut letters = BTreeMap::new();
+    for (clue, filled) in cells {
+        if let (Digit::Letter(ch), Some(d)) = (clue, filled.digit()) {
+            if *letters.entry(ch).or_insert(d) != d {
+                return false;
+            }
+        }
+    }
+    let mut seen = [false; 10];
+    letters
+        .into_values()
+        .all(|d| !core::mem::replace(&mut seen[usize::from(d)], true))
+}
+
+/// 掛け算の虫食算
+///
+/// 被乗数, 乗数, 部分積 (乗数の下の桁から順), 積を持つ。被乗数は乗数より短くてもよい。
+/// 乗数の `0` の桁の部分積は、書くのを省いた空の行か `0` だけの行にする。
+/// 積を省いた問題では積は空の行で、解くと積も埋まる。
+#[derive(Debug, Clone)]
+pub struct Mushikui {
+    multiplicand: Vec<Digit>,
+    multiplier: Vec<Digit>,
+    partial_product: Vec<Vec<Digit>>,
+    product: Vec<Digit>,
+}
+
+/// 推論だけで数字が決まるマス ([`Mushikui::hint`])
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Hint {
+    /// 行 ([`DigitConstraint`] と同じ数え方)
+    pub row: usize,
+    /// 列 (行の左端から 0 から数える)
+    pub column: usize,
+    /// 入る数字
+    pub digit: u8,
+    /// 決まる理由 (英語)
+    pub reason: String,
+    cell: String,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} is {}: {}", self.cell, self.digit, self.reason)
+    }
+}
+
+// 推論の 1 段
+struct Step {
+    row: usize,
+    column: usize,
+    value: u8,
+    // 仮定した値か
+    guess: bool,
+    // マスの説明
+    cell: String,
+    reason: String,
+}
+
+/// 埋めた虫食算が解でない理由 ([`Mushikui::verify`])
+///
+/// 行は [`DigitConstraint`] と同じく被乗数, 乗数, 部分積, 積の順に 0 から数え、列は行の左端から数える。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VerifyError {
+    /// 行の数か長さが問題と違う
+    Shape,
+    /// 数字の入っていないマス
+    NotFilled { row: usize, column: usize },
+    /// 問題の数字と違うマス
+    ClueMismatch { row: usize, column: usize },
+    /// 先頭が `0` の行
+    LeadingZero { row: usize },
+    /// 同じ文字に違う数字か、違う文字に同じ数字が入っている
+    Letters,
+    /// 部分積が被乗数と乗数の桁の積でない (乗数の下の桁から 0 から数える)
+    PartialProduct(usize),
+    /// 積が部分積の和でない
+    Product,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            VerifyError::Shape => write!(f, "rows do not match the puzzle"),
+            VerifyError::NotFilled { row, column } => {
+                write!(f, "cell ({}, {}) is not filled", row, column)
+            }
+            VerifyError::ClueMismatch { row, column } => {
+                write!(f, "cell ({}, {}) does not match the clue", row, column)
+            }
+            VerifyError::LeadingZero { row } => write!(f, "row {} starts with 0", row),
+            VerifyError::Letters => write!(f, "letters are not assigned consistently"),
+            VerifyError::PartialProduct(j) => write!(
+                f,
+                "partial product {} is not the multiplicand times digit {} from the right of the multiplier",
+                j + 1,
+                j + 1
+            ),
+            VerifyError::Product => {
+                write!(f, "the product is not the sum of the shifted partial products")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VerifyError {}
+
+/// マスに数字を入れられなかった理由 ([`Mushikui::fix`], [`Session::fix`])
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FixError {
+    /// そのマスがない
+    NoSuchCell { row: usize, column: usize },
+    /// `0` から `9` でない
+    NotADigit(u8),
+    /// 入れると解がなくなることが推論でわかる
+    Contradiction,
+}
+
+impl Display for FixError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FixError::NoSuchCell { row, column } => {
+                write!(f, "cell ({}, {}) does not exist", row, column)
+            }
+            FixError::NotADigit(d) => write!(f, "{} is not a digit", d),
+            FixError::Contradiction => write!(f, "the digit contradicts the other cells"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FixError {}
+
+/// 掛け算の虫食算の解 ([`Mushikui::solutions`])
+///
+/// 被乗数と乗数の各桁だけを持ち、部分積と積は表示するときに計算する。
+/// 桁数に上限はない (本の Q22 のような 24 桁 × 20 桁の問題も表せる)。
+#[derive(Debug, Clone)]
+pub struct Solution<'a> {
+    puzzle: &'a Mushikui,
+    // 上の桁から
+    multiplicand: Vec<u8>,
+    multiplier: Vec<u8>,
+}
+
+/// マスを 1 つずつ埋めながら解く途中の状態 ([`Mushikui::session`])
+///
+/// 入れた数字から推論した各マスの候補を持ち、次に入れるときはそこから推論を続ける。
+pub struct Session {
+    model: Model,
+    options: SolveOptions,
+    domains: Vec<Domain>,
+}
+
+/// 虫食算として読めなかった理由
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MushikuiError {
+    /// マスとして読めない文字
+    BadCharacter(char),
+    /// 割り算の先頭が `0` の行 (商, 除数と被除数, 部分積と余りの順に数えた行番号)
+    ///
+    /// ほかの虫食算では、先頭の `0` を許すかは [`SolveOptions::allow_leading_zero`] で決める。
+    LeadingZero { row: usize },
+    /// 行の長さが計算として合わない
+    InconsistentRowLengths,
+    /// 部分積の行数が乗数の桁数と違う
+    WrongPartialProductCount { expected: usize, actual: usize },
+    /// 行数が足りない
+    TooFewRows(usize),
+    /// 行数が多すぎる
+    TooManyRows(usize),
+    /// 割り算の 2 行目が `除数)被除数` になっていない
+    MissingDivisor,
+    /// 覆面算に `=` がない
+    MissingEquals,
+    /// 割り算の行の位置が合わない (商, 除数と被除数, 部分積と余りの順に数えた行番号)
+    MisalignedRow { row: usize },
+    /// JSON として読めないか、必要なキーがない
+    InvalidJson(String),
+    /// 1 行の符号 ([`Mushikui::to_code`]) が `m.` で始まっていない
+    InvalidCode,
+}
+
+impl Display for MushikuiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MushikuiError::BadCharacter(ch) => write!(f, "unexpected character {:?}", ch),
+            MushikuiError::LeadingZero { row } => write!(f, "row {} starts with 0", row),
+            MushikuiError::InconsistentRowLengths => {
+                write!(f, "row lengths are inconsistent with the calculation")
+            }
+            MushikuiError::WrongPartialProductCount { expected, actual } => write!(
+                f,
+                "expected {} partial products, found {}",
+                expected, actual
+            ),
+            MushikuiError::TooFewRows(n) => write!(f, "too few rows: found {}", n),
+            MushikuiError::TooManyRows(n) => write!(f, "too many rows: found {}", n),
+            MushikuiError::MissingEquals => {
+                write!(f, "expected `=` between the addends and the sum")
+            }
+            MushikuiError::MissingDivisor => {
+                write!(f, "the second row must be `divisor)dividend`")
+            }
+            MushikuiError::MisalignedRow { row } => {
+                write!(f, "row {} is not aligned with the division", row)
+            }
+            MushikuiError::InvalidJson(reason) => write!(f, "invalid JSON: {}", reason),
+            MushikuiError::InvalidCode => write!(f, "a puzzle code must start with `m.`"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MushikuiError {}
+
+/// 探索の設定
+#[derive(Debug, Clone, Default)]
+pub struct SolveOptions {
+    /// 見つける解の個数の上限 (`None` なら全部)
+    pub max_solutions: Option<usize>,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+    /// マスに入る数字の条件 (すべて満たす解だけを探す)
+    pub constraints: Vec<DigitConstraint>,
+    /// マスに置く数字を絞るフック
+    pub filter: Option<DigitFilter>,
+    /// 2 桁以上の行の先頭にも `0` を許す (桁をそろえて `0` を詰めた問題のため。割り算には効かない)
+    pub allow_leading_zero: bool,
+    /// 解のマス全体で数字 `d` がちょうど `digit_counts[d]` 回使われる
+    ///
+    /// `0` から `9` を 1 回ずつなら `[1; 10]`、`1` から `9` を 1 回ずつなら `[0, 1, 1, 1, 1, 1, 1, 1, 1, 1]`。
+    /// 積を省いた掛け算では積のマスは数えない。
+    pub digit_counts: Option<[usize; 10]>,
+    /// 解のマス全体で数字を使う回数の条件 (すべて満たす解だけを探す)
+    pub digit_limits: Vec<DigitLimit>,
+    /// 掛け算の被乗数と乗数が同じ数 (平方の虫食算)。桁数が違えば解はない
+    pub operands_equal: bool,
+    /// 被乗数と乗数の桁数が同じ掛け算で、入れ替えても解になる組は被乗数 ≤ 乗数の方だけを解とする
+    pub dedupe_commutative: bool,
+}
+
+/// 虫食算の難しさ ([`Mushikui::rate`])
+///
+/// 比べるときは仮定を重ねた深さ, 仮定した回数, ノード数の順に比べる。大きいほど難しい。
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Difficulty {
+    /// 仮定を重ねた最大の深さ (推論だけで解ければ `0`)
+    pub guess_depth: usize,
+    /// 値を仮定して分岐した回数
+    pub guesses: u64,
+    /// 探索したノード数
+    pub nodes: u64,
+}
+
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "guess depth {}, {} guesses, {} nodes",
+            self.guess_depth, self.guesses, self.nodes
+        )
+    }
+}
+
+/// マスに入る数字の条件
+///
+/// 行は各虫食算の行の順 (掛け算なら被乗数, 乗数, 部分積, 積) に 0 から数え、
+/// 列は行の左端から 0 から数える。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::backtrack::Domain;
+/// use book_puzzle_algorithm_rs::mushikui::DigitConstraint;
+///
+/// // 虫食いのマスは奇数
+/// let odd = DigitConstraint::Hidden([1, 3, 5, 7, 9].into_iter().collect());
+/// // 7 は現れない
+/// let no_seven = DigitConstraint::All(Domain::range(0, 9).without(7));
+/// # let _ = (odd, no_seven);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DigitConstraint {
+    /// 虫食いのマス (`*` と英字) には `Domain` の数字だけが入る
+    Hidden(Domain),
+    /// すべてのマスに `Domain` の数字だけが入る
+    All(Domain),
+    /// `row` 行目の `column` 列目のマスには `digits` の数字だけが入る
+    Cell {
+        row: usize,
+        column: usize,
+        digits: Domain,
+    },
+}
+
+/// 解のマス全体で数字を使う回数の条件 ([`SolveOptions::digit_limits`])
+///
+/// `digits` のどの数字も、それぞれ `min` 回以上 `max` 回以下使う。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::backtrack::Domain;
+/// use book_puzzle_algorithm_rs::mushikui::DigitLimit;
+///
+/// // どの数字も 2 回まで
+/// let at_most_twice = DigitLimit::at_most(Domain::range(0, 9), 2);
+/// // 3 をちょうど 4 回
+/// let four_threes = DigitLimit::exactly(3, 4);
+/// # let _ = (at_most_twice, four_threes);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DigitLimit {
+    /// 条件をかける数字
+    pub digits: Domain,
+    /// 使う回数の下限
+    pub min: usize,
+    /// 使う回数の上限
+    pub max: usize,
+}
+
+impl DigitLimit {
+    /// `digits` のどの数字も `max` 回まで
+    pub fn at_most(digits: Domain, max: usize) -> Self {
+        Self {
+            digits,
+            min: 0,
+            max,
+        }
+    }
+
+    /// 数字 `digit` をちょうど `count` 回
+    pub fn exactly(digit: u8, count: usize) -> Self {
+        Self {
+            digits: Domain::singleton(digit),
+            min: count,
+            max: count,
+        }
+    }
+}
+
+/// 虫食算のマス
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cell {
+    /// 行 ([`DigitConstraint`] と同じ数え方)
+    pub row: usize,
+    /// 列 (行の左端から 0 から数える)
+    pub column: usize,
+    /// 虫食いのマス (`*` と英字) か
+    pub hidden: bool,
+}
+
+/// マスにその数字を置いてよいかを決めるフック
+///
+/// 探索で試す数字も、制約から決まる数字も、このフックを通ったものだけになる。
+#[derive(Clone)]
+pub struct DigitFilter(Arc<dyn Fn(Cell, u8) -> bool + Send + Sync>);
+
+impl DigitFilter {
+    /// `filter` が `true` を返す数字だけを置く
+    pub fn new(filter: impl Fn(Cell, u8) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    /// すべてのマスが素数 (2, 3, 5, 7)
+    pub fn prime() -> Self {
+        Self::new(|_, digit| is_prime(digit))
+    }
+
+    /// 虫食いのマスが素数 (2, 3, 5, 7)
+    pub fn hidden_prime() -> Self {
+        Self::new(|cell, digit| !cell.hidden || is_prime(digit))
+    }
+
+    /// マス `cell` に `digit` を置いてよいか
+    pub fn accept(&self, cell: Cell, digit: u8) -> bool {
+        (self.0)(cell, digit)
+    }
+}
+
+impl core::fmt::Debug for DigitFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DigitFilter").finish_non_exhaustive()
+    }
+}
+
+fn is_prime(digit: u8) -> bool {
+    matches!(digit, 2 | 3 | 5 | 7)
+}
+
+// 各行のマスとその変数に `options` の条件を当てはめて領域を狭める
+fn restrict<'a>(
+    problem: &mut Problem,
+    rows: impl IntoIterator<Item = (&'a [Digit], &'a [Var])>,
+    options: &SolveOptions,
+) {
+    if options.constraints.is_empty()
+        && options.filter.is_none()
+        && options.digit_counts.is_none()
+        && options.digit_limits.is_empty()
+    {
+        return;
+    }
+    let mut cells = Vec::new();
+    for (r, (row, vars)) in rows.into_iter().enumerate() {
+        for (c, (digit, &var)) in row.iter().zip(vars).enumerate() {
+            cells.push(var);
+            for constraint in &options.constraints {
+                match *constraint {
+                    DigitConstraint::Hidden(digits) if digit.digit().is_none() => {
+                        problem.restrict(var, digits)
+                    }
+                    DigitConstraint::All(digits) => problem.restrict(var, digits),
+                    DigitConstraint::Cell {
+                        row,
+                        column,
+                        digits,
+                    } if (row, column) == (r, c) => problem.restrict(var, digits),
+                    _ => {}
+                }
+            }
+            if let Some(filter) = &options.filter {
+                let cell = Cell {
+                    row: r,
+                    column: c,
+                    hidden: digit.digit().is_none(),
+                };
+                let digits = (0..=9).filter(|&d| filter.accept(cell, d)).collect();
+                problem.restrict(var, digits);
+            }
+        }
+    }
+    if options.digit_counts.is_none() && options.digit_limits.is_empty() {
+        return;
+    }
+    let (mut min, mut max) = ([0; 10], [cells.len(); 10]);
+    if let Some(counts) = options.digit_counts {
+        min = counts;
+        max = counts;
+    }
+    for limit in &options.digit_limits {
+        for d in limit.digits.iter().filter(|&d| d <= 9) {
+            let d = usize::from(d);
+            min[d] = min[d].max(limit.min);
+            max[d] = max[d].min(limit.max);
+        }
+    }
+    problem.add_propagator(DigitCount { cells, min, max });
+}
+
+// 各数字 `d` がマス `cells` に `min[d]` 回以上 `max[d]` 回以下入る (同じ変数のマスが複数あってよい)
+struct DigitCount {
+    cells: Vec<Var>,
+    min: [usize; 10],
+    max: [usize; 10],
+}
+
+impl Propagator for DigitCount {
+    fn watches(&self) -> Vec<Var> {
+        self.cells.clone()
+    }
+
+    // 上限に達した数字は他のマスから除き、入りうるマスが下限ちょうどならそこに決める
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let n = self.cells.len();
+        if self.min.iter().sum::<usize>() > n || self.max.iter().sum::<usize>() < n {
+            return Err(Conflict);
+        }
+        for ((d, &min), &max) in (0..).zip(&self.min).zip(&self.max) {
+            let fixed = self.cells.iter().filter(|&&v| store.value(v) == Some(d));
+            let possible = self.cells.iter().filter(|&&v| store.domain(v).contains(d));
+            let (fixed, possible) = (fixed.count(), possible.count());
+            if fixed > max || possible < min {
+                return Err(Conflict);
+            }
+            for &v in &self.cells {
+                if fixed == max && store.value(v) != Some(d) {
+                    store.remove(v, d)?;
+                } else if possible == min && store.domain(v).contains(d) {
+                    store.assign(v, d)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// 行 `a` と `b` は同じ数
+struct SameNumber {
+    a: Vec<Var>,
+    b: Vec<Var>,
+}
+
+impl Propagator for SameNumber {
+    fn watches(&self) -> Vec<Var> {
+        self.a.iter().chain(&self.b).copied().collect()
+    }
+
+    // 同じ桁どうしの領域を共通部分に狭める
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        if self.a.len() != self.b.len() {
+            return Err(Conflict);
+        }
+        for (&a, &b) in self.a.iter().zip(&self.b) {
+            let domain = store.domain(a).intersect(store.domain(b));
+            store.restrict(a, domain)?;
+            store.restrict(b, domain)?;
+        }
+        Ok(())
+    }
+}
+
+impl Mushikui {
+    /// 各行の文字 (数字, `*`, 覆面算の英字) から虫食算を作る
+    ///
+    /// # Panics
+    ///
+    /// [`Mushikui::try_new`] が失敗するとき
+    pub fn new(
+        multiplicand: &[char],
+        multiplier: &[char],
+        partial_product: &[Vec<char>],
+        product: &[char],
+    ) -> Self {
+        match Self::try_new(multiplicand, multiplier, partial_product, product) {
+            Ok(mushikui) => mushikui,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// 各行の文字 (数字, `*`, 覆面算の英字) から虫食算を作る
+    ///
+    /// `product` が空なら積を省いた問題にする。
+    /// 行の長さが計算として合わないときはエラーを返す。
+    /// 先頭が `0` の行は、[`SolveOptions::allow_leading_zero`] がなければ解がない。
+    pub fn try_new(
+        multiplicand: &[char],
+        multiplier: &[char],
+        partial_product: &[Vec<char>],
+        product: &[char],
+    ) -> Result<Self, MushikuiError> {
+        let mut builder = MushikuiBuilder::new()
+            .multiplicand(multiplicand)?
+            .multiplier(multiplier)?;
+        for part in partial_product {
+            builder = builder.partial_product(&part[..])?;
+        }
+        if !product.is_empty() {
+            builder = builder.product(product)?;
+        }
+        builder.build()
+    }
+
+    fn calculate_partial_product(&self, d: u8) -> Vec<u8> {
+        let multiplicand = self
+            .multiplicand
+            .iter()
+            .rev()
+            .map_while(|digit| digit.digit())
+            .collect::<Vec<_>>();
+        let mut prod = Vec::new();
+        let mut carry = 0;
+        for m in &multiplicand {
+            let e = m * d + carry;
+            assert!(e <= 90);
+            prod.push(e % 10);
+            carry = e / 10;
+        }
+        if carry > 0 {
+            assert!(carry <= 9);
+            if multiplicand.len() == self.multiplicand.len() {
+                prod.push(carry);
+            }
+        }
+        prod.reverse();
+        prod
+    }
+
+    fn calculate_product(&self) -> Vec<u8> {
+        let mut prod = Vec::new();
+        let mut carry = 0;
+        let partial_product = &self.partial_product;
+        for k in 0..(partial_product[partial_product.len() - 1].len() + self.multiplier.len() - 1) {
+            let mut s = 0;
+            for (j, part) in partial_product.iter().enumerate() {
+                if k >= j && k - j < part.len() {
+                    let d = part[part.len() - (k - j) - 1].digit().unwrap_or(0);
+                    s += u32::from(d);
+                }
+            }
+            prod.push(((s + carry) % 10) as u8);
+            carry = (s + carry) / 10;
+        }
+        while carry > 0 {
+            prod.push((carry % 10) as u8);
+            carry /= 10;
+        }
+        prod.reverse();
+        prod
+    }
+
+    /// すべての解を返す
+    pub fn solve(&self) -> Vec<Self> {
+        self.solve_with_options(&SolveOptions::default())
+            .into_solutions()
+    }
+
+    /// `options` にしたがって解を探す
+    pub fn solve_with_options(&self, options: &SolveOptions) -> SolveOutcome<Self> {
+        self.solve_with_stats(options).0
+    }
+
+    /// `options` にしたがって解を探し、探索の統計も返す
+    pub fn solve_with_stats(&self, options: &SolveOptions) -> (SolveOutcome<Self>, SearchStats) {
+        let mut solutions = Vec::new();
+        let (termination, stats) = self.solve_with(options, |solution| {
+            solutions.push(solution);
+            ControlFlow::Continue(())
+        });
+        (SolveOutcome::new(termination, solutions), stats)
+    }
+
+    /// `options` にしたがって解を探し、見つけるたびに `on_solution` を呼ぶ
+    ///
+    /// `on_solution` が [`ControlFlow::Break`] を返すと探索をやめる。
+    pub fn solve_with(
+        &self,
+        options: &SolveOptions,
+        mut on_solution: impl FnMut(Self) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        let model = self.model(options);
+        model.solve_with(options, |values| on_solution(model.fill(values)))
+    }
+
+    /// `options` にしたがって解を探し、解を [`Solution`] で返す
+    ///
+    /// 解ごとに虫食算を作らないので、解が多い問題でもメモリを使わない。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::{Mushikui, SolveOptions};
+    ///
+    /// let puzzle: Mushikui = " *1\n 2*\n**3\n*4*\n****".parse().unwrap();
+    /// let outcome = puzzle.solutions(&SolveOptions::default());
+    /// let solution = &outcome.solutions()[0];
+    /// assert_eq!((solution.multiplicand(), solution.multiplier()), (&[7, 1][..], &[2, 3][..]));
+    /// assert_eq!(solution.product(), [1, 6, 3, 3]);
+    /// assert_eq!(solution.to_string(), "  71\n  23\n----\n 213\n142\n----\n1633");
+    /// ```
+    pub fn solutions(&self, options: &SolveOptions) -> SolveOutcome<Solution<'_>> {
+        let model = self.model(options);
+        let number = |vars: &[Var], values: &[u8]| {
+            vars.iter().map(|v| values[v.index()]).collect::<Vec<_>>()
+        };
+        let mut solutions = Vec::new();
+        let (termination, _) = model.solve_with(options, |values| {
+            solutions.push(Solution {
+                puzzle: self,
+                multiplicand: number(&model.multiplicand, values),
+                multiplier: number(&model.multiplier, values),
+            });
+            ControlFlow::Continue(())
+        });
+        SolveOutcome::new(termination, solutions)
+    }
+
+    /// 被乗数と乗数になりうる数の組をすべて試して、すべての解を返す
+    ///
+    /// 探索の結果と突き合わせるための素朴な解き方で、被乗数と乗数が合わせて 6 桁を超える問題では `None` を返す。
+    /// 解は被乗数, 乗数の小さい順に並ぶ。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::Mushikui;
+    ///
+    /// let puzzle: Mushikui = "  ***\n   **\n ****\n***\n*****".parse().unwrap();
+    /// let solutions = puzzle.solve_bruteforce().unwrap();
+    /// assert_eq!(solutions.len(), puzzle.count_solutions());
+    /// ```
+    pub fn solve_bruteforce(&self) -> Option<Vec<Self>> {
+        let (n, m) = (self.multiplicand.len(), self.multiplier.len());
+        if n + m > 6 {
+            return None;
+        }
+        // 手掛かりの数字と合う `len` 桁の数 (先頭は `0` でない)
+        let numbers = |row: &[Digit]| {
+            let len = row.len() as u32;
+            (10u128.pow(len - 1).max(1)..10u128.pow(len))
+                .filter(|x| {
+                    x.to_string()
+                        .bytes()
+                        .zip(row)
+                        .all(|(b, clue)| clue.accept(b - b'0'))
+                })
+                .collect::<Vec<_>>()
+        };
+        let multipliers = numbers(&self.multiplier);
+        let mut solutions = Vec::new();
+        for multiplicand in numbers(&self.multiplicand) {
+            for &multiplier in &multipliers {
+                let solution = Solution {
+                    puzzle: self,
+                    multiplicand: digits(multiplicand),
+                    multiplier: digits(multiplier),
+                }
+                .to_mushikui();
+                if self.verify(&solution).is_ok() {
+                    solutions.push(solution);
+                }
+            }
+        }
+        Some(solutions)
+    }
+
+    // `options` の条件を当てはめた制約充足問題
+    fn model(&self, options: &SolveOptions) -> Model {
+        let mut model = Model::new(self, options.allow_leading_zero);
+        let vars = model
+            .rows()
+            .into_iter()
+            .map(<[Var]>::to_vec)
+            .collect::<Vec<_>>();
+        restrict(
+            &mut model.problem,
+            self.rows().into_iter().zip(vars.iter().map(|row| &row[..])),
+            options,
+        );
+        if model.problem.num_propagators() > model.rules.len() {
+            model.rules.push(Rule::DigitCount);
+        }
+        if options.operands_equal {
+            model.problem.add_propagator(SameNumber {
+                a: model.multiplicand.clone(),
+                b: model.multiplier.clone(),
+            });
+            model.rules.push(Rule::SameNumber);
+        }
+        model
+    }
+
+    /// マスを 1 つずつ埋めながら解くための状態を作る
+    ///
+    /// 探索の前の推論で矛盾が見つかれば `None` を返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::{FixError, Mushikui, SolveOptions};
+    ///
+    /// let puzzle: Mushikui = " *1\n 2*\n**3\n*4*\n****".parse().unwrap();
+    /// let mut session = puzzle.session(&SolveOptions::default()).unwrap();
+    /// assert_eq!(session.fix(0, 0, 8), Err(FixError::Contradiction));
+    /// session.fix(0, 0, 7).unwrap();
+    /// assert_eq!(session.solve().into_solutions().len(), 1);
+    /// ```
+    pub fn session(&self, options: &SolveOptions) -> Option<Session> {
+        let model = self.model(options);
+        let domains = model.problem.preprocess().ok()?;
+        Some(Session {
+            model,
+            options: options.clone(),
+            domains,
+        })
+    }
+
+    /// `row` 行目の `column` 列目のマスを数字 `digit` の手掛かりにする
+    ///
+    /// 行と列は [`DigitConstraint`] と同じく数える。
+    pub fn fix(&mut self, row: usize, column: usize, digit: u8) -> Result<(), FixError> {
+        if digit > 9 {
+            return Err(FixError::NotADigit(digit));
+        }
+        let cell = match row {
+            0 => self.multiplicand.get_mut(column),
+            1 => self.multiplier.get_mut(column),
+            r if r < self.partial_product.len() + 2 => self.partial_product[r - 2].get_mut(column),
+            r if r == self.partial_product.len() + 2 => self.product.get_mut(column),
+            _ => None,
+        };
+        match cell {
+            Some(cell) => {
+                *cell = Digit::Fix(digit);
+                Ok(())
+            }
+            None => Err(FixError::NoSuchCell { row, column }),
+        }
+    }
+
+    /// 解の個数
+    ///
+    /// 解を作らずに数えるだけなので、解が多い問題でも速い。
+    pub fn count_solutions(&self) -> usize {
+        self.count_solutions_with(&SolveOptions::default()).0
+    }
+
+    /// `options` にしたがって解を数え、探索の終わり方と統計も返す
+    ///
+    /// 時間切れや中断で打ち切ったときは、それまでに見つかった解の個数を返す。
+    pub fn count_solutions_with(
+        &self,
+        options: &SolveOptions,
+    ) -> (usize, Termination, SearchStats) {
+        let model = self.model(options);
+        let mut count = 0;
+        let (termination, stats) = model.solve_with(options, |_| {
+            count += 1;
+            ControlFlow::Continue(())
+        });
+        (count, termination, stats)
+    }
+
+    /// 解がちょうど 1 つか
+    ///
+    /// 2 つ目の解が見つかった時点で探索をやめる。
+    pub fn is_unique(&self) -> bool {
+        let options = SolveOptions {
+            max_solutions: Some(2),
+            ..SolveOptions::default()
+        };
+        self.count_solutions_with(&options).0 == 1
+    }
+
+    /// `options` の条件のもとで、探索の前の推論で絞った各マスに入りうる数字
+    ///
+    /// 行は被乗数, 乗数, 部分積, 積の順。矛盾が見つかれば `None` を返す。
+    /// 積を省いた問題では、積の行は入りうる最大の桁数で、先頭に `0` も入りうる。
+    pub fn candidates(&self, options: &SolveOptions) -> Option<Vec<Vec<Domain>>> {
+        let model = self.model(options);
+        let domains = model.problem.preprocess().ok()?;
+        Some(model.domains(&domains))
+    }
+
+    /// `options` の条件のもとで、最初の解にたどり着くまでの推論をマスの数字が決まった順に説明する
+    ///
+    /// 1 行が 1 つのマスで、どの計算から決まったか (または仮定したか) を英語で書く。
+    /// 解がなければ `None` を返す。
+    pub fn explain(&self, options: &SolveOptions) -> Option<Vec<String>> {
+        let steps = self.reasoning(options)?;
+        Some(
+            steps
+                .iter()
+                .map(|step| format!("{} is {}: {}", step.cell, step.value, step.reason))
+                .collect(),
+        )
+    }
+
+    /// `options` の条件のもとで、仮定せずに推論だけで数字が決まる虫食いのマスを 1 つ、理由と合わせて返す
+    ///
+    /// 推論だけでは決まるマスがないときや、解がないときは `None` を返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::{Mushikui, SolveOptions};
+    ///
+    /// let puzzle: Mushikui = " 27\n  *\n**9\n**9".parse().unwrap();
+    /// let hint = puzzle.hint(&SolveOptions::default()).unwrap();
+    /// assert_eq!((hint.row, hint.column, hint.digit), (1, 0, 7));
+    /// ```
+    pub fn hint(&self, options: &SolveOptions) -> Option<Hint> {
+        let steps = self.reasoning(options)?;
+        let step = steps.into_iter().next().filter(|step| !step.guess)?;
+        Some(Hint {
+            row: step.row,
+            column: step.column,
+            digit: step.value,
+            reason: step.reason,
+            cell: step.cell,
+        })
+    }
+
+    // 最初の解にたどり着くまでの推論の各段
+    fn reasoning(&self, options: &SolveOptions) -> Option<Vec<Step>> {
+        let model = self.model(options);
+        let deductions = model.problem.explain(&model.brancher)?;
+        let rows = model.rows();
+        // 各変数の (行, 左から数えた列, 右から数えた桁)
+        let mut cells = vec![(0, 0, 0); model.problem.num_vars()];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, v) in row.iter().enumerate() {
+                cells[v.index()] = (r, c, row.len() - c - 1);
+            }
+        }
+        let parts = self.partial_product.len();
+        let row_name = |r: usize| match r {
+            0 => "the multiplicand".to_string(),
+            1 => "the multiplier".to_string(),
+            r if r < parts + 2 => format!("partial product {}", r - 1),
+            _ => "the product".to_string(),
+        };
+        let partial_product = |j: usize| {
+            format!(
+                "partial product {} is the multiplicand times digit {} from the right of the multiplier",
+                j + 1,
+                j + 1
+            )
+        };
+        let product = || "the product is the sum of the shifted partial products".to_string();
+        let steps = deductions
+            .iter()
+            .map(|deduction| {
+                let (r, c, k) = cells[deduction.var.index()];
+                let cell = format!("digit {} from the right of {}", k + 1, row_name(r));
+                let reason = match deduction.reason {
+                    Reason::Preprocessor(j) if j < parts => partial_product(j),
+                    Reason::Preprocessor(_) => product(),
+                    Reason::Propagator(j) => match model.rules[j] {
+                        Rule::PartialProduct => partial_product(j),
+                        Rule::Product => product(),
+                        Rule::Letters => "the same letter stands for the same digit and different letters for different digits".to_string(),
+                        Rule::DigitCount => "each digit is used as many times as the options allow".to_string(),
+                        Rule::SameNumber => "the multiplicand and the multiplier are the same number".to_string(),
+                    },
+                    Reason::Guess => {
+                        let candidates = deduction
+                            .candidates
+                            .iter()
+                            .map(|d| d.to_string())
+                            .collect::<Vec<_>>();
+                        format!("guessed among {}", candidates.join(", "))
+                    }
+                };
+                let carries = if let Reason::Preprocessor(_) = deduction.reason {
+                    ", checking every possible carry column by column"
+                } else {
+                    ""
+                };
+                Step {
+                    row: r,
+                    column: c,
+                    value: deduction.value,
+                    guess: matches!(deduction.reason, Reason::Guess),
+                    cell,
+                    reason: format!("{}{}", reason, carries),
+                }
+            })
+            .collect();
+        Some(steps)
+    }
+
+    /// すべてのマスを埋めた `filled` が、この問題の解かを確かめる
+    ///
+    /// 探索はせず、手掛かりとの一致と計算の正しさだけを見る。先頭の `0` は許さない。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::{Mushikui, VerifyError};
+    ///
+    /// let puzzle: Mushikui = " *1\n 2*\n**3\n*4*\n****".parse().unwrap();
+    /// let answer: Mushikui = " 71\n 23\n213\n142\n1633".parse().unwrap();
+    /// assert_eq!(puzzle.verify(&answer), Ok(()));
+    /// let wrong: Mushikui = " 71\n 23\n213\n142\n1643".parse().unwrap();
+    /// assert_eq!(puzzle.verify(&wrong), Err(VerifyError::Product));
+    /// ```
+    pub fn verify(&self, filled: &Mushikui) -> Result<(), VerifyError> {
+        let (mut rows, filled_rows) = (self.rows(), filled.rows());
+        // 積を省いた問題の積は何が入ってもよい
+        let any = vec![Digit::Any; filled.product.len()];
+        if self.product.is_empty() {
+            *rows.last_mut().unwrap() = &any;
+        }
+        if rows.len() != filled_rows.len()
+            || rows
+                .iter()
+                .zip(&filled_rows)
+                .any(|(r, f)| r.len() != f.len())
+        {
+            return Err(VerifyError::Shape);
+        }
+        for (r, (row, filled_row)) in rows.iter().zip(&filled_rows).enumerate() {
+            for (c, (clue, d)) in row.iter().zip(filled_row.iter()).enumerate() {
+                match d.digit() {
+                    Some(d) if clue.accept(d) => {}
+                    Some(_) => return Err(VerifyError::ClueMismatch { row: r, column: c }),
+                    None => return Err(VerifyError::NotFilled { row: r, column: c }),
+                }
+            }
+            // 部分積は `0` だけの行になりうる
+            let single_zero = r >= 2 && r + 1 < rows.len() && filled_row.len() == 1;
+            if filled_row.first().and_then(|d| d.digit()) == Some(0) && !single_zero {
+                return Err(VerifyError::LeadingZero { row: r });
+            }
+        }
+        let cells = rows
+            .iter()
+            .zip(&filled_rows)
+            .flat_map(|(r, f)| r.iter().zip(f.iter()));
+        if !letters_consistent(cells.map(|(&clue, &d)| (clue, d))) {
+            return Err(VerifyError::Letters);
+        }
+        let digits = |row: &[Digit]| row.iter().filter_map(|d| d.digit()).collect::<Vec<_>>();
+        for (j, part) in filled.partial_product.iter().enumerate() {
+            let d = filled.multiplier[filled.multiplier.len() - j - 1]
+                .digit()
+                .unwrap();
+            let expected = match (d, part.len()) {
+                (0, 0) => Vec::new(),
+                (0, _) => vec![0],
+                (d, _) => filled.calculate_partial_product(d),
+            };
+            if expected != digits(part) {
+                return Err(VerifyError::PartialProduct(j));
+            }
+        }
+        if filled.calculate_product() == digits(&filled.product) {
+            Ok(())
+        } else {
+            Err(VerifyError::Product)
+        }
+    }
+
+    /// 難しさを見積もる
+    ///
+    /// すべての解を探し、仮定を重ねた深さ, 仮定した回数, 探索したノード数を数える。
+    pub fn rate(&self) -> Difficulty {
+        let (_, stats) = self.solve_with(&SolveOptions::default(), |_| ControlFlow::Continue(()));
+        Difficulty {
+            guess_depth: stats.max_depth,
+            guesses: stats.guesses,
+            nodes: stats.nodes_expanded,
+        }
+    }
+
+    // 被乗数, 乗数, 部分積, 積の順に全行
+    fn rows(&self) -> Vec<&[Digit]> {
+        let mut rows = vec![&self.multiplicand[..], &self.multiplier[..]];
+        rows.extend(self.partial_product.iter().map(|part| &part[..]));
+        rows.push(&self.product[..]);
+        rows
+    }
+}
+
+impl Session {
+    /// `row` 行目の `column` 列目のマスに数字 `digit` を入れ、それまでの推論の続きから各マスの候補を狭める
+    ///
+    /// 行と列は [`Mushikui::candidates`] と同じく数える。入れられなければ状態は変えない。
+    pub fn fix(&mut self, row: usize, column: usize, digit: u8) -> Result<(), FixError> {
+        if digit > 9 {
+            return Err(FixError::NotADigit(digit));
+        }
+        let var = *self
+            .model
+            .rows()
+            .get(row)
+            .and_then(|vars| vars.get(column))
+            .ok_or(FixError::NoSuchCell { row, column })?;
+        self.domains = self
+            .model
+            .problem
+            .assign(&self.domains, var, digit)
+            .map_err(|_| FixError::Contradiction)?;
+        Ok(())
+    }
+
+    /// 今の各マスの候補 (行と列は [`Mushikui::candidates`] と同じ)
+    pub fn candidates(&self) -> Vec<Vec<Domain>> {
+        self.model.domains(&self.domains)
+    }
+
+    /// 今の候補から解を探す
+    pub fn solve(&self) -> SolveOutcome<Mushikui> {
+        let mut solutions = Vec::new();
+        let (termination, _) = self.solve_with(|solution| {
+            solutions.push(solution);
+            ControlFlow::Continue(())
+        });
+        SolveOutcome::new(termination, solutions)
+    }
+
+    /// 今の候補から解を探し、見つけるたびに `on_solution` を呼ぶ
+    ///
+    /// 探索の設定は [`Mushikui::session`] に渡したもの。
+    pub fn solve_with(
+        &self,
+        mut on_solution: impl FnMut(Mushikui) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        self.model.problem.solve_from(
+            &self.domains,
+            &self.model.brancher,
+            &search_options(&self.options),
+            |values| on_solution(self.model.fill(values)),
+        )
+    }
+}
+
+impl Solution<'_> {
+    /// 被乗数の各桁 (上の桁から)
+    pub fn multiplicand(&self) -> &[u8] {
+        &self.multiplicand
+    }
+
+    /// 乗数の各桁 (上の桁から)
+    pub fn multiplier(&self) -> &[u8] {
+        &self.multiplier
+    }
+
+    /// 積の各桁 (上の桁から。先頭に `0` を付けない)
+    pub fn product(&self) -> Vec<u8> {
+        let (a, b) = (&self.multiplicand, &self.multiplier);
+        // 下の桁から足し込む
+        let mut product = vec![0u32; a.len() + b.len()];
+        for (i, &x) in a.iter().rev().enumerate() {
+            for (j, &y) in b.iter().rev().enumerate() {
+                product[i + j] += u32::from(x) * u32::from(y);
+            }
+        }
+        let mut carry = 0;
+        for d in product.iter_mut() {
+            *d += carry;
+            carry = *d / 10;
+            *d %= 10;
+        }
+        trim(product.iter().rev().map(|&d| d as u8).collect())
+    }
+
+    /// 全マスを埋めた虫食算
+    pub fn to_mushikui(&self) -> Mushikui {
+        // `width` 桁になるよう先頭を `0` で埋めた数 (`digits` は先頭に `0` を付けない)
+        let row = |digits: Vec<u8>, width: usize| {
+            let zeros = width.saturating_sub(digits.len());
+            core::iter::repeat(Digit::Fix(0))
+                .take(zeros)
+                .chain(digits.into_iter().map(Digit::Fix))
+                .collect::<Vec<_>>()
+        };
+        let puzzle = self.puzzle;
+        let partial_product = puzzle
+            .partial_product
+            .iter()
+            .enumerate()
+            .map(|(j, part)| {
+                let d = self.multiplier[self.multiplier.len() - 1 - j];
+                if part.is_empty() {
+                    Vec::new()
+                } else {
+                    row(multiply_digit(&self.multiplicand, d), part.len())
+                }
+            })
+            .collect();
+        Mushikui {
+            multiplicand: row(trim(self.multiplicand.clone()), puzzle.multiplicand.len()),
+            multiplier: row(trim(self.multiplier.clone()), puzzle.multiplier.len()),
+            partial_product,
+            product: row(self.product(), puzzle.product.len()),
+        }
+    }
+}
+
+// `n` の各桁 (上の桁から)
+fn digits(n: u128) -> Vec<u8> {
+    n.to_string().bytes().map(|b| b - b'0').collect()
+}
+
+// 先頭の `0` を取り除いた各桁 (`0` なら `[0]`)
+fn trim(mut digits: Vec<u8>) -> Vec<u8> {
+    let zeros = digits.iter().take_while(|&&d| d == 0).count();
+    digits.drain(..zeros.min(digits.len().saturating_sub(1)));
+    digits
+}
+
+// 各桁 `a` の数と 1 桁の数 `d` の積の各桁 (先頭に `0` を付けない)
+fn multiply_digit(a: &[u8], d: u8) -> Vec<u8> {
+    let mut product = Vec::with_capacity(a.len() + 1);
+    let mut carry = 0;
+    for &x in a.iter().rev() {
+        let y = x * d + carry;
+        product.push(y % 10);
+        carry = y / 10;
+    }
+    product.push(carry);
+    product.reverse();
+    trim(product)
+}
+
+impl Display for Solution<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.to_mushikui().fmt(f)
+    }
+}
+
+// 探索を打ち切る条件
+fn search_options(options: &SolveOptions) -> backtrack::Options {
+    backtrack::Options {
+        max_solutions: options.max_solutions,
+        deadline: options.deadline,
+        cancel: options.cancel.clone(),
+        on_progress: options.on_progress.clone(),
+    }
+}
+
+// 部分積の行 `row` (左から) は被乗数 `multiplicand` (左から) と乗数の 1 桁 `digit` の積
+// (`digit` が `0` なら `row` は空か `0` だけ)
+struct PartialProduct {
+    multiplicand: Vec<Var>,
+    digit: Var,
+    row: Vec<Var>,
+}
+
+impl Propagator for PartialProduct {
+    fn watches(&self) -> Vec<Var> {
+        let mut vars = self.multiplicand.clone();
+        vars.push(self.digit);
+        vars
+    }
+
+    // 被乗数の下の桁から決まっているところまで掛ける
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let d = match store.value(self.digit) {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        if d == 0 {
+            match self.row[..] {
+                [] => return Ok(()),
+                [v] => return store.assign(v, 0),
+                _ => {}
+            }
+        }
+        let mut carry = 0;
+        for (k, &m) in self.multiplicand.iter().rev().enumerate() {
+            let m = match store.value(m) {
+                Some(m) => m,
+                None => return Ok(()),
+            };
+            if k >= self.row.len() {
+                return Err(Conflict);
+            }
+            let e = m * d + carry;
+            store.assign(self.row[self.row.len() - k - 1], e % 10)?;
+            carry = e / 10;
+        }
+        let len = self.multiplicand.len();
+        match (carry, self.row.len() - len) {
+            (0, 0) => Ok(()),
+            // 先頭の `0` は部分積の変数の値の範囲で除く
+            (carry, 1) => store.assign(self.row[0], carry),
+            _ => Err(Conflict),
+        }
+    }
+}
+
+// 積の行 `product` (左から) は部分積の行 `rows` (j 行目は j 桁ずらす) の和
+struct Product {
+    rows: Vec<Vec<Var>>,
+    product: Vec<Var>,
+}
+
+impl Propagator for Product {
+    fn watches(&self) -> Vec<Var> {
+        self.rows.iter().flatten().copied().collect()
+    }
+
+    // 下の位から、部分積がすべて決まっている位まで足す
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let columns = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(j, row)| j + row.len())
+            .max()
+            .unwrap_or(0);
+        let mut carry = 0;
+        for k in 0..columns {
+            let mut s = carry;
+            for (j, row) in self.rows.iter().enumerate() {
+                if k >= j && k - j < row.len() {
+                    match store.value(row[row.len() - (k - j) - 1]) {
+                        Some(d) => s += u32::from(d),
+                        None => return Ok(()),
+                    }
+                }
+            }
+            if k >= self.product.len() {
+                return Err(Conflict);
+            }
+            store.assign(self.product[self.product.len() - k - 1], (s % 10) as u8)?;
+            carry = s / 10;
+        }
+        let mut k = columns;
+        while carry > 0 {
+            if k >= self.product.len() {
+                return Err(Conflict);
+            }
+            store.assign(self.product[self.product.len() - k - 1], (carry % 10) as u8)?;
+            carry /= 10;
+            k += 1;
+        }
+        // 残りの上の桁は `0` (積の先頭の `0` は変数の値の範囲で除く)
+        for &v in &self.product[..self.product.len() - k] {
+            store.assign(v, 0)?;
+        }
+        Ok(())
+    }
+}
+
+// 被乗数を下の桁から決めていき、部分積のその桁に数字があれば乗数の桁も決める
+struct MushikuiBrancher {
+    multiplicand: Vec<Var>,
+    multiplier: Vec<Var>,
+    // clue_digits[i]: 部分積の下から i 桁目に数字がある乗数の桁 (下から)
+    clue_digits: Vec<Vec<usize>>,
+}
+
+impl Brancher for MushikuiBrancher {
+    fn select(&self, store: &Store) -> Option<Var> {
+        let n = self.multiplicand.len();
+        for (i, digits) in self.clue_digits.iter().enumerate() {
+            let m = self.multiplicand[n - i - 1];
+            if !store.is_assigned(m) {
+                return Some(m);
+            }
+            for &j in digits {
+                let d = self.multiplier[self.multiplier.len() - j - 1];
+                if !store.is_assigned(d) {
+                    return Some(d);
+                }
+            }
+        }
+        self.multiplier
+            .iter()
+            .rev()
+            .copied()
+            .find(|&d| !store.is_assigned(d))
+    }
+}
+
+// 掛け算の制約の種類
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Rule {
+    // 部分積 = 被乗数 × 乗数の 1 桁
+    PartialProduct,
+    // 積 = ずらした部分積の和
+    Product,
+    // 同じ文字は同じ数字、違う文字は違う数字
+    Letters,
+    // 各数字を使う回数
+    DigitCount,
+    // 被乗数と乗数が同じ数
+    SameNumber,
+}
+
+// 虫食算の各マスを変数にした制約充足問題
+struct Model {
+    problem: Problem,
+    // 加えた各制約の種類 (推論の理由の説明に使う)
+    rules: Vec<Rule>,
+    brancher: MushikuiBrancher,
+    multiplicand: Vec<Var>,
+    multiplier: Vec<Var>,
+    partial_product: Vec<Vec<Var>>,
+    product: Vec<Var>,
+    // 積を省いた問題か
+    omitted_product: bool,
+}
+
+impl Model {
+    // `leading_zero` なら 2 桁以上の行の先頭にも `0` を許す
+    fn new(mushikui: &Mushikui, leading_zero: bool) -> Self {
+        let mut problem = Problem::new();
+        let lo = |row: &[Digit]| u8::from(!leading_zero || row.len() == 1);
+        let mut vars = |row: &[Digit], leading: u8, rest: u8| {
+            row.iter()
+                .enumerate()
+                .map(|(i, d)| problem.new_var(d.domain(if i == 0 { leading } else { rest })))
+                .collect::<Vec<_>>()
+        };
+        let multiplicand = vars(&mushikui.multiplicand, lo(&mushikui.multiplicand), 0);
+        let multiplier = vars(&mushikui.multiplier, lo(&mushikui.multiplier), 0);
+        let partial_product = mushikui
+            .partial_product
+            .iter()
+            .map(|part| vars(part, u8::from(!leading_zero && part.len() > 1), 0))
+            .collect::<Vec<_>>();
+        // 積を省いた問題は、積の桁数を最大にとって先頭の `0` を許す
+        let omitted = vec![Digit::Any; columns(&partial_product) + 1];
+        let product = if mushikui.product.is_empty() {
+            vars(&omitted, 0, 0)
+        } else {
+            vars(
+                &mushikui.product,
+                u8::from(!leading_zero && mushikui.product.len() > 1),
+                0,
+            )
+        };
+        let mut rules = Vec::new();
+        for (j, row) in partial_product.iter().enumerate() {
+            problem.add_propagator(PartialProduct {
+                multiplicand: multiplicand.clone(),
+                digit: multiplier[multiplier.len() - j - 1],
+                row: row.clone(),
+            });
+            rules.push(Rule::PartialProduct);
+        }
+        problem.add_propagator(Product {
+            rows: partial_product.clone(),
+            product: product.clone(),
+        });
+        rules.push(Rule::Product);
+        // 探索の前に列ごとの計算で各マスの候補を絞る
+        for (j, row) in partial_product.iter().enumerate() {
+            problem.add_preprocessor(PartialProductColumns {
+                multiplicand: multiplicand.clone(),
+                digit: multiplier[multiplier.len() - j - 1],
+                row: row.clone(),
+            });
+        }
+        problem.add_preprocessor(ProductColumns {
+            rows: partial_product.clone(),
+            product: product.clone(),
+            shifted: true,
+        });
+        let vars = [&multiplicand, &multiplier]
+            .into_iter()
+            .chain(&partial_product)
+            .chain([&product])
+            .flatten();
+        let cells = mushikui
+            .rows()
+            .into_iter()
+            .flatten()
+            .copied()
+            .zip(vars.copied());
+        if let Some(letters) = Letters::new(cells) {
+            problem.add_propagator(letters);
+            rules.push(Rule::Letters);
+        }
+        let clue_digits = (0..multiplicand.len())
+            .map(|i| {
+                (0..multiplier.len())
+                    .filter(|&j| {
+                        let part = &mushikui.partial_product[j];
+                        i < part.len() && part[part.len() - i - 1].digit().is_some()
+                    })
+                    .collect()
+            })
+            .collect();
+        let brancher = MushikuiBrancher {
+            multiplicand: multiplicand.clone(),
+            multiplier: multiplier.clone(),
+            clue_digits,
+        };
+        Self {
+            problem,
+            rules,
+            brancher,
+            multiplicand,
+            multiplier,
+            partial_product,
+            product,
+            omitted_product: mushikui.product.is_empty(),
+        }
+    }
+
+    // `options` にしたがって解を探す (`options.dedupe_commutative` なら入れ替えただけの解を除く)
+    fn solve_with(
+        &self,
+        options: &SolveOptions,
+        mut on_solution: impl FnMut(&[u8]) -> ControlFlow<()>,
+    ) -> (Termination, SearchStats) {
+        let domains = match self.problem.preprocess() {
+            Ok(domains)
+                if options.dedupe_commutative
+                    && self.multiplicand.len() == self.multiplier.len() =>
+            {
+                domains
+            }
+            _ => {
+                return self.problem.solve_with(
+                    &self.brancher,
+                    &search_options(options),
+                    on_solution,
+                )
+            }
+        };
+        // 除いた解を数えないよう、解の個数の上限はここで数える
+        let search = backtrack::Options {
+            max_solutions: None,
+            ..search_options(options)
+        };
+        let (mut found, mut limit) = (0, false);
+        let (termination, stats) =
+            self.problem
+                .solve_from(&domains, &self.brancher, &search, |values| {
+                    if self.is_swapped(&domains, values) {
+                        return ControlFlow::Continue(());
+                    }
+                    found += 1;
+                    let flow = on_solution(values);
+                    if flow.is_continue() && options.max_solutions.map_or(false, |max| found >= max)
+                    {
+                        limit = true;
+                        return ControlFlow::Break(());
+                    }
+                    flow
+                });
+        (
+            if limit {
+                Termination::Complete
+            } else {
+                termination
+            },
+            stats,
+        )
+    }
+
+    // 被乗数 > 乗数の解で、被乗数と乗数を入れ替えても `domains` から解になるか
+    fn is_swapped(&self, domains: &[Domain], values: &[u8]) -> bool {
+        let digits = |vars: &[Var]| vars.iter().map(|v| values[v.index()]).collect::<Vec<_>>();
+        let (a, b) = (digits(&self.multiplicand), digits(&self.multiplier));
+        if a <= b {
+            return false;
+        }
+        let mut domains = domains.to_vec();
+        for (vars, row) in [(&self.multiplicand, &b), (&self.multiplier, &a)] {
+            for (&var, &v) in vars.iter().zip(row) {
+                domains = match self.problem.assign(&domains, var, v) {
+                    Ok(domains) => domains,
+                    Err(Conflict) => return false,
+                };
+            }
+        }
+        let once = backtrack::Options {
+            max_solutions: Some(1),
+            ..backtrack::Options::default()
+        };
+        let mut found = false;
+        self.problem
+            .solve_from(&domains, &self.brancher, &once, |_| {
+                found = true;
+                ControlFlow::Break(())
+            });
+        found
+    }
+
+    // 被乗数, 乗数, 部分積, 積の順に全行の変数
+    fn rows(&self) -> Vec<&[Var]> {
+        let mut rows = vec![&self.multiplicand[..], &self.multiplier[..]];
+        rows.extend(self.partial_product.iter().map(|part| &part[..]));
+        rows.push(&self.product[..]);
+        rows
+    }
+
+    // 全行の各マスの領域
+    fn domains(&self, domains: &[Domain]) -> Vec<Vec<Domain>> {
+        self.rows()
+            .into_iter()
+            .map(|row| row.iter().map(|v| domains[v.index()]).collect())
+            .collect()
+    }
+
+    // 解の値を埋めた虫食算 (積を省いた問題なら積の先頭の `0` は除く。積が 0 なら `0` だけ)
+    fn fill(&self, values: &[u8]) -> Mushikui {
+        let row = |vars: &[Var]| {
+            vars.iter()
+                .map(|v| Digit::Fix(values[v.index()]))
+                .collect::<Vec<_>>()
+        };
+        let mut product = row(&self.product);
+        if self.omitted_product {
+            let zeros = product.iter().take_while(|d| d.digit() == Some(0)).count();
+            product.drain(..zeros.min(product.len() - 1));
+        }
+        Mushikui {
+            multiplicand: row(&self.multiplicand),
+            multiplier: row(&self.multiplier),
+            partial_product: self.partial_product.iter().map(|part| row(part)).collect(),
+            product,
+        }
+    }
+}
+
+// 部分積の行 (j 行目は j 桁ずらす) の和の、繰り上がりを除いた桁数
+fn columns<T>(rows: &[Vec<T>]) -> usize {
+    rows.iter()
+        .enumerate()
+        .map(|(j, row)| j + row.len())
+        .max()
+        .unwrap_or(0)
+}
+
+impl Display for Mushikui {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let width = self
+            .product
+            .len()
+            .max(columns(&self.partial_product))
+            .max(self.multiplicand.len());
+        let multiplicand = self
+            .multiplicand
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        writeln!(
+            f,
+            "{digits:>width$}",
+            digits = multiplicand.join(""),
+            width = width
+        )?;
+        let multiplier = self
+            .multiplier
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        writeln!(
+            f,
+            "{digits:>width$}",
+            digits = multiplier.join(""),
+            width = width
+        )?;
+        write!(f, "{}", "-".repeat(width))?;
+        for (i, part) in self.partial_product.iter().enumerate() {
+            if part.is_empty() {
+                continue;
+            }
+            let part = part.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            write!(
+                f,
+                "\n{digits:>width$}",
+                digits = part.join(""),
+                width = width - i
+            )?;
+        }
+        // 積を省いた問題は部分積で終わる
+        if self.product.is_empty() {
+            return Ok(());
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", "-".repeat(width))?;
+        let product = self
+            .product
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        write!(
+            f,
+            "{digits:>width$}",
+            digits = product.join(""),
+            width = width
+        )
+    }
+}
+
+// 読み飛ばす行 (空行, `#` で始まる注釈, `-` と `=` だけの線)。`line` は前後の空白を除いたもの
+fn is_ignored(line: &str) -> bool {
+    line.is_empty() || line.starts_with('#') || line.chars().all(|ch| ch == '-' || ch == '=')
+}
+
+// 先頭の `0` を除いた数字
+fn strip(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&d| d == 0).count();
+    &digits[zeros..]
+}
+
+/// 次のような形式の文字列から虫食算を作る
+///
+/// ```text
+///  *1
+///  2*
+/// ----
+///  **3
+/// *4*
+/// ----
+/// ****
+/// ```
+///
+/// 虫食いのマスは `*` のほか `?`, `_`, `.` でも書ける。乗数の行は頭に `×` (か `x` と空白) を付けてもよい。
+/// 空行, `#` で始まる注釈の行, `-` や `=` だけの線の行は読み飛ばし、行の前後の空白は無視する。
+///
+/// 乗数の `0` の桁の部分積は `0` だけの行にするか、書かずに省く。
+/// 省いたときは、各部分積が積の右端から何桁ずれているかで乗数のどの桁の分かを決める。
+///
+/// 部分積が乗数の桁数だけあって、最後の行が被乗数より左にずれて終わっていれば (乗数が 1 桁なら 3 行なら)、
+/// 積を省いた問題として読む。
+///
+/// # Panics
+///
+/// 虫食算として読めないとき。panic させたくなければ [`str::parse`] を使う。
+pub fn mushikui_from(s: &str) -> Mushikui {
+    match s.parse() {
+        Ok(mushikui) => mushikui,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+impl FromStr for Mushikui {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // 各行のマスと右端の位置
+        let lines = s
+            .lines()
+            .filter(|line| !is_ignored(line.trim()))
+            .map(|line| {
+                (
+                    line.trim().chars().collect(),
+                    line.trim_end().chars().count(),
+                )
+            })
+            .collect::<Vec<(Vec<char>, usize)>>();
+        let mut lines = lines;
+        if let Some((multiplier, _)) = lines.get_mut(1) {
+            // 乗数の行の頭に書いた `×` や `x` は読み飛ばす
+            let op = match multiplier.first() {
+                Some('×') => 1,
+                Some('x') if multiplier.get(1).map_or(false, |ch| ch.is_whitespace()) => 1,
+                _ => 0,
+            };
+            let skip = op
+                + multiplier[op..]
+                    .iter()
+                    .take_while(|ch| ch.is_whitespace())
+                    .count();
+            multiplier.drain(..skip);
+        }
+        let n = lines.len();
+        if n < 3 {
+            return Err(MushikuiError::TooFewRows(n));
+        }
+        let m = lines[1].0.len();
+        // 積を省いた書き方か (部分積が乗数の桁数だけあり、最後の部分積が左にずれている)
+        let skeleton = n == m + 2 && (m == 1 || lines[n - 1].1 < lines[0].1);
+        let (parts, product) = if skeleton {
+            (&lines[2..], &[][..])
+        } else if n < 4 {
+            return Err(MushikuiError::TooFewRows(n));
+        } else {
+            (&lines[2..(n - 1)], &lines[n - 1].0[..])
+        };
+        let mut partial_product = parts.iter().map(|(row, _)| row.clone()).collect::<Vec<_>>();
+        if parts.len() < m {
+            // 乗数の `0` の桁の部分積を省いた書き方。積の右端からのずれで何桁目の分かを読む
+            let wrong = MushikuiError::WrongPartialProductCount {
+                expected: m,
+                actual: parts.len(),
+            };
+            partial_product = vec![Vec::new(); m];
+            let mut last = None;
+            for (row, end) in parts {
+                let shift = match lines[n - 1].1.checked_sub(*end) {
+                    Some(shift) if shift < m && last.map_or(true, |last| shift > last) => shift,
+                    _ => return Err(wrong),
+                };
+                partial_product[shift] = row.clone();
+                last = Some(shift);
+            }
+            // 乗数の最上位の桁は `0` でない
+            if last != Some(m - 1) {
+                return Err(wrong);
+            }
+        }
+        Mushikui::try_new(&lines[0].0, &lines[1].0, &partial_product, product)
+    }
+}
+
+impl Mushikui {
+    /// JSON のオブジェクトにする
+    ///
+    /// ```json
+    /// {"multiplicand":"*1","multiplier":"2*","partial_products":["**3","*4*"],"product":"****"}
+    /// ```
+    ///
+    /// 積を省いた問題には `"product"` を書かない。
+    pub fn to_json(&self) -> Value {
+        let row = |row: &[Digit]| Value::String(row.iter().map(|d| d.to_string()).collect());
+        let mut fields = vec![
+            ("multiplicand".to_string(), row(&self.multiplicand)),
+            ("multiplier".to_string(), row(&self.multiplier)),
+            (
+                "partial_products".to_string(),
+                Value::Array(self.partial_product.iter().map(|part| row(part)).collect()),
+            ),
+        ];
+        if !self.product.is_empty() {
+            fields.push(("product".to_string(), row(&self.product)));
+        }
+        Value::Object(fields)
+    }
+
+    /// [`Mushikui::to_json`] の形の JSON から虫食算を作る
+    ///
+    /// `"product"` がないか空文字列なら積を省いた問題にする。
+    /// ほかのキー (問題の名前や出典など) は読み飛ばす。
+    pub fn from_json(s: &str) -> Result<Self, MushikuiError> {
+        let value = json::parse(s).map_err(|e| MushikuiError::InvalidJson(e.to_string()))?;
+        Self::from_json_value(&value)
+    }
+
+    /// 読んだ JSON の値から虫食算を作る
+    pub fn from_json_value(value: &Value) -> Result<Self, MushikuiError> {
+        let invalid = |expected: &str| MushikuiError::InvalidJson(format!("expected {}", expected));
+        let row = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .ok_or_else(|| invalid(&format!("a string `{}`", key)))
+        };
+        let parts = value
+            .get("partial_products")
+            .and_then(Value::as_array)
+            .and_then(|parts| parts.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| invalid("an array of strings `partial_products`"))?;
+        let mut builder = MushikuiBuilder::new()
+            .multiplicand(row("multiplicand")?)?
+            .multiplier(row("multiplier")?)?;
+        for part in parts {
+            builder = builder.partial_product(part)?;
+        }
+        match value.get("product") {
+            None => {}
+            Some(product) => match product.as_str() {
+                Some("") => {}
+                Some(product) => builder = builder.product(product)?,
+                None => return Err(invalid("a string `product`")),
+            },
+        }
+        builder.build()
+    }
+}
+
+impl Mushikui {
+    /// URL にそのまま入れられる 1 行の符号にする
+    ///
+    /// `m.` に続けて被乗数, 乗数, 部分積, 積の行を `-` で区切って並べる。虫食いのマスは `_` にする。
+    /// 省いた部分積や積は空の行にする。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::Mushikui;
+    ///
+    /// let puzzle: Mushikui = " *1\n 2*\n**3\n*4*\n****".parse().unwrap();
+    /// assert_eq!(puzzle.to_code(), "m._1-2_-__3-_4_-____");
+    /// let decoded = Mushikui::from_code("m._1-2_-__3-_4_-____").unwrap();
+    /// assert_eq!(decoded.to_string(), puzzle.to_string());
+    /// ```
+    pub fn to_code(&self) -> String {
+        let rows = self
+            .rows()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| match d {
+                        Digit::Any => '_'.to_string(),
+                        d => d.to_string(),
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        format!("m.{}", rows.join("-"))
+    }
+
+    /// [`Mushikui::to_code`] の符号から虫食算を作る
+    ///
+    /// 虫食いのマスは `_` のほか `*` などでもよい。
+    pub fn from_code(code: &str) -> Result<Self, MushikuiError> {
+        let rows = code
+            .trim()
+            .strip_prefix("m.")
+            .ok_or(MushikuiError::InvalidCode)?
+            .split('-')
+            .collect::<Vec<_>>();
+        let (multiplicand, multiplier) = match rows[..] {
+            [multiplicand, multiplier, _, ..] => (multiplicand, multiplier),
+            _ => return Err(MushikuiError::TooFewRows(rows.len())),
+        };
+        let mut builder = MushikuiBuilder::new()
+            .multiplicand(multiplicand)?
+            .multiplier(multiplier)?;
+        let (product, parts) = rows[2..].split_last().unwrap();
+        for &part in parts {
+            builder = builder.partial_product(part)?;
+        }
+        if !product.is_empty() {
+            builder = builder.product(*product)?;
+        }
+        builder.build()
+    }
+}
+
+impl Puzzle for Mushikui {
+    type Solution = Mushikui;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(&self, options: &Self::Options, on_solution: F) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        Mushikui::solve_with(self, options, on_solution)
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        Mushikui::verify(self, solution).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 本の Q22 (24 桁 × 20 桁)
+    const Q22: &str = "
+                    ************************
+                        ********************
+--------------------------------------------
+                   *********************9*0*
+                  ********************8*1**
+                  ******************7*2***
+                ******************6*3****
+               *****************5*4*****
+               ***************4*5******
+             ***************3*6*******
+             *************2*7********
+           *************1*8*********
+           ***********0*9**********
+         ***********9*0***********
+        **********8*1************
+        ********7*2*************
+       *******6*3**************
+      ******5*4***************
+     *****4*5****************
+    ****3*6*****************
+  ****2*7******************
+  **1*8*******************
+**0*9********************
+--------------------------------------------
+********************************************
+";
+
+    #[test]
+    fn solutions_of_operands_longer_than_u128() {
+        let puzzle: Mushikui = Q22.parse().unwrap();
+        let outcome = puzzle.solutions(&SolveOptions::default());
+        let solutions = outcome.solutions();
+        assert_eq!(solutions.len(), 1);
+        let solution = &solutions[0];
+        assert_eq!(solution.multiplicand().len(), 24);
+        assert_eq!(solution.multiplier().len(), 20);
+        assert_eq!(solution.product().len(), 44);
+        let filled = solution.to_mushikui();
+        assert!(puzzle.verify(&filled).is_ok());
+        assert_eq!(filled.to_string(), puzzle.solve()[0].to_string());
+    }
+
+    fn domain(digits: &[u8]) -> Domain {
+        digits.iter().copied().collect()
+    }
+
+    #[test]
+    fn column_preprocessing_prunes_domains() {
+        // 積の一の位の 5 = a * b mod 10 から a と b は奇数で片方が 5、積が 2 桁なので 1 * 5 は除かれる
+        let puzzle: Mushikui = " *\n *\n--\n*5\n--\n*5".parse().unwrap();
+        let odd = domain(&[3, 5, 7, 9]);
+        let tens = domain(&[1, 2, 3, 4]);
+        let five = Domain::singleton(5);
+        assert_eq!(
+            puzzle.candidates(&SolveOptions::default()).unwrap(),
+            [vec![odd], vec![odd], vec![tens, five], vec![tens, five]]
+        );
+
+        // 一の位は 7 * 3 mod 10 = 1 に決まり、繰り上がりの 2 と 2 桁の積から十の位は 3 * 1 + 2 か 3 * 2 + 2
+        let puzzle: Mushikui = " *7\n  3\n---\n **\n---\n **".parse().unwrap();
+        let one = Domain::singleton(1);
+        let tens = domain(&[5, 8]);
+        assert_eq!(
+            puzzle.candidates(&SolveOptions::default()).unwrap(),
+            [
+                vec![domain(&[1, 2]), Domain::singleton(7)],
+                vec![Domain::singleton(3)],
+                vec![tens, one],
+                vec![tens, one],
+            ]
+        );
+    }
+
+    #[test]
+    fn reasoning_follows_the_options() {
+        // 02 x 9 = 18 は先頭の 0 を許したときだけの解
+        let puzzle: Mushikui = "**\n 9\n--\n*8\n--\n*8".parse().unwrap();
+        let options = SolveOptions {
+            allow_leading_zero: true,
+            ..SolveOptions::default()
+        };
+        assert_eq!(puzzle.explain(&SolveOptions::default()), None);
+        assert_eq!(puzzle.candidates(&SolveOptions::default()), None);
+        let explanation = puzzle.explain(&options).unwrap();
+        assert!(explanation[1].starts_with("digit 2 from the right of the multiplicand is 0: "));
+        assert_eq!(
+            puzzle.candidates(&options).unwrap()[0],
+            [Domain::singleton(0), Domain::singleton(2)]
+        );
+
+        // *7 x **: 乗数の一の位は、被乗数と同じ数であることから 7 に決まる
+        let puzzle: Mushikui = "  *7\n  **\n----\n ***\n***\n----\n****".parse().unwrap();
+        let options = SolveOptions {
+            operands_equal: true,
+            ..SolveOptions::default()
+        };
+        let hint = puzzle.hint(&options).unwrap();
+        assert_eq!((hint.row, hint.column, hint.digit), (1, 1, 7));
+        assert_eq!(
+            hint.reason,
+            "the multiplicand and the multiplier are the same number"
+        );
+    }
+}
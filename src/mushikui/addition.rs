@@ -0,0 +1,431 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use super::columns::ProductColumns;
+use super::{
+    is_ignored, letters_consistent, restrict, strip, Digit, Letters, MushikuiError, SolveOptions,
+};
+use crate::backtrack::{self, Conflict, Domain, Problem, Propagator, Sequence, Store, Var};
+use crate::puzzle::{Puzzle, SearchStats, Termination};
+
+/// 足し算の虫食算
+///
+/// 縦に並べた 2 つ以上の足す数と、その和を持つ。足す数が多くても、探索の前に列ごとの繰り上がりで候補を絞る。
+///
+/// ```text
+///   ***
+///   *2*
+/// + *8*
+/// -----
+///  1**0
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdditionMushikui {
+    addends: Vec<Vec<Digit>>,
+    sum: Vec<Digit>,
+}
+
+impl Puzzle for AdditionMushikui {
+    type Solution = AdditionMushikui;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(
+        &self,
+        options: &Self::Options,
+        mut on_solution: F,
+    ) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        let mut model = Model::new(&self.addends, &self.sum, options.allow_leading_zero);
+        let rows = self.addends.iter().chain([&self.sum]);
+        let vars = model.rows.iter().chain([&model.sum]);
+        restrict(
+            &mut model.problem,
+            rows.zip(vars).map(|(row, vars)| (&row[..], &vars[..])),
+            options,
+        );
+        model.problem.solve_with(
+            &model.brancher,
+            &backtrack::Options {
+                max_solutions: options.max_solutions,
+                deadline: options.deadline,
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone(),
+            },
+            |values| {
+                let (addends, sum) = model.fill(values);
+                on_solution(AdditionMushikui { addends, sum })
+            },
+        )
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        if self.addends.len() != solution.addends.len() {
+            return false;
+        }
+        let rows = self.addends.iter().chain([&self.sum]);
+        let filled = solution.addends.iter().chain([&solution.sum]);
+        let cells = rows.clone().zip(filled.clone());
+        if !letters_consistent(cells.flat_map(|(r, f)| r.iter().copied().zip(f.iter().copied()))) {
+            return false;
+        }
+        match rows
+            .zip(filled)
+            .map(|(row, filled)| digits(row, filled))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(mut values) => {
+                let sum = values.pop().unwrap();
+                add(&values) == strip(&sum)
+            }
+            None => false,
+        }
+    }
+}
+
+// 手掛かり `row` に合う `filled` の数字 (先頭が `0` の 2 桁以上の行は合わない)
+pub(super) fn digits(row: &[Digit], filled: &[Digit]) -> Option<Vec<u8>> {
+    if row.len() != filled.len() {
+        return None;
+    }
+    let digits = row
+        .iter()
+        .zip(filled)
+        .map(|(clue, d)| d.digit().filter(|&d| clue.accept(d)))
+        .collect::<Option<Vec<_>>>()?;
+    if digits.len() > 1 && digits[0] == 0 {
+        return None;
+    }
+    Some(digits)
+}
+
+// 数の和 (先頭の `0` は除く)
+pub(super) fn add(rows: &[Vec<u8>]) -> Vec<u8> {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut sum = Vec::new();
+    let mut carry = 0;
+    for k in 0..columns {
+        let s = carry
+            + rows
+                .iter()
+                .filter(|row| k < row.len())
+                .map(|row| u32::from(row[row.len() - k - 1]))
+                .sum::<u32>();
+        sum.push((s % 10) as u8);
+        carry = s / 10;
+    }
+    while carry > 0 {
+        sum.push((carry % 10) as u8);
+        carry /= 10;
+    }
+    sum.reverse();
+    strip(&sum).to_vec()
+}
+
+// 右揃えの行 `rows` の和が `sum`
+pub(super) struct Sum {
+    pub(super) rows: Vec<Vec<Var>>,
+    pub(super) sum: Vec<Var>,
+}
+
+impl Propagator for Sum {
+    fn watches(&self) -> Vec<Var> {
+        self.rows
+            .iter()
+            .flatten()
+            .chain(&self.sum)
+            .copied()
+            .collect()
+    }
+
+    // 下の位から、繰り上がりが決まっている位まで足す。
+    // 和の数字が決まっていて足す数の決まっていない変数が 1 つだけなら、その数字も絞れる
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let columns = self.rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut carry = 0;
+        for k in 0..columns {
+            if k >= self.sum.len() {
+                return Err(Conflict);
+            }
+            let mut s = carry;
+            let mut unknown = Vec::new();
+            for row in &self.rows {
+                if k < row.len() {
+                    let v = row[row.len() - k - 1];
+                    match store.value(v) {
+                        Some(d) => s += u32::from(d),
+                        None => unknown.push(v),
+                    }
+                }
+            }
+            let target = self.sum[self.sum.len() - k - 1];
+            match (&unknown[..], store.value(target)) {
+                ([], _) => store.assign(target, (s % 10) as u8)?,
+                (&[v, ..], Some(t)) if unknown.iter().all(|&u| u == v) => {
+                    // 同じ変数が m 個なら m * d の一の位が決まる
+                    let m = unknown.len() as u32;
+                    let r = (10 + u32::from(t) - s % 10) % 10;
+                    for d in store.domain(v).iter() {
+                        if m * u32::from(d) % 10 != r {
+                            store.remove(v, d)?;
+                        }
+                    }
+                    match store.value(v) {
+                        Some(d) => s += m * u32::from(d),
+                        None => return Ok(()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+            carry = s / 10;
+        }
+        let mut k = columns;
+        while carry > 0 {
+            if k >= self.sum.len() {
+                return Err(Conflict);
+            }
+            store.assign(self.sum[self.sum.len() - k - 1], (carry % 10) as u8)?;
+            carry /= 10;
+            k += 1;
+        }
+        // 残りの上の桁は `0` (和の先頭の `0` は変数の値の範囲で除く)
+        for &v in &self.sum[..self.sum.len() - k] {
+            store.assign(v, 0)?;
+        }
+        Ok(())
+    }
+}
+
+// 右揃えの足し算の各マスを変数にした制約充足問題
+pub(super) struct Model {
+    pub(super) problem: Problem,
+    pub(super) brancher: Sequence,
+    pub(super) rows: Vec<Vec<Var>>,
+    pub(super) sum: Vec<Var>,
+}
+
+impl Model {
+    // `leading_zero` なら 2 桁以上の行の先頭にも `0` を許す
+    pub(super) fn new(rows: &[Vec<Digit>], sum: &[Digit], leading_zero: bool) -> Self {
+        let mut problem = Problem::new();
+        let lo = |row: &[Digit], i: usize| u8::from(!leading_zero && i == 0 && row.len() > 1);
+        // 同じ文字のマスは 1 つの変数にする
+        let mut letters = BTreeMap::new();
+        for row in rows.iter().map(|row| &row[..]).chain([sum]) {
+            for (i, &d) in row.iter().enumerate() {
+                if let Digit::Letter(ch) = d {
+                    let domain = d.domain(lo(row, i));
+                    letters
+                        .entry(ch)
+                        .and_modify(|dom: &mut Domain| *dom = dom.intersect(domain))
+                        .or_insert(domain);
+                }
+            }
+        }
+        let letters = letters
+            .into_iter()
+            .map(|(ch, domain)| (ch, problem.new_var(domain)))
+            .collect::<BTreeMap<_, _>>();
+        let mut vars = |row: &[Digit]| {
+            row.iter()
+                .enumerate()
+                .map(|(i, &d)| match d {
+                    Digit::Letter(ch) => letters[&ch],
+                    _ => problem.new_var(d.domain(lo(row, i))),
+                })
+                .collect::<Vec<_>>()
+        };
+        let rows = rows.iter().map(|row| vars(row)).collect::<Vec<_>>();
+        let sum = vars(sum);
+        problem.add_propagator(Sum {
+            rows: rows.clone(),
+            sum: sum.clone(),
+        });
+        // 探索の前に、繰り上がりを列ごとに調べて各マスの候補を絞る
+        problem.add_preprocessor(ProductColumns {
+            rows: rows.clone(),
+            product: sum.clone(),
+            shifted: false,
+        });
+        let cells = letters.into_iter().map(|(ch, v)| (Digit::Letter(ch), v));
+        if let Some(letters) = Letters::new(cells) {
+            problem.add_propagator(letters);
+        }
+        // 下の位から、足す数を上から順に決めてから和を決める
+        let mut order = Vec::new();
+        for k in 0..sum.len() {
+            for row in &rows {
+                if k < row.len() {
+                    order.push(row[row.len() - k - 1]);
+                }
+            }
+            order.push(sum[sum.len() - k - 1]);
+        }
+        Self {
+            problem,
+            brancher: Sequence(order),
+            rows,
+            sum,
+        }
+    }
+
+    // 解の値を埋めた各行と和
+    pub(super) fn fill(&self, values: &[u8]) -> (Vec<Vec<Digit>>, Vec<Digit>) {
+        let row = |vars: &[Var]| {
+            vars.iter()
+                .map(|v| Digit::Fix(values[v.index()]))
+                .collect::<Vec<_>>()
+        };
+        (self.rows.iter().map(|r| row(r)).collect(), row(&self.sum))
+    }
+}
+
+// 右揃えで `op` を最後の行の左端に付けて表示する
+pub(super) fn fmt_rows(
+    f: &mut Formatter<'_>,
+    op: char,
+    rows: &[Vec<Digit>],
+    result: &[Digit],
+) -> core::fmt::Result {
+    let digits = |row: &[Digit]| row.iter().map(|d| d.to_string()).collect::<String>();
+    let width = rows
+        .iter()
+        .map(|row| row.len() + 2)
+        .chain([result.len()])
+        .max()
+        .unwrap_or(0);
+    for (i, row) in rows.iter().enumerate() {
+        if i + 1 < rows.len() {
+            writeln!(f, "{digits:>width$}", digits = digits(row), width = width)?;
+        } else {
+            writeln!(
+                f,
+                "{op}{digits:>width$}",
+                op = op,
+                digits = digits(row),
+                width = width - 1
+            )?;
+        }
+    }
+    writeln!(f, "{}", "-".repeat(width))?;
+    write!(
+        f,
+        "{digits:>width$}",
+        digits = digits(result),
+        width = width
+    )
+}
+
+// 行ごとに読み、`op` で始まる行はそれを除く。線や注釈の行は読み飛ばす
+pub(super) fn parse_rows(s: &str, op: char) -> Result<Vec<Vec<Digit>>, MushikuiError> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !is_ignored(line))
+        .map(|line| {
+            let row = line
+                .strip_prefix(op)
+                .unwrap_or(line)
+                .trim_start()
+                .chars()
+                .map(Digit::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            if row.is_empty() {
+                Err(MushikuiError::InconsistentRowLengths)
+            } else {
+                Ok(row)
+            }
+        })
+        .collect()
+}
+
+impl Display for AdditionMushikui {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_rows(f, '+', &self.addends, &self.sum)
+    }
+}
+
+/// 次のような形式の文字列から足し算の虫食算を作る
+///
+/// ```text
+///   ***
+/// + *8*
+/// -----
+///  1**0
+/// ```
+///
+/// 最後の行が和で、それより上が足す数。行は右揃えで読み、行頭の `+` と、線 (`-` や `=` だけ) や `#` で始まる注釈の行は読み飛ばす。
+impl FromStr for AdditionMushikui {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows = parse_rows(s, '+')?;
+        if rows.len() < 3 {
+            return Err(MushikuiError::TooFewRows(rows.len()));
+        }
+        let sum = rows.pop().unwrap();
+        if rows.iter().any(|row| row.len() > sum.len()) {
+            return Err(MushikuiError::InconsistentRowLengths);
+        }
+        Ok(AdditionMushikui { addends: rows, sum })
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use core::fmt::Debug;
+
+    // `len` 桁の数 (1 桁なら `0` も入る)
+    pub(in crate::mushikui) fn numbers(len: usize) -> core::ops::Range<u32> {
+        match len {
+            1 => 0..10,
+            _ => 10u32.pow(len as u32 - 1)..10u32.pow(len as u32),
+        }
+    }
+
+    // 問題と解の数の組 `cases` について、探索で見つかる解の数が `bruteforce` で数えた数と合うか確かめる
+    pub(in crate::mushikui) fn check_counts<P>(cases: &[(&str, usize)], bruteforce: fn(&P) -> usize)
+    where
+        P: Puzzle + FromStr,
+        P::Err: Debug,
+    {
+        for &(s, expected) in cases {
+            let puzzle: P = s.parse().unwrap();
+            let count = puzzle.solve().len();
+            assert_eq!(count, bruteforce(&puzzle), "{}", s);
+            assert_eq!(count, expected, "{}", s);
+        }
+    }
+
+    // 足す数の組をすべて試して、和が和の行の桁数になるものを数える
+    fn count_bruteforce(puzzle: &AdditionMushikui) -> usize {
+        let mut sums = alloc::vec![0];
+        for addend in &puzzle.addends {
+            sums = sums
+                .iter()
+                .flat_map(|&s| numbers(addend.len()).map(move |x| s + x))
+                .collect();
+        }
+        let range = numbers(puzzle.sum.len());
+        sums.iter().filter(|s| range.contains(s)).count()
+    }
+
+    #[test]
+    fn counts_match_bruteforce() {
+        check_counts(
+            &[
+                // 2 桁 + 2 桁の和は 2 桁か 3 桁なので、2 つを合わせると 90 * 90 通り
+                (" **\n+**\n---\n***\n", 4860),
+                (" **\n+**\n---\n **\n", 3240),
+                ("  *\n  *\n+ *\n---\n **\n", 780),
+                (" ***\n+ **\n----\n****\n", 4905),
+            ],
+            count_bruteforce,
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use super::{
+    is_ignored, AdditionMushikui, Cryptarithm, Division, Mushikui, MushikuiError, SolveOptions,
+    SubtractionMushikui,
+};
+use crate::puzzle::{Puzzle, SearchStats, Termination};
+
+/// 演算の種類を問わない虫食算
+///
+/// 文字列から読むときは、書かれた演算子で種類を決める。
+///
+/// - `=` を含む 1 行の式: 覆面算 ([`Cryptarithm`])
+/// - `)` か `÷` を含む行がある: 割り算 ([`Division`])
+/// - `+` で始まる行がある: 足し算 ([`AdditionMushikui`])
+/// - `-` で始まる行 (線を除く) がある: 引き算 ([`SubtractionMushikui`])
+/// - それ以外 (`×` や `x` で始まる行があってもなくても): 掛け算 ([`Mushikui`])
+///
+/// ```
+/// use book_puzzle_algorithm_rs::mushikui::AnyMushikui;
+/// use book_puzzle_algorithm_rs::puzzle::Puzzle;
+///
+/// let puzzle: AnyMushikui = "  *8\n+ 4*\n----\n 1*3".parse().unwrap();
+/// assert!(matches!(puzzle, AnyMushikui::Addition(_)));
+///
+/// let puzzle: AnyMushikui = " *1\n× 2*\n----\n **3\n*4*\n----\n****".parse().unwrap();
+/// assert!(matches!(puzzle, AnyMushikui::Multiplication(_)));
+/// assert_eq!(puzzle.solve().len(), 1);
+///
+/// let puzzle: AnyMushikui = "   *\n7÷**\n  **\n   0".parse().unwrap();
+/// assert!(matches!(puzzle, AnyMushikui::Division(_)));
+/// assert_eq!(puzzle.solve().len(), 8);
+///
+/// let puzzle: AnyMushikui = "SEND + MORE = MONEY".parse().unwrap();
+/// assert!(matches!(puzzle, AnyMushikui::Cryptarithm(_)));
+/// ```
+#[derive(Debug, Clone)]
+pub enum AnyMushikui {
+    Multiplication(Mushikui),
+    Addition(AdditionMushikui),
+    Subtraction(SubtractionMushikui),
+    Division(Division),
+    Cryptarithm(Cryptarithm),
+}
+
+impl Puzzle for AnyMushikui {
+    /// 問題と同じ種類の、数字で埋まった虫食算
+    type Solution = AnyMushikui;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(
+        &self,
+        options: &Self::Options,
+        mut on_solution: F,
+    ) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        match self {
+            AnyMushikui::Multiplication(p) => {
+                p.solve_with(options, |s| on_solution(AnyMushikui::Multiplication(s)))
+            }
+            AnyMushikui::Addition(p) => {
+                p.solve_with(options, |s| on_solution(AnyMushikui::Addition(s)))
+            }
+            AnyMushikui::Subtraction(p) => {
+                p.solve_with(options, |s| on_solution(AnyMushikui::Subtraction(s)))
+            }
+            AnyMushikui::Division(p) => {
+                p.solve_with(options, |s| on_solution(AnyMushikui::Division(s)))
+            }
+            AnyMushikui::Cryptarithm(p) => {
+                p.solve_with(options, |s| on_solution(AnyMushikui::Cryptarithm(s)))
+            }
+        }
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        match (self, solution) {
+            (AnyMushikui::Multiplication(p), AnyMushikui::Multiplication(s)) => {
+                Puzzle::verify(p, s)
+            }
+            (AnyMushikui::Addition(p), AnyMushikui::Addition(s)) => p.verify(s),
+            (AnyMushikui::Subtraction(p), AnyMushikui::Subtraction(s)) => p.verify(s),
+            (AnyMushikui::Division(p), AnyMushikui::Division(s)) => p.verify(s),
+            (AnyMushikui::Cryptarithm(p), AnyMushikui::Cryptarithm(s)) => p.verify(s),
+            _ => false,
+        }
+    }
+}
+
+impl Display for AnyMushikui {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            AnyMushikui::Multiplication(p) => p.fmt(f),
+            AnyMushikui::Addition(p) => p.fmt(f),
+            AnyMushikui::Subtraction(p) => p.fmt(f),
+            AnyMushikui::Division(p) => p.fmt(f),
+            AnyMushikui::Cryptarithm(p) => p.fmt(f),
+        }
+    }
+}
+
+impl FromStr for AnyMushikui {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines = s
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !is_ignored(line));
+        let any = |f: fn(&str) -> bool| lines.clone().any(f);
+        if lines.clone().count() == 1 && any(|line| line.contains('=')) {
+            s.parse().map(AnyMushikui::Cryptarithm)
+        } else if any(|line| line.contains(')') || line.contains('÷')) {
+            s.parse().map(AnyMushikui::Division)
+        } else if any(|line| line.starts_with('+')) {
+            s.parse().map(AnyMushikui::Addition)
+        } else if any(|line| line.starts_with('-')) {
+            s.parse().map(AnyMushikui::Subtraction)
+        } else {
+            s.parse().map(AnyMushikui::Multiplication)
+        }
+    }
+}
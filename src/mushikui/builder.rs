@@ -0,0 +1,173 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{Digit, Mushikui, MushikuiError};
+
+/// [`MushikuiBuilder`] に渡せる 1 行
+pub trait IntoRow {
+    /// 左の桁から順に並べたマス
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError>;
+}
+
+impl IntoRow for &str {
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError> {
+        self.trim().chars().map(Digit::try_from).collect()
+    }
+}
+
+impl IntoRow for &String {
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError> {
+        self.as_str().into_row()
+    }
+}
+
+impl IntoRow for &[char] {
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError> {
+        self.iter().copied().map(Digit::try_from).collect()
+    }
+}
+
+impl IntoRow for &[Digit] {
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError> {
+        Ok(self.to_vec())
+    }
+}
+
+impl IntoRow for Vec<Digit> {
+    fn into_row(self) -> Result<Vec<Digit>, MushikuiError> {
+        Ok(self)
+    }
+}
+
+/// 虫食算を 1 行ずつ組み立てる
+///
+/// 行を加えるたびに、それまでの行と矛盾しないかを確かめる。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::mushikui::MushikuiBuilder;
+///
+/// # fn main() -> Result<(), book_puzzle_algorithm_rs::mushikui::MushikuiError> {
+/// let mushikui = MushikuiBuilder::new()
+///     .multiplicand("*1")?
+///     .multiplier("2*")?
+///     .partial_product("**3")?
+///     .partial_product("*4*")?
+///     .product("****")?
+///     .build()?;
+/// assert_eq!(mushikui.solve().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MushikuiBuilder {
+    multiplicand: Option<Vec<Digit>>,
+    multiplier: Option<Vec<Digit>>,
+    partial_product: Vec<Vec<Digit>>,
+    product: Option<Vec<Digit>>,
+}
+
+impl MushikuiBuilder {
+    /// 行のない状態から始める
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 被乗数の行
+    pub fn multiplicand(mut self, row: impl IntoRow) -> Result<Self, MushikuiError> {
+        self.multiplicand = Some(Self::row(row)?);
+        self.check()?;
+        Ok(self)
+    }
+
+    /// 乗数の行
+    pub fn multiplier(mut self, row: impl IntoRow) -> Result<Self, MushikuiError> {
+        self.multiplier = Some(Self::row(row)?);
+        self.check()?;
+        Ok(self)
+    }
+
+    /// 部分積の行 (乗数の下の桁の分から順に加える)
+    ///
+    /// 乗数の `0` の桁の分は、空の行 (書くのを省いた行) か `0` だけの行にする。
+    pub fn partial_product(mut self, row: impl IntoRow) -> Result<Self, MushikuiError> {
+        let row = row.into_row()?;
+        self.partial_product.push(row);
+        self.check()?;
+        Ok(self)
+    }
+
+    /// 積の行
+    pub fn product(mut self, row: impl IntoRow) -> Result<Self, MushikuiError> {
+        self.product = Some(Self::row(row)?);
+        self.check()?;
+        Ok(self)
+    }
+
+    /// 虫食算を作る
+    ///
+    /// 積の行を加えていなければ、積を省いた問題にする。
+    pub fn build(self) -> Result<Mushikui, MushikuiError> {
+        let rows = usize::from(self.multiplicand.is_some())
+            + usize::from(self.multiplier.is_some())
+            + self.partial_product.len()
+            + usize::from(self.product.is_some());
+        match (self.multiplicand, self.multiplier) {
+            (Some(multiplicand), Some(multiplier)) => {
+                if self.partial_product.len() != multiplier.len() {
+                    return Err(MushikuiError::WrongPartialProductCount {
+                        expected: multiplier.len(),
+                        actual: self.partial_product.len(),
+                    });
+                }
+                Ok(Mushikui {
+                    multiplicand,
+                    multiplier,
+                    partial_product: self.partial_product,
+                    product: self.product.unwrap_or_default(),
+                })
+            }
+            _ => Err(MushikuiError::TooFewRows(rows)),
+        }
+    }
+
+    // 空でない行 (先頭の `0` を許すかは解くときに決める)
+    fn row(row: impl IntoRow) -> Result<Vec<Digit>, MushikuiError> {
+        let row = row.into_row()?;
+        if row.is_empty() {
+            Err(MushikuiError::InconsistentRowLengths)
+        } else {
+            Ok(row)
+        }
+    }
+
+    // ここまでに加えた行の長さが掛け算として合うか
+    fn check(&self) -> Result<(), MushikuiError> {
+        let n = self.multiplicand.as_ref().map(|row| row.len());
+        let m = self.multiplier.as_ref().map(|row| row.len());
+        if let Some(m) = m {
+            if self.partial_product.len() > m {
+                return Err(MushikuiError::WrongPartialProductCount {
+                    expected: m,
+                    actual: self.partial_product.len(),
+                });
+            }
+        }
+        let consistent = match (n, m) {
+            (Some(n), Some(m)) => self
+                .product
+                .as_ref()
+                .map_or(true, |row| n + m - 1 <= row.len() && row.len() <= n + m),
+            _ => true,
+        } && n.map_or(true, |n| {
+            // 乗数の `0` の桁の分は省くか 1 桁
+            self.partial_product
+                .iter()
+                .all(|row| row.len() <= 1 || row.len() == n || row.len() == n + 1)
+        });
+        if consistent {
+            Ok(())
+        } else {
+            Err(MushikuiError::InconsistentRowLengths)
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::{Digit, Mushikui};
+use crate::backtrack::Domain;
+
+// 0 から 9 の値をとる数。`Var` は one-hot の 10 個の変数の先頭
+#[derive(Debug, Copy, Clone)]
+enum Term {
+    Const(u8),
+    Var { first: i32, domain: Domain },
+}
+
+impl Term {
+    fn domain(self) -> Domain {
+        match self {
+            Term::Const(v) => Domain::singleton(v),
+            Term::Var { domain, .. } => domain,
+        }
+    }
+}
+
+// 「値が v」のリテラル。定数なら真偽
+enum Lit {
+    True,
+    False,
+    Var(i32),
+}
+
+fn lit(term: Term, v: u8) -> Lit {
+    match term {
+        Term::Const(c) if c == v => Lit::True,
+        Term::Const(_) => Lit::False,
+        Term::Var { first, domain } if domain.contains(v) => Lit::Var(first + i32::from(v)),
+        Term::Var { .. } => Lit::False,
+    }
+}
+
+#[derive(Default)]
+struct Cnf {
+    vars: i32,
+    clauses: Vec<Vec<i32>>,
+    comments: Vec<String>,
+}
+
+impl Cnf {
+    // 値が `domain` のどれか 1 つの数
+    fn digit(&mut self, domain: Domain) -> Term {
+        let first = self.vars + 1;
+        self.vars += 10;
+        let values = (0..10).map(|v| first + v);
+        self.clauses
+            .push(domain.iter().map(|v| first + i32::from(v)).collect());
+        for v in 0..10 {
+            if !domain.contains(v) {
+                self.clauses.push(vec![-(first + i32::from(v))]);
+            }
+        }
+        for (i, a) in values.clone().enumerate() {
+            for b in values.clone().skip(i + 1) {
+                self.clauses.push(vec![-a, -b]);
+            }
+        }
+        Term::Var { first, domain }
+    }
+
+    // 条件 `conds` がすべて成り立てば `concls` もすべて成り立つ (`concls` が空なら条件は成り立たない)
+    fn implies(&mut self, conds: &[(Term, u8)], concls: &[(Term, u8)]) {
+        let mut premise = Vec::new();
+        for &(term, v) in conds {
+            match lit(term, v) {
+                Lit::True => {}
+                Lit::False => return,
+                Lit::Var(x) => premise.push(-x),
+            }
+        }
+        if concls.is_empty() {
+            self.clauses.push(premise);
+            return;
+        }
+        for &(term, v) in concls {
+            match lit(term, v) {
+                Lit::True => {}
+                Lit::False => self.clauses.push(premise.clone()),
+                Lit::Var(x) => {
+                    let mut clause = premise.clone();
+                    clause.push(x);
+                    self.clauses.push(clause);
+                }
+            }
+        }
+    }
+
+    // a = b
+    fn equal(&mut self, a: Term, b: Term) {
+        for v in a.domain().iter() {
+            self.implies(&[(a, v)], &[(b, v)]);
+        }
+    }
+
+    // 下の桁から並べた 2 数の和 (下の桁から)
+    fn add(&mut self, a: &[Term], b: &[Term]) -> Vec<Term> {
+        let width = a.len().max(b.len());
+        let at = |x: &[Term], k: usize| x.get(k).copied().unwrap_or(Term::Const(0));
+        let mut carry = Term::Const(0);
+        let mut sum = Vec::new();
+        for k in 0..width {
+            let (x, y) = (at(a, k), at(b, k));
+            let s = self.digit(Domain::range(0, 9));
+            let next = self.digit(Domain::range(0, 1));
+            for u in x.domain().iter() {
+                for v in y.domain().iter() {
+                    for c in carry.domain().iter() {
+                        let e = u + v + c;
+                        self.implies(
+                            &[(x, u), (y, v), (carry, c)],
+                            &[(s, e % 10), (next, e / 10)],
+                        );
+                    }
+                }
+            }
+            sum.push(s);
+            carry = next;
+        }
+        sum.push(carry);
+        sum
+    }
+
+    fn dimacs(&self) -> String {
+        let mut s = String::new();
+        for comment in &self.comments {
+            writeln!(s, "c {}", comment).unwrap();
+        }
+        writeln!(s, "p cnf {} {}", self.vars, self.clauses.len()).unwrap();
+        for clause in &self.clauses {
+            for x in clause {
+                write!(s, "{} ", x).unwrap();
+            }
+            writeln!(s, "0").unwrap();
+        }
+        s
+    }
+}
+
+impl Mushikui {
+    /// 虫食算を CNF にして DIMACS 形式で返す
+    ///
+    /// 各マスは数字 `0` から `9` に対応する 10 個の変数で表す。
+    /// `c cell <行> <列> <変数>` のコメントは、その行と列のマスの数字 `d` が変数 `<変数> + d` に対応することを表す。
+    /// 行は被乗数, 乗数, 部分積, 積の順に 0 から数え、列は左端から数える。
+    /// 部分積と積は列ごとの繰り上がりを変数にして表す。
+    pub fn to_cnf(&self) -> String {
+        let mut cnf = Cnf::default();
+        let mut letters = BTreeMap::new();
+        // 各行の数 (下の桁から)
+        let mut rows = Vec::new();
+        for (r, row) in self.rows().into_iter().enumerate() {
+            let mut terms = Vec::new();
+            for (c, &digit) in row.iter().enumerate() {
+                // 多桁の行と被乗数, 乗数の先頭は 0 でない
+                let lo = u8::from(c == 0 && (r <= 1 || row.len() > 1));
+                let term = cnf.digit(digit.domain(lo));
+                if let Term::Var { first, .. } = term {
+                    cnf.comments.push(format!("cell {} {} {}", r, c, first));
+                }
+                if let Digit::Letter(ch) = digit {
+                    letters.entry(ch).or_insert_with(Vec::new).push(term);
+                }
+                terms.push(term);
+            }
+            terms.reverse();
+            rows.push(terms);
+        }
+        // 同じ文字には同じ数字、違う文字には違う数字
+        let groups = letters.into_values().collect::<Vec<_>>();
+        for (i, group) in groups.iter().enumerate() {
+            for &term in &group[1..] {
+                cnf.equal(group[0], term);
+            }
+            for other in &groups[i + 1..] {
+                for v in 0..10 {
+                    cnf.implies(&[(group[0], v), (other[0], v)], &[]);
+                }
+            }
+        }
+        let multiplicand = &rows[0];
+        let multiplier = &rows[1];
+        let n = multiplicand.len();
+        let parts = &rows[2..rows.len() - 1];
+        // 部分積 = 被乗数 × 乗数の 1 桁
+        for (j, part) in parts.iter().enumerate() {
+            let d = multiplier[j];
+            if part.len() < n {
+                // 乗数の 0 の桁の分 (空か 0 だけ)
+                cnf.implies(&[], &[(d, 0)]);
+                if let Some(&y) = part.first() {
+                    cnf.implies(&[], &[(y, 0)]);
+                }
+                continue;
+            }
+            let mut carry = Term::Const(0);
+            for k in 0..n {
+                let next = cnf.digit(Domain::range(0, 8));
+                for m in multiplicand[k].domain().iter() {
+                    for v in d.domain().iter() {
+                        for c in carry.domain().iter() {
+                            let e = m * v + c;
+                            cnf.implies(
+                                &[(multiplicand[k], m), (d, v), (carry, c)],
+                                &[(part[k], e % 10), (next, e / 10)],
+                            );
+                        }
+                    }
+                }
+                carry = next;
+            }
+            if part.len() == n {
+                cnf.implies(&[], &[(carry, 0)]);
+            } else {
+                cnf.implies(&[(carry, 0)], &[]);
+                cnf.equal(carry, part[n]);
+            }
+        }
+        // 積を省いた問題なら積は決めない
+        if rows[rows.len() - 1].is_empty() {
+            return cnf.dimacs();
+        }
+        // 積 = 部分積を 1 桁ずつずらした和
+        let mut sum = Vec::new();
+        for (j, part) in parts.iter().enumerate() {
+            let mut shifted = vec![Term::Const(0); j];
+            shifted.extend(part);
+            sum = if j == 0 {
+                shifted
+            } else {
+                cnf.add(&sum, &shifted)
+            };
+        }
+        let product = &rows[rows.len() - 1];
+        for k in 0..sum.len().max(product.len()) {
+            let at = |x: &[Term]| x.get(k).copied().unwrap_or(Term::Const(0));
+            cnf.equal(at(&sum), at(product));
+        }
+        cnf.dimacs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // DIMACS を読み、`c cell` のマスの変数を `filled` の数字に決めてから単位伝播する
+    //
+    // 繰り上がりなどのほかの変数はマスの数字から決まるので、矛盾しなければすべての変数が決まる。
+    // 矛盾すれば `None`、そうでなければ各変数の値 (`0` 番目は使わない) を返す。
+    fn propagate(dimacs: &str, filled: &Mushikui) -> Option<Vec<Option<bool>>> {
+        let rows = filled.rows();
+        let mut assignment = Vec::new();
+        let mut cells = Vec::new();
+        let mut clauses = Vec::new();
+        for line in dimacs.lines() {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            let number = |x: &str| x.parse::<usize>().unwrap();
+            match words[..] {
+                ["c", "cell", r, c, first] => {
+                    cells.push((number(first), rows[number(r)][number(c)].digit().unwrap()))
+                }
+                ["p", "cnf", vars, _] => assignment = vec![None; number(vars) + 1],
+                _ => clauses.push(
+                    words[..words.len() - 1]
+                        .iter()
+                        .map(|x| x.parse::<i32>().unwrap())
+                        .collect::<Vec<_>>(),
+                ),
+            }
+        }
+        for (first, d) in cells {
+            for v in 0..10 {
+                assignment[first + usize::from(v)] = Some(v == d);
+            }
+        }
+        let value = |assignment: &[Option<bool>], x: i32| {
+            assignment[x.unsigned_abs() as usize].map(|v| v == (x > 0))
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for clause in &clauses {
+                if clause.iter().any(|&x| value(&assignment, x) == Some(true)) {
+                    continue;
+                }
+                let free = clause
+                    .iter()
+                    .filter(|&&x| value(&assignment, x).is_none())
+                    .collect::<Vec<_>>();
+                match free[..] {
+                    [] => return None,
+                    [&x] => {
+                        assignment[x.unsigned_abs() as usize] = Some(x > 0);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Some(assignment)
+    }
+
+    #[test]
+    fn solution_satisfies_the_cnf() {
+        let puzzle: Mushikui = " *1\n 2*\n**3\n*4*\n****".parse().unwrap();
+        let dimacs = puzzle.to_cnf();
+        // コメントはマスごとに 1 行
+        let cells = dimacs
+            .lines()
+            .filter(|line| line.starts_with("c cell "))
+            .count();
+        assert_eq!(cells, 14);
+
+        let answer: Mushikui = " 71\n 23\n213\n142\n1633".parse().unwrap();
+        let assignment = propagate(&dimacs, &answer).unwrap();
+        assert!(assignment[1..].iter().all(Option::is_some));
+
+        // 掛け算が合わない数字や、掛け算は合っていても手掛かりと合わない数字は矛盾する
+        for wrong in [" 71\n 23\n213\n142\n1643", " 73\n 23\n219\n146\n1679"] {
+            let wrong: Mushikui = wrong.parse().unwrap();
+            assert_eq!(propagate(&dimacs, &wrong), None, "{}", wrong);
+        }
+    }
+}
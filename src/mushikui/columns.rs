@@ -0,0 +1,318 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::backtrack::{Conflict, Domain, Propagator, Store, Var};
+
+// 探索の前に、列ごとの繰り上がりを全部試して各マスの数字を絞る制約。
+// 前から届く繰り上がりと、後ろの桁の条件を満たせる繰り上がりの両方を求め、
+// どちらにも入る繰り上がりで使われる数字だけを残す。
+
+// 部分積の行 `row` は被乗数 `multiplicand` と乗数の 1 桁 `digit` の積
+// (`digit` が `0` なら `row` は空か `0` だけ)
+pub(super) struct PartialProductColumns {
+    pub(super) multiplicand: Vec<Var>,
+    pub(super) digit: Var,
+    pub(super) row: Vec<Var>,
+}
+
+// 1 桁の掛け算の繰り上がりは 8 以下
+const CARRIES: u8 = 9;
+
+fn carries(set: u16) -> impl Iterator<Item = u8> {
+    (0..CARRIES).filter(move |&c| set >> c & 1 == 1)
+}
+
+impl Propagator for PartialProductColumns {
+    fn watches(&self) -> Vec<Var> {
+        let mut vars = self.multiplicand.clone();
+        vars.push(self.digit);
+        vars.extend(&self.row);
+        vars
+    }
+
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let n = self.multiplicand.len();
+        // `digit` が `0` になれるか
+        let zero = store.domain(self.digit).contains(0)
+            && match self.row[..] {
+                [] => true,
+                [y] => store.domain(y).contains(0),
+                _ => false,
+            };
+        let extra = match self.row.len().checked_sub(n) {
+            Some(extra) if extra <= 1 => extra,
+            _ if zero => {
+                store.assign(self.digit, 0)?;
+                return match self.row[..] {
+                    [y] => store.assign(y, 0),
+                    _ => Ok(()),
+                };
+            }
+            _ => return Err(Conflict),
+        };
+        // 下から k 桁目の被乗数と部分積
+        let m = |k: usize| store.domain(self.multiplicand[n - k - 1]);
+        let r = |k: usize| store.domain(self.row[self.row.len() - k - 1]);
+        // 最後の繰り上がりが部分積の残りの桁になる (先頭の `0` は部分積の変数の値の範囲で除く)
+        let accept = |c: u8| {
+            if extra == 0 {
+                c == 0
+            } else {
+                store.domain(self.row[0]).contains(c)
+            }
+        };
+        let mut digits = Domain::EMPTY;
+        let mut m_support = vec![Domain::EMPTY; n];
+        let mut r_support = vec![Domain::EMPTY; self.row.len()];
+        for d in store.domain(self.digit).iter() {
+            // k 桁目の (繰り上がり, 被乗数の数字) から (部分積の数字, 次の繰り上がり)
+            let step = |k: usize, c: u8, x: u8| {
+                let e = x * d + c;
+                Some((e % 10, e / 10)).filter(|&(y, _)| r(k).contains(y))
+            };
+            let mut forward = vec![0u16; n + 1];
+            forward[0] = 1;
+            for k in 0..n {
+                for c in carries(forward[k]) {
+                    for x in m(k).iter() {
+                        if let Some((_, next)) = step(k, c, x) {
+                            forward[k + 1] |= 1 << next;
+                        }
+                    }
+                }
+            }
+            let mut backward = vec![0u16; n + 1];
+            backward[n] = (0..CARRIES)
+                .filter(|&c| accept(c))
+                .fold(0, |set, c| set | 1 << c);
+            for k in (0..n).rev() {
+                for c in 0..CARRIES {
+                    for x in m(k).iter() {
+                        match step(k, c, x) {
+                            Some((_, next)) if backward[k + 1] >> next & 1 == 1 => {
+                                backward[k] |= 1 << c;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            if backward[0] & 1 == 0 {
+                continue;
+            }
+            digits = digits.with(d);
+            for k in 0..n {
+                for c in carries(forward[k] & backward[k]) {
+                    for x in m(k).iter() {
+                        match step(k, c, x) {
+                            Some((y, next)) if backward[k + 1] >> next & 1 == 1 => {
+                                m_support[k] = m_support[k].with(x);
+                                r_support[k] = r_support[k].with(y);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            for c in carries(forward[n] & backward[n]) {
+                if extra == 1 {
+                    r_support[n] = r_support[n].with(c);
+                }
+            }
+        }
+        if zero {
+            digits = digits.with(0);
+            for (k, support) in m_support.iter_mut().enumerate() {
+                *support = m(k);
+            }
+            r_support[0] = r_support[0].with(0);
+        }
+        store.restrict(self.digit, digits)?;
+        for (k, &domain) in m_support.iter().enumerate() {
+            store.restrict(self.multiplicand[n - k - 1], domain)?;
+        }
+        for (k, &domain) in r_support.iter().enumerate() {
+            store.restrict(self.row[self.row.len() - k - 1], domain)?;
+        }
+        Ok(())
+    }
+}
+
+// 積の行 `product` は部分積の行 `rows` (`shifted` なら j 行目は j 桁ずらす) の和
+//
+// 足し算では `rows` を足す数, `product` を和にして、ずらさずに使う。
+pub(super) struct ProductColumns {
+    pub(super) rows: Vec<Vec<Var>>,
+    pub(super) product: Vec<Var>,
+    pub(super) shifted: bool,
+}
+
+// 数の集合 (0 から `len - 1` まで)
+type Set = Vec<bool>;
+
+fn members(set: &[bool]) -> impl Iterator<Item = usize> + '_ {
+    (0..set.len()).filter(move |&i| set[i])
+}
+
+// {a + b | a ∈ lhs, b ∈ rhs}
+fn sumset(lhs: &[bool], rhs: &[bool]) -> Set {
+    let mut sum = vec![false; lhs.len() + rhs.len()];
+    for a in members(lhs) {
+        for b in members(rhs) {
+            sum[a + b] = true;
+        }
+    }
+    sum
+}
+
+fn digits(domain: Domain) -> Set {
+    (0..10).map(|v| domain.contains(v)).collect()
+}
+
+impl Propagator for ProductColumns {
+    fn watches(&self) -> Vec<Var> {
+        self.rows
+            .iter()
+            .flatten()
+            .chain(&self.product)
+            .copied()
+            .collect()
+    }
+
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let shift = |j: usize| if self.shifted { j } else { 0 };
+        let columns = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(j, row)| shift(j) + row.len())
+            .max()
+            .unwrap_or(0);
+        let extra = match self.product.len().checked_sub(columns) {
+            Some(extra) => extra,
+            None => return Err(Conflict),
+        };
+        // 下から k 桁目の部分積のマスと、その数字の和になりうる値
+        let cells = (0..columns)
+            .map(|k| {
+                self.rows
+                    .iter()
+                    .enumerate()
+                    .map(|(j, row)| (shift(j), row))
+                    .filter(|&(j, row)| k >= j && k - j < row.len())
+                    .map(|(j, row)| row[row.len() - (k - j) - 1])
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let sums = cells
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .fold(vec![true], |set, &v| sumset(&set, &digits(store.domain(v))))
+            })
+            .collect::<Vec<_>>();
+        let product = self
+            .product
+            .iter()
+            .map(|&v| store.domain(v))
+            .collect::<Vec<_>>();
+        let p = |k: usize| product[product.len() - k - 1];
+        // 繰り上がりは 9 * 行数 を超えない
+        let limit = 9 * self.rows.len() + 1;
+        // k 桁目の (繰り上がり, 部分積の和) から次の繰り上がり
+        let step = |k: usize, c: usize, s: usize| {
+            let e = c + s;
+            Some(e / 10).filter(|_| p(k).contains((e % 10) as u8))
+        };
+        // 最後の繰り上がりが積の残りの桁になる (先頭の `0` は積の変数の値の範囲で除く)
+        let accept = |c: usize| {
+            let mut rest = c;
+            for i in 0..extra {
+                let y = (rest % 10) as u8;
+                if !product[extra - i - 1].contains(y) {
+                    return false;
+                }
+                rest /= 10;
+            }
+            rest == 0
+        };
+        let mut forward = vec![vec![false; limit]; columns + 1];
+        forward[0][0] = true;
+        for k in 0..columns {
+            for c in members(&forward[k]).collect::<Vec<_>>() {
+                for s in members(&sums[k]) {
+                    if let Some(next) = step(k, c, s) {
+                        forward[k + 1][next] = true;
+                    }
+                }
+            }
+        }
+        let mut backward = vec![vec![false; limit]; columns + 1];
+        for c in 0..limit {
+            backward[columns][c] = accept(c);
+        }
+        for k in (0..columns).rev() {
+            for c in 0..limit {
+                backward[k][c] = members(&sums[k])
+                    .any(|s| step(k, c, s).map_or(false, |next| backward[k + 1][next]));
+            }
+        }
+        if !backward[0][0] {
+            return Err(Conflict);
+        }
+        for k in 0..columns {
+            // 使える和と積の数字
+            let mut good = vec![false; sums[k].len()];
+            let mut support = Domain::EMPTY;
+            for c in (0..limit).filter(|&c| forward[k][c] && backward[k][c]) {
+                for s in members(&sums[k]) {
+                    match step(k, c, s) {
+                        Some(next) if backward[k + 1][next] => {
+                            good[s] = true;
+                            support = support.with(((c + s) % 10) as u8);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            store.restrict(self.product[self.product.len() - k - 1], support)?;
+            // 各マスは、他のマスの和と合わせて使える和になる数字だけ残す
+            let cells = &cells[k];
+            let domains = cells
+                .iter()
+                .map(|&v| digits(store.domain(v)))
+                .collect::<Vec<_>>();
+            let mut suffix = vec![vec![true]; cells.len() + 1];
+            for i in (0..cells.len()).rev() {
+                suffix[i] = sumset(&suffix[i + 1], &domains[i]);
+            }
+            let mut prefix = vec![true];
+            for (i, &v) in cells.iter().enumerate() {
+                let others = sumset(&prefix, &suffix[i + 1]);
+                let support = store
+                    .domain(v)
+                    .iter()
+                    .filter(|&x| {
+                        members(&others)
+                            .any(|o| good.get(o + usize::from(x)).copied().unwrap_or(false))
+                    })
+                    .collect();
+                store.restrict(v, support)?;
+                prefix = sumset(&prefix, &domains[i]);
+            }
+        }
+        let mut support = vec![Domain::EMPTY; extra];
+        for c in (0..limit).filter(|&c| forward[columns][c] && backward[columns][c]) {
+            let mut rest = c;
+            for i in 0..extra {
+                support[extra - i - 1] = support[extra - i - 1].with((rest % 10) as u8);
+                rest /= 10;
+            }
+        }
+        for (i, &domain) in support.iter().enumerate() {
+            store.restrict(self.product[i], domain)?;
+        }
+        Ok(())
+    }
+}
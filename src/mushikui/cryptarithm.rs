@@ -0,0 +1,177 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use super::addition::{add, digits, Model};
+use super::{letters_consistent, restrict, strip, Digit, MushikuiError, SolveOptions};
+use crate::backtrack;
+use crate::puzzle::{Puzzle, SearchStats, Termination};
+
+/// 1 行で書いた足し算の覆面算
+///
+/// `SEND + MORE = MONEY` のように、同じ文字には同じ数字、違う文字には違う数字が入る。
+/// 2 文字以上の語の先頭は `0` にならない。数字や `*` も混ぜられる。
+///
+/// 下の位から文字に数字を割り当て、位ごとの和で枝刈りする。
+#[derive(Debug, Clone)]
+pub struct Cryptarithm {
+    addends: Vec<Vec<Digit>>,
+    sum: Vec<Digit>,
+}
+
+impl Puzzle for Cryptarithm {
+    /// 各文字を数字に置き換えた式
+    type Solution = Cryptarithm;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(
+        &self,
+        options: &Self::Options,
+        mut on_solution: F,
+    ) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        let mut model = Model::new(&self.addends, &self.sum, options.allow_leading_zero);
+        let words = self.addends.iter().chain([&self.sum]);
+        let vars = model.rows.iter().chain([&model.sum]);
+        restrict(
+            &mut model.problem,
+            words.zip(vars).map(|(word, vars)| (&word[..], &vars[..])),
+            options,
+        );
+        model.problem.solve_with(
+            &model.brancher,
+            &backtrack::Options {
+                max_solutions: options.max_solutions,
+                deadline: options.deadline,
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone(),
+            },
+            |values| {
+                let (addends, sum) = model.fill(values);
+                on_solution(Cryptarithm { addends, sum })
+            },
+        )
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        if self.addends.len() != solution.addends.len() {
+            return false;
+        }
+        let words = self.addends.iter().chain([&self.sum]);
+        let filled = solution.addends.iter().chain([&solution.sum]);
+        let cells = words.clone().zip(filled.clone());
+        if !letters_consistent(cells.flat_map(|(w, f)| w.iter().copied().zip(f.iter().copied()))) {
+            return false;
+        }
+        match words
+            .zip(filled)
+            .map(|(word, filled)| digits(word, filled))
+            .collect::<Option<Vec<_>>>()
+        {
+            Some(mut values) => {
+                let sum = values.pop().unwrap();
+                add(&values) == strip(&sum)
+            }
+            None => false,
+        }
+    }
+}
+
+impl Display for Cryptarithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let word = |word: &[Digit]| word.iter().map(|d| d.to_string()).collect::<String>();
+        let addends = self.addends.iter().map(|w| word(w)).collect::<Vec<_>>();
+        write!(f, "{} = {}", addends.join(" + "), word(&self.sum))
+    }
+}
+
+/// `SEND + MORE = MONEY` のような 1 行の式から覆面算を作る
+impl FromStr for Cryptarithm {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lhs, rhs) = s.split_once('=').ok_or(MushikuiError::MissingEquals)?;
+        let word = |word: &str| {
+            let word = word
+                .trim()
+                .chars()
+                .map(Digit::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            if word.is_empty() {
+                Err(MushikuiError::InconsistentRowLengths)
+            } else {
+                Ok(word)
+            }
+        };
+        let addends = lhs.split('+').map(word).collect::<Result<Vec<_>, _>>()?;
+        let sum = word(rhs)?;
+        if addends.len() < 2 {
+            return Err(MushikuiError::TooFewRows(addends.len() + 1));
+        }
+        if addends.iter().any(|w| w.len() > sum.len()) {
+            return Err(MushikuiError::InconsistentRowLengths);
+        }
+        Ok(Cryptarithm { addends, sum })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(s: &str, options: &SolveOptions) -> Vec<String> {
+        let puzzle: Cryptarithm = s.parse().unwrap();
+        let solutions = puzzle.solve_with_options(options).into_solutions();
+        // `verify` は先頭の 0 を許さない
+        if !options.allow_leading_zero {
+            assert!(solutions.iter().all(|solution| puzzle.verify(solution)));
+        }
+        let mut solutions = solutions
+            .iter()
+            .map(|solution| solution.to_string())
+            .collect::<Vec<_>>();
+        solutions.sort();
+        solutions
+    }
+
+    #[test]
+    fn send_more_money() {
+        assert_eq!(
+            solve("SEND + MORE = MONEY", &SolveOptions::default()),
+            ["9567 + 1085 = 10652"]
+        );
+    }
+
+    #[test]
+    fn single_letters_may_be_zero() {
+        // A = 0 なら B = 0 になり、違う文字に同じ数字は入らない
+        assert_eq!(
+            solve("A + A = B", &SolveOptions::default()),
+            ["1 + 1 = 2", "2 + 2 = 4", "3 + 3 = 6", "4 + 4 = 8"]
+        );
+    }
+
+    #[test]
+    fn leading_letters_are_not_zero() {
+        // 1 桁の和は 17 以下なので、C は 1
+        let solutions = solve("A + B = CD", &SolveOptions::default());
+        assert!(!solutions.is_empty());
+        assert!(solutions.iter().all(|s| s.contains(" = 1")));
+
+        // 先頭の 0 を許すと A + B = D の解も入る
+        let options = SolveOptions {
+            allow_leading_zero: true,
+            ..SolveOptions::default()
+        };
+        let with_zero = solve("A + B = CD", &options);
+        assert!(with_zero.contains(&"2 + 3 = 05".to_string()));
+        assert!(solutions.iter().all(|s| with_zero.contains(s)));
+        assert!(with_zero
+            .iter()
+            .all(|s| s.contains(" = 1") || s.contains(" = 0")));
+    }
+}
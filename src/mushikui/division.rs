@@ -0,0 +1,760 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use super::{
+    is_ignored, letters_consistent, restrict, strip, Digit, Letters, MushikuiError, PartialProduct,
+    SolveOptions,
+};
+use crate::backtrack::{self, Conflict, Domain, Problem, Propagator, Sequence, Store, Var};
+use crate::puzzle::{Puzzle, SearchStats, Termination};
+
+/// 割り算 (筆算) の虫食算
+///
+/// 商, 除数, 被除数と、商の `0` でない桁ごとの部分積と余りを持つ。
+/// 余りは、引き算の結果に次の部分積までの被除数の桁を下ろしたもの。
+/// 最後の余りが割り算の余りになる。
+///
+/// ```text
+///     ***
+///   -----
+/// **)****
+///    **
+///    --
+///    ***
+///    ***
+///    ---
+///      0
+/// ```
+#[derive(Debug, Clone)]
+pub struct Division {
+    divisor: Vec<Digit>,
+    dividend: Vec<Digit>,
+    quotient: Vec<Digit>,
+    steps: Vec<Step>,
+}
+
+// 商の 1 桁ぶんの部分積と余り
+#[derive(Debug, Clone)]
+struct Step {
+    // 部分積の右端の列 (被除数の左端を 0 とする)
+    column: usize,
+    product: Vec<Digit>,
+    remainder: Vec<Digit>,
+}
+
+impl Division {
+    // 余りの行 i の右端の列
+    fn remainder_column(&self, i: usize) -> usize {
+        match self.steps.get(i + 1) {
+            Some(step) => step.column,
+            None => self.dividend.len() - 1,
+        }
+    }
+
+    // 余りの行 i のうち、被除数から下ろした桁の数
+    fn brought_down(&self, i: usize) -> usize {
+        self.remainder_column(i) - self.steps[i].column
+    }
+
+    // 商の列 c の桁 (商のない列なら `None`)
+    fn quotient_digit(&self, column: usize) -> Option<Digit> {
+        let offset = self.dividend.len() - self.quotient.len();
+        column.checked_sub(offset).map(|index| self.quotient[index])
+    }
+}
+
+impl Puzzle for Division {
+    type Solution = Division;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(
+        &self,
+        options: &Self::Options,
+        mut on_solution: F,
+    ) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        let mut model = Model::new(self);
+        let mut vars = vec![&model.divisor[..], &model.dividend[..], &model.quotient[..]];
+        for (_, product, remainder) in &model.steps {
+            vars.push(&product[..]);
+            vars.push(&remainder[..]);
+        }
+        restrict(
+            &mut model.problem,
+            self.rows().into_iter().zip(vars),
+            options,
+        );
+        model.problem.solve_with(
+            &model.brancher,
+            &backtrack::Options {
+                max_solutions: options.max_solutions,
+                deadline: options.deadline,
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone(),
+            },
+            |values| on_solution(model.fill(values)),
+        )
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        let shape = |d: &Division| {
+            let mut shape = vec![d.divisor.len(), d.dividend.len(), d.quotient.len()];
+            for step in &d.steps {
+                shape.extend([step.column, step.product.len(), step.remainder.len()]);
+            }
+            shape
+        };
+        if shape(self) != shape(solution) {
+            return false;
+        }
+        let (rows, filled) = (self.rows(), solution.rows());
+        let mut values = Vec::new();
+        for (row, filled_row) in rows.iter().zip(&filled) {
+            let mut digits = Vec::new();
+            for (clue, d) in row.iter().zip(filled_row.iter()) {
+                match d.digit() {
+                    Some(d) if clue.accept(d) => digits.push(d),
+                    _ => return false,
+                }
+            }
+            if digits.len() > 1 && digits[0] == 0 {
+                return false;
+            }
+            values.push(digits);
+        }
+        let cells = rows
+            .iter()
+            .zip(&filled)
+            .flat_map(|(r, f)| r.iter().zip(f.iter()));
+        if !letters_consistent(cells.map(|(&clue, &d)| (clue, d))) {
+            return false;
+        }
+        let (divisor, dividend) = (&values[0], &values[1]);
+        if divisor[0] == 0 || dividend[0] == 0 {
+            return false;
+        }
+        let first = solution.steps[0].column;
+        if !less(&dividend[..first], divisor) {
+            return false;
+        }
+        let mut minuend = dividend[..=first].to_vec();
+        for (i, step) in solution.steps.iter().enumerate() {
+            let (product, remainder) = (&values[3 + 2 * i], &values[4 + 2 * i]);
+            let q = match solution.quotient_digit(step.column) {
+                Some(Digit::Fix(q)) if q > 0 => q,
+                _ => return false,
+            };
+            let k = solution.brought_down(i);
+            let high = &remainder[..remainder.len() - k];
+            if mul_digit(divisor, q) != strip(product)
+                || sub(&minuend, product).as_deref() != Some(strip(high))
+                || !less(high, divisor)
+            {
+                return false;
+            }
+            let columns = step.column + 1..=solution.remainder_column(i);
+            if remainder[remainder.len() - k..] != dividend[columns.clone()] {
+                return false;
+            }
+            // 下ろしても除数に届かない桁の商は 0
+            for (j, c) in columns.enumerate() {
+                if c == solution.remainder_column(i) && i + 1 < solution.steps.len() {
+                    break;
+                }
+                let prefix = &remainder[..remainder.len() - k + j + 1];
+                if solution.quotient_digit(c).and_then(Digit::digit) != Some(0)
+                    || !less(prefix, divisor)
+                {
+                    return false;
+                }
+            }
+            minuend = remainder.clone();
+        }
+        true
+    }
+}
+
+impl Division {
+    // 除数, 被除数, 商, 部分積と余りの順に全行
+    fn rows(&self) -> Vec<&[Digit]> {
+        let mut rows = vec![&self.divisor[..], &self.dividend[..], &self.quotient[..]];
+        for step in &self.steps {
+            rows.push(&step.product[..]);
+            rows.push(&step.remainder[..]);
+        }
+        rows
+    }
+}
+
+// 数として a < b か
+fn less(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (strip(a), strip(b));
+    (a.len(), a) < (b.len(), b)
+}
+
+fn mul_digit(a: &[u8], d: u8) -> Vec<u8> {
+    let mut prod = Vec::new();
+    let mut carry = 0;
+    for &x in a.iter().rev() {
+        let e = x * d + carry;
+        prod.push(e % 10);
+        carry = e / 10;
+    }
+    prod.push(carry);
+    prod.reverse();
+    strip(&prod).to_vec()
+}
+
+// a - b (負になるときは `None`)
+fn sub(a: &[u8], b: &[u8]) -> Option<Vec<u8>> {
+    let mut diff = Vec::new();
+    let mut borrow = 0;
+    for k in 0..a.len().max(b.len()) {
+        let digit = |row: &[u8]| {
+            if k < row.len() {
+                row[row.len() - k - 1]
+            } else {
+                0
+            }
+        };
+        let (x, y) = (digit(a), digit(b) + borrow);
+        if x >= y {
+            diff.push(x - y);
+            borrow = 0;
+        } else {
+            diff.push(x + 10 - y);
+            borrow = 1;
+        }
+    }
+    if borrow > 0 {
+        return None;
+    }
+    diff.reverse();
+    Some(strip(&diff).to_vec())
+}
+
+fn digit_at(store: &Store, row: &[Var], k: usize) -> Option<u8> {
+    if k < row.len() {
+        store.value(row[row.len() - k - 1])
+    } else {
+        Some(0)
+    }
+}
+
+// `minuend - subtrahend = difference` (どれも右揃え)
+pub(super) struct Difference {
+    pub(super) minuend: Vec<Var>,
+    pub(super) subtrahend: Vec<Var>,
+    pub(super) difference: Vec<Var>,
+}
+
+impl Propagator for Difference {
+    fn watches(&self) -> Vec<Var> {
+        self.minuend
+            .iter()
+            .chain(&self.subtrahend)
+            .copied()
+            .collect()
+    }
+
+    // 下の位から、引かれる数と引く数が決まっている位まで引く
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let columns = self.minuend.len().max(self.subtrahend.len());
+        let mut borrow = 0;
+        for k in 0..columns {
+            let (m, s) = match (
+                digit_at(store, &self.minuend, k),
+                digit_at(store, &self.subtrahend, k),
+            ) {
+                (Some(m), Some(s)) => (m, s + borrow),
+                _ => return Ok(()),
+            };
+            let d = if m >= s {
+                borrow = 0;
+                m - s
+            } else {
+                borrow = 1;
+                m + 10 - s
+            };
+            if k < self.difference.len() {
+                let len = self.difference.len();
+                store.assign(self.difference[len - k - 1], d)?;
+            } else if d != 0 {
+                return Err(Conflict);
+            }
+        }
+        if borrow > 0 {
+            return Err(Conflict);
+        }
+        for &v in self.difference.iter().rev().skip(columns) {
+            store.assign(v, 0)?;
+        }
+        Ok(())
+    }
+}
+
+// 数として `lhs < rhs`
+struct Less {
+    lhs: Vec<Var>,
+    rhs: Vec<Var>,
+}
+
+impl Propagator for Less {
+    fn watches(&self) -> Vec<Var> {
+        self.lhs.iter().chain(&self.rhs).copied().collect()
+    }
+
+    // すべて決まったら比べる
+    fn propagate(&self, store: &mut Store) -> Result<(), Conflict> {
+        let values = |row: &[Var]| {
+            row.iter()
+                .map(|&v| store.value(v))
+                .collect::<Option<Vec<_>>>()
+        };
+        match (values(&self.lhs), values(&self.rhs)) {
+            (Some(lhs), Some(rhs)) if !less(&lhs, &rhs) => Err(Conflict),
+            _ => Ok(()),
+        }
+    }
+}
+
+// 割り算の虫食算の各マスを変数にした制約充足問題
+struct Model {
+    problem: Problem,
+    brancher: Sequence,
+    divisor: Vec<Var>,
+    dividend: Vec<Var>,
+    quotient: Vec<Var>,
+    // 各ステップの部分積の右端の列, 部分積, 余り
+    steps: Vec<(usize, Vec<Var>, Vec<Var>)>,
+}
+
+// 先頭の桁は (1 桁の行でなければ) 0 でない
+fn leading(row: &[Digit], i: usize) -> u8 {
+    u8::from(i == 0 && row.len() > 1)
+}
+
+impl Model {
+    fn new(division: &Division) -> Self {
+        let mut problem = Problem::new();
+        let len = division.dividend.len();
+
+        let divisor = division
+            .divisor
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| problem.new_var(d.domain(u8::from(i == 0))))
+            .collect::<Vec<_>>();
+
+        // 被除数の桁は、それを下ろした余りの行のマスでもある
+        let mut dividend_domains = division
+            .dividend
+            .iter()
+            .enumerate()
+            .map(|(i, &d)| d.domain(u8::from(i == 0)))
+            .collect::<Vec<_>>();
+        for (i, step) in division.steps.iter().enumerate() {
+            let k = division.brought_down(i);
+            let remainder = &step.remainder;
+            for j in 0..k {
+                let p = remainder.len() - k + j;
+                let c = step.column + 1 + j;
+                dividend_domains[c] =
+                    dividend_domains[c].intersect(remainder[p].domain(leading(remainder, p)));
+            }
+        }
+        let dividend = dividend_domains
+            .into_iter()
+            .map(|d| problem.new_var(d))
+            .collect::<Vec<_>>();
+
+        let quotient = (0..division.quotient.len())
+            .map(|i| {
+                let c = len - division.quotient.len() + i;
+                let d = division.quotient[i];
+                if division.steps.iter().any(|step| step.column == c) {
+                    problem.new_var(d.domain(1))
+                } else {
+                    problem.new_var(d.domain(0).intersect(Domain::singleton(0)))
+                }
+            })
+            .collect::<Vec<_>>();
+        let quotient_var = |c: usize| quotient[c + quotient.len() - len];
+
+        let mut steps = Vec::new();
+        for (i, step) in division.steps.iter().enumerate() {
+            let product = step
+                .product
+                .iter()
+                .enumerate()
+                .map(|(j, &d)| problem.new_var(d.domain(u8::from(j == 0))))
+                .collect::<Vec<_>>();
+            let k = division.brought_down(i);
+            let high = step.remainder.len() - k;
+            let mut remainder = step.remainder[..high]
+                .iter()
+                .enumerate()
+                .map(|(j, &d)| problem.new_var(d.domain(leading(&step.remainder, j))))
+                .collect::<Vec<_>>();
+            remainder.extend_from_slice(&dividend[step.column + 1..=division.remainder_column(i)]);
+            steps.push((step.column, product, remainder));
+        }
+
+        let mut cells = Vec::new();
+        for (row, vars) in division.rows().into_iter().zip(
+            [&divisor, &dividend, &quotient].into_iter().chain(
+                steps
+                    .iter()
+                    .flat_map(|(_, product, remainder)| [product, remainder]),
+            ),
+        ) {
+            cells.extend(row.iter().copied().zip(vars.iter().copied()));
+        }
+        if let Some(letters) = Letters::new(cells) {
+            problem.add_propagator(letters);
+        }
+
+        let first = division.steps[0].column;
+        if first > 0 {
+            problem.add_propagator(Less {
+                lhs: dividend[..first].to_vec(),
+                rhs: divisor.clone(),
+            });
+        }
+        for (i, step) in division.steps.iter().enumerate() {
+            let (_, product, remainder) = &steps[i];
+            let minuend = if i == 0 {
+                dividend[..=first].to_vec()
+            } else {
+                steps[i - 1].2.clone()
+            };
+            let k = division.brought_down(i);
+            let high = remainder[..remainder.len() - k].to_vec();
+            problem.add_propagator(PartialProduct {
+                multiplicand: divisor.clone(),
+                digit: quotient_var(step.column),
+                row: product.clone(),
+            });
+            problem.add_propagator(Difference {
+                minuend,
+                subtrahend: product.clone(),
+                difference: high.clone(),
+            });
+            if !high.is_empty() {
+                problem.add_propagator(Less {
+                    lhs: high,
+                    rhs: divisor.clone(),
+                });
+            }
+            // 商が 0 の列では、下ろしても除数に届かない
+            let last = i + 1 == division.steps.len();
+            for j in 0..k {
+                if j + 1 == k && !last {
+                    break;
+                }
+                problem.add_propagator(Less {
+                    lhs: remainder[..remainder.len() - k + j + 1].to_vec(),
+                    rhs: divisor.clone(),
+                });
+            }
+        }
+
+        // 除数を下の桁から決め、その桁に数字のある部分積の商の桁も決める。
+        // 残りは商の桁と、引かれる数の新しく現れる桁を上のステップから順に決める
+        let mut order = Vec::new();
+        for p in 0..divisor.len() {
+            order.push(divisor[divisor.len() - p - 1]);
+            for step in &division.steps {
+                let product = &step.product;
+                if p < product.len() && !product[product.len() - p - 1].is_any() {
+                    order.push(quotient_var(step.column));
+                }
+            }
+        }
+        for (i, step) in division.steps.iter().enumerate() {
+            order.push(quotient_var(step.column));
+            let start = if i == 0 {
+                0
+            } else {
+                division.steps[i - 1].column + 1
+            };
+            order.extend(dividend[start..=step.column].iter().rev());
+        }
+        let last = division.steps[division.steps.len() - 1].column;
+        order.extend(dividend[last + 1..].iter().rev());
+
+        Self {
+            problem,
+            brancher: Sequence(order),
+            divisor,
+            dividend,
+            quotient,
+            steps,
+        }
+    }
+
+    // 解の値を埋めた割り算
+    fn fill(&self, values: &[u8]) -> Division {
+        let row = |vars: &[Var]| {
+            vars.iter()
+                .map(|v| Digit::Fix(values[v.index()]))
+                .collect::<Vec<_>>()
+        };
+        Division {
+            divisor: row(&self.divisor),
+            dividend: row(&self.dividend),
+            quotient: row(&self.quotient),
+            steps: self
+                .steps
+                .iter()
+                .map(|(column, product, remainder)| Step {
+                    column: *column,
+                    product: row(product),
+                    remainder: row(remainder),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Division {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let digits = |row: &[Digit]| row.iter().map(|d| d.to_string()).collect::<String>();
+        // 被除数の列 c の右端までの幅
+        let offset = self.divisor.len() + 1;
+        let width = |column: usize| offset + column + 1;
+        let last = self.dividend.len() - 1;
+        writeln!(
+            f,
+            "{digits:>width$}",
+            digits = digits(&self.quotient),
+            width = width(last)
+        )?;
+        writeln!(
+            f,
+            "{dashes:>width$}",
+            dashes = "-".repeat(self.dividend.len() + 1),
+            width = width(last)
+        )?;
+        write!(f, "{}){}", digits(&self.divisor), digits(&self.dividend))?;
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "{digits:>width$}",
+                digits = digits(&step.product),
+                width = width(step.column)
+            )?;
+            writeln!(
+                f,
+                "{dashes:>width$}",
+                dashes = "-".repeat(step.product.len()),
+                width = width(step.column)
+            )?;
+            write!(
+                f,
+                "{digits:>width$}",
+                digits = digits(&step.remainder),
+                width = width(self.remainder_column(i))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 次のような形式の文字列から割り算の虫食算を作る
+///
+/// ```text
+///     ***
+///   -----
+/// **)****
+///    **
+///    --
+///    ***
+///    ***
+///    ---
+///      0
+/// ```
+///
+/// 1 行目が商、2 行目が `除数)被除数` (`)` の代わりに `÷` でもよい) で、そのあとに部分積と余りの行が交互に続く。
+/// 各行の位置は右端の列で合わせる。線 (`-` や `=` だけ) や `#` で始まる注釈の行は読み飛ばす。
+impl FromStr for Division {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // (左端の列, 行の文字)
+        let lines = s
+            .lines()
+            .map(|line| {
+                let body = line.trim();
+                (line.len() - line.trim_start().len(), body)
+            })
+            .filter(|(_, body)| !is_ignored(body))
+            .collect::<Vec<_>>();
+        if lines.len() < 4 {
+            return Err(MushikuiError::TooFewRows(lines.len()));
+        }
+        let row = |body: &str, index: usize| -> Result<Vec<Digit>, MushikuiError> {
+            let row = body
+                .chars()
+                .map(Digit::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            match row.first() {
+                None => Err(MushikuiError::MissingDivisor),
+                Some(Digit::Fix(0)) if row.len() > 1 || index < 2 => {
+                    Err(MushikuiError::LeadingZero { row: index })
+                }
+                Some(_) => Ok(row),
+            }
+        };
+
+        let (start, body) = lines[1];
+        let (divisor, dividend) = body
+            .split_once(&[')', '÷'][..])
+            .ok_or(MushikuiError::MissingDivisor)?;
+        let dividend_start = start + body.chars().count() - dividend.trim_start().chars().count();
+        let (divisor, dividend) = (row(divisor.trim(), 1)?, row(dividend.trim(), 1)?);
+        // 右端の列 (被除数の左端を 0 とする)
+        let len = dividend.len();
+        let column = |index: usize| {
+            let (start, body) = lines[index];
+            (start + body.chars().count())
+                .checked_sub(dividend_start + 1)
+                .filter(|&c| c < len)
+                .ok_or(MushikuiError::MisalignedRow { row: index })
+        };
+
+        let quotient = row(lines[0].1, 0)?;
+        if column(0)? != dividend.len() - 1 || quotient.len() > dividend.len() {
+            return Err(MushikuiError::MisalignedRow { row: 0 });
+        }
+        if lines.len() % 2 != 0 {
+            return Err(MushikuiError::MisalignedRow {
+                row: lines.len() - 1,
+            });
+        }
+        let mut steps = Vec::new();
+        for index in (2..lines.len()).step_by(2) {
+            let column = column(index)?;
+            let product = row(lines[index].1, index)?;
+            let remainder = row(lines[index + 1].1, index + 1)?;
+            let previous = steps.last().map(|step: &Step| step.column);
+            let misaligned = match previous {
+                Some(previous) => column <= previous,
+                None => column != dividend.len() - quotient.len(),
+            };
+            if misaligned {
+                return Err(MushikuiError::MisalignedRow { row: index });
+            }
+            if product.len() != divisor.len() && product.len() != divisor.len() + 1 {
+                return Err(MushikuiError::InconsistentRowLengths);
+            }
+            steps.push(Step {
+                column,
+                product,
+                remainder,
+            });
+        }
+        let division = Division {
+            divisor,
+            dividend,
+            quotient,
+            steps,
+        };
+        for i in 0..division.steps.len() {
+            let index = 3 + 2 * i;
+            if column(index)? != division.remainder_column(i)
+                || division.steps[i].remainder.len() < division.brought_down(i)
+            {
+                return Err(MushikuiError::MisalignedRow { row: index });
+            }
+        }
+        Ok(division)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::addition::tests::{check_counts, numbers};
+    use super::*;
+
+    fn to_digits(n: u32) -> Vec<u8> {
+        n.to_string().bytes().map(|b| b - b'0').collect()
+    }
+
+    fn value(digits: &[u8]) -> u32 {
+        digits.iter().fold(0, |v, &d| v * 10 + u32::from(d))
+    }
+
+    // `divisor` で `dividend` を割る筆算 (商が 0 なら `None`)
+    //
+    // 商の 0 でない桁ごとに部分積を書き、引いた結果に次の部分積までの桁を下ろして余りの行にする。
+    fn long_division(divisor: u32, dividend: u32) -> Option<Division> {
+        let fix = |n: u32| to_digits(n).into_iter().map(Digit::Fix).collect::<Vec<_>>();
+        let quotient = to_digits(dividend / divisor);
+        if quotient == [0] {
+            return None;
+        }
+        let digits = to_digits(dividend);
+        let offset = digits.len() - quotient.len();
+        let columns = (offset..digits.len())
+            .filter(|&c| quotient[c - offset] > 0)
+            .collect::<Vec<_>>();
+        let mut minuend = value(&digits[..=columns[0]]);
+        let mut steps = Vec::new();
+        for (i, &column) in columns.iter().enumerate() {
+            let product = divisor * u32::from(quotient[column - offset]);
+            let next = columns.get(i + 1).copied().unwrap_or(digits.len() - 1);
+            let remainder = (minuend - product) * 10u32.pow((next - column) as u32)
+                + value(&digits[column + 1..=next]);
+            steps.push(Step {
+                column,
+                product: fix(product),
+                remainder: fix(remainder),
+            });
+            minuend = remainder;
+        }
+        Some(Division {
+            divisor: fix(divisor),
+            dividend: fix(dividend),
+            quotient: quotient.into_iter().map(Digit::Fix).collect(),
+            steps,
+        })
+    }
+
+    // 除数と被除数の組をすべて試して、筆算が `puzzle` に合うものを数える
+    fn count_bruteforce(puzzle: &Division) -> usize {
+        let mut count = 0;
+        // 0 では割れない
+        for divisor in numbers(puzzle.divisor.len()).filter(|&d| d > 0) {
+            for dividend in numbers(puzzle.dividend.len()) {
+                if let Some(division) = long_division(divisor, dividend) {
+                    if puzzle.verify(&division) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn counts_match_bruteforce() {
+        check_counts(
+            &[
+                // 1 桁の除数
+                ("   **\n*)***\n   *\n   **\n   **\n    *\n", 230),
+                ("   *\n*)**\n  **\n   *\n", 336),
+                // 2 桁の除数
+                ("     *\n**)***\n   ***\n    **\n", 30799),
+            ],
+            count_bruteforce,
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{Digit, Mushikui, SolveOptions};
+use crate::rng::Rng;
+
+// 最初に虫食いにするマスの割合
+const HIDE: f64 = 0.8;
+
+impl Mushikui {
+    /// 被乗数 `multiplicand_len` 桁, 乗数 `multiplier_len` 桁の、解がただ 1 つの虫食算を作る
+    ///
+    /// 掛け算を乱数で選んで大半のマスを隠し、解が 2 つ以上あるあいだは、
+    /// 見つかった解どうしで数字の違うマスを 1 つずつ見せていく。
+    /// 最後に [`Mushikui::minimize`] で余分な数字を隠す。
+    ///
+    /// # Panics
+    ///
+    /// 桁数が `0` のとき
+    pub fn generate(multiplicand_len: usize, multiplier_len: usize, rng: &mut Rng) -> Mushikui {
+        assert!(multiplicand_len > 0 && multiplier_len > 0);
+        let answer = random_multiplication(multiplicand_len, multiplier_len, rng);
+        let mut puzzle = answer.clone();
+        for row in puzzle.rows_mut() {
+            for cell in row.iter_mut() {
+                if rng.chance(HIDE) {
+                    *cell = Digit::Any;
+                }
+            }
+        }
+        let options = SolveOptions {
+            max_solutions: Some(2),
+            ..SolveOptions::default()
+        };
+        loop {
+            let solutions = puzzle.solve_with_options(&options).into_solutions();
+            if solutions.len() < 2 {
+                return puzzle.minimize(rng).unwrap_or(puzzle);
+            }
+            // 2 つの解の違うマスに答えの数字を入れれば、少なくとも一方は解でなくなる
+            let mut differ = Vec::new();
+            for (r, (a, b)) in solutions[0]
+                .rows()
+                .iter()
+                .zip(solutions[1].rows())
+                .enumerate()
+            {
+                for (c, (x, y)) in a.iter().zip(b).enumerate() {
+                    if x.digit() != y.digit() {
+                        differ.push((r, c));
+                    }
+                }
+            }
+            let (r, c) = differ[rng.below(differ.len())];
+            let digit = answer.rows()[r][c];
+            puzzle.rows_mut()[r][c] = digit;
+        }
+    }
+
+    /// 解がただ 1 つのまま、数字のマスをできるだけ虫食いにする
+    ///
+    /// 数字のマスを乱数で決めた順に 1 つずつ隠し、解が 1 つでなくなるなら戻す。
+    /// 結果の数字はどれを隠しても解が 2 つ以上になる (局所的に最小)。
+    /// 解がただ 1 つでなければ `None` を返す。
+    pub fn minimize(&self, rng: &mut Rng) -> Option<Mushikui> {
+        if !self.is_unique() {
+            return None;
+        }
+        let mut puzzle = self.clone();
+        let mut clues = Vec::new();
+        for (r, row) in self.rows().iter().enumerate() {
+            for (c, d) in row.iter().enumerate() {
+                if d.digit().is_some() {
+                    clues.push((r, c));
+                }
+            }
+        }
+        rng.shuffle(&mut clues);
+        for (r, c) in clues {
+            let digit = core::mem::replace(&mut puzzle.rows_mut()[r][c], Digit::Any);
+            if !puzzle.is_unique() {
+                puzzle.rows_mut()[r][c] = digit;
+            }
+        }
+        Some(puzzle)
+    }
+
+    // 被乗数, 乗数, 部分積, 積の順に全行
+    fn rows_mut(&mut self) -> Vec<&mut Vec<Digit>> {
+        let mut rows = vec![&mut self.multiplicand, &mut self.multiplier];
+        rows.extend(self.partial_product.iter_mut());
+        rows.push(&mut self.product);
+        rows
+    }
+}
+
+// 数字がすべて決まった掛け算 (乗数の桁は 0 でない)
+fn random_multiplication(
+    multiplicand_len: usize,
+    multiplier_len: usize,
+    rng: &mut Rng,
+) -> Mushikui {
+    let row = |len: usize, lo: u8, rng: &mut Rng| {
+        (0..len)
+            .map(|i| Digit::Fix(rng.range(if i == 0 { 1 } else { lo }, 9)))
+            .collect::<Vec<_>>()
+    };
+    let multiplicand = row(multiplicand_len, 0, rng);
+    let multiplier = row(multiplier_len, 1, rng);
+    let mut mushikui = Mushikui {
+        multiplicand,
+        multiplier,
+        partial_product: Vec::new(),
+        product: Vec::new(),
+    };
+    mushikui.partial_product = mushikui
+        .multiplier
+        .iter()
+        .rev()
+        .map(|d| {
+            let part = mushikui.calculate_partial_product(d.digit().unwrap());
+            part.into_iter().map(Digit::Fix).collect()
+        })
+        .collect();
+    mushikui.product = mushikui
+        .calculate_product()
+        .into_iter()
+        .map(Digit::Fix)
+        .collect();
+    mushikui
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_puzzles_have_a_unique_solution() {
+        for seed in 0..5 {
+            let mut rng = Rng::new(seed);
+            let puzzle = Mushikui::generate(3, 2, &mut rng);
+            assert_eq!(puzzle.count_solutions(), 1, "{}", puzzle);
+            assert_eq!(puzzle.multiplicand.len(), 3);
+            assert_eq!(puzzle.multiplier.len(), 2);
+        }
+    }
+
+    #[test]
+    fn minimized_clues_are_all_needed() {
+        let mut rng = Rng::new(1);
+        let puzzle = Mushikui::generate(2, 2, &mut rng);
+        let mut hidden = puzzle.clone();
+        for r in 0..puzzle.rows().len() {
+            for c in 0..puzzle.rows()[r].len() {
+                if puzzle.rows()[r][c].digit().is_some() {
+                    hidden.rows_mut()[r][c] = Digit::Any;
+                    assert!(hidden.count_solutions() > 1, "{}", hidden);
+                    hidden.rows_mut()[r][c] = puzzle.rows()[r][c];
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::backtrack::Domain;
+
+use super::{Digit, Mushikui, SolveOptions};
+
+impl Mushikui {
+    // 右端をそろえる各行と、その行を左にずらす桁数 (省いた部分積の行は除く)
+    fn layout(&self) -> Vec<(&[Digit], usize)> {
+        let mut rows = vec![(&self.multiplicand[..], 0), (&self.multiplier[..], 0)];
+        for (i, part) in self.partial_product.iter().enumerate() {
+            if !part.is_empty() {
+                rows.push((&part[..], i));
+            }
+        }
+        if !self.product.is_empty() {
+            rows.push((&self.product[..], 0));
+        }
+        rows
+    }
+
+    /// LaTeX の `array` 環境で書く (数式モードの中に置く)
+    ///
+    /// 1 桁を 1 列にして、乗数の行の左に `\times`、虫食いのマスに `\square` を置き、
+    /// 乗数の下と積の上に `\hline` で線を引く。
+    pub fn render_latex(&self) -> String {
+        let rows = self.layout();
+        let width = rows
+            .iter()
+            .map(|(row, shift)| row.len() + shift)
+            .max()
+            .unwrap_or(0);
+        let mut s = format!("\\begin{{array}}{{c*{{{}}}{{c}}}}\n", width);
+        for (r, &(row, shift)) in rows.iter().enumerate() {
+            let mut cells = vec![String::new(); width + 1];
+            if r == 1 {
+                cells[0] = "\\times".to_string();
+            }
+            for (i, d) in row.iter().enumerate() {
+                cells[width + 1 - shift - row.len() + i] = match d {
+                    Digit::Any => "\\square".to_string(),
+                    d => d.to_string(),
+                };
+            }
+            s.push_str(&cells.join(" & "));
+            if r + 1 < rows.len() {
+                s.push_str(" \\\\");
+            }
+            if r == 1 || (r + 2 == rows.len() && !self.product.is_empty()) {
+                s.push_str(" \\hline");
+            }
+            s.push('\n');
+        }
+        s.push_str("\\end{array}");
+        s
+    }
+
+    /// 罫線の文字 (`─`) と `×` を使って書く
+    ///
+    /// 虫食いのマスは `□` にする。
+    pub fn render_unicode(&self) -> String {
+        let rows = self.layout();
+        let width = rows
+            .iter()
+            .map(|(row, shift)| row.len() + shift)
+            .chain([self.multiplier.len() + 2])
+            .max()
+            .unwrap_or(0);
+        let rule = "─".repeat(width);
+        let mut lines = Vec::new();
+        for (r, &(row, shift)) in rows.iter().enumerate() {
+            let digits = row
+                .iter()
+                .map(|d| match d {
+                    Digit::Any => '□'.to_string(),
+                    d => d.to_string(),
+                })
+                .collect::<String>();
+            let pad = width - shift - row.len();
+            let line = if r == 1 {
+                format!("×{}{}", " ".repeat(pad - 1), digits)
+            } else {
+                format!("{}{}", " ".repeat(pad), digits)
+            };
+            lines.push(line);
+            if r == 1 || (r + 2 == rows.len() && !self.product.is_empty()) {
+                lines.push(rule.clone());
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// `options` の条件のもとで探索の前の推論で絞った各マスの候補を、数独の候補の書き込みのように書く
+    ///
+    /// 数字の決まったマスはその数字、決まっていないマスは入りうる数字を小さい順に並べる。
+    /// マスは空白で区切った同じ幅の列にする。矛盾が見つかれば `None` を返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::mushikui::{Mushikui, SolveOptions};
+    ///
+    /// let puzzle: Mushikui = "  9\n  *\n 27\n 27".parse().unwrap();
+    /// let candidates = puzzle.render_candidates(&SolveOptions::default()).unwrap();
+    /// assert_eq!(candidates, "  9\n  3\n---\n2 7\n---\n2 7");
+    /// ```
+    pub fn render_candidates(&self, options: &SolveOptions) -> Option<String> {
+        let candidates = self.candidates(options)?;
+        let parts = self.partial_product.len();
+        // 右端をそろえる各行と、その行を左にずらす桁数 (省いた部分積の行は除く)
+        let rows = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.is_empty())
+            .map(|(r, row)| (r, &row[..], if r >= 2 && r < parts + 2 { r - 2 } else { 0 }))
+            .collect::<Vec<_>>();
+        let text = |d: Domain| d.iter().map(|v| v.to_string()).collect::<String>();
+        let cell_width = rows
+            .iter()
+            .flat_map(|(_, row, _)| row.iter())
+            .map(|&d| d.len())
+            .max()
+            .unwrap_or(1);
+        let columns = rows
+            .iter()
+            .map(|(_, row, shift)| row.len() + shift)
+            .max()
+            .unwrap_or(0);
+        let rule = "-".repeat(columns * (cell_width + 1) - 1);
+        let mut lines = Vec::new();
+        for &(r, row, shift) in &rows {
+            let pad = columns - shift - row.len();
+            let cells = (0..pad)
+                .map(|_| String::new())
+                .chain(row.iter().map(|&d| text(d)))
+                .map(|cell| format!("{:>width$}", cell, width = cell_width))
+                .collect::<Vec<_>>();
+            if r == parts + 2 {
+                lines.push(rule.clone());
+            }
+            lines.push(cells.join(" ").trim_end().to_string());
+            if r == 1 {
+                lines.push(rule.clone());
+            }
+        }
+        Some(lines.join("\n"))
+    }
+}
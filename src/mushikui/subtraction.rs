@@ -0,0 +1,167 @@
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use core::ops::ControlFlow;
+use core::str::FromStr;
+
+use super::addition::{add, digits, fmt_rows, parse_rows, Model};
+use super::{letters_consistent, restrict, strip, Digit, MushikuiError, SolveOptions};
+use crate::backtrack;
+use crate::puzzle::{Puzzle, SearchStats, Termination};
+
+/// 引き算の虫食算
+///
+/// 引かれる数, 引く数, 差を持つ。`差 + 引く数 = 引かれる数` の足し算として解く。
+///
+/// ```text
+///   ***
+/// - *8*
+/// -----
+///    *5
+/// ```
+#[derive(Debug, Clone)]
+pub struct SubtractionMushikui {
+    minuend: Vec<Digit>,
+    subtrahend: Vec<Digit>,
+    difference: Vec<Digit>,
+}
+
+impl Puzzle for SubtractionMushikui {
+    type Solution = SubtractionMushikui;
+    type Options = SolveOptions;
+
+    fn solve_with<F>(
+        &self,
+        options: &Self::Options,
+        mut on_solution: F,
+    ) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>,
+    {
+        let mut model = Model::new(
+            &[self.subtrahend.clone(), self.difference.clone()],
+            &self.minuend,
+            options.allow_leading_zero,
+        );
+        let rows = [
+            (&self.minuend[..], &model.sum[..]),
+            (&self.subtrahend[..], &model.rows[0][..]),
+            (&self.difference[..], &model.rows[1][..]),
+        ];
+        restrict(&mut model.problem, rows, options);
+        model.problem.solve_with(
+            &model.brancher,
+            &backtrack::Options {
+                max_solutions: options.max_solutions,
+                deadline: options.deadline,
+                cancel: options.cancel.clone(),
+                on_progress: options.on_progress.clone(),
+            },
+            |values| {
+                let (mut rows, minuend) = model.fill(values);
+                let difference = rows.pop().unwrap();
+                let subtrahend = rows.pop().unwrap();
+                on_solution(SubtractionMushikui {
+                    minuend,
+                    subtrahend,
+                    difference,
+                })
+            },
+        )
+    }
+
+    fn verify(&self, solution: &Self::Solution) -> bool {
+        let cells = [
+            (&self.minuend, &solution.minuend),
+            (&self.subtrahend, &solution.subtrahend),
+            (&self.difference, &solution.difference),
+        ];
+        if !letters_consistent(
+            cells
+                .into_iter()
+                .flat_map(|(r, f)| r.iter().copied().zip(f.iter().copied())),
+        ) {
+            return false;
+        }
+        match (
+            digits(&self.minuend, &solution.minuend),
+            digits(&self.subtrahend, &solution.subtrahend),
+            digits(&self.difference, &solution.difference),
+        ) {
+            (Some(minuend), Some(subtrahend), Some(difference)) => {
+                add(&[subtrahend, difference]) == strip(&minuend)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for SubtractionMushikui {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        fmt_rows(
+            f,
+            '-',
+            &[self.minuend.clone(), self.subtrahend.clone()],
+            &self.difference,
+        )
+    }
+}
+
+/// 次のような形式の文字列から引き算の虫食算を作る
+///
+/// ```text
+///   ***
+/// - *8*
+/// -----
+///    *5
+/// ```
+///
+/// 引かれる数, 引く数, 差の 3 行を右揃えで読む。行頭の `-` と、線 (`-` や `=` だけ) や `#` で始まる注釈の行は読み飛ばす。
+impl FromStr for SubtractionMushikui {
+    type Err = MushikuiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = parse_rows(s, '-')?;
+        match rows.len() {
+            0..=2 => return Err(MushikuiError::TooFewRows(rows.len())),
+            3 => {}
+            n => return Err(MushikuiError::TooManyRows(n)),
+        }
+        let [minuend, subtrahend, difference]: [Vec<Digit>; 3] = rows.try_into().unwrap();
+        if subtrahend.len() > minuend.len() || difference.len() > minuend.len() {
+            return Err(MushikuiError::InconsistentRowLengths);
+        }
+        Ok(SubtractionMushikui {
+            minuend,
+            subtrahend,
+            difference,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::addition::tests::{check_counts, numbers};
+    use super::*;
+
+    // 引かれる数と引く数の組をすべて試して、差が差の行の桁数になるものを数える
+    fn count_bruteforce(puzzle: &SubtractionMushikui) -> usize {
+        let differences = numbers(puzzle.difference.len());
+        numbers(puzzle.minuend.len())
+            .flat_map(|m| numbers(puzzle.subtrahend.len()).filter_map(move |s| m.checked_sub(s)))
+            .filter(|d| differences.contains(d))
+            .count()
+    }
+
+    #[test]
+    fn counts_match_bruteforce() {
+        check_counts(
+            &[
+                ("**\n-**\n---\n**\n", 3240),
+                ("**\n-**\n---\n *\n", 855),
+                ("***\n- **\n----\n  *\n", 45),
+                ("***\n- **\n----\n ***\n", 76095),
+            ],
+            count_bruteforce,
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! パズル共通のインターフェース
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::ops::ControlFlow;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+pub use std::time::Instant;
+
+/// 締め切りの時刻
+///
+/// `std` なしでは時計がないので値を作れず、締め切りは常に `None` になる。
+#[cfg(not(feature = "std"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Instant {}
+
+/// 文字列から読めて、表示できて、解けるパズル
+pub trait Puzzle: FromStr + Display {
+    /// 解の型
+    type Solution;
+
+    /// 探索の設定
+    type Options: Default;
+
+    /// 既定の設定で解を探す
+    fn solve(&self) -> Vec<Self::Solution> {
+        self.solve_with_options(&Self::Options::default())
+            .into_solutions()
+    }
+
+    /// `options` にしたがって解を探す
+    fn solve_with_options(&self, options: &Self::Options) -> SolveOutcome<Self::Solution> {
+        self.solve_with_stats(options).0
+    }
+
+    /// `options` にしたがって解を探し、探索の統計も返す
+    fn solve_with_stats(
+        &self,
+        options: &Self::Options,
+    ) -> (SolveOutcome<Self::Solution>, SearchStats) {
+        let mut solutions = Vec::new();
+        let (termination, stats) = self.solve_with(options, |solution| {
+            solutions.push(solution);
+            ControlFlow::Continue(())
+        });
+        (SolveOutcome::new(termination, solutions), stats)
+    }
+
+    /// `options` にしたがって解を探し、見つけるたびに `on_solution` を呼ぶ
+    ///
+    /// `on_solution` が [`ControlFlow::Break`] を返すと探索をやめる。
+    fn solve_with<F>(&self, options: &Self::Options, on_solution: F) -> (Termination, SearchStats)
+    where
+        F: FnMut(Self::Solution) -> ControlFlow<()>;
+
+    /// `solution` がこのパズルの解になっているか
+    fn verify(&self, solution: &Self::Solution) -> bool;
+}
+
+/// 探索の結果
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SolveOutcome<T> {
+    /// 探索を最後まで (または解の個数の上限まで) 行った
+    Complete(Vec<T>),
+    /// 時間切れか中断で探索を打ち切った。それまでに見つかった解を持つ
+    TimedOut(Vec<T>),
+}
+
+/// 探索の終わり方
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Termination {
+    /// 探索を最後まで (または解の個数の上限まで) 行った
+    Complete,
+    /// 解を受け取るコールバックが探索をやめさせた
+    Stopped,
+    /// 時間切れか中断で探索を打ち切った
+    TimedOut,
+}
+
+impl<T> SolveOutcome<T> {
+    /// 探索の終わり方と見つかった解から作る
+    pub fn new(termination: Termination, solutions: Vec<T>) -> Self {
+        match termination {
+            Termination::Complete | Termination::Stopped => SolveOutcome::Complete(solutions),
+            Termination::TimedOut => SolveOutcome::TimedOut(solutions),
+        }
+    }
+
+    /// 見つかった解
+    pub fn solutions(&self) -> &[T] {
+        match self {
+            SolveOutcome::Complete(solutions) | SolveOutcome::TimedOut(solutions) => solutions,
+        }
+    }
+
+    /// 見つかった解
+    pub fn into_solutions(self) -> Vec<T> {
+        match self {
+            SolveOutcome::Complete(solutions) | SolveOutcome::TimedOut(solutions) => solutions,
+        }
+    }
+
+    /// 各解を `f` で変換する
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> SolveOutcome<U> {
+        match self {
+            SolveOutcome::Complete(solutions) => {
+                SolveOutcome::Complete(solutions.into_iter().map(f).collect())
+            }
+            SolveOutcome::TimedOut(solutions) => {
+                SolveOutcome::TimedOut(solutions.into_iter().map(f).collect())
+            }
+        }
+    }
+
+    /// 探索を打ち切ったか
+    pub fn is_timed_out(&self) -> bool {
+        matches!(self, SolveOutcome::TimedOut(_))
+    }
+}
+
+/// 別スレッドから探索を中断するためのトークン
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// 新しいトークン
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 探索を中断させる
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// 中断させられたか
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 探索の統計
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct SearchStats {
+    /// 展開したノード数
+    pub nodes_expanded: u64,
+    /// 到達した最大の深さ
+    pub max_depth: usize,
+    /// 枝刈りした枝の数
+    pub pruned: u64,
+    /// 値を仮定して分岐したノード数 (バックトラックだけが数える)
+    pub guesses: u64,
+    /// 探索にかかった時間
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    /// 深さ `depth` の解を見つけるまでの有効分岐因子 b*
+    ///
+    /// 展開したノード数を N として、どのノードも b* 個の子を持つ深さ `depth` の木のノード数
+    /// `1 + b* + b*^2 + ... + b*^depth` が `N + 1` になる値。下界が強いほど 1 に近く、盤面の難しさにあまりよらないので、
+    /// 同じ盤面の集まりで下界やアルゴリズムを比べるのに使う。`depth` が 0 のときは `None` を返す。
+    ///
+    /// ```
+    /// use book_puzzle_algorithm_rs::puzzle::SearchStats;
+    ///
+    /// // 1 + 2 + 4 + 8 = 14 + 1
+    /// let stats = SearchStats {
+    ///     nodes_expanded: 14,
+    ///     ..SearchStats::default()
+    /// };
+    /// let b = stats.effective_branching_factor(3).unwrap();
+    /// assert!((b - 2.0).abs() < 1e-9);
+    /// ```
+    pub fn effective_branching_factor(&self, depth: usize) -> Option<f64> {
+        if depth == 0 {
+            return None;
+        }
+        let target = self.nodes_expanded as f64 + 1.0;
+        // 木のノード数は b について単調に増えるので二分法で解く (b = N + 1 なら必ず超える)
+        let nodes = |b: f64| (0..depth).fold(1.0, |sum, _| sum * b + 1.0);
+        let (mut low, mut high) = (0.0, target);
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            if nodes(mid) < target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some((low + high) / 2.0)
+    }
+}
+
+/// 探索の途中経過
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Progress {
+    /// 展開したノード数
+    pub nodes_expanded: u64,
+    /// いま展開しているノードの深さ
+    pub current_depth: usize,
+    /// これまでに見た評価値の最小値 (評価値を使わない探索では `None`)
+    pub best_estimate: Option<u32>,
+}
+
+/// `interval` ノードごとに途中経過を受け取るコールバック
+#[derive(Clone)]
+pub struct OnProgress {
+    interval: u64,
+    callback: Arc<dyn Fn(&Progress) + Send + Sync>,
+}
+
+impl OnProgress {
+    /// `interval` ノードごとに `callback` を呼ぶ
+    pub fn new(interval: u64, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Self {
+            interval: interval.max(1),
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for OnProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnProgress")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+// ノードを数えながら、締め切りと中断を数回に 1 回だけ確かめる
+pub(crate) struct Monitor {
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+    on_progress: Option<OnProgress>,
+    start: Option<Instant>,
+    stats: SearchStats,
+    best_estimate: Option<u32>,
+    interrupted: bool,
+}
+
+impl Monitor {
+    const INTERVAL: u64 = 1024;
+
+    pub(crate) fn new(
+        deadline: Option<Instant>,
+        cancel: Option<CancelToken>,
+        on_progress: Option<OnProgress>,
+    ) -> Self {
+        Self {
+            deadline,
+            cancel,
+            on_progress,
+            start: now(),
+            stats: SearchStats::default(),
+            best_estimate: None,
+            interrupted: false,
+        }
+    }
+
+    pub(crate) fn visit(&mut self, depth: usize, estimate: Option<u32>) {
+        self.stats.nodes_expanded += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        if let Some(estimate) = estimate {
+            self.best_estimate = Some(self.best_estimate.map_or(estimate, |e| e.min(estimate)));
+        }
+        if self.stats.nodes_expanded % Self::INTERVAL == 0 && !self.interrupted {
+            self.interrupted = self.deadline.map_or(false, is_past)
+                || self.cancel.as_ref().map_or(false, |c| c.is_cancelled());
+        }
+        if let Some(on_progress) = &self.on_progress {
+            if self.stats.nodes_expanded % on_progress.interval == 0 {
+                (on_progress.callback)(&Progress {
+                    nodes_expanded: self.stats.nodes_expanded,
+                    current_depth: depth,
+                    best_estimate: self.best_estimate,
+                });
+            }
+        }
+    }
+
+    pub(crate) fn prune(&mut self) {
+        self.stats.pruned += 1;
+    }
+
+    pub(crate) fn guess(&mut self) {
+        self.stats.guesses += 1;
+    }
+
+    // 別のスレッドで探索した結果を合わせる
+    #[cfg(feature = "std")]
+    pub(crate) fn merge(&mut self, termination: Termination, stats: &SearchStats) {
+        self.stats.nodes_expanded += stats.nodes_expanded;
+        self.stats.max_depth = self.stats.max_depth.max(stats.max_depth);
+        self.stats.pruned += stats.pruned;
+        self.stats.guesses += stats.guesses;
+        self.interrupted |= termination == Termination::TimedOut;
+    }
+
+    pub(crate) fn is_interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    // これまでの統計 (時間は探索を始めてから今まで)
+    pub(crate) fn stats(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.start.map_or(Duration::ZERO, elapsed_since),
+            ..self.stats
+        }
+    }
+
+    pub(crate) fn finish(self, stopped: bool) -> (Termination, SearchStats) {
+        let termination = if self.interrupted {
+            Termination::TimedOut
+        } else if stopped {
+            Termination::Stopped
+        } else {
+            Termination::Complete
+        };
+        (termination, self.stats())
+    }
+}
+
+#[cfg(feature = "std")]
+fn now() -> Option<Instant> {
+    Some(Instant::now())
+}
+
+#[cfg(not(feature = "std"))]
+fn now() -> Option<Instant> {
+    None
+}
+
+#[cfg(feature = "std")]
+fn is_past(deadline: Instant) -> bool {
+    Instant::now() >= deadline
+}
+
+#[cfg(not(feature = "std"))]
+fn is_past(deadline: Instant) -> bool {
+    match deadline {}
+}
+
+#[cfg(feature = "std")]
+fn elapsed_since(start: Instant) -> Duration {
+    start.elapsed()
+}
+
+#[cfg(not(feature = "std"))]
+fn elapsed_since(start: Instant) -> Duration {
+    match start {}
+}
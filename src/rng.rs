@@ -0,0 +1,98 @@
+//! 擬似乱数 (xorshift64)
+//!
+//! 問題の生成などで使う。同じ種からは同じ列が出る。
+
+/// xorshift64 による擬似乱数
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// 種 `seed` から作る
+    pub fn new(seed: u64) -> Self {
+        // 状態が 0 だと 0 しか出ないので、0 になったときだけ別の値にする
+        let state = (seed ^ 0x9e37_79b9_7f4a_7c15).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        Rng(if state == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            state
+        })
+    }
+
+    /// 次の 64 bit
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// `0` 以上 `n` 未満の整数
+    ///
+    /// # Panics
+    ///
+    /// `n` が `0` のとき
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0);
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// `lo` 以上 `hi` 以下の整数
+    pub fn range(&mut self, lo: u8, hi: u8) -> u8 {
+        lo + self.below(usize::from(hi - lo) + 1) as u8
+    }
+
+    /// 確率 `p` で `true`
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// 並びをランダムに入れ替える
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let (mut a, mut b, mut c) = (Rng::new(42), Rng::new(42), Rng::new(43));
+        let xs = (0..10).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let ys = (0..10).map(|_| b.next_u64()).collect::<Vec<_>>();
+        let zs = (0..10).map(|_| c.next_u64()).collect::<Vec<_>>();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut rng = Rng::new(0);
+        for n in 1..20 {
+            let mut seen = [false; 20];
+            for _ in 0..1000 {
+                let x = rng.below(n);
+                assert!(x < n);
+                seen[x] = true;
+            }
+            // 十分な回数引けば、どの値も出る
+            assert!(seen[..n].iter().all(|&s| s));
+        }
+        for _ in 0..1000 {
+            let x = rng.range(3, 7);
+            assert!((3..=7).contains(&x));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn below_zero_panics() {
+        Rng::new(0).below(0);
+    }
+}
@@ -0,0 +1,359 @@
+//! 汎用の SMA* (覚えるノードの数に上限を決めた A*)
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+use crate::ida_star::{Cost, SearchState};
+use crate::puzzle::{CancelToken, Instant, Monitor, OnProgress, SearchStats, Termination};
+
+// ゴールに着けない子の評価値
+const INFINITY: usize = usize::MAX;
+
+/// 探索の設定
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// 覚えるノードの数の上限 (2 以上)
+    ///
+    /// 手順は根から葉までのノードを覚えておくので、見つかるのは `max_nodes - 1` 手以下の手順だけ。
+    pub max_nodes: usize,
+    /// 手数の上限
+    pub max_depth: usize,
+    /// この時刻を過ぎたら探索を打ち切る
+    pub deadline: Option<Instant>,
+    /// 探索を中断するためのトークン
+    pub cancel: Option<CancelToken>,
+    /// 途中経過を受け取るコールバック
+    pub on_progress: Option<OnProgress>,
+    /// 下界にかける重み (1 以上 [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) 以下)
+    ///
+    /// 1 より大きいと評価値を `手数 + weight * 下界` にする。見つかる手順は最短手順の `weight` 倍以内の手数になる。
+    pub weight: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1 << 20,
+            max_depth: usize::MAX,
+            deadline: None,
+            cancel: None,
+            on_progress: None,
+            weight: 1.0,
+        }
+    }
+}
+
+// 覚えているノード
+struct Node<S: SearchState> {
+    state: S,
+    // 根からの手数
+    g: usize,
+    // 評価値 (子の評価値の最小値で更新する)
+    f: usize,
+    // 状態だけから決まる評価値 (親の評価値以上にしてある)
+    own: usize,
+    // 親と、親の何番目の手で来たか
+    parent: Option<(usize, usize)>,
+    // 試す手と、手ごとの子 (覚えていれば番号) と子の評価値 (まだ作っていなければ `None`)
+    moves: Vec<S::Move>,
+    children: Vec<Option<usize>>,
+    child_f: Vec<Option<usize>>,
+}
+
+impl<S: SearchState> Node<S> {
+    // 評価値と手数による並びのキー
+    fn key(&self, id: usize) -> (usize, Reverse<usize>, usize) {
+        (self.f, Reverse(self.g), id)
+    }
+
+    // 子を覚えていないか
+    fn is_leaf(&self) -> bool {
+        self.children.iter().all(Option::is_none)
+    }
+
+    // まだ作っていないか忘れた子のうち、次に作る手の番号 (作ったことのない子, 評価値の小さい子の順)
+    fn next_move(&self) -> Option<usize> {
+        (0..self.moves.len())
+            .filter(|&i| self.children[i].is_none() && self.child_f[i] != Some(INFINITY))
+            .min_by_key(|&i| self.child_f[i].map_or((false, 0), |f| (true, f)))
+    }
+
+    // 子の評価値から求めた評価値
+    fn backed_up(&self) -> usize {
+        let min = self
+            .child_f
+            .iter()
+            .map(|f| f.unwrap_or(self.own))
+            .min()
+            .unwrap_or(INFINITY);
+        self.own.max(min)
+    }
+}
+
+// 覚えているノードと、展開を待つノードと葉の集まり
+struct Memory<S: SearchState> {
+    nodes: Vec<Option<Node<S>>>,
+    free: Vec<usize>,
+    len: usize,
+    // 作る子が残っているノードとゴール (評価値の小さい順, 同じなら深い順に取り出す)
+    open: BTreeSet<(usize, Reverse<usize>, usize)>,
+    // 子を覚えていない根以外のノード (評価値の大きい順, 同じなら浅い順に捨てる)
+    leaves: BTreeSet<(usize, Reverse<usize>, usize)>,
+}
+
+impl<S: SearchState> Memory<S> {
+    fn node(&self, id: usize) -> &Node<S> {
+        self.nodes[id].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, id: usize) -> &mut Node<S> {
+        self.nodes[id].as_mut().unwrap()
+    }
+
+    fn insert(&mut self, node: Node<S>) -> usize {
+        self.len += 1;
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.update(id);
+        id
+    }
+
+    // 子が変わった `id` の評価値を更新して集まりに入れ直し、評価値が変わったら親にも伝える
+    fn update(&mut self, mut id: usize) {
+        loop {
+            let node = self.node(id);
+            let old = node.key(id);
+            let f = node.backed_up();
+            self.open.remove(&old);
+            self.leaves.remove(&old);
+            let node = self.node_mut(id);
+            let changed = node.f != f;
+            node.f = f;
+            let key = node.key(id);
+            let (expandable, leaf, parent) = (
+                node.state.is_goal() || node.next_move().is_some(),
+                node.is_leaf() && node.parent.is_some(),
+                node.parent,
+            );
+            if expandable {
+                self.open.insert(key);
+            }
+            if leaf {
+                self.leaves.insert(key);
+            }
+            match parent {
+                Some((p, i)) if changed || self.node(p).child_f[i] != Some(f) => {
+                    self.node_mut(p).child_f[i] = Some(f);
+                    id = p;
+                }
+                _ => return,
+            }
+        }
+    }
+
+    // 評価値が最も大きく、その中で最も浅い葉を捨て、評価値を親に覚えさせる
+    fn forget(&mut self) {
+        let &key = match self.leaves.iter().next_back() {
+            Some(key) => key,
+            None => return,
+        };
+        let id = key.2;
+        self.leaves.remove(&key);
+        self.open.remove(&key);
+        let node = self.nodes[id].take().unwrap();
+        self.free.push(id);
+        self.len -= 1;
+        let (p, i) = node.parent.unwrap();
+        let parent = self.node_mut(p);
+        parent.children[i] = None;
+        parent.child_f[i] = Some(node.f);
+        self.update(p);
+    }
+}
+
+/// `start` からゴールまでの最短手順を SMA* で探す
+///
+/// A* と同じように評価値の小さいノードから広げるが、覚えるノードが `options.max_nodes` を超えたら
+/// 評価値の最も大きい葉を捨て、その評価値を親に覚えさせる。捨てた葉は親の評価値が最小になったときに作り直す。
+/// A* のメモリが足りない盤面でも決まったメモリで解け、IDA* より同じノードを展開し直す回数が少ない。
+/// 状態を見分けないので、同じ状態に別の経路で着いたときは別のノードになる。
+/// 見つかった最短手順 (手の列) を返す。`start.heuristic()` は手数の下界であること。
+///
+/// ```
+/// use book_puzzle_algorithm_rs::fifteen_puzzle::Board;
+/// use book_puzzle_algorithm_rs::sma_star::{sma_star, Options};
+///
+/// let board: Board = "8 6 7 2 5 4 3 0 1".parse().unwrap();
+/// let options = Options {
+///     max_nodes: 10000,
+///     ..Options::default()
+/// };
+/// let (path, _, _) = sma_star(&board, &options);
+/// assert_eq!(path.unwrap().len(), 31);
+/// ```
+///
+/// # Panics
+///
+/// `options.weight` が 1 未満か [`MAX_WEIGHT`](crate::ida_star::MAX_WEIGHT) より大きいとき
+pub fn sma_star<S>(start: &S, options: &Options) -> (Option<Vec<S::Move>>, Termination, SearchStats)
+where
+    S: SearchState + Clone,
+{
+    let cost = Cost::new(options.weight, options.max_depth);
+    let mut monitor = Monitor::new(
+        options.deadline,
+        options.cancel.clone(),
+        options.on_progress.clone(),
+    );
+    // 根から葉までの手順を覚えられる深さ
+    let max_depth = options.max_nodes.max(2) - 1;
+    let mut memory = Memory {
+        nodes: Vec::new(),
+        free: Vec::new(),
+        len: 0,
+        open: BTreeSet::new(),
+        leaves: BTreeSet::new(),
+    };
+    let own = match cost.f(0, start.heuristic()) {
+        Some(f) => f,
+        None => {
+            let (termination, stats) = monitor.finish(false);
+            return (None, termination, stats);
+        }
+    };
+    let moves = start.moves().into_iter().collect::<Vec<_>>();
+    memory.insert(Node {
+        state: start.clone(),
+        g: 0,
+        f: own,
+        own,
+        parent: None,
+        children: moves.iter().map(|_| None).collect(),
+        child_f: moves.iter().map(|_| None).collect(),
+        moves,
+    });
+    let mut found = None;
+    while let Some(&(f, _, id)) = memory.open.iter().next() {
+        if monitor.is_interrupted() || f == INFINITY {
+            break;
+        }
+        let node = memory.node(id);
+        monitor.visit(node.g, Some(node.state.heuristic()));
+        if node.state.is_goal() {
+            found = Some(id);
+            break;
+        }
+        let i = node.next_move().unwrap();
+        let mv = node.moves[i];
+        let g = node.g + 1;
+        let mut state = node.state.clone();
+        let child_f = if !state.apply(mv) {
+            None
+        } else if !state.is_goal() && g >= max_depth {
+            // 子の先を覚えられないので、ゴールでなければ捨てる
+            monitor.prune();
+            None
+        } else {
+            cost.f(g, state.heuristic()).map(|child| child.max(f))
+        };
+        let own = match child_f {
+            Some(own) => own,
+            None => {
+                memory.node_mut(id).child_f[i] = Some(INFINITY);
+                memory.update(id);
+                continue;
+            }
+        };
+        let moves = state
+            .moves()
+            .into_iter()
+            .filter(|&next| !S::is_redundant(mv, next))
+            .collect::<Vec<_>>();
+        let child = memory.insert(Node {
+            state,
+            g,
+            f: own,
+            own,
+            parent: Some((id, i)),
+            children: moves.iter().map(|_| None).collect(),
+            child_f: moves.iter().map(|_| None).collect(),
+            moves,
+        });
+        memory.node_mut(id).children[i] = Some(child);
+        memory.node_mut(id).child_f[i] = Some(own);
+        memory.update(id);
+        while memory.len > options.max_nodes.max(2) {
+            memory.forget();
+        }
+    }
+    let path = found.map(|mut id| {
+        let mut path = Vec::new();
+        while let Some((parent, i)) = memory.node(id).parent {
+            path.push(memory.node(parent).moves[i]);
+            id = parent;
+        }
+        path.reverse();
+        path
+    });
+    let (termination, stats) = monitor.finish(false);
+    (path, termination, stats)
+}
+
+#[cfg(all(test, feature = "fifteen-puzzle"))]
+mod tests {
+    use super::*;
+    use crate::fifteen_puzzle::Board;
+
+    // 8 パズルの盤面と最短手数
+    const BOARDS: [(&str, usize); 3] = [
+        ("1 2 3 4 0 6 7 5 8", 2),
+        ("4 1 3 7 2 6 0 5 8", 6),
+        ("8 1 3 4 0 2 7 6 5", 14),
+    ];
+
+    fn solve(board: &Board, max_nodes: usize) -> Option<usize> {
+        let options = Options {
+            max_nodes,
+            ..Options::default()
+        };
+        let (path, termination, _) = sma_star(board, &options);
+        assert_eq!(termination, Termination::Complete);
+        let path = path?;
+        let mut solved = board.clone();
+        for &dir in &path {
+            assert!(solved.apply(dir));
+        }
+        assert!(solved.is_solved());
+        Some(path.len())
+    }
+
+    #[test]
+    fn finds_the_optimal_length_with_little_memory() {
+        for (board, depth) in BOARDS {
+            let board: Board = board.parse().unwrap();
+            // 根から葉までの `depth + 1` 個を覚えられれば足りる
+            for max_nodes in [depth + 1, depth + 2, 1 << 10] {
+                assert_eq!(solve(&board, max_nodes), Some(depth), "{}", board);
+            }
+        }
+    }
+
+    #[test]
+    fn gives_up_when_the_path_does_not_fit() {
+        for (board, depth) in BOARDS {
+            let board: Board = board.parse().unwrap();
+            for max_nodes in 2..=depth {
+                assert_eq!(solve(&board, max_nodes), None, "{} {}", board, max_nodes);
+            }
+        }
+    }
+}
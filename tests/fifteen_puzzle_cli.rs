@@ -0,0 +1,153 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// `args` を付けて `15-puzzle` を起動し、`input` を標準入力に渡す
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_15-puzzle"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    assert!(!output.status.success(), "{:?}", output);
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn input_errors_are_reported_without_usage() {
+    let output = run(&["-"], "2 1 3 4 5 6 7 8 0");
+    assert_eq!(stderr(&output), "this board cannot be solved\n");
+
+    let output = run(&["-"], "1 2 3 4 5");
+    let message = stderr(&output);
+    assert!(message.starts_with("invalid board: "));
+    assert!(!message.contains("usage:"));
+}
+
+#[test]
+fn argument_errors_are_reported_with_usage() {
+    let output = run(&["--no-such-option"], "");
+    let message = stderr(&output);
+    assert!(message.starts_with("unknown option: --no-such-option\nusage: 15-puzzle"));
+}
+
+#[test]
+fn help_prints_usage_to_stdout() {
+    for args in [&["-h"][..], &["--help"], &["generate", "--help"]] {
+        let output = run(args, "");
+        assert!(output.status.success(), "{:?}", output);
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(text.starts_with("usage: 15-puzzle "));
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn generate_checks_the_optimal_depth() {
+    let output = run(&["generate", "--size", "3x3", "--optimal-depth", "32"], "");
+    assert!(stderr(&output).starts_with(
+        "--optimal-depth 32 is too large: every 3x3 board is solved in at most 31 moves\n"
+    ));
+
+    let output = run(&["generate", "--size", "3x3", "--optimal-depth", "0"], "");
+    assert!(output.status.success(), "{:?}", output);
+    let numbers = String::from_utf8(output.stdout).unwrap();
+    let numbers = numbers.split_whitespace().collect::<Vec<_>>();
+    assert_eq!(numbers, ["1", "2", "3", "4", "5", "6", "7", "8", "0"]);
+}
+
+#[test]
+fn bench_checks_the_optimal_depth() {
+    let output = run(&["bench", "--size", "3x3", "--optimal-depth", "40"], "");
+    assert!(stderr(&output).starts_with(
+        "--optimal-depth 40 is too large: every 3x3 board is solved in at most 31 moves\n"
+    ));
+
+    // 0 手と、3x3 の既定の手数の盤面はすぐ作れる
+    for depth in [&["--optimal-depth", "0"][..], &[]] {
+        let args = [&["bench", "--size", "3x3", "--count", "2"][..], depth].concat();
+        let output = run(&args, "");
+        assert!(output.status.success(), "{:?}", output);
+    }
+}
+
+#[test]
+fn format_moves_prints_only_the_moves() {
+    let output = run(&["--format", "moves", "-"], "1 2 3 4 0 6 7 5 8");
+    assert!(output.status.success(), "{:?}", output);
+    let moves = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(moves, "DR\n");
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "2 moves (2 tile moves)\n"
+    );
+
+    // そのまま `verify --moves` に渡せる
+    let output = run(
+        &["verify", "--moves", moves.trim(), "-"],
+        "1 2 3 4 0 6 7 5 8",
+    );
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn rejects_huge_weights() {
+    for weight in ["1e30", "inf", "101"] {
+        let output = run(&["--weight", weight, "-"], "8 6 7 2 5 4 3 0 1");
+        assert!(stderr(&output).starts_with("--weight must be between 1 and 100\n"));
+    }
+    let output = run(&["--weight", "100", "-"], "8 6 7 2 5 4 3 0 1");
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn reports_when_sma_star_runs_out_of_states() {
+    for format in ["boards", "moves", "json"] {
+        let output = run(
+            &[
+                "--algorithm",
+                "sma-star",
+                "--max-states",
+                "10",
+                "--format",
+                format,
+                "-",
+            ],
+            "8 6 7 2 5 4 3 0 1",
+        );
+        assert_eq!(
+            stderr(&output),
+            "no solution of at most 9 moves within --max-states 10\n"
+        );
+        assert!(output.stdout.is_empty());
+    }
+}
+
+#[test]
+fn all_and_count_use_the_singular_for_one_solution() {
+    // 最短手順は DR だけ
+    let output = run(&["--count", "-"], "1 2 3 4 0 6 7 5 8");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1 shortest solution\n"
+    );
+
+    let output = run(&["--all", "-"], "1 2 3 4 0 6 7 5 8");
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "DR\n1 shortest solution\n"
+    );
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// `args` を付けて `mushikui` を起動し、`input` を標準入力に渡す
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mushikui"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn solves_addition() {
+    let output = run(&["--first"], "  *8\n+ 4*\n----\n 1*3\n");
+    assert_eq!(
+        stdout(&output),
+        "  *8\n+ 4*\n----\n 1*3\n\n1 solution\n  58\n+ 45\n----\n 103\n"
+    );
+}
+
+#[test]
+fn solves_subtraction() {
+    let output = run(&["--quiet"], " 9*\n- *7\n---\n  5\n");
+    assert_eq!(stdout(&output), "1 solution\n  92\n- 87\n----\n   5\n");
+}
+
+#[test]
+fn solves_division() {
+    let output = run(&["--count"], "   *\n7÷**\n  **\n   0\n");
+    assert!(stdout(&output).ends_with("\n\n8\n"));
+}
+
+#[test]
+fn batch_reads_every_operator() {
+    let input =
+        " *1\n× 2*\n----\n **3\n*4*\n----\n****\n\n 9*\n- *7\n---\n  5\n\n  *8\n+ 4*\n----\n 1*3\n";
+    let output = run(&["--batch"], input);
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(text.contains("#1: unique"));
+    assert!(text.contains("#2: unique"));
+    assert!(text.contains("#3: multiple solutions"));
+    assert!(text.ends_with("2 of 3 puzzles have a unique solution\n"));
+}
+
+#[test]
+fn rejects_multiplication_only_features_for_other_operators() {
+    let output = run(&["hint"], "  *8\n+ 4*\n----\n 1*3\n");
+    assert!(!output.status.success());
+    let message = String::from_utf8(output.stderr).unwrap();
+    assert!(message.starts_with("hint supports only multiplication puzzles"));
+}
+
+#[test]
+fn help_prints_usage_to_stdout() {
+    for args in [&["-h"][..], &["--help"], &["generate", "--help"]] {
+        let output = run(args, "");
+        assert!(output.status.success(), "{:?}", output);
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(text.starts_with("usage: mushikui "));
+        assert!(output.stderr.is_empty());
+    }
+}
+
+#[test]
+fn every_book_problem_has_a_unique_solution() {
+    let list = stdout(&run(&["demo", "--list"], ""));
+    let names = list.lines().collect::<Vec<_>>();
+    assert_eq!(names.len(), 8);
+    for name in names {
+        let output = run(&["demo", name, "--count"], "");
+        assert!(stdout(&output).ends_with("\n\n1\n"), "{}", name);
+    }
+}
+
+#[test]
+fn rejects_conflicting_modes() {
+    for (args, message) in [
+        (&["--all", "--count"][..], "--all and --count"),
+        (&["--count", "--first"], "--count and --first"),
+        (&["--first", "--batch"], "--first and --batch"),
+    ] {
+        let output = run(args, "  *8\n+ 4*\n----\n 1*3\n");
+        assert!(!output.status.success());
+        let text = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            text.starts_with(&format!(
+                "{} cannot be used together\nusage: mushikui ",
+                message
+            )),
+            "{}",
+            text
+        );
+    }
+    // 同じ指定を繰り返すのはよい
+    let output = run(&["--count", "--count"], "  *8\n+ 4*\n----\n 1*3\n");
+    assert!(stdout(&output).ends_with("\n\n5\n"));
+}
@@ -0,0 +1,43 @@
+//! ブラウザから呼ぶための wasm-bindgen のラッパ
+
+use book_puzzle_algorithm_rs::fifteen_puzzle::{Board, N};
+use book_puzzle_algorithm_rs::mushikui::Mushikui;
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+/// 虫食算を解いて、解 (虫食算と同じ形式の文字列) の配列を返す
+#[wasm_bindgen]
+pub fn solve_mushikui(text: &str) -> Result<JsValue, JsValue> {
+    let mushikui: Mushikui = text
+        .parse()
+        .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+    let solutions = Array::new();
+    for solution in mushikui.solve() {
+        solutions.push(&JsValue::from_str(&solution.to_string()));
+    }
+    Ok(solutions.into())
+}
+
+/// 16 個の数 (`0` は空きマス) の盤面を解いて、完成までの各盤面の配列を返す
+///
+/// 解けなければ `null` を返す。
+#[wasm_bindgen]
+pub fn solve_fifteen(board: &[u8]) -> Result<JsValue, JsValue> {
+    let mut board = Board::try_new(&board.chunks(N).collect::<Vec<_>>())
+        .map_err(|e| JsValue::from_str(&format!("{}", e)))?;
+    let boards = match book_puzzle_algorithm_rs::fifteen_puzzle::solve(&mut board) {
+        Some(boards) => boards,
+        None => return Ok(JsValue::NULL),
+    };
+    let steps = Array::new();
+    for board in boards {
+        let values = board
+            .board()
+            .iter()
+            .flatten()
+            .map(|v| v.get())
+            .collect::<Vec<_>>();
+        steps.push(&Uint8Array::from(&values[..]));
+    }
+    Ok(steps.into())
+}